@@ -64,7 +64,7 @@ use crossbeam_channel::{Receiver, Sender};
 use lsp_types::{
     notification::{Exit, Initialized},
     request::{Initialize, Shutdown},
-    InitializeParams, InitializeResult, ServerCapabilities,
+    InitializeParams,
 };
 
 pub type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
@@ -77,15 +77,20 @@ pub use crate::{
 /// To attach server to standard input/output streams, use the `stdio_transport`
 /// function to create corresponding `sender` and `receiver` pair.
 ///
+/// `caps` is usually `ServerCapabilities`, but can be any value serializing
+/// to capabilities, which is useful for protocol extensions not yet supported
+/// by `lsp-types`.
+///
 /// `server` should use the `handle_shutdown` function to handle the `Shutdown`
 /// request.
 pub fn run_server(
-    caps: ServerCapabilities,
+    caps: impl serde::Serialize,
     receiver: Receiver<RawMessage>,
     sender: Sender<RawMessage>,
     server: impl FnOnce(InitializeParams, &Receiver<RawMessage>, &Sender<RawMessage>) -> Result<()>,
 ) -> Result<()> {
     log::info!("lsp server initializes");
+    let caps = serde_json::to_value(caps)?;
     let params = initialize(&receiver, &sender, caps)?;
     log::info!("lsp server initialized, serving requests");
     server(params, &receiver, &sender)?;
@@ -115,7 +120,7 @@ pub fn handle_shutdown(req: RawRequest, sender: &Sender<RawMessage>) -> Option<R
 fn initialize(
     receiver: &Receiver<RawMessage>,
     sender: &Sender<RawMessage>,
-    caps: ServerCapabilities,
+    caps: serde_json::Value,
 ) -> Result<InitializeParams> {
     let (id, params) = match receiver.recv() {
         Ok(RawMessage::Request(req)) => match req.cast::<Initialize>() {
@@ -124,7 +129,8 @@ fn initialize(
         },
         msg => Err(format!("expected initialize request, got {:?}", msg))?,
     };
-    let resp = RawResponse::ok::<Initialize>(id, &InitializeResult { capabilities: caps });
+    let result = serde_json::json!({ "capabilities": caps });
+    let resp = RawResponse { id, result: Some(result), error: None };
     sender.send(RawMessage::Response(resp)).unwrap();
     match receiver.recv() {
         Ok(RawMessage::Notification(n)) => {
@@ -10,27 +10,13 @@ use crate::{Assist, AssistCtx, AssistId};
 
 pub(crate) fn add_impl(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     let nominal = ctx.node_at_offset::<ast::NominalDef>()?;
-    let name = nominal.name()?;
+    let header = impl_header(&nominal)?;
     ctx.add_action(AssistId("add_impl"), "add impl", |edit| {
         edit.target(nominal.syntax().text_range());
-        let type_params = nominal.type_param_list();
         let start_offset = nominal.syntax().text_range().end();
         let mut buf = String::new();
-        buf.push_str("\n\nimpl");
-        if let Some(type_params) = &type_params {
-            format!(buf, "{}", type_params.syntax());
-        }
-        buf.push_str(" ");
-        buf.push_str(name.text().as_str());
-        if let Some(type_params) = type_params {
-            let lifetime_params = type_params
-                .lifetime_params()
-                .filter_map(|it| it.lifetime_token())
-                .map(|it| it.text().clone());
-            let type_params =
-                type_params.type_params().filter_map(|it| it.name()).map(|it| it.text().clone());
-            join(lifetime_params.chain(type_params)).surround_with("<", ">").to_buf(&mut buf);
-        }
+        buf.push_str("\n\n");
+        buf.push_str(&header);
         buf.push_str(" {\n");
         edit.set_cursor(start_offset + TextUnit::of_str(&buf));
         buf.push_str("\n}");
@@ -40,6 +26,52 @@ pub(crate) fn add_impl(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     ctx.build()
 }
 
+pub(crate) fn add_trait_impl(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let nominal = ctx.node_at_offset::<ast::NominalDef>()?;
+    let (type_params, self_ty) = impl_header_parts(&nominal)?;
+    ctx.add_action(AssistId("add_trait_impl"), "add trait impl", |edit| {
+        edit.target(nominal.syntax().text_range());
+        let start_offset = nominal.syntax().text_range().end();
+        let mut buf = String::new();
+        format!(buf, "\n\nimpl{} ", type_params);
+        edit.set_cursor(start_offset + TextUnit::of_str(&buf));
+        format!(buf, " for {} {{\n\n}}", self_ty);
+        edit.insert(start_offset, buf);
+    });
+
+    ctx.build()
+}
+
+/// Builds the header of an inherent impl for `nominal`, like
+/// `impl<'a, T: Clone> Foo<'a, T>`.
+pub(crate) fn impl_header(nominal: &ast::NominalDef) -> Option<String> {
+    let (type_params, self_ty) = impl_header_parts(nominal)?;
+    Some(format!("impl{} {}", type_params, self_ty))
+}
+
+/// Returns the type parameters of an impl for `nominal` and its self type,
+/// like `<'a, T: Clone>` and `Foo<'a, T>`.
+fn impl_header_parts(nominal: &ast::NominalDef) -> Option<(String, String)> {
+    let name = nominal.name()?;
+    let type_params = nominal.type_param_list();
+    let mut params = String::new();
+    if let Some(type_params) = &type_params {
+        format!(params, "{}", type_params.syntax());
+    }
+    let mut self_ty = String::new();
+    self_ty.push_str(name.text().as_str());
+    if let Some(type_params) = type_params {
+        let lifetime_params = type_params
+            .lifetime_params()
+            .filter_map(|it| it.lifetime_token())
+            .map(|it| it.text().clone());
+        let type_params =
+            type_params.type_params().filter_map(|it| it.name()).map(|it| it.text().clone());
+        join(lifetime_params.chain(type_params)).surround_with("<", ">").to_buf(&mut self_ty);
+    }
+    Some((params, self_ty))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -60,6 +92,20 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_add_trait_impl() {
+        check_assist(
+            add_trait_impl,
+            "struct Foo {<|>}\n",
+            "struct Foo {}\n\nimpl <|> for Foo {\n\n}\n",
+        );
+        check_assist(
+            add_trait_impl,
+            "struct Foo<'a, T: Clone> {<|>}",
+            "struct Foo<'a, T: Clone> {}\n\nimpl<'a, T: Clone> <|> for Foo<'a, T> {\n\n}",
+        );
+    }
+
     #[test]
     fn add_impl_target() {
         check_assist_target(
@@ -1,12 +1,20 @@
+use std::iter::successors;
+
 use crate::{
     ast_editor::{AstBuilder, AstEditor},
+    auto_import::auto_import_text_edit,
+    utils::item_path,
     Assist, AssistCtx, AssistId,
 };
 
-use hir::{db::HirDatabase, HasSource};
-use ra_db::FilePosition;
-use ra_syntax::ast::{self, AstNode, ImplItemKind, NameOwner};
-use ra_syntax::SmolStr;
+use hir::{db::HirDatabase, HasSource, Module, ModuleDef, PathResolution, SourceAnalyzer};
+use ra_db::FileId;
+use ra_syntax::ast::{
+    self, AstNode, DefaultTypeParamOwner, ImplItemKind, NameOwner, PathSegmentKind,
+    TypeAscriptionOwner, TypeParamsOwner, TypeRefKind,
+};
+use ra_syntax::{SmolStr, SyntaxKind::USE_ITEM, SyntaxNode, TextRange};
+use ra_text_edit::TextEditBuilder;
 
 #[derive(PartialEq)]
 enum AddMissingImplMembersMode {
@@ -41,13 +49,12 @@ fn add_missing_impl_members_inner(
     let impl_node = ctx.node_at_offset::<ast::ImplBlock>()?;
     let impl_item_list = impl_node.item_list()?;
 
-    let trait_def = {
-        let file_id = ctx.frange.file_id;
-        let position = FilePosition { file_id, offset: impl_node.syntax().text_range().start() };
-        let analyzer = hir::SourceAnalyzer::new(ctx.db, position.file_id, impl_node.syntax(), None);
-
-        resolve_target_trait_def(ctx.db, &analyzer, &impl_node)?
-    };
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let analyzer = SourceAnalyzer::new(db, file_id, impl_node.syntax(), None);
+    let hir_trait = resolve_target_trait(db, &analyzer, &impl_node)?;
+    let trait_source = hir_trait.source(db);
+    let trait_def = trait_source.ast;
 
     let def_name = |kind| -> Option<SmolStr> {
         match kind {
@@ -76,12 +83,21 @@ fn add_missing_impl_members_inner(
         return None;
     }
 
+    let mut rewriter = ItemRewriter {
+        db,
+        trait_file_id: trait_source.file_id.original_file(db),
+        substs: trait_substs(&trait_def, &impl_node),
+        analyzer: &analyzer,
+        file_id,
+        impl_node: impl_node.syntax(),
+        imports: Vec::new(),
+    };
+    let items: Vec<_> = missing_items.iter().map(|it| rewriter.rewrite(it)).collect();
+    let imports = rewriter.imports;
+
     ctx.add_action(AssistId(assist_id), label, |edit| {
         let n_existing_items = impl_item_list.impl_items().count();
-        let items = missing_items.into_iter().map(|it| match it.kind() {
-            ImplItemKind::FnDef(def) => strip_docstring(add_body(def).into()),
-            _ => strip_docstring(it),
-        });
+        let items = items.into_iter().map(|it| strip_docstring(add_placeholder(it)));
         let mut ast_editor = AstEditor::new(impl_item_list);
 
         ast_editor.append_items(items);
@@ -90,7 +106,21 @@ fn add_missing_impl_members_inner(
         let cursor_position = first_new_item.syntax().text_range().start();
         ast_editor.into_text_edit(edit.text_edit_builder());
 
-        edit.set_cursor(cursor_position);
+        let mut import_edit = TextEditBuilder::default();
+        for segments in imports.iter() {
+            auto_import_text_edit(
+                impl_node.syntax(),
+                impl_node.syntax(),
+                segments,
+                &mut import_edit,
+            );
+        }
+        let import_edit = import_edit.finish();
+        for atom in import_edit.as_atoms() {
+            edit.text_edit_builder().replace(atom.delete, atom.insert.clone());
+        }
+
+        edit.set_cursor(import_edit.apply_to_offset(cursor_position).unwrap_or(cursor_position));
     });
 
     ctx.build()
@@ -102,6 +132,66 @@ fn strip_docstring(item: ast::ImplItem) -> ast::ImplItem {
     ast_editor.ast().to_owned()
 }
 
+/// Gives a value to the members which have none in the trait. Consts are only
+/// given a value if their type has an obvious one, as `unimplemented!()` can't
+/// be evaluated at compile time: the others are left without a value for the
+/// compiler to point at.
+fn add_placeholder(item: ast::ImplItem) -> ast::ImplItem {
+    match item.kind() {
+        ImplItemKind::FnDef(def) => add_body(def).into(),
+        ImplItemKind::TypeAliasDef(def) if def.type_ref().is_none() => match def.name() {
+            Some(name) => {
+                AstBuilder::<ast::ImplItem>::from_text(&format!("type {} = ();", name.text()))
+            }
+            None => item,
+        },
+        ImplItemKind::ConstDef(def) if def.body().is_none() => {
+            match def.ascribed_type().and_then(|it| placeholder_value(&it)) {
+                Some(value) => {
+                    let text = def.syntax().text().to_string();
+                    let text = format!("{} = {};", text.trim_end_matches(';'), value);
+                    AstBuilder::<ast::ImplItem>::from_text(&text)
+                }
+                None => item,
+            }
+        }
+        _ => item,
+    }
+}
+
+/// Returns a constant expression of type `ty`, like `0` for an integer.
+fn placeholder_value(ty: &ast::TypeRef) -> Option<String> {
+    let res = match ty.kind() {
+        TypeRefKind::ParenType(it) => placeholder_value(&it.type_ref()?)?,
+        TypeRefKind::TupleType(it) => {
+            let fields =
+                it.fields().map(|it| placeholder_value(&it)).collect::<Option<Vec<_>>>()?;
+            match fields.as_slice() {
+                [field] => format!("({},)", field),
+                _ => format!("({})", fields.join(", ")),
+            }
+        }
+        TypeRefKind::ArrayType(it) => {
+            format!("[{}; {}]", placeholder_value(&it.type_ref()?)?, it.expr()?.syntax().text())
+        }
+        TypeRefKind::ReferenceType(it) if !it.is_mut() => match it.type_ref()?.kind() {
+            TypeRefKind::SliceType(_) => "&[]".to_string(),
+            TypeRefKind::PathType(it) if it.syntax().text() == "str" => "\"\"".to_string(),
+            _ => return None,
+        },
+        TypeRefKind::PathType(it) => match it.syntax().text().to_string().as_str() {
+            "i8" | "i16" | "i32" | "i64" | "i128" | "isize" | "u8" | "u16" | "u32" | "u64"
+            | "u128" | "usize" => "0".to_string(),
+            "f32" | "f64" => "0.0".to_string(),
+            "bool" => "false".to_string(),
+            "char" => "'\\0'".to_string(),
+            _ => return None,
+        },
+        _ => return None,
+    };
+    Some(res)
+}
+
 fn add_body(fn_def: ast::FnDef) -> ast::FnDef {
     let mut ast_editor = AstEditor::new(fn_def.clone());
     if fn_def.body().is_none() {
@@ -113,12 +203,12 @@ fn add_body(fn_def: ast::FnDef) -> ast::FnDef {
 }
 
 /// Given an `ast::ImplBlock`, resolves the target trait (the one being
-/// implemented) to a `ast::TraitDef`.
-fn resolve_target_trait_def(
+/// implemented).
+fn resolve_target_trait(
     db: &impl HirDatabase,
-    analyzer: &hir::SourceAnalyzer,
+    analyzer: &SourceAnalyzer,
     impl_block: &ast::ImplBlock,
-) -> Option<ast::TraitDef> {
+) -> Option<hir::Trait> {
     let ast_path = impl_block
         .target_trait()
         .map(|it| it.syntax().clone())
@@ -126,11 +216,153 @@ fn resolve_target_trait_def(
         .path()?;
 
     match analyzer.resolve_path(db, &ast_path) {
-        Some(hir::PathResolution::Def(hir::ModuleDef::Trait(def))) => Some(def.source(db).ast),
+        Some(PathResolution::Def(ModuleDef::Trait(def))) => Some(def),
         _ => None,
     }
 }
 
+/// Pairs the type parameters of the trait with the type arguments given to it
+/// in the impl, or with their defaults.
+fn trait_substs(trait_def: &ast::TraitDef, impl_block: &ast::ImplBlock) -> Vec<(SmolStr, String)> {
+    let args: Vec<String> = impl_block
+        .target_trait()
+        .and_then(|it| ast::PathType::cast(it.syntax().clone()))
+        .and_then(|it| it.path())
+        .and_then(|it| it.segment())
+        .and_then(|it| it.type_arg_list())
+        .into_iter()
+        .flat_map(|it| it.type_args())
+        .filter_map(|it| it.type_ref())
+        .map(|it| it.syntax().text().to_string())
+        .collect();
+    let params = trait_def.type_param_list().into_iter().flat_map(|it| it.type_params());
+    params
+        .enumerate()
+        .filter_map(|(idx, param)| {
+            let name = param.name()?.text().clone();
+            let arg = match args.get(idx) {
+                Some(arg) => arg.clone(),
+                None => param.default_type()?.syntax().text().to_string(),
+            };
+            Some((name, arg))
+        })
+        .collect()
+}
+
+/// Rewrites the members of a trait so that they can be copied into an impl of
+/// it: the type parameters of the trait are replaced by their arguments, and
+/// the paths are made to resolve from the module of the impl.
+struct ItemRewriter<'a, DB> {
+    db: &'a DB,
+    trait_file_id: FileId,
+    substs: Vec<(SmolStr, String)>,
+    /// The analyzer of the impl.
+    analyzer: &'a SourceAnalyzer,
+    file_id: FileId,
+    impl_node: &'a SyntaxNode,
+    /// The paths which need to be imported in the module of the impl.
+    imports: Vec<Vec<SmolStr>>,
+}
+
+impl<'a, DB: HirDatabase> ItemRewriter<'a, DB> {
+    fn rewrite(&mut self, item: &ast::ImplItem) -> ast::ImplItem {
+        let paths = item
+            .syntax()
+            .descendants()
+            .filter_map(ast::Path::cast)
+            .filter(|it| it.qualifier().is_none())
+            .filter(|it| it.syntax().ancestors().all(|it| it.kind() != USE_ITEM));
+        let mut edits: Vec<_> = paths.filter_map(|it| self.rewrite_path(&it)).collect();
+        edits.sort_by_key(|(range, _)| range.start());
+
+        let start = item.syntax().text_range().start();
+        let mut text = item.syntax().text().to_string();
+        for (range, replace_with) in edits.into_iter().rev() {
+            let range = (range.start() - start).to_usize()..(range.end() - start).to_usize();
+            text.replace_range(range, &replace_with);
+        }
+        AstBuilder::<ast::ImplItem>::from_text(&text)
+    }
+
+    /// Rewrites the path starting with the segment `path`, if needed.
+    fn rewrite_path(&mut self, path: &ast::Path) -> Option<(TextRange, String)> {
+        if let Some(PathSegmentKind::Name(name_ref)) = path.segment()?.kind() {
+            if let Some((_, arg)) = self.substs.iter().find(|(name, _)| name == name_ref.text()) {
+                let is_qualifier = path.syntax().parent().and_then(ast::Path::cast).is_some();
+                let arg = if is_qualifier && !is_plain_path(arg) {
+                    format!("<{}>", arg)
+                } else {
+                    arg.clone()
+                };
+                return Some((path.syntax().text_range(), arg));
+            }
+        }
+
+        // `self::`, `super::` and `crate::` are rewritten together with the
+        // first named segment following them.
+        let target =
+            successors(Some(path.clone()), |it| it.syntax().parent().and_then(ast::Path::cast))
+                .find(|it| match it.segment().and_then(|it| it.kind()) {
+                    Some(PathSegmentKind::Name(_)) => true,
+                    _ => false,
+                })?;
+        let name_ref = target.segment()?.name_ref()?;
+        let name = name_ref.text().to_string();
+        if name == "Self" {
+            return None;
+        }
+        let range = TextRange::from_to(
+            target.syntax().text_range().start(),
+            name_ref.syntax().text_range().end(),
+        );
+        let is_prefixed = target != *path;
+
+        let trait_analyzer =
+            SourceAnalyzer::new(self.db, self.trait_file_id, target.syntax(), None);
+        let def = match trait_analyzer.resolve_path(self.db, &target)? {
+            PathResolution::Def(ModuleDef::BuiltinType(_)) => return None,
+            PathResolution::Def(it) => it,
+            _ => return None,
+        };
+        let res = self.analyzer.resolve_path(self.db, &AstBuilder::<ast::Path>::from_text(&name));
+        if res == Some(PathResolution::Def(def)) {
+            return if is_prefixed { Some((range, name)) } else { None };
+        }
+
+        let (def_module, def_name) = module_and_name(self.db, def)?;
+        let full_path = item_path(self.db, def_module, self.file_id, self.impl_node, &def_name)?;
+        if res.is_none() && def_name == name && full_path.contains("::") {
+            let segments: Vec<SmolStr> = full_path.split("::").map(SmolStr::from).collect();
+            if !self.imports.contains(&segments) {
+                self.imports.push(segments);
+            }
+            return if is_prefixed { Some((range, name)) } else { None };
+        }
+        Some((range, full_path))
+    }
+}
+
+fn is_plain_path(text: &str) -> bool {
+    text.chars().all(|c| c.is_alphanumeric() || c == '_' || c == ':')
+}
+
+fn module_and_name(db: &impl HirDatabase, def: ModuleDef) -> Option<(Module, String)> {
+    let (module, name) = match def {
+        ModuleDef::Module(it) => (it.parent(db)?, it.name(db)?),
+        ModuleDef::Function(it) => (it.module(db), it.name(db)),
+        ModuleDef::Struct(it) => (it.module(db), it.name(db)?),
+        ModuleDef::Union(it) => (it.module(db), it.name(db)?),
+        ModuleDef::Enum(it) => (it.module(db), it.name(db)?),
+        ModuleDef::Trait(it) => (it.module(db), it.name(db)?),
+        ModuleDef::TypeAlias(it) => (it.module(db), it.name(db)),
+        ModuleDef::EnumVariant(_)
+        | ModuleDef::Const(_)
+        | ModuleDef::Static(_)
+        | ModuleDef::BuiltinType(_) => return None,
+    };
+    Some((module, name.to_string()))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -172,7 +404,7 @@ struct S;
 
 impl Foo for S {
     fn bar(&self) {}
-    <|>type Output;
+    <|>type Output = ();
     const CONST: usize = 42;
     fn foo(&self) { unimplemented!() }
     fn baz(&self) { unimplemented!() }
@@ -300,7 +532,7 @@ trait Foo {
 }
 struct S;
 impl Foo for S {
-    <|>type Output;
+    <|>type Output = ();
     fn foo(&self) { unimplemented!() }
 }"#,
         )
@@ -337,4 +569,152 @@ impl Foo for S {
         )
     }
 
+    #[test]
+    fn test_substitute_trait_type_params() {
+        check_assist(
+            add_missing_impl_members,
+            "
+trait From<T> {
+    fn from(value: T) -> Self;
+    fn from_all(values: &[T]) -> Vec<Self>;
+}
+struct S;
+impl From<u32> for S {}<|>",
+            "
+trait From<T> {
+    fn from(value: T) -> Self;
+    fn from_all(values: &[T]) -> Vec<Self>;
+}
+struct S;
+impl From<u32> for S {
+    <|>fn from(value: u32) -> Self { unimplemented!() }
+    fn from_all(values: &[u32]) -> Vec<Self> { unimplemented!() }
+}",
+        )
+    }
+
+    #[test]
+    fn test_substitute_default_type_params() {
+        check_assist(
+            add_missing_impl_members,
+            "
+trait Add<Rhs = Self> {
+    type Output;
+    fn add(self, rhs: Rhs) -> Self::Output;
+}
+struct S;
+impl Add for S {}<|>",
+            "
+trait Add<Rhs = Self> {
+    type Output;
+    fn add(self, rhs: Rhs) -> Self::Output;
+}
+struct S;
+impl Add for S {
+    <|>type Output = ();
+    fn add(self, rhs: Self) -> Self::Output { unimplemented!() }
+}",
+        )
+    }
+
+    #[test]
+    fn test_associated_type_and_const_placeholders() {
+        check_assist(
+            add_missing_impl_members,
+            r#"
+enum Option<T> { Some(T), None }
+trait Iterator {
+    type Item: Clone;
+    const SIZE: usize;
+    const NAME: &'static str;
+    const BOUNDS: (u8, [bool; 2]);
+    const FIRST: Self::Item;
+    fn next(&mut self) -> Option<Self::Item>;
+}
+struct S;
+impl Iterator for S {}<|>"#,
+            r#"
+enum Option<T> { Some(T), None }
+trait Iterator {
+    type Item: Clone;
+    const SIZE: usize;
+    const NAME: &'static str;
+    const BOUNDS: (u8, [bool; 2]);
+    const FIRST: Self::Item;
+    fn next(&mut self) -> Option<Self::Item>;
+}
+struct S;
+impl Iterator for S {
+    <|>type Item = ();
+    const SIZE: usize = 0;
+    const NAME: &'static str = "";
+    const BOUNDS: (u8, [bool; 2]) = (0, [false; 2]);
+    const FIRST: Self::Item;
+    fn next(&mut self) -> Option<Self::Item> { unimplemented!() }
+}"#,
+        )
+    }
+
+    #[test]
+    fn test_import_paths_from_trait_module() {
+        check_assist(
+            add_missing_impl_members,
+            "
+mod a {
+    pub struct Bar;
+    pub trait Foo {
+        fn foo(&self) -> Bar;
+        fn baz(&self) -> super::Baz;
+    }
+}
+struct Baz;
+struct S;
+impl a::Foo for S {}<|>",
+            "
+use crate::a::Bar;
+
+mod a {
+    pub struct Bar;
+    pub trait Foo {
+        fn foo(&self) -> Bar;
+        fn baz(&self) -> super::Baz;
+    }
+}
+struct Baz;
+struct S;
+impl a::Foo for S {
+    <|>fn foo(&self) -> Bar { unimplemented!() }
+    fn baz(&self) -> Baz { unimplemented!() }
+}",
+        )
+    }
+
+    #[test]
+    fn test_qualify_shadowed_paths() {
+        check_assist(
+            add_missing_impl_members,
+            "
+mod a {
+    pub struct Bar;
+    pub trait Foo {
+        fn foo(&self) -> Bar;
+    }
+}
+struct Bar;
+struct S;
+impl a::Foo for S {}<|>",
+            "
+mod a {
+    pub struct Bar;
+    pub trait Foo {
+        fn foo(&self) -> Bar;
+    }
+}
+struct Bar;
+struct S;
+impl a::Foo for S {
+    <|>fn foo(&self) -> crate::a::Bar { unimplemented!() }
+}",
+        )
+    }
 }
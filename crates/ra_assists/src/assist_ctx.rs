@@ -1,5 +1,5 @@
 use hir::db::HirDatabase;
-use ra_db::FileRange;
+use ra_db::{FileId, FileRange, SourceRootId};
 use ra_fmt::{leading_indent, reindent};
 use ra_syntax::{
     algo::{find_covering_element, find_node_at_offset},
@@ -7,6 +7,7 @@ use ra_syntax::{
     TokenAtOffset,
 };
 use ra_text_edit::TextEditBuilder;
+use relative_path::RelativePathBuf;
 
 use crate::{AssistAction, AssistId, AssistLabel};
 
@@ -91,7 +92,7 @@ impl<'a, DB: HirDatabase> AssistCtx<'a, DB> {
             Assist::Unresolved(labels) => labels.push(label),
             Assist::Resolved(labels_actions) => {
                 let action = {
-                    let mut edit = AssistBuilder::default();
+                    let mut edit = AssistBuilder::new(self.frange.file_id);
                     f(&mut edit);
                     edit.build()
                 };
@@ -101,6 +102,13 @@ impl<'a, DB: HirDatabase> AssistCtx<'a, DB> {
         self
     }
 
+    /// Whether the edits of the actions are computed. Assists which search the
+    /// workspace for their edits decide whether they are applicable from the
+    /// syntax at the cursor, and only search when this is set.
+    pub(crate) fn should_compute_edit(&self) -> bool {
+        self.should_compute_edit
+    }
+
     pub(crate) fn build(self) -> Option<Assist> {
         Some(self.assist)
     }
@@ -121,14 +129,27 @@ impl<'a, DB: HirDatabase> AssistCtx<'a, DB> {
     }
 }
 
-#[derive(Default)]
 pub(crate) struct AssistBuilder {
+    file_id: FileId,
     edit: TextEditBuilder,
+    other_file_edits: Vec<(FileId, TextEditBuilder)>,
+    new_files: Vec<(SourceRootId, RelativePathBuf, String)>,
     cursor_position: Option<TextUnit>,
     target: Option<TextRange>,
 }
 
 impl AssistBuilder {
+    fn new(file_id: FileId) -> AssistBuilder {
+        AssistBuilder {
+            file_id,
+            edit: TextEditBuilder::default(),
+            other_file_edits: Vec::new(),
+            new_files: Vec::new(),
+            cursor_position: None,
+            target: None,
+        }
+    }
+
     /// Replaces specified `range` of text with a given string.
     pub(crate) fn replace(&mut self, range: TextRange, replace_with: impl Into<String>) {
         self.edit.replace(range, replace_with.into())
@@ -177,9 +198,53 @@ impl AssistBuilder {
         &mut self.edit
     }
 
+    /// Returns the `TextEditBuilder` of `file_id`, which may be a file other
+    /// than the one the assist was invoked in.
+    fn text_edit_builder_for(&mut self, file_id: FileId) -> &mut TextEditBuilder {
+        if file_id == self.file_id {
+            return &mut self.edit;
+        }
+        let idx = match self.other_file_edits.iter().position(|(id, _)| *id == file_id) {
+            Some(idx) => idx,
+            None => {
+                self.other_file_edits.push((file_id, TextEditBuilder::default()));
+                self.other_file_edits.len() - 1
+            }
+        };
+        &mut self.other_file_edits[idx].1
+    }
+
+    /// Replaces specified `range` of text in `file_id` with a given string.
+    pub(crate) fn replace_in(
+        &mut self,
+        file_id: FileId,
+        range: TextRange,
+        replace_with: impl Into<String>,
+    ) {
+        self.text_edit_builder_for(file_id).replace(range, replace_with.into())
+    }
+
+    /// Creates a file at `path` in `source_root`, with `text` as contents.
+    pub(crate) fn create_file(
+        &mut self,
+        source_root: SourceRootId,
+        path: RelativePathBuf,
+        text: impl Into<String>,
+    ) {
+        self.new_files.push((source_root, path, text.into()))
+    }
+
     fn build(self) -> AssistAction {
+        let mut other_file_edits: Vec<_> = self
+            .other_file_edits
+            .into_iter()
+            .map(|(file_id, edit)| (file_id, edit.finish()))
+            .collect();
+        other_file_edits.sort_by_key(|(file_id, _)| *file_id);
         AssistAction {
             edit: self.edit.finish(),
+            other_file_edits,
+            new_files: self.new_files,
             cursor_position: self.cursor_position,
             target: self.target,
         }
@@ -282,12 +282,30 @@ impl AstBuilder<ast::Expr> {
     }
 }
 
+impl AstBuilder<ast::Path> {
+    pub fn from_text(text: &str) -> ast::Path {
+        ast_node_from_file_text(&format!("use {};", text))
+    }
+}
+
 impl AstBuilder<ast::NameRef> {
     pub fn new(text: &str) -> ast::NameRef {
         ast_node_from_file_text(&format!("fn f() {{ {}; }}", text))
     }
 }
 
+impl AstBuilder<ast::ImplItem> {
+    pub fn from_text(text: &str) -> ast::ImplItem {
+        ast_node_from_file_text(&format!("impl S {{ {} }}", text))
+    }
+}
+
+impl AstBuilder<ast::ImplBlock> {
+    pub fn from_header(header: &str) -> ast::ImplBlock {
+        ast_node_from_file_text(&format!("{} {{}}", header))
+    }
+}
+
 fn ast_node_from_file_text<N: AstNode>(text: &str) -> N {
     let parse = SourceFile::parse(text);
     let res = parse.tree().syntax().descendants().find_map(N::cast).unwrap().to_owned();
@@ -0,0 +1,202 @@
+use hir::db::HirDatabase;
+use ra_fmt::leading_indent;
+use ra_syntax::ast::{self, AstNode};
+
+use crate::{
+    invert_if::{if_keyword_range, invert_boolean_expression},
+    Assist, AssistCtx, AssistId,
+};
+
+/// Replaces an `if` ending the body of a function which returns `()` with an
+/// early return when the condition does not hold, followed by the contents of
+/// the `if`:
+///
+/// ```text
+/// if cond { body }  =>  if !cond { return; } body
+/// ```
+pub(crate) fn convert_to_guarded_return(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let if_expr: ast::IfExpr = ctx.node_at_offset()?;
+    let if_range = if_keyword_range(&if_expr)?;
+    if !ctx.frange.range.is_subrange(&if_range) || if_expr.else_branch().is_some() {
+        return None;
+    }
+    let cond = if_expr.condition()?;
+    if cond.pat().is_some() {
+        return None;
+    }
+    let cond_expr = cond.expr()?;
+    let then_block = if_expr.then_branch()?;
+
+    // Nothing may follow the `if` in the body of the function, as it would be
+    // skipped by the early return.
+    let stmt = match if_expr.syntax().parent().and_then(ast::ExprStmt::cast) {
+        Some(stmt) => stmt.syntax().clone(),
+        None => if_expr.syntax().clone(),
+    };
+    if stmt.next_sibling().is_some() {
+        return None;
+    }
+    let fn_def = stmt.parent().and_then(|it| it.parent()).and_then(ast::FnDef::cast)?;
+    if let Some(ret_type) = fn_def.ret_type() {
+        if ret_type.type_ref()?.syntax().text().to_string() != "()" {
+            return None;
+        }
+    }
+    let contents = block_contents(&then_block)?;
+    if contents.is_empty() {
+        return None;
+    }
+
+    ctx.add_action(AssistId("convert_to_guarded_return"), "convert to guarded return", |edit| {
+        let indent = leading_indent(if_expr.syntax()).unwrap_or_default();
+        let mut buf = format!("if {} {{\n", invert_boolean_expression(&cond_expr));
+        buf.push_str(&format!("{}    return;\n{}}}\n", indent, indent));
+        let lines =
+            contents.lines().map(|line| if line.starts_with("    ") { &line[4..] } else { line });
+        buf.push_str(&format!("{}{}", indent, lines.collect::<Vec<_>>().join("\n")));
+        edit.target(if_range);
+        edit.replace(if_expr.syntax().text_range(), buf);
+        edit.set_cursor(if_expr.syntax().text_range().start());
+    });
+
+    ctx.build()
+}
+
+/// The text between the braces of `block`, without surrounding whitespace.
+fn block_contents(block: &ast::Block) -> Option<String> {
+    let text = block.syntax().text().to_string();
+    if !text.starts_with('{') || !text.ends_with('}') {
+        return None;
+    }
+    Some(text[1..text.len() - 1].trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    #[test]
+    fn convert_inside_fn() {
+        check_assist(
+            convert_to_guarded_return,
+            r#"
+fn main() {
+    bar();
+    i<|>f true {
+        foo();
+
+        // comment
+        bar();
+    }
+}
+"#,
+            r#"
+fn main() {
+    bar();
+    <|>if !true {
+        return;
+    }
+    foo();
+
+    // comment
+    bar();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_negated_condition() {
+        check_assist(
+            convert_to_guarded_return,
+            r#"
+fn main(x: u32) {
+    <|>if x != 1 {
+        foo();
+    }
+}
+"#,
+            r#"
+fn main(x: u32) {
+    <|>if x == 1 {
+        return;
+    }
+    foo();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_not_applicable_with_following_statements() {
+        check_assist_not_applicable(
+            convert_to_guarded_return,
+            r#"
+fn main() {
+    <|>if true {
+        foo();
+    }
+    bar();
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_not_applicable_with_else_or_in_loop() {
+        check_assist_not_applicable(
+            convert_to_guarded_return,
+            r#"
+fn main() {
+    <|>if true {
+        foo();
+    } else {
+        bar();
+    }
+}
+"#,
+        );
+        check_assist_not_applicable(
+            convert_to_guarded_return,
+            r#"
+fn main() {
+    loop {
+        <|>if true {
+            foo();
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_not_applicable_with_return_value() {
+        check_assist_not_applicable(
+            convert_to_guarded_return,
+            r#"
+fn main() -> u32 {
+    <|>if true {
+        foo();
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_target() {
+        check_assist_target(
+            convert_to_guarded_return,
+            r#"
+fn main() {
+    <|>if true {
+        foo();
+    }
+}
+"#,
+            "if",
+        );
+    }
+}
@@ -0,0 +1,642 @@
+use hir::{db::HirDatabase, source_binder, AdtDef, ModuleDef, PathResolution, VariantDef};
+use itertools::Itertools;
+use ra_db::{FileId, SourceDatabase};
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    ast::{self, AstNode, NameOwner, StructKind, TypeAscriptionOwner, TypeParamsOwner},
+    SyntaxKind::{self, *},
+    SyntaxNode, SyntaxToken, TextRange,
+};
+
+use crate::{
+    search::{self, Usage, UsageEdits},
+    Assist, AssistCtx, AssistId,
+};
+
+/// Converts a tuple struct to a struct with named fields `field1`, `field2`,
+/// and so on. Constructor calls, patterns and `.0` field accesses are updated
+/// across the workspace. The assist is offered based on the struct alone, and
+/// the workspace is only searched when the edit is computed: no edit is made
+/// if the constructor is used as a function value, as in `.map(S)`.
+pub(crate) fn convert_tuple_struct_to_named_struct(
+    mut ctx: AssistCtx<impl HirDatabase>,
+) -> Option<Assist> {
+    let strukt = struct_at_cursor(&ctx)?;
+    let field_list = match strukt.kind() {
+        StructKind::Tuple(it) => it,
+        _ => return None,
+    };
+    let fields: Vec<_> = field_list.fields().collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let names: Vec<_> = (1..=fields.len()).map(|i| format!("field{}", i)).collect();
+    let usage_edits = if ctx.should_compute_edit() {
+        let hir_struct = hir_struct(db, file_id, &strukt)?;
+        to_named_usage_edits(db, hir_struct, &names)?
+    } else {
+        Vec::new()
+    };
+
+    let text = db.file_text(file_id);
+    let indent = leading_indent(strukt.syntax()).unwrap_or_default();
+    let mut buf = String::from(" {\n");
+    for (field, name) in fields.iter().zip(names.iter()) {
+        let ty = field.type_ref()?;
+        let attrs_and_vis = &text[TextRange::from_to(
+            field.syntax().text_range().start(),
+            ty.syntax().text_range().start(),
+        )];
+        buf.push_str(&format!(
+            "{}    {}{}: {},\n",
+            indent,
+            attrs_and_vis,
+            name,
+            ty.syntax().text()
+        ));
+    }
+    buf.push_str(&indent);
+    buf.push('}');
+
+    ctx.add_action(
+        AssistId("convert_tuple_struct_to_named_struct"),
+        "convert to named struct",
+        |edit| {
+            edit.target(strukt.syntax().text_range());
+            let list_range = field_list.syntax().text_range();
+            match strukt.where_clause() {
+                Some(where_clause) => {
+                    edit.delete(list_range);
+                    let end = strukt.syntax().text_range().end();
+                    edit.replace(
+                        TextRange::from_to(where_clause.syntax().text_range().end(), end),
+                        buf,
+                    );
+                }
+                None => {
+                    edit.replace(
+                        TextRange::from_to(list_range.start(), strukt.syntax().text_range().end()),
+                        buf,
+                    );
+                }
+            }
+            for (file_id, range, text) in usage_edits {
+                edit.replace_in(file_id, range, text);
+            }
+        },
+    );
+    ctx.build()
+}
+
+/// Converts a struct with named fields to a tuple struct. Struct literals,
+/// patterns and field accesses are updated across the workspace. The fields
+/// of the literals are reordered to match the definition, and the fields
+/// missing from a pattern are replaced with `_`. Like for the conversion to a
+/// named struct, the workspace is only searched when the edit is computed, and
+/// no edit is made if a literal uses the `..base` syntax.
+pub(crate) fn convert_named_struct_to_tuple_struct(
+    mut ctx: AssistCtx<impl HirDatabase>,
+) -> Option<Assist> {
+    let strukt = struct_at_cursor(&ctx)?;
+    let field_list = match strukt.kind() {
+        StructKind::Named(it) => it,
+        _ => return None,
+    };
+    let fields: Vec<_> = field_list.fields().collect();
+    if fields.is_empty() {
+        return None;
+    }
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let names: Vec<_> =
+        fields.iter().map(|it| Some(it.name()?.text().to_string())).collect::<Option<_>>()?;
+    let usage_edits = if ctx.should_compute_edit() {
+        let hir_struct = hir_struct(db, file_id, &strukt)?;
+        to_tuple_usage_edits(db, hir_struct, &names)?
+    } else {
+        Vec::new()
+    };
+
+    let text = db.file_text(file_id);
+    let types = fields
+        .iter()
+        .map(|field| {
+            let ty = field.ascribed_type()?;
+            let name = field.name()?;
+            let attrs_and_vis = &text[TextRange::from_to(
+                field.syntax().text_range().start(),
+                name.syntax().text_range().start(),
+            )];
+            Some(format!("{}{}", attrs_and_vis, ty.syntax().text()))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let tuple = format!("({})", types.join(", "));
+    let head_end = match strukt.type_param_list() {
+        Some(it) => it.syntax().text_range().end(),
+        None => strukt.name()?.syntax().text_range().end(),
+    };
+
+    ctx.add_action(
+        AssistId("convert_named_struct_to_tuple_struct"),
+        "convert to tuple struct",
+        |edit| {
+            edit.target(strukt.syntax().text_range());
+            let list_end = field_list.syntax().text_range().end();
+            match strukt.where_clause() {
+                Some(where_clause) => {
+                    edit.insert(head_end, tuple);
+                    let where_end = where_clause.syntax().text_range().end();
+                    edit.replace(TextRange::from_to(where_end, list_end), ";");
+                }
+                None => edit.replace(TextRange::from_to(head_end, list_end), tuple + ";"),
+            }
+            for (file_id, range, text) in usage_edits {
+                edit.replace_in(file_id, range, text);
+            }
+        },
+    );
+    ctx.build()
+}
+
+/// Returns the struct at the cursor, if the cursor is on its header rather
+/// than on its fields.
+fn struct_at_cursor(ctx: &AssistCtx<impl HirDatabase>) -> Option<ast::StructDef> {
+    let strukt = ctx.node_at_offset::<ast::StructDef>()?;
+    if strukt.is_union() {
+        return None;
+    }
+    let fields_start = match strukt.kind() {
+        StructKind::Tuple(it) => it.syntax().text_range().start(),
+        StructKind::Named(it) => it.syntax().text_range().start(),
+        StructKind::Unit => return None,
+    };
+    if ctx.frange.range.start() > fields_start {
+        return None;
+    }
+    Some(strukt)
+}
+
+fn hir_struct(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    strukt: &ast::StructDef,
+) -> Option<hir::Struct> {
+    let module = source_binder::module_from_child_node(db, file_id, strukt.syntax())?;
+    Some(source_binder::struct_from_module(db, module, strukt))
+}
+
+/// Finds the constructors and the patterns of `strukt`, including the ones
+/// spelled `Self`. Returns `None` if the constructor is used as a value or if
+/// a literal uses `..base`, which can't be converted.
+fn struct_usages(db: &impl HirDatabase, strukt: hir::Struct) -> Option<Vec<(FileId, Usage)>> {
+    let name = strukt.name(db)?.to_string();
+    let usages = search::find_constructor_usages(db, &[&name, "Self"], |res| match res {
+        PathResolution::Def(ModuleDef::Struct(it)) => it == strukt,
+        PathResolution::SelfType(imp) => {
+            imp.target_ty(db).as_adt().map(|(adt, _)| adt) == Some(AdtDef::Struct(strukt))
+        }
+        _ => false,
+    });
+    let is_convertible = |usage: &Usage| match usage {
+        Usage::Value(_) => false,
+        Usage::StructLit(lit) => lit.named_field_list().and_then(|it| it.spread()).is_none(),
+        _ => true,
+    };
+    if !usages.iter().all(|(_, usage)| is_convertible(usage)) {
+        return None;
+    }
+    Some(usages)
+}
+
+/// Finds the usages of the tuple struct `strukt` and converts them to use the
+/// field `names`.
+fn to_named_usage_edits(
+    db: &impl HirDatabase,
+    strukt: hir::Struct,
+    names: &[String],
+) -> Option<Vec<(FileId, TextRange, String)>> {
+    let mut usages = struct_usages(db, strukt)?;
+    for file_id in search::files_with_tuple_fields(db, names.len()) {
+        let parse = db.parse(file_id);
+        let field_exprs = parse
+            .tree()
+            .syntax()
+            .descendants()
+            .filter_map(ast::FieldExpr::cast)
+            .filter(|it| tuple_field_index(it).map_or(false, |idx| idx < names.len()));
+        for field_expr in field_exprs {
+            if is_field_of(db, file_id, &field_expr, strukt) {
+                usages.push((file_id, Usage::Field(field_expr)));
+            }
+        }
+    }
+    search::edit_usages(db, usages, |edits, _, usage| to_named_usage(edits, usage, names))
+}
+
+/// Finds the usages of the struct `strukt` with the fields `names` and
+/// converts them to use the positions of the fields.
+fn to_tuple_usage_edits(
+    db: &impl HirDatabase,
+    strukt: hir::Struct,
+    names: &[String],
+) -> Option<Vec<(FileId, TextRange, String)>> {
+    let mut usages = struct_usages(db, strukt)?;
+    for name in names.iter() {
+        for (file_id, name_ref) in search::find_name_refs(db, name) {
+            let field_expr = match name_ref.syntax().parent().and_then(ast::FieldExpr::cast) {
+                Some(it) => it,
+                None => continue,
+            };
+            if is_field_of(db, file_id, &field_expr, strukt) {
+                usages.push((file_id, Usage::Field(field_expr)));
+            }
+        }
+    }
+    search::edit_usages(db, usages, |edits, _, usage| to_tuple_usage(edits, usage, names))
+}
+
+fn is_field_of(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    field_expr: &ast::FieldExpr,
+    strukt: hir::Struct,
+) -> bool {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, field_expr.syntax(), None);
+    analyzer.resolve_field(field_expr).map(|field| field.parent_def(db))
+        == Some(VariantDef::Struct(strukt))
+}
+
+fn to_named_usage(
+    edits: &UsageEdits,
+    usage: &Usage,
+    names: &[String],
+) -> Option<(TextRange, String)> {
+    let res = match usage {
+        Usage::Call(call) => {
+            let path = edits.render(call.expr()?.syntax().text_range());
+            let fields = call
+                .arg_list()?
+                .args()
+                .zip(names.iter())
+                .map(|(arg, name)| format!("{}: {}", name, edits.render(arg.syntax().text_range())))
+                .collect::<Vec<_>>();
+            (call.syntax().text_range(), named_fields(&path, fields))
+        }
+        Usage::TupleStructPat(pat) => {
+            let path = edits.render(pat.path()?.syntax().text_range());
+            let args: Vec<_> = pat.args().collect();
+            let dotdot = child_token(pat.syntax(), DOTDOT);
+            // The arguments after `..` match the last fields.
+            let before_dotdot = match &dotdot {
+                Some(dotdot) => args
+                    .iter()
+                    .take_while(|arg| {
+                        arg.syntax().text_range().start() < dotdot.text_range().start()
+                    })
+                    .count(),
+                None => args.len(),
+            };
+            let mut fields = args
+                .iter()
+                .enumerate()
+                .map(|(i, arg)| {
+                    let idx = if i < before_dotdot {
+                        i
+                    } else {
+                        (names.len() + i).checked_sub(args.len())?
+                    };
+                    Some(format!(
+                        "{}: {}",
+                        names.get(idx)?,
+                        edits.render(arg.syntax().text_range())
+                    ))
+                })
+                .collect::<Option<Vec<_>>>()?;
+            if dotdot.is_some() {
+                fields.push("..".to_string());
+            }
+            (pat.syntax().text_range(), named_fields(&path, fields))
+        }
+        Usage::Field(field_expr) => {
+            let name = names.get(tuple_field_index(field_expr)?)?;
+            (field_expr.index_token()?.text_range(), name.clone())
+        }
+        Usage::Value(_) | Usage::StructLit(_) | Usage::StructPat(_) => return None,
+    };
+    Some(res)
+}
+
+fn to_tuple_usage(
+    edits: &UsageEdits,
+    usage: &Usage,
+    names: &[String],
+) -> Option<(TextRange, String)> {
+    let res = match usage {
+        Usage::StructLit(lit) => {
+            let path = edits.render(lit.path()?.syntax().text_range());
+            let fields: Vec<_> = lit
+                .named_field_list()?
+                .fields()
+                .map(|field| {
+                    let name_ref = field.name_ref()?;
+                    let text = match field.expr() {
+                        Some(expr) => edits.render(expr.syntax().text_range()),
+                        None => name_ref.text().to_string(),
+                    };
+                    Some((name_ref.text().to_string(), text))
+                })
+                .collect::<Option<_>>()?;
+            let args = names
+                .iter()
+                .filter_map(|name| fields.iter().find(|(it, _)| it == name))
+                .map(|(_, text)| text);
+            (lit.syntax().text_range(), format!("{}({})", path, args.format(", ")))
+        }
+        Usage::StructPat(pat) => {
+            let path = edits.render(pat.path()?.syntax().text_range());
+            let field_pat_list = pat.field_pat_list()?;
+            let mut fields = Vec::new();
+            for field_pat in field_pat_list.field_pats() {
+                let name = field_pat.name()?.text().to_string();
+                fields.push((name, edits.render(field_pat.pat()?.syntax().text_range())));
+            }
+            for bind_pat in field_pat_list.bind_pats() {
+                let name = bind_pat.name()?.text().to_string();
+                fields.push((name, edits.render(bind_pat.syntax().text_range())));
+            }
+            let has_dotdot = child_token(field_pat_list.syntax(), DOTDOT).is_some();
+            let mut args: Vec<_> = names
+                .iter()
+                .map(|name| match fields.iter().find(|(it, _)| it == name) {
+                    Some((_, text)) => text.clone(),
+                    None => "_".to_string(),
+                })
+                .collect();
+            if has_dotdot {
+                let last_bound =
+                    names.iter().rposition(|name| fields.iter().any(|(it, _)| it == name));
+                args.truncate(last_bound.map_or(0, |it| it + 1));
+                if args.len() < names.len() {
+                    args.push("..".to_string());
+                }
+            }
+            (pat.syntax().text_range(), format!("{}({})", path, args.join(", ")))
+        }
+        Usage::Field(field_expr) => {
+            let name_ref = field_expr.name_ref()?;
+            let index = names.iter().position(|it| *it == name_ref.text().as_str())?;
+            (name_ref.syntax().text_range(), index.to_string())
+        }
+        Usage::Call(_) | Usage::Value(_) | Usage::TupleStructPat(_) => return None,
+    };
+    Some(res)
+}
+
+fn tuple_field_index(field_expr: &ast::FieldExpr) -> Option<usize> {
+    field_expr.index_token()?.text().parse().ok()
+}
+
+fn named_fields(path: &str, fields: Vec<String>) -> String {
+    if fields.is_empty() {
+        format!("{} {{}}", path)
+    } else {
+        format!("{} {{ {} }}", path, fields.join(", "))
+    }
+}
+
+fn child_token(node: &SyntaxNode, kind: SyntaxKind) -> Option<SyntaxToken> {
+    node.children_with_tokens().filter_map(|it| it.as_token().cloned()).find(|it| it.kind() == kind)
+}
+
+#[cfg(test)]
+mod tests {
+    use hir::mock::MockDatabase;
+    use ra_db::FileRange;
+
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_in_files, check_assist_not_applicable};
+
+    #[test]
+    fn convert_tuple_struct_updates_usages() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            r#"
+struct Inner;
+struct A<|>(pub u32, Inner);
+
+impl A {
+    fn new(inner: Inner) -> A {
+        A(0, inner)
+    }
+
+    fn from_self(self) -> Self {
+        let Self(x, inner) = self;
+        Self(x + 1, inner)
+    }
+}
+
+fn f(a: A) -> u32 {
+    match a {
+        A(0, ..) => a.0,
+        A(.., _) => 1,
+    }
+}
+"#,
+            r#"
+struct Inner;
+struct A<|> {
+    pub field1: u32,
+    field2: Inner,
+}
+
+impl A {
+    fn new(inner: Inner) -> A {
+        A { field1: 0, field2: inner }
+    }
+
+    fn from_self(self) -> Self {
+        let Self { field1: x, field2: inner } = self;
+        Self { field1: x + 1, field2: inner }
+    }
+}
+
+fn f(a: A) -> u32 {
+    match a {
+        A { field1: 0, .. } => a.field1,
+        A { field2: _, .. } => 1,
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_nested_constructors() {
+        check_assist(
+            convert_tuple_struct_to_named_struct,
+            r#"
+struct W<|>(Option<Box<W>>);
+
+fn f() -> W {
+    W(Some(Box::new(W(None))))
+}
+"#,
+            r#"
+struct W<|> {
+    field1: Option<Box<W>>,
+}
+
+fn f() -> W {
+    W { field1: Some(Box::new(W { field1: None })) }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_used_as_function() {
+        check_assist_not_applicable(
+            convert_tuple_struct_to_named_struct,
+            r#"
+struct A<|>(u32);
+
+fn f() -> Vec<A> {
+    vec![1, 2].into_iter().map(A).collect()
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_is_offered_without_searching_usages() {
+        let (db, position) = MockDatabase::with_position(
+            r#"
+//- /main.rs
+struct A<|>(u32);
+
+fn f() -> Vec<A> {
+    vec![1, 2].into_iter().map(A).collect()
+}
+"#,
+        );
+        let frange = FileRange {
+            file_id: position.file_id,
+            range: TextRange::offset_len(position.offset, 0.into()),
+        };
+        let assist = AssistCtx::with_ctx(&db, frange, false, convert_tuple_struct_to_named_struct);
+        assert!(assist.is_some());
+    }
+
+    #[test]
+    fn convert_named_struct_updates_usages() {
+        check_assist(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct A<|> {
+    pub a: u32,
+    b: bool,
+    c: char,
+}
+
+impl A {
+    fn new(c: char) -> A {
+        A { c, b: true, a: 0 }
+    }
+
+    fn a(&self) -> u32 {
+        let Self { a, .. } = self;
+        *a + self.a
+    }
+}
+
+fn f(a: A) -> bool {
+    match a {
+        A { c: 'x', b, .. } => b,
+        A { a: 0, b: _, c: _ } => a.b,
+        A { .. } => false,
+    }
+}
+"#,
+            r#"
+struct A<|>(pub u32, bool, char);
+
+impl A {
+    fn new(c: char) -> A {
+        A(0, true, c)
+    }
+
+    fn a(&self) -> u32 {
+        let Self(a, ..) = self;
+        *a + self.0
+    }
+}
+
+fn f(a: A) -> bool {
+    match a {
+        A(_, b, 'x') => b,
+        A(0, _, _) => a.1,
+        A(..) => false,
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_named_struct_with_base_literal() {
+        check_assist_not_applicable(
+            convert_named_struct_to_tuple_struct,
+            r#"
+struct A<|> { a: u32, b: u32 }
+
+fn f(a: A) -> A {
+    A { a: 0, ..a }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn convert_tuple_struct_in_other_files() {
+        check_assist_in_files(
+            convert_tuple_struct_to_named_struct,
+            r#"
+//- /lib.rs
+mod foo;
+pub struct Point<|>(pub i32, pub i32);
+
+//- /foo.rs
+use crate::Point;
+
+fn origin() -> Point {
+    Point(0, 0)
+}
+
+fn x(p: &Point) -> i32 {
+    p.0
+}
+"#,
+            r#"
+//- /lib.rs
+mod foo;
+pub struct Point {
+    pub field1: i32,
+    pub field2: i32,
+}
+
+//- /foo.rs
+use crate::Point;
+
+fn origin() -> Point {
+    Point { field1: 0, field2: 0 }
+}
+
+fn x(p: &Point) -> i32 {
+    p.field1
+}
+"#,
+        );
+    }
+}
@@ -0,0 +1,818 @@
+use hir::{
+    db::HirDatabase, ApplicationTy, Either, HasSource, HirDisplay, Mutability, Ty, TypeCtor,
+};
+use itertools::Itertools;
+use ra_fmt::{leading_indent, reindent};
+use ra_syntax::{
+    ast::{self, AstNode, BinOp, LoopBodyOwner, PathSegmentKind, SelfParamKind},
+    AstPtr, SyntaxElement,
+    SyntaxKind::*,
+    SyntaxNode, TextRange, TextUnit,
+};
+
+use crate::{Assist, AssistCtx, AssistId};
+
+const NEW_FN_NAME: &str = "fun_name";
+
+/// Extracts the selected expression or statements into a new function, which
+/// is inserted after the enclosing item. If the selection uses `self`, it is
+/// extracted into a method of the enclosing impl instead.
+///
+/// The locals used by the selection become parameters. They are passed by
+/// `&mut` if they are modified, by `&` if they are not trivially copyable and
+/// are used after the selection or by another iteration of a loop around it,
+/// and by value otherwise. The locals defined by the selection and used after
+/// it are returned, as a tuple if there are several of them. Selections with
+/// `return`, `?`, `break` or `continue` which leave the selection are not
+/// supported, and neither are selections which pass a local to a macro only.
+pub(crate) fn extract_function(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    if ctx.frange.range.is_empty() {
+        return None;
+    }
+    let body = extraction_body(&ctx)?;
+    let range = body.range();
+    let first_node = body.first_node();
+    let fn_def = first_node.ancestors().find_map(ast::FnDef::cast)?;
+    if !range.is_subrange(&fn_def.body()?.syntax().text_range()) {
+        return None;
+    }
+    if has_unsupported_control_flow(&body) {
+        return None;
+    }
+
+    let db = ctx.db;
+    let analyzer = hir::SourceAnalyzer::new(db, ctx.frange.file_id, &first_node, None);
+    let root = fn_def.syntax().ancestors().last()?;
+
+    let mut locals: Vec<Local> = Vec::new();
+    let mut self_usage = None;
+    for node in body.descendants() {
+        let (name_ref, usage, written) = if let Some(path_expr) = ast::PathExpr::cast(node.clone())
+        {
+            let path = match path_expr.path() {
+                Some(it) if it.qualifier().is_none() => it,
+                _ => continue,
+            };
+            let written = is_written(db, &analyzer, path_expr.syntax());
+            match path.segment().and_then(|it| it.kind()) {
+                Some(PathSegmentKind::Name(it)) => (it, Usage::Path(path_expr), written),
+                Some(PathSegmentKind::SelfKw) => {
+                    self_usage = Some(written || self_usage == Some(true));
+                    continue;
+                }
+                _ => continue,
+            }
+        } else if let Some(field) = ast::NamedField::cast(node) {
+            // A shorthand field `S { a }` uses the local `a`.
+            match (field.name_ref(), field.expr()) {
+                (Some(name_ref), None) => {
+                    (name_ref.clone(), Usage::ShorthandField(name_ref), false)
+                }
+                _ => continue,
+            }
+        } else {
+            continue;
+        };
+        let pat = match analyzer.resolve_local_name(&name_ref).map(|it| it.ptr()) {
+            Some(Either::A(it)) => it,
+            _ => continue,
+        };
+        if pat.syntax_node_ptr().range().is_subrange(&range) {
+            continue;
+        }
+        match locals.iter_mut().find(|it| it.pat == pat) {
+            Some(local) => {
+                local.written |= written;
+                local.usages.push(usage);
+            }
+            None => locals.push(Local {
+                name: name_ref.text().to_string(),
+                pat,
+                written,
+                usages: vec![usage],
+            }),
+        }
+    }
+    if passes_local_to_macro_only(&fn_def, &body, &locals) {
+        return None;
+    }
+
+    let is_method = self_usage.is_some();
+    let impl_item_list = fn_def.syntax().parent().filter(|it| it.kind() == ITEM_LIST);
+    if is_method
+        && impl_item_list.and_then(|it| it.parent()).map(|it| it.kind()) != Some(IMPL_BLOCK)
+    {
+        return None;
+    }
+
+    let mut params = Vec::new();
+    for local in locals {
+        let pat = local.pat.to_node(&root);
+        let ty = analyzer.type_of_pat(db, &pat)?;
+        if !is_nameable(&ty) {
+            return None;
+        }
+        let mode = if local.written {
+            if is_ref(&ty, Mutability::Mut) {
+                ParamMode::Value
+            } else {
+                ParamMode::MutRef
+            }
+        } else if !is_trivially_copy(&ty)
+            && (is_used_after(&analyzer, &pat, range) || is_in_loop_around(&body, &pat))
+        {
+            ParamMode::Ref
+        } else {
+            ParamMode::Value
+        };
+        params.push(Param { local, ty, mode });
+    }
+
+    let mut outputs = Vec::new();
+    if let Body::Stmts { .. } = &body {
+        for bind_pat in body.descendants().filter_map(ast::BindPat::cast) {
+            let pat = ast::Pat::from(bind_pat.clone());
+            if !is_used_after(&analyzer, &pat, range) {
+                continue;
+            }
+            let ty = analyzer.type_of_pat(db, &pat)?;
+            if !is_nameable(&ty) {
+                return None;
+            }
+            outputs.push((bind_pat.name()?.text().to_string(), bind_pat.is_mutable(), ty));
+        }
+    }
+    let ret_ty = match body.tail_expr() {
+        Some(expr) => {
+            if !outputs.is_empty() {
+                return None;
+            }
+            let ty = analyzer.type_of(db, &expr)?;
+            if !is_nameable(&ty) {
+                return None;
+            }
+            Some(ty)
+        }
+        None => None,
+    };
+
+    let (anchor, assist_id, label) = if is_method {
+        (fn_def.syntax().clone(), AssistId("extract_method"), "extract into method")
+    } else {
+        (top_level_item(&fn_def), AssistId("extract_function"), "extract into function")
+    };
+    ctx.add_action(assist_id, label, |edit| {
+        let indent = leading_indent(&anchor).unwrap_or_default();
+        let body_indent = format!("{}    ", indent);
+
+        let mut fn_text = String::new();
+        fn_text.push_str(&format!("\n\n{}fn {}(", indent, NEW_FN_NAME));
+        let mut param_list = Vec::new();
+        match self_usage {
+            Some(true) => param_list.push("&mut self".to_string()),
+            Some(false) => param_list.push("&self".to_string()),
+            None => (),
+        }
+        param_list.extend(params.iter().map(|param| {
+            let prefix = match param.mode {
+                ParamMode::Value => "",
+                ParamMode::Ref => "&",
+                ParamMode::MutRef => "&mut ",
+            };
+            format!("{}: {}{}", param.local.name, prefix, param.ty.display(db))
+        }));
+        fn_text.push_str(&format!("{})", param_list.join(", ")));
+        match &ret_ty {
+            Some(ty) if !is_unit(ty) => fn_text.push_str(&format!(" -> {}", ty.display(db))),
+            Some(_) => (),
+            None => match outputs.as_slice() {
+                [] => (),
+                [(_, _, ty)] => fn_text.push_str(&format!(" -> {}", ty.display(db))),
+                _ => fn_text.push_str(&format!(
+                    " -> ({})",
+                    outputs.iter().map(|(_, _, ty)| ty.display(db).to_string()).join(", ")
+                )),
+            },
+        }
+        fn_text.push_str(" {\n");
+        fn_text.push_str(&body_indent);
+        let body_text = body_text(&root, &body, &params);
+        fn_text.push_str(&reindent(&body_text, &body_indent));
+        match outputs.as_slice() {
+            [] => (),
+            [(name, _, _)] => fn_text.push_str(&format!("\n{}{}", body_indent, name)),
+            _ => fn_text.push_str(&format!(
+                "\n{}({})",
+                body_indent,
+                outputs.iter().map(|(name, _, _)| name).join(", ")
+            )),
+        }
+        fn_text.push_str(&format!("\n{}}}", indent));
+
+        let args = params
+            .iter()
+            .map(|param| match param.mode {
+                ParamMode::Value => param.local.name.clone(),
+                ParamMode::Ref => format!("&{}", param.local.name),
+                ParamMode::MutRef => format!("&mut {}", param.local.name),
+            })
+            .join(", ");
+        let receiver = if is_method { "self." } else { "" };
+        let call = format!("{}{}({})", receiver, NEW_FN_NAME, args);
+        let binding = |(name, is_mut, _): &(String, bool, Ty)| {
+            if *is_mut {
+                format!("mut {}", name)
+            } else {
+                name.clone()
+            }
+        };
+        let replacement = match (&body, outputs.as_slice()) {
+            (Body::Expr(_), _) | (Body::Stmts { tail: Some(_), .. }, _) => call,
+            (Body::Stmts { .. }, []) => format!("{};", call),
+            (Body::Stmts { .. }, [output]) => format!("let {} = {};", binding(output), call),
+            (Body::Stmts { .. }, _) => {
+                format!("let ({}) = {};", outputs.iter().map(binding).join(", "), call)
+            }
+        };
+
+        edit.target(range);
+        edit.replace(range, replacement);
+        edit.insert(anchor.text_range().end(), fn_text);
+        edit.set_cursor(range.start());
+    });
+
+    ctx.build()
+}
+
+/// The code which is extracted.
+enum Body {
+    /// A single expression.
+    Expr(ast::Expr),
+    /// Consecutive statements of a block, optionally followed by the tail
+    /// expression of the block.
+    Stmts { stmts: Vec<ast::Stmt>, tail: Option<ast::Expr> },
+}
+
+impl Body {
+    fn nodes(&self) -> Vec<SyntaxNode> {
+        match self {
+            Body::Expr(expr) => vec![expr.syntax().clone()],
+            Body::Stmts { stmts, tail } => stmts
+                .iter()
+                .map(|it| it.syntax().clone())
+                .chain(tail.iter().map(|it| it.syntax().clone()))
+                .collect(),
+        }
+    }
+
+    fn first_node(&self) -> SyntaxNode {
+        self.nodes()[0].clone()
+    }
+
+    fn range(&self) -> TextRange {
+        let nodes = self.nodes();
+        TextRange::from_to(nodes[0].text_range().start(), nodes.last().unwrap().text_range().end())
+    }
+
+    fn descendants(&self) -> impl Iterator<Item = SyntaxNode> {
+        self.nodes().into_iter().flat_map(|it| it.descendants())
+    }
+
+    fn tail_expr(&self) -> Option<ast::Expr> {
+        match self {
+            Body::Expr(expr) => Some(expr.clone()),
+            Body::Stmts { tail, .. } => tail.clone(),
+        }
+    }
+}
+
+/// A local which is defined outside of the extracted code and used inside it.
+struct Local {
+    name: String,
+    pat: AstPtr<ast::Pat>,
+    written: bool,
+    usages: Vec<Usage>,
+}
+
+enum Usage {
+    Path(ast::PathExpr),
+    /// The name of a shorthand field, as in `S { a }`.
+    ShorthandField(ast::NameRef),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ParamMode {
+    Value,
+    Ref,
+    MutRef,
+}
+
+struct Param {
+    local: Local,
+    ty: Ty,
+    mode: ParamMode,
+}
+
+/// Finds the expression or the statements which exactly cover the selection,
+/// ignoring surrounding whitespace.
+fn extraction_body(ctx: &AssistCtx<impl HirDatabase>) -> Option<Body> {
+    let range = trim_whitespace(ctx, ctx.frange.range)?;
+    let node = match ctx.covering_node_for_range(range) {
+        SyntaxElement::Node(it) => it,
+        SyntaxElement::Token(it) => it.parent(),
+    };
+    if node.text_range() == range {
+        if let Some(expr) = ast::Expr::cast(node.clone()) {
+            if expr.syntax().kind() == PATH_EXPR {
+                return None;
+            }
+            return Some(Body::Expr(expr));
+        }
+        if let Some(stmt) = ast::Stmt::cast(node.clone()) {
+            return Some(Body::Stmts { stmts: vec![stmt], tail: None });
+        }
+    }
+    let block = ast::Block::cast(node)?;
+    let stmts: Vec<ast::Stmt> =
+        block.statements().filter(|it| it.syntax().text_range().is_subrange(&range)).collect();
+    let tail = block.expr().filter(|it| it.syntax().text_range().is_subrange(&range));
+    let body = Body::Stmts { stmts, tail };
+    let nodes = body.nodes();
+    if nodes.is_empty() || body.range() != range {
+        return None;
+    }
+    Some(body)
+}
+
+fn trim_whitespace(ctx: &AssistCtx<impl HirDatabase>, range: TextRange) -> Option<TextRange> {
+    let mut start = range.start();
+    let mut end = range.end();
+    while start < end {
+        match ctx.covering_node_for_range(TextRange::offset_len(start, 1.into())) {
+            SyntaxElement::Token(it) if it.kind() == WHITESPACE => start = it.text_range().end(),
+            _ => break,
+        }
+    }
+    while start < end {
+        match ctx.covering_node_for_range(TextRange::from_to(end - TextUnit::from(1), end)) {
+            SyntaxElement::Token(it) if it.kind() == WHITESPACE => end = it.text_range().start(),
+            _ => break,
+        }
+    }
+    if start < end {
+        Some(TextRange::from_to(start, end))
+    } else {
+        None
+    }
+}
+
+/// Checks for `return`, `?`, `break` and `continue` which would leave the
+/// extracted code.
+fn has_unsupported_control_flow(body: &Body) -> bool {
+    let range = body.range();
+    let nested_in = |node: &SyntaxNode, kinds: &[_]| {
+        node.ancestors()
+            .skip(1)
+            .take_while(|it| it.text_range().is_subrange(&range))
+            .any(|it| kinds.contains(&it.kind()))
+    };
+    body.descendants().any(|node| match node.kind() {
+        RETURN_EXPR | TRY_EXPR => !nested_in(&node, &[LAMBDA_EXPR, FN_DEF]),
+        BREAK_EXPR | CONTINUE_EXPR => {
+            let has_label = node.children_with_tokens().any(|it| it.kind() == LIFETIME);
+            has_label || !nested_in(&node, &[LAMBDA_EXPR, FN_DEF, LOOP_EXPR, WHILE_EXPR, FOR_EXPR])
+        }
+        _ => false,
+    })
+}
+
+/// Checks if the expression `expr`, or a field or an element of it, is
+/// assigned to, mutably borrowed or used as the receiver of a `&mut self`
+/// method.
+fn is_written(db: &impl HirDatabase, analyzer: &hir::SourceAnalyzer, expr: &SyntaxNode) -> bool {
+    let mut expr = expr.clone();
+    while let Some(parent) = expr.parent() {
+        let is_base = match parent.kind() {
+            FIELD_EXPR | INDEX_EXPR => parent.first_child().as_ref() == Some(&expr),
+            _ => false,
+        };
+        if !is_base {
+            break;
+        }
+        expr = parent;
+    }
+    let parent = match expr.parent() {
+        Some(it) => it,
+        None => return false,
+    };
+    if let Some(bin_expr) = ast::BinExpr::cast(parent.clone()) {
+        let is_lhs = bin_expr.lhs().map_or(false, |lhs| lhs.syntax() == &expr);
+        return is_lhs && bin_expr.op_kind().map_or(false, is_assignment);
+    }
+    if let Some(ref_expr) = ast::RefExpr::cast(parent.clone()) {
+        return ref_expr.is_mut();
+    }
+    if let Some(method_call) = ast::MethodCallExpr::cast(parent) {
+        let is_receiver = method_call.expr().map_or(false, |it| it.syntax() == &expr);
+        return is_receiver
+            && analyzer
+                .resolve_method_call(&method_call)
+                .and_then(|func| func.source(db).ast.param_list()?.self_param())
+                .map_or(false, |self_param| self_param.kind() == SelfParamKind::MutRef);
+    }
+    false
+}
+
+fn is_assignment(op: BinOp) -> bool {
+    match op {
+        BinOp::Assignment
+        | BinOp::AddAssign
+        | BinOp::DivAssign
+        | BinOp::MulAssign
+        | BinOp::RemAssign
+        | BinOp::ShrAssign
+        | BinOp::ShlAssign
+        | BinOp::SubAssign
+        | BinOp::BitOrAssign
+        | BinOp::BitAndAssign
+        | BinOp::BitXorAssign => true,
+        _ => false,
+    }
+}
+
+/// Checks if an identifier passed to a macro in the extracted code is spelled
+/// like a local defined outside of it which isn't otherwise used. The macro
+/// may use that local, which the new function wouldn't have.
+fn passes_local_to_macro_only(fn_def: &ast::FnDef, body: &Body, locals: &[Local]) -> bool {
+    let range = body.range();
+    let outer_names: Vec<_> = fn_def
+        .syntax()
+        .descendants()
+        .filter_map(ast::BindPat::cast)
+        .filter(|it| !it.syntax().text_range().is_subrange(&range))
+        .filter_map(|it| Some(it.name()?.text().clone()))
+        .collect();
+    body.descendants()
+        .filter(|it| it.kind() == TOKEN_TREE)
+        .flat_map(|it| it.descendants_with_tokens())
+        .filter_map(|it| it.into_token())
+        .filter(|it| it.kind() == IDENT)
+        .any(|ident| {
+            outer_names.contains(ident.text())
+                && !locals.iter().any(|local| local.name == ident.text().as_str())
+        })
+}
+
+/// Checks if the extracted code is in the body of a loop which doesn't define
+/// `pat`, so that each iteration of the loop uses the same local.
+fn is_in_loop_around(body: &Body, pat: &ast::Pat) -> bool {
+    let pat_range = pat.syntax().text_range();
+    body.first_node()
+        .ancestors()
+        .take_while(|it| it.kind() != FN_DEF && it.kind() != LAMBDA_EXPR)
+        .filter_map(|it| match it.kind() {
+            LOOP_EXPR => ast::LoopExpr::cast(it)?.loop_body(),
+            WHILE_EXPR => ast::WhileExpr::cast(it)?.loop_body(),
+            FOR_EXPR => ast::ForExpr::cast(it)?.loop_body(),
+            _ => None,
+        })
+        .any(|loop_body| {
+            let loop_range = loop_body.syntax().text_range();
+            body.range().is_subrange(&loop_range) && !pat_range.is_subrange(&loop_range)
+        })
+}
+
+fn is_used_after(analyzer: &hir::SourceAnalyzer, pat: &ast::Pat, range: TextRange) -> bool {
+    let bind_pat = match ast::BindPat::cast(pat.syntax().clone()) {
+        Some(it) => it,
+        None => return false,
+    };
+    analyzer.find_all_refs(&bind_pat).iter().any(|it| it.range.start() >= range.end())
+}
+
+/// Builds the body of the new function: the extracted code, without its
+/// original indentation and with the parameters passed by reference
+/// dereferenced where needed.
+fn body_text(root: &SyntaxNode, body: &Body, params: &[Param]) -> String {
+    let range = body.range();
+    let mut edits = Vec::new();
+    for param in params.iter().filter(|it| it.mode != ParamMode::Value) {
+        let name = &param.local.name;
+        for usage in param.local.usages.iter() {
+            match usage {
+                Usage::Path(it) if !is_auto_deref(it.syntax()) => {
+                    edits.push((it.syntax().text_range(), format!("*{}", name)))
+                }
+                Usage::Path(_) => (),
+                Usage::ShorthandField(it) => {
+                    edits.push((it.syntax().text_range(), format!("{}: *{}", name, name)))
+                }
+            }
+        }
+    }
+    edits.sort_by_key(|(range, _)| range.start());
+
+    let mut text = root.text().slice(range).to_string();
+    for (edit_range, replacement) in edits.into_iter().rev() {
+        let start = (edit_range.start() - range.start()).to_usize();
+        let end = (edit_range.end() - range.start()).to_usize();
+        text.replace_range(start..end, &replacement);
+    }
+
+    let indent = leading_indent(&body.first_node()).unwrap_or_default();
+    text.lines()
+        .enumerate()
+        .map(
+            |(i, line)| {
+                if i > 0 && line.starts_with(indent.as_str()) {
+                    &line[indent.len()..]
+                } else {
+                    line
+                }
+            },
+        )
+        .join("\n")
+}
+
+/// Checks if a reference passed in place of `expr` is dereferenced
+/// automatically, because `expr` is the receiver of a method call or of a
+/// field access.
+fn is_auto_deref(expr: &SyntaxNode) -> bool {
+    match expr.parent() {
+        Some(parent) => match parent.kind() {
+            FIELD_EXPR | METHOD_CALL_EXPR => parent.first_child().as_ref() == Some(expr),
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+/// The item which contains `fn_def` and is not nested inside of another item
+/// other than a module.
+fn top_level_item(fn_def: &ast::FnDef) -> SyntaxNode {
+    fn_def
+        .syntax()
+        .ancestors()
+        .find(|it| match it.parent() {
+            Some(parent) => {
+                parent.kind() == SOURCE_FILE
+                    || (parent.kind() == ITEM_LIST
+                        && parent.parent().map(|it| it.kind()) == Some(MODULE))
+            }
+            None => true,
+        })
+        .unwrap_or_else(|| fn_def.syntax().clone())
+}
+
+/// Checks if `ty` is fully known and can be written down in the signature of
+/// the new function.
+fn is_nameable(ty: &Ty) -> bool {
+    match ty {
+        Ty::Apply(ApplicationTy { ctor: TypeCtor::FnDef(_), .. }) => false,
+        Ty::Apply(a_ty) => a_ty.parameters.iter().all(is_nameable),
+        _ => false,
+    }
+}
+
+fn is_trivially_copy(ty: &Ty) -> bool {
+    match ty {
+        Ty::Apply(a_ty) => match a_ty.ctor {
+            TypeCtor::Bool | TypeCtor::Char | TypeCtor::Int(_) | TypeCtor::Float(_) => true,
+            TypeCtor::Ref(Mutability::Shared) => true,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+fn is_ref(ty: &Ty, mutability: Mutability) -> bool {
+    match ty {
+        Ty::Apply(a_ty) => a_ty.ctor == TypeCtor::Ref(mutability),
+        _ => false,
+    }
+}
+
+fn is_unit(ty: &Ty) -> bool {
+    match ty {
+        Ty::Apply(a_ty) => a_ty.ctor == TypeCtor::Tuple { cardinality: 0 },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist_range, check_assist_range_not_applicable};
+
+    #[test]
+    fn extract_expression() {
+        check_assist_range(
+            extract_function,
+            "
+fn foo() {
+    let a = 1;
+    let b = <|>a + 2<|>;
+}",
+            "
+fn foo() {
+    let a = 1;
+    let b = <|>fun_name(a);
+}
+
+fn fun_name(a: i32) -> i32 {
+    a + 2
+}",
+        );
+    }
+
+    #[test]
+    fn extract_statements_with_mutated_local() {
+        check_assist_range(
+            extract_function,
+            "
+struct S;
+impl S {
+    fn get(&self) -> i32 { 0 }
+}
+fn foo() {
+    let mut n = 1;
+    let s = S;
+    <|>n += s.get();
+    n += 1;<|>
+    s.get();
+    n;
+}",
+            "
+struct S;
+impl S {
+    fn get(&self) -> i32 { 0 }
+}
+fn foo() {
+    let mut n = 1;
+    let s = S;
+    <|>fun_name(&mut n, &s);
+    s.get();
+    n;
+}
+
+fn fun_name(n: &mut i32, s: &S) {
+    *n += s.get();
+    *n += 1;
+}",
+        );
+    }
+
+    #[test]
+    fn extract_statements_returning_locals() {
+        check_assist_range(
+            extract_function,
+            "
+fn foo() {
+    <|>let a = 1;
+    let mut b = 2;<|>
+    b += a;
+}",
+            "
+fn foo() {
+    <|>let (a, mut b) = fun_name();
+    b += a;
+}
+
+fn fun_name() -> (i32, i32) {
+    let a = 1;
+    let mut b = 2;
+    (a, b)
+}",
+        );
+    }
+
+    #[test]
+    fn extract_into_method() {
+        check_assist_range(
+            extract_function,
+            "
+struct S { n: u32 }
+impl S {
+    fn foo(&mut self) {
+        <|>self.n += 1;<|>
+    }
+}",
+            "
+struct S { n: u32 }
+impl S {
+    fn foo(&mut self) {
+        <|>self.fun_name();
+    }
+
+    fn fun_name(&mut self) {
+        self.n += 1;
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn extract_expression_with_shorthand_field() {
+        check_assist_range(
+            extract_function,
+            "
+struct P { x: i32 }
+fn foo() {
+    let x = 1;
+    let p = <|>P { x }<|>;
+}",
+            "
+struct P { x: i32 }
+fn foo() {
+    let x = 1;
+    let p = <|>fun_name(x);
+}
+
+fn fun_name(x: i32) -> P {
+    P { x }
+}",
+        );
+    }
+
+    #[test]
+    fn extract_statements_borrows_locals_used_by_a_loop() {
+        check_assist_range(
+            extract_function,
+            "
+struct N;
+impl N {
+    fn get(&self) -> i32 { 0 }
+}
+fn foo() {
+    let n = N;
+    loop {
+        <|>n.get();<|>
+    }
+}",
+            "
+struct N;
+impl N {
+    fn get(&self) -> i32 { 0 }
+}
+fn foo() {
+    let n = N;
+    loop {
+        <|>fun_name(&n);
+    }
+}
+
+fn fun_name(n: &N) {
+    n.get();
+}",
+        );
+    }
+
+    #[test]
+    fn extract_function_not_applicable_with_local_passed_to_macro() {
+        check_assist_range_not_applicable(
+            extract_function,
+            "
+fn foo() {
+    let a = 1;
+    <|>println!(\"{}\", a);<|>
+}",
+        );
+    }
+
+    #[test]
+    fn extract_function_not_applicable_with_return() {
+        check_assist_range_not_applicable(
+            extract_function,
+            "
+fn foo(n: u32) -> u32 {
+    <|>if n > 0 {
+        return 1;
+    }<|>
+    n
+}",
+        );
+    }
+
+    #[test]
+    fn extract_function_allows_break_inside_selected_loop() {
+        check_assist_range(
+            extract_function,
+            "
+fn foo() {
+    <|>loop {
+        break;
+    }<|>
+}",
+            "
+fn foo() {
+    <|>fun_name()
+}
+
+fn fun_name() {
+    loop {
+        break;
+    }
+}",
+        );
+    }
+}
@@ -0,0 +1,314 @@
+use hir::{db::HirDatabase, source_binder, ModuleDef, PathResolution};
+use ra_db::SourceDatabase;
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    ast::{self, AstNode, AttrsOwner, NameOwner, StructKind, TypeParamsOwner, VisibilityOwner},
+    SyntaxNode, TextRange, TextUnit,
+};
+
+use crate::{
+    ast_editor::AstBuilder,
+    search::{self, Usage, UsageEdits},
+    utils::item_path,
+    Assist, AssistCtx, AssistId,
+};
+
+/// Extracts the fields of an enum variant into a new struct named after the
+/// variant, which the variant then wraps. Constructors and patterns of the
+/// variant are updated across the workspace, so that `E::V(a, b)` becomes
+/// `E::V(V(a, b))`. The assist is not available for generic enums, or if a
+/// type with the name of the variant is already in scope.
+pub(crate) fn extract_struct_from_enum_variant(
+    mut ctx: AssistCtx<impl HirDatabase>,
+) -> Option<Assist> {
+    let variant = ctx.node_at_offset::<ast::EnumVariant>()?;
+    let name = variant.name()?;
+    let field_list = match variant.kind() {
+        StructKind::Tuple(it) if it.fields().next().is_some() => it.syntax().clone(),
+        StructKind::Named(it) if it.fields().next().is_some() => it.syntax().clone(),
+        _ => return None,
+    };
+    if ctx.frange.range.start() > field_list.text_range().start() {
+        return None;
+    }
+    let enum_def = variant.parent_enum();
+    if enum_def.type_param_list().is_some() {
+        return None;
+    }
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let module = source_binder::module_from_child_node(db, file_id, enum_def.syntax())?;
+    let hir_variant = source_binder::enum_from_module(db, module, &enum_def)
+        .variants(db)
+        .into_iter()
+        .find(|it| it.name(db).map_or(false, |it| it.to_string() == name.text().as_str()))?;
+
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, enum_def.syntax(), None);
+    if analyzer.resolve_path(db, &AstBuilder::<ast::Path>::from_text(name.text())).is_some() {
+        return None;
+    }
+
+    // The usages are only searched for when the edit is computed.
+    let usage_edits = if ctx.should_compute_edit() {
+        let usages =
+            search::find_constructor_usages(db, &[name.text().as_str()], |res| match res {
+                PathResolution::Def(ModuleDef::EnumVariant(it)) => it == hir_variant,
+                _ => false,
+            });
+        search::edit_usages(db, usages, |edits, usage_file_id, usage| {
+            let struct_path =
+                item_path(db, module, usage_file_id, usage.syntax(), name.text().as_str())?;
+            wrap_usage(edits, usage, &struct_path)
+        })?
+    } else {
+        Vec::new()
+    };
+
+    let text = db.file_text(file_id);
+    let new_struct = struct_text(&text, &enum_def, &variant)?;
+    let indent = leading_indent(enum_def.syntax()).unwrap_or_default();
+
+    ctx.add_action(
+        AssistId("extract_struct_from_enum_variant"),
+        "extract struct from enum variant",
+        |edit| {
+            edit.target(variant.syntax().text_range());
+            edit.insert(
+                enum_def.syntax().text_range().start(),
+                format!("{}\n\n{}", new_struct, indent),
+            );
+            edit.replace(field_list.text_range(), format!("({})", name.text()));
+            for (file_id, range, text) in usage_edits {
+                edit.replace_in(file_id, range, text);
+            }
+        },
+    );
+    ctx.build()
+}
+
+/// Builds the new struct. It gets the visibility and the derives of the enum,
+/// and its fields are as visible as the struct.
+fn struct_text(text: &str, enum_def: &ast::EnumDef, variant: &ast::EnumVariant) -> Option<String> {
+    let vis =
+        enum_def.visibility().map(|it| format!("{} ", it.syntax().text())).unwrap_or_default();
+    let indent = leading_indent(enum_def.syntax()).unwrap_or_default();
+    let mut buf = String::new();
+    for attr in enum_def.attrs() {
+        if attr.as_call().map_or(false, |(name, _)| name == "derive") {
+            buf.push_str(&format!("{}\n{}", attr.syntax().text(), indent));
+        }
+    }
+    let with_vis = |field: &SyntaxNode, offset: TextUnit| {
+        let range = field.text_range();
+        format!(
+            "{}{}{}",
+            &text[TextRange::from_to(range.start(), offset)],
+            vis,
+            &text[TextRange::from_to(offset, range.end())]
+        )
+    };
+    match variant.kind() {
+        StructKind::Tuple(list) => {
+            let fields = list
+                .fields()
+                .map(|it| Some(with_vis(it.syntax(), it.type_ref()?.syntax().text_range().start())))
+                .collect::<Option<Vec<_>>>()?;
+            buf.push_str(&format!(
+                "{}struct {}({});",
+                vis,
+                variant.name()?.text(),
+                fields.join(", ")
+            ));
+        }
+        StructKind::Named(list) => {
+            buf.push_str(&format!("{}struct {} {{\n", vis, variant.name()?.text()));
+            for field in list.fields() {
+                let field = with_vis(field.syntax(), field.name()?.syntax().text_range().start());
+                buf.push_str(&format!("{}    {},\n", indent, field));
+            }
+            buf.push_str(&format!("{}}}", indent));
+        }
+        StructKind::Unit => return None,
+    }
+    Some(buf)
+}
+
+/// Wraps the arguments of a constructor or a pattern into the new struct.
+fn wrap_usage(edits: &UsageEdits, usage: &Usage, struct_path: &str) -> Option<(TextRange, String)> {
+    let path = match usage {
+        Usage::Call(call) => call.expr()?.syntax().text_range(),
+        Usage::StructLit(lit) => {
+            if lit.named_field_list()?.spread().is_some() {
+                return None;
+            }
+            lit.path()?.syntax().text_range()
+        }
+        Usage::TupleStructPat(pat) => pat.path()?.syntax().text_range(),
+        Usage::StructPat(pat) => pat.path()?.syntax().text_range(),
+        Usage::Value(_) | Usage::Field(_) => return None,
+    };
+    let range = usage.syntax().text_range();
+    let fields = edits.render(TextRange::from_to(path.end(), range.end()));
+    Some((range, format!("{}({}{})", edits.render(path), struct_path, fields)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_in_files, check_assist_not_applicable};
+
+    #[test]
+    fn extract_tuple_variant() {
+        check_assist(
+            extract_struct_from_enum_variant,
+            r#"
+enum E {
+    A<|>(u32, String),
+    B { x: i32 },
+}
+
+fn f(e: E) -> u32 {
+    match e {
+        E::A(n, _) => n,
+        E::B { .. } => 0,
+    }
+}
+
+fn g() -> E {
+    E::A(1, String::new())
+}
+"#,
+            r#"
+struct A(u32, String);
+
+enum E {
+    A<|>(A),
+    B { x: i32 },
+}
+
+fn f(e: E) -> u32 {
+    match e {
+        E::A(A(n, _)) => n,
+        E::B { .. } => 0,
+    }
+}
+
+fn g() -> E {
+    E::A(A(1, String::new()))
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn extract_named_variant() {
+        check_assist(
+            extract_struct_from_enum_variant,
+            r#"
+#[derive(Debug, Clone)]
+pub enum E {
+    V<|> { a: u32, b: bool },
+}
+
+fn f(e: E) -> bool {
+    match e {
+        E::V { b, .. } => b,
+    }
+}
+
+fn g() -> E {
+    E::V { a: 1, b: true }
+}
+"#,
+            r#"
+#[derive(Debug, Clone)]
+pub struct V {
+    pub a: u32,
+    pub b: bool,
+}
+
+#[derive(Debug, Clone)]
+pub enum E {
+    V<|>(V),
+}
+
+fn f(e: E) -> bool {
+    match e {
+        E::V(V { b, .. }) => b,
+    }
+}
+
+fn g() -> E {
+    E::V(V { a: 1, b: true })
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn extract_variant_in_other_modules() {
+        check_assist_in_files(
+            extract_struct_from_enum_variant,
+            r#"
+//- /lib.rs
+mod foo;
+pub mod m {
+    pub enum E {
+        V<|>(u32),
+    }
+}
+
+//- /foo.rs
+use crate::m::E;
+
+fn f() -> E {
+    E::V(0)
+}
+"#,
+            r#"
+//- /lib.rs
+mod foo;
+pub mod m {
+    pub struct V(pub u32);
+
+    pub enum E {
+        V(V),
+    }
+}
+
+//- /foo.rs
+use crate::m::E;
+
+fn f() -> E {
+    E::V(crate::m::V(0))
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn extract_variant_name_taken() {
+        check_assist_not_applicable(
+            extract_struct_from_enum_variant,
+            r#"
+struct A;
+
+enum E {
+    A<|>(u32),
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn extract_variant_of_generic_enum() {
+        check_assist_not_applicable(
+            extract_struct_from_enum_variant,
+            r#"
+enum E<T> {
+    A<|>(T),
+}
+"#,
+        );
+    }
+}
@@ -1,10 +1,23 @@
-use itertools::Itertools;
-use std::fmt::Write;
-
-use hir::{db::HirDatabase, AdtDef, FieldSource, HasSource};
-use ra_syntax::ast::{self, AstNode};
+use std::iter::successors;
 
-use crate::{Assist, AssistCtx, AssistId};
+use hir::{
+    db::HirDatabase, AdtDef, FieldSource, HasSource, MissingPat, Module, ModuleDef, PathResolution,
+    SourceAnalyzer, StructField, VariantDef,
+};
+use itertools::Itertools;
+use ra_db::FileId;
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    ast::{self, AstNode},
+    SyntaxKind::{self, WHITESPACE},
+    SyntaxNode, TextUnit, T,
+};
+
+use crate::{
+    ast_editor::AstBuilder,
+    utils::{item_path, to_snake_case},
+    Assist, AssistCtx, AssistId,
+};
 
 fn is_trivial_arm(arm: &ast::MatchArm) -> bool {
     fn single_pattern(arm: &ast::MatchArm) -> Option<ast::PatKind> {
@@ -19,82 +32,203 @@ fn is_trivial_arm(arm: &ast::MatchArm) -> bool {
 
 pub(crate) fn fill_match_arms(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
     let match_expr = ctx.node_at_offset::<ast::MatchExpr>()?;
+    let arms: Vec<_> = match_expr.match_arm_list().into_iter().flat_map(|it| it.arms()).collect();
+
+    // If there are no arms, or only a trivial one possibly created by the
+    // match postfix completion, the whole match is rewritten. Trivial match
+    // arm is the catch all arm.
+    let rewrite_match = match arms.as_slice() {
+        [] => true,
+        [arm] => is_trivial_arm(arm),
+        _ => false,
+    };
 
-    // We already have some match arms, so we don't provide any assists.
-    // Unless if there is only one trivial match arm possibly created
-    // by match postfix complete. Trivial match arm is the catch all arm.
-    if let Some(arm_list) = match_expr.match_arm_list() {
-        let mut arm_iter = arm_list.arms();
-        let first = arm_iter.next();
-
-        match &first {
-            // If there arm list is empty or there is only one trivial arm, then proceed.
-            Some(arm) if is_trivial_arm(arm) => {
-                if arm_iter.next() != None {
-                    return None;
-                }
-            }
-            None => {}
+    let expr = match_expr.expr()?;
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let analyzer = SourceAnalyzer::new(db, file_id, expr.syntax(), None);
+    let missing = analyzer.missing_match_arms(db, &match_expr, |_| rewrite_match)?;
+    if missing.is_empty() {
+        return None;
+    }
+    let mut renderer = PatRenderer {
+        db,
+        analyzer: &analyzer,
+        file_id,
+        node: match_expr.syntax(),
+        bindings: Vec::new(),
+    };
+    let mut new_arms = Vec::new();
+    for pat in missing.iter() {
+        renderer.bindings.clear();
+        new_arms.push(format!("{} => (),", renderer.render(pat)?));
+    }
 
+    ctx.add_action(AssistId("fill_match_arms"), "fill match arms", |edit| {
+        edit.target(match_expr.syntax().text_range());
+        edit.set_cursor(expr.syntax().text_range().start());
+        let last_arm = match arms.last() {
+            Some(it) if !rewrite_match => it,
             _ => {
-                return None;
+                let mut buf = format!("match {} {{\n", expr.syntax().text());
+                for arm in new_arms.iter() {
+                    buf.push_str(&format!("    {}\n", arm));
+                }
+                buf.push_str("}");
+                edit.replace_node_and_indent(match_expr.syntax(), buf);
+                return;
             }
+        };
+
+        // The new arms go after the last one, which needs a comma unless its
+        // body is a block.
+        let indent = leading_indent(last_arm.syntax()).unwrap_or_default();
+        let mut offset = last_arm.syntax().text_range().end();
+        let mut buf = String::new();
+        if next_non_whitespace(last_arm.syntax()) == Some(T![,]) {
+            offset += TextUnit::of_char(',');
+        } else if last_arm.expr().map(|it| it.syntax().kind()) != Some(SyntaxKind::BLOCK_EXPR) {
+            buf.push(',');
         }
-    };
+        for arm in new_arms.iter() {
+            buf.push_str(&format!("\n{}{}", indent, arm));
+        }
+        edit.insert(offset, buf);
+    });
 
-    let expr = match_expr.expr()?;
-    let analyzer = hir::SourceAnalyzer::new(ctx.db, ctx.frange.file_id, expr.syntax(), None);
-    let match_expr_ty = analyzer.type_of(ctx.db, &expr)?;
-    let enum_def = analyzer.autoderef(ctx.db, match_expr_ty).find_map(|ty| match ty.as_adt() {
-        Some((AdtDef::Enum(e), _)) => Some(e),
-        _ => None,
-    })?;
-    let enum_name = enum_def.name(ctx.db)?;
-    let db = ctx.db;
+    ctx.build()
+}
 
-    ctx.add_action(AssistId("fill_match_arms"), "fill match arms", |edit| {
-        let mut buf = format!("match {} {{\n", expr.syntax().text().to_string());
-        let variants = enum_def.variants(db);
-        for variant in variants {
-            let name = match variant.name(db) {
-                Some(it) => it,
-                None => continue,
-            };
-            write!(&mut buf, "    {}::{}", enum_name, name.to_string()).unwrap();
-
-            let pat = variant
-                .fields(db)
-                .into_iter()
-                .map(|field| {
-                    let name = field.name(db).to_string();
-                    let src = field.source(db);
-                    match src.ast {
-                        FieldSource::Named(_) => name,
-                        FieldSource::Pos(_) => "_".to_string(),
+fn next_non_whitespace(node: &SyntaxNode) -> Option<SyntaxKind> {
+    successors(node.next_sibling_or_token(), |it| it.next_sibling_or_token())
+        .map(|it| it.kind())
+        .find(|it| *it != WHITESPACE)
+}
+
+/// Renders the patterns of missing arms. The paths to the variants are
+/// qualified as much as needed in the scope of the `match`, and the fields of
+/// the variants are bound.
+struct PatRenderer<'a, DB> {
+    db: &'a DB,
+    analyzer: &'a SourceAnalyzer,
+    file_id: FileId,
+    node: &'a SyntaxNode,
+    /// The names bound by the pattern being rendered.
+    bindings: Vec<String>,
+}
+
+impl<'a, DB: HirDatabase> PatRenderer<'a, DB> {
+    fn render(&mut self, pat: &MissingPat) -> Option<String> {
+        let res = match pat {
+            MissingPat::Wild => "_".to_string(),
+            MissingPat::Bool(value) => value.to_string(),
+            MissingPat::Tuple(fields) => {
+                let fields = fields.iter().map(|it| self.render(it)).collect::<Option<Vec<_>>>()?;
+                match fields.as_slice() {
+                    [field] => format!("({},)", field),
+                    _ => format!("({})", fields.join(", ")),
+                }
+            }
+            MissingPat::Variant(variant, fields) => {
+                let path = self.variant_path(*variant)?;
+                let hir_fields = variant.fields(self.db);
+                let is_named = match hir_fields.first() {
+                    Some(field) => match field.source(self.db).ast {
+                        FieldSource::Named(_) => true,
+                        FieldSource::Pos(_) => false,
+                    },
+                    None => return Some(path),
+                };
+                let mut buf = Vec::new();
+                for (field, pat) in hir_fields.iter().zip(fields) {
+                    let name = field.name(self.db).to_string();
+                    let pat = match pat {
+                        MissingPat::Wild if is_named => self.fresh_binding(&name),
+                        MissingPat::Wild => {
+                            let base = self.binding_base(field);
+                            self.fresh_binding(&base)
+                        }
+                        _ => self.render(pat)?,
+                    };
+                    if is_named && pat != name {
+                        buf.push(format!("{}: {}", name, pat));
+                    } else {
+                        buf.push(pat);
                     }
-                })
-                .collect::<Vec<_>>();
+                }
+                if is_named {
+                    format!("{} {{ {} }}", path, buf.join(", "))
+                } else {
+                    format!("{}({})", path, buf.join(", "))
+                }
+            }
+        };
+        Some(res)
+    }
 
-            match pat.first().map(|s| s.as_str()) {
-                Some("_") => write!(&mut buf, "({})", pat.join(", ")).unwrap(),
-                Some(_) => write!(&mut buf, "{{{}}}", pat.join(", ")).unwrap(),
-                None => (),
-            };
+    fn variant_path(&self, variant: VariantDef) -> Option<String> {
+        match variant {
+            VariantDef::Struct(it) => {
+                let name = it.name(self.db)?.to_string();
+                self.item_path(ModuleDef::Struct(it), &name, it.module(self.db))
+            }
+            VariantDef::EnumVariant(it) => {
+                let name = it.name(self.db)?.to_string();
+                if self.resolves_to(&name, ModuleDef::EnumVariant(it)) {
+                    return Some(name);
+                }
+                let enum_def = it.parent_enum(self.db);
+                let enum_name = enum_def.name(self.db)?.to_string();
+                let enum_path = self.item_path(
+                    ModuleDef::Enum(enum_def),
+                    &enum_name,
+                    enum_def.module(self.db),
+                )?;
+                Some(format!("{}::{}", enum_path, name))
+            }
+        }
+    }
 
-            buf.push_str(" => (),\n");
+    fn item_path(&self, def: ModuleDef, name: &str, def_module: Module) -> Option<String> {
+        if self.resolves_to(name, def) {
+            return Some(name.to_string());
         }
-        buf.push_str("}");
-        edit.target(match_expr.syntax().text_range());
-        edit.set_cursor(expr.syntax().text_range().start());
-        edit.replace_node_and_indent(match_expr.syntax(), buf);
-    });
+        item_path(self.db, def_module, self.file_id, self.node, name)
+    }
 
-    ctx.build()
+    fn resolves_to(&self, name: &str, def: ModuleDef) -> bool {
+        let path = AstBuilder::<ast::Path>::from_text(name);
+        self.analyzer.resolve_path(self.db, &path) == Some(PathResolution::Def(def))
+    }
+
+    /// The name of the binding of a positional field, after its type.
+    fn binding_base(&self, field: &StructField) -> String {
+        let name = match field.ty(self.db).as_adt() {
+            Some((AdtDef::Struct(it), _)) => it.name(self.db),
+            Some((AdtDef::Enum(it), _)) => it.name(self.db),
+            _ => None,
+        };
+        match name.map(|it| to_snake_case(&it.to_string())) {
+            Some(it) if SyntaxKind::from_keyword(&it).is_none() => it,
+            _ => "it".to_string(),
+        }
+    }
+
+    fn fresh_binding(&mut self, base: &str) -> String {
+        let mut name = base.to_string();
+        let mut idx = 1;
+        while self.bindings.contains(&name) {
+            name = format!("{}{}", base, idx);
+            idx += 1;
+        }
+        self.bindings.push(name.clone());
+        name
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::helpers::{check_assist, check_assist_target};
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
 
     use super::fill_match_arms;
 
@@ -130,9 +264,9 @@ mod tests {
                 match <|>a {
                     A::As => (),
                     A::Bs => (),
-                    A::Cs(_) => (),
-                    A::Ds(_, _) => (),
-                    A::Es{x, y} => (),
+                    A::Cs(it) => (),
+                    A::Ds(it, it1) => (),
+                    A::Es { x, y } => (),
                 }
             }
             "#,
@@ -185,7 +319,7 @@ mod tests {
 
             fn foo(a: &mut A) {
                 match <|>a {
-                    A::Es{x, y} => (),
+                    A::Es { x, y } => (),
                 }
             }
             "#,
@@ -237,6 +371,106 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fill_match_arms_partial() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            enum E { A, B, C }
+
+            fn main() {
+                match E::A<|> {
+                    E::A => {}
+                    E::C => ()
+                }
+            }
+            "#,
+            r#"
+            enum E { A, B, C }
+
+            fn main() {
+                match <|>E::A {
+                    E::A => {}
+                    E::C => (),
+                    E::B => (),
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_nested() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            enum Option<T> { None, Some(T) }
+            use Option::*;
+            enum Dir { Up, Down }
+
+            fn main(dir: Option<Dir>) {
+                match dir<|> {
+                    Some(Dir::Up) => (),
+                }
+            }
+            "#,
+            r#"
+            enum Option<T> { None, Some(T) }
+            use Option::*;
+            enum Dir { Up, Down }
+
+            fn main(dir: Option<Dir>) {
+                match <|>dir {
+                    Some(Dir::Up) => (),
+                    None => (),
+                    Some(Dir::Down) => (),
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_bool_tuple() {
+        check_assist(
+            fill_match_arms,
+            r#"
+            fn main(a: bool, b: bool) {
+                match (a, b)<|> {
+                    (true, _) => (),
+                    (_, true) => (),
+                }
+            }
+            "#,
+            r#"
+            fn main(a: bool, b: bool) {
+                match <|>(a, b) {
+                    (true, _) => (),
+                    (_, true) => (),
+                    (false, false) => (),
+                }
+            }
+            "#,
+        );
+    }
+
+    #[test]
+    fn fill_match_arms_exhaustive() {
+        check_assist_not_applicable(
+            fill_match_arms,
+            r#"
+            enum E { X, Y }
+
+            fn main() {
+                match E::X<|> {
+                    E::X => (),
+                    E::Y => (),
+                }
+            }
+            "#,
+        );
+    }
+
     #[test]
     fn fill_match_arms_target() {
         check_assist_target(
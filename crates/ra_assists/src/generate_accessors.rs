@@ -0,0 +1,415 @@
+use hir::{db::HirDatabase, source_binder};
+use itertools::Itertools;
+use ra_fmt::{leading_indent, reindent};
+use ra_syntax::{
+    ast::{self, AstNode, NameOwner, TypeAscriptionOwner, VisibilityOwner},
+    TextUnit,
+};
+
+use crate::{
+    add_impl::impl_header,
+    assist_ctx::AssistBuilder,
+    ast_editor::{AstBuilder, AstEditor},
+    Assist, AssistCtx, AssistId,
+};
+
+/// A named field of the struct the methods are generated for.
+struct Field {
+    name: String,
+    ty: String,
+    is_copy: bool,
+    is_selected: bool,
+}
+
+/// Generates a `new` function taking the selected fields, or all fields if
+/// nothing is selected. The fields which are not selected are initialized
+/// with `Default::default()`.
+pub(crate) fn generate_new(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let strukt = ctx.covering_element().ancestors().find_map(ast::StructDef::cast)?;
+    let fields = struct_fields(&ctx, &strukt, false)?;
+    let impl_block = inherent_impl(&strukt);
+    if has_method(impl_block.as_ref(), "new") {
+        return None;
+    }
+
+    let params = fields
+        .iter()
+        .filter(|it| it.is_selected)
+        .map(|it| format!("{}: {}", it.name, it.ty))
+        .join(", ");
+    let inits = fields
+        .iter()
+        .map(|it| {
+            if it.is_selected {
+                it.name.clone()
+            } else {
+                format!("{}: Default::default()", it.name)
+            }
+        })
+        .join(", ");
+    let method = format!(
+        "{}fn new({}) -> Self {{\n    Self {{ {} }}\n}}",
+        visibility(&strukt),
+        params,
+        inits
+    );
+    let target = ImplTarget::new(&strukt, impl_block)?;
+
+    ctx.add_action(AssistId("generate_new"), "generate new", |edit| {
+        edit.target(strukt.syntax().text_range());
+        target.insert_methods(edit, vec![method]);
+    });
+    ctx.build()
+}
+
+/// Generates getters for the selected fields, or for the field at the cursor,
+/// or for all fields. `Copy` fields are returned by value, others by
+/// reference.
+pub(crate) fn generate_getters(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let strukt = ctx.covering_element().ancestors().find_map(ast::StructDef::cast)?;
+    let fields = struct_fields(&ctx, &strukt, true)?;
+    let impl_block = inherent_impl(&strukt);
+    let vis = visibility(&strukt);
+
+    let methods: Vec<String> = fields
+        .iter()
+        .filter(|it| it.is_selected && !has_method(impl_block.as_ref(), &it.name))
+        .map(|it| {
+            let (ty, value) = if it.is_copy {
+                (it.ty.clone(), format!("self.{}", it.name))
+            } else {
+                (format!("&{}", it.ty), format!("&self.{}", it.name))
+            };
+            format!("{}fn {}(&self) -> {} {{\n    {}\n}}", vis, it.name, ty, value)
+        })
+        .collect();
+    if methods.is_empty() {
+        return None;
+    }
+    let label = if methods.len() == 1 { "generate getter" } else { "generate getters" };
+    let target = ImplTarget::new(&strukt, impl_block)?;
+
+    ctx.add_action(AssistId("generate_getters"), label, |edit| {
+        edit.target(strukt.syntax().text_range());
+        target.insert_methods(edit, methods);
+    });
+    ctx.build()
+}
+
+/// Generates setters for the selected fields, or for the field at the cursor,
+/// or for all fields.
+pub(crate) fn generate_setters(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let strukt = ctx.covering_element().ancestors().find_map(ast::StructDef::cast)?;
+    let fields = struct_fields(&ctx, &strukt, true)?;
+    let impl_block = inherent_impl(&strukt);
+    let vis = visibility(&strukt);
+
+    let methods: Vec<String> = fields
+        .iter()
+        .filter(|it| {
+            it.is_selected && !has_method(impl_block.as_ref(), &format!("set_{}", it.name))
+        })
+        .map(|it| {
+            format!(
+                "{}fn set_{}(&mut self, {}: {}) {{\n    self.{} = {};\n}}",
+                vis, it.name, it.name, it.ty, it.name, it.name
+            )
+        })
+        .collect();
+    if methods.is_empty() {
+        return None;
+    }
+    let label = if methods.len() == 1 { "generate setter" } else { "generate setters" };
+    let target = ImplTarget::new(&strukt, impl_block)?;
+
+    ctx.add_action(AssistId("generate_setters"), label, |edit| {
+        edit.target(strukt.syntax().text_range());
+        target.insert_methods(edit, methods);
+    });
+    ctx.build()
+}
+
+/// Collects the named fields of `strukt`. The fields overlapping with the
+/// selection are selected; without a selection, the field at the cursor is
+/// selected if `at_cursor` is set. If no field is selected, all are.
+fn struct_fields(
+    ctx: &AssistCtx<impl HirDatabase>,
+    strukt: &ast::StructDef,
+    at_cursor: bool,
+) -> Option<Vec<Field>> {
+    let field_defs: Vec<ast::NamedFieldDef> = match strukt.kind() {
+        ast::StructKind::Named(it) => it.fields().collect(),
+        _ => return None,
+    };
+    if field_defs.is_empty() {
+        return None;
+    }
+
+    let file_id = ctx.frange.file_id;
+    let module = source_binder::module_from_child_node(ctx.db, file_id, strukt.syntax())?;
+    let hir_fields = source_binder::struct_from_module(ctx.db, module, strukt).fields(ctx.db);
+    let analyzer = hir::SourceAnalyzer::new(ctx.db, file_id, strukt.syntax(), None);
+
+    let range = ctx.frange.range;
+    let mut res = Vec::new();
+    for field_def in field_defs.iter() {
+        let name = field_def.name()?.text().to_string();
+        let ty = field_def.ascribed_type()?.syntax().text().to_string();
+        let is_copy = hir_fields
+            .iter()
+            .find(|it| it.name(ctx.db).to_string() == name)
+            .map(|it| it.ty(ctx.db))
+            .map_or(false, |ty| ty != hir::Ty::Unknown && analyzer.impls_copy(ctx.db, ty));
+        let field_range = field_def.syntax().text_range();
+        let is_selected = if range.is_empty() {
+            at_cursor && field_range.start() <= range.start() && range.start() <= field_range.end()
+        } else {
+            field_range.start() < range.end() && range.start() < field_range.end()
+        };
+        res.push(Field { name, ty, is_copy, is_selected });
+    }
+    if res.iter().all(|it| !it.is_selected) {
+        res.iter_mut().for_each(|it| it.is_selected = true);
+    }
+    Some(res)
+}
+
+/// Finds the inherent impl of `strukt` among its sibling items.
+fn inherent_impl(strukt: &ast::StructDef) -> Option<ast::ImplBlock> {
+    let name = strukt.name()?;
+    strukt.syntax().parent()?.children().filter_map(ast::ImplBlock::cast).find(|it| {
+        if it.target_trait().is_some() {
+            return false;
+        }
+        match it.target_type().map(|it| it.kind()) {
+            Some(ast::TypeRefKind::PathType(it)) => it
+                .path()
+                .and_then(|it| it.segment())
+                .and_then(|it| it.name_ref())
+                .map_or(false, |it| it.text() == name.text()),
+            _ => false,
+        }
+    })
+}
+
+fn has_method(impl_block: Option<&ast::ImplBlock>, name: &str) -> bool {
+    let item_list = match impl_block.and_then(|it| it.item_list()) {
+        Some(it) => it,
+        None => return false,
+    };
+    item_list.impl_items().any(|it| match it.kind() {
+        ast::ImplItemKind::FnDef(it) => it.name().map_or(false, |it| it.text() == name),
+        _ => false,
+    })
+}
+
+/// The generated methods get the visibility of the struct.
+fn visibility(strukt: &ast::StructDef) -> String {
+    strukt.visibility().map(|it| format!("{} ", it.syntax())).unwrap_or_default()
+}
+
+/// Where the generated methods go: the existing inherent impl, or a new impl
+/// after the struct.
+enum ImplTarget {
+    Existing(ast::ItemList),
+    New { strukt: ast::StructDef, header: String },
+}
+
+impl ImplTarget {
+    fn new(strukt: &ast::StructDef, impl_block: Option<ast::ImplBlock>) -> Option<ImplTarget> {
+        let res = match impl_block {
+            Some(it) => ImplTarget::Existing(it.item_list()?),
+            None => ImplTarget::New {
+                strukt: strukt.clone(),
+                header: impl_header(&strukt.clone().into())?,
+            },
+        };
+        Some(res)
+    }
+
+    fn insert_methods(self, edit: &mut AssistBuilder, methods: Vec<String>) {
+        match self {
+            ImplTarget::Existing(item_list) => {
+                let indent = match item_list.impl_items().last() {
+                    Some(it) => leading_indent(it.syntax()).unwrap_or_default().to_string(),
+                    None => {
+                        format!("    {}", leading_indent(item_list.syntax()).unwrap_or_default())
+                    }
+                };
+                let n_existing_items = item_list.impl_items().count();
+                let mut ast_editor = AstEditor::new(item_list);
+                ast_editor.append_items(
+                    methods
+                        .iter()
+                        .map(|it| AstBuilder::<ast::ImplItem>::from_text(&reindent(it, &indent))),
+                );
+                let first_new_item = ast_editor.ast().impl_items().nth(n_existing_items).unwrap();
+                let cursor_position = first_new_item.syntax().text_range().start();
+                ast_editor.into_text_edit(edit.text_edit_builder());
+                edit.set_cursor(cursor_position);
+            }
+            ImplTarget::New { strukt, header } => {
+                let item_list =
+                    AstBuilder::<ast::ImplBlock>::from_header(&header).item_list().unwrap();
+                let mut ast_editor = AstEditor::new(item_list);
+                ast_editor.append_items(
+                    methods
+                        .iter()
+                        .map(|it| AstBuilder::<ast::ImplItem>::from_text(&reindent(it, "    "))),
+                );
+                let impl_text = format!("{} {}", header, ast_editor.ast().syntax().text());
+                let indent = leading_indent(strukt.syntax()).unwrap_or_default();
+                let offset = strukt.syntax().text_range().end();
+                let prefix = format!("\n\n{}", indent);
+                let cursor_position = offset
+                    + TextUnit::of_str(&prefix)
+                    + TextUnit::of_str(&format!("{} {{\n{}    ", header, indent));
+                edit.insert(offset, format!("{}{}", prefix, reindent(&impl_text, &indent)));
+                edit.set_cursor(cursor_position);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_range};
+
+    #[test]
+    fn generate_new_creates_impl() {
+        check_assist(
+            generate_new,
+            "
+struct Foo<T: Clone> {<|>
+    a: u32,
+    b: T,
+}",
+            "
+struct Foo<T: Clone> {
+    a: u32,
+    b: T,
+}
+
+impl<T: Clone> Foo<T> {
+    <|>fn new(a: u32, b: T) -> Self {
+        Self { a, b }
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn generate_new_with_selected_fields() {
+        check_assist_range(
+            generate_new,
+            "
+pub struct Foo {
+    <|>a: u32,<|>
+    b: String,
+}
+
+impl Foo {
+    fn foo(&self) {}
+}",
+            "
+pub struct Foo {
+    a: u32,
+    b: String,
+}
+
+impl Foo {
+    fn foo(&self) {}
+    <|>pub fn new(a: u32) -> Self {
+        Self { a, b: Default::default() }
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn generate_new_not_applicable_if_exists() {
+        check_assist_not_applicable(
+            generate_new,
+            "
+struct Foo {<|>
+    a: u32,
+}
+
+impl Foo {
+    fn new() -> Self { Foo { a: 0 } }
+}",
+        );
+    }
+
+    #[test]
+    fn generate_getters_for_copy_and_other_fields() {
+        check_assist(
+            generate_getters,
+            "
+#[lang = \"copy\"]
+trait Copy {}
+impl Copy for u32 {}
+struct Bar;
+struct Fo<|>o {
+    a: u32,
+    b: Bar,
+}",
+            "
+#[lang = \"copy\"]
+trait Copy {}
+impl Copy for u32 {}
+struct Bar;
+struct Foo {
+    a: u32,
+    b: Bar,
+}
+
+impl Foo {
+    <|>fn a(&self) -> u32 {
+        self.a
+    }
+    fn b(&self) -> &Bar {
+        &self.b
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn generate_setter_for_field_at_cursor() {
+        check_assist(
+            generate_setters,
+            "
+struct Foo {
+    a: u32,
+    b: <|>u32,
+}
+
+impl Foo {
+    fn set_a(&mut self, a: u32) {
+        self.a = a;
+    }
+}",
+            "
+struct Foo {
+    a: u32,
+    b: u32,
+}
+
+impl Foo {
+    fn set_a(&mut self, a: u32) {
+        self.a = a;
+    }
+    <|>fn set_b(&mut self, b: u32) {
+        self.b = b;
+    }
+}",
+        );
+    }
+
+    #[test]
+    fn generate_getters_not_applicable_to_tuple_struct() {
+        check_assist_not_applicable(generate_getters, "struct Foo(<|>u32);");
+    }
+}
@@ -0,0 +1,845 @@
+use std::collections::HashSet;
+
+use hir::{db::HirDatabase, source_binder, HasSource, ImplItem, ModuleDef, PathResolution};
+use ra_db::{FileId, SourceDatabase};
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    algo::find_covering_element,
+    ast::{
+        self, AstNode, AstToken, NameOwner, PathSegmentKind, SelfParamKind, TypeParamsOwner,
+        VisibilityOwner,
+    },
+    SyntaxKind::*,
+    SyntaxNode, TextRange, TextUnit,
+};
+
+use crate::{Assist, AssistCtx, AssistId};
+
+/// The label of the loop which replaces the body of a function with early
+/// returns; `return x` becomes `break 'inline x`.
+const RETURN_LABEL: &str = "'inline";
+
+/// Replaces a call of a function or method defined in the workspace with the
+/// body of the function.
+///
+/// Arguments which are paths or literals and are used at most once are
+/// substituted for their parameters, the others are bound with `let`, as is
+/// `self` when the receiver is not a path or is not only auto-dereferenced.
+/// Locals clashing with the names at the call site are renamed, and early
+/// returns become breaks out of a labeled loop. Functions using `?` are not
+/// supported. A second action inlines all the calls of a private function and
+/// removes it.
+pub(crate) fn inline_function(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let name_ref = ctx.node_at_offset::<ast::NameRef>()?;
+    let call = CallSite::from_name_ref(&name_ref)?;
+    let file_id = ctx.frange.file_id;
+    let func = resolve_function(ctx.db, file_id, &name_ref)?;
+    let callee = Callee::new(ctx.db, func)?;
+    let module = source_binder::module_from_child_node(ctx.db, file_id, name_ref.syntax())?;
+    let replacement = callee.inline(module, &call)?;
+    let call_range = call.syntax().text_range();
+
+    let all_calls = if callee.file_id == file_id { callee.all_calls(ctx.db, func) } else { None };
+
+    ctx.add_action(AssistId("inline_function"), "inline call", |edit| {
+        edit.target(call_range);
+        edit.replace(call_range, replacement);
+        edit.set_cursor(call_range.start());
+    });
+    if let Some(calls) = all_calls {
+        ctx.add_action(
+            AssistId("inline_function"),
+            "inline all calls and remove the function",
+            |edit| {
+                let removal = callee.removal_range();
+                let mut cursor = call_range.start();
+                for (range, text) in calls.iter() {
+                    if range.end() <= call_range.start() {
+                        cursor = cursor + TextUnit::of_str(text) - range.len();
+                    }
+                }
+                if removal.end() <= call_range.start() {
+                    cursor = cursor - removal.len();
+                }
+                edit.target(call_range);
+                for (range, text) in calls {
+                    edit.replace(range, text);
+                }
+                edit.delete(removal);
+                edit.set_cursor(cursor);
+            },
+        );
+    }
+    ctx.build()
+}
+
+/// A call which can be inlined: either a call of a path, like `foo(x)` or
+/// `S::foo(s, x)`, or a method call, like `s.foo(x)`.
+enum CallSite {
+    Call(ast::CallExpr),
+    Method(ast::MethodCallExpr),
+}
+
+impl CallSite {
+    /// Finds the call of which `name_ref` is the callee.
+    fn from_name_ref(name_ref: &ast::NameRef) -> Option<CallSite> {
+        let parent = name_ref.syntax().parent()?;
+        if let Some(it) = ast::MethodCallExpr::cast(parent) {
+            return Some(CallSite::Method(it));
+        }
+        let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+        let path_expr = path.syntax().parent().and_then(ast::PathExpr::cast)?;
+        let call = path_expr.syntax().parent().and_then(ast::CallExpr::cast)?;
+        if call.expr()?.syntax() != path_expr.syntax() {
+            return None;
+        }
+        Some(CallSite::Call(call))
+    }
+
+    fn syntax(&self) -> &SyntaxNode {
+        match self {
+            CallSite::Call(it) => it.syntax(),
+            CallSite::Method(it) => it.syntax(),
+        }
+    }
+
+    /// Splits the call into the receiver, which is passed for `self`, and the
+    /// remaining arguments.
+    fn receiver_and_args(&self, has_self: bool) -> Option<(Option<ast::Expr>, Vec<ast::Expr>)> {
+        match self {
+            CallSite::Method(it) => Some((it.expr(), it.arg_list()?.args().collect())),
+            CallSite::Call(it) => {
+                let mut args: Vec<_> = it.arg_list()?.args().collect();
+                if !has_self {
+                    return Some((None, args));
+                }
+                if args.is_empty() {
+                    return None;
+                }
+                let receiver = args.remove(0);
+                Some((Some(receiver), args))
+            }
+        }
+    }
+}
+
+/// Resolves the function `name_ref` refers to, if it is the name of a called
+/// method or the last segment of a path.
+fn resolve_function(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    name_ref: &ast::NameRef,
+) -> Option<hir::Function> {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+    let parent = name_ref.syntax().parent()?;
+    if let Some(call) = ast::MethodCallExpr::cast(parent) {
+        return analyzer.resolve_method_call(&call);
+    }
+    let path = name_ref.syntax().ancestors().find_map(ast::Path::cast)?;
+    match analyzer.resolve_path(db, &path)? {
+        PathResolution::Def(ModuleDef::Function(it)) => Some(it),
+        PathResolution::AssocItem(ImplItem::Method(it)) => Some(it),
+        _ => None,
+    }
+}
+
+/// A local of the inlined function together with the ranges of its usages.
+struct Local {
+    pat: ast::BindPat,
+    usages: Vec<TextRange>,
+}
+
+/// The function being inlined.
+struct Callee {
+    file_id: FileId,
+    module: hir::Module,
+    /// Whether the body only refers to its own locals, so that it can be
+    /// inlined into other modules.
+    is_self_contained: bool,
+    root: SyntaxNode,
+    fn_def: ast::FnDef,
+    body: ast::Block,
+    self_param: Option<(SelfParamKind, Vec<ast::PathExpr>)>,
+    params: Vec<Local>,
+    locals: Vec<Local>,
+    returns: Vec<ast::ReturnExpr>,
+}
+
+impl Callee {
+    fn new(db: &impl HirDatabase, func: hir::Function) -> Option<Callee> {
+        let src = func.source(db);
+        let file_id = src.file_id.original_file(db);
+        if hir::HirFileId::from(file_id) != src.file_id {
+            return None;
+        }
+        if db.source_root(db.file_source_root(file_id)).is_library {
+            return None;
+        }
+        let fn_def = src.ast;
+        // The type parameters of generic callees would have to be substituted.
+        let has_type_params = |it: Option<ast::TypeParamList>| {
+            it.map_or(false, |it| it.type_params().next().is_some())
+        };
+        if has_type_params(fn_def.type_param_list()) {
+            return None;
+        }
+        match func.container(db) {
+            Some(hir::Container::Trait(_)) => return None,
+            Some(hir::Container::ImplBlock(it)) => {
+                if has_type_params(it.source(db).ast.type_param_list()) {
+                    return None;
+                }
+            }
+            None => {}
+        }
+        let body = fn_def.body()?;
+        let root = fn_def.syntax().ancestors().last()?;
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, body.syntax(), None);
+
+        let own_nodes: Vec<SyntaxNode> = body
+            .syntax()
+            .descendants()
+            .filter(|node| {
+                node.ancestors()
+                    .take_while(|it| it != body.syntax())
+                    .all(|it| it.kind() != FN_DEF && it.kind() != LAMBDA_EXPR)
+            })
+            .collect();
+        if own_nodes.iter().any(|it| it.kind() == TRY_EXPR) {
+            return None;
+        }
+        let returns = own_nodes.iter().cloned().filter_map(ast::ReturnExpr::cast).collect();
+
+        let param_list = fn_def.param_list()?;
+        let self_param = param_list.self_param().map(|param| {
+            let usages = body
+                .syntax()
+                .descendants()
+                .filter_map(ast::PathExpr::cast)
+                .filter(|it| is_self(it) && !is_in_nested_fn(it.syntax(), &body))
+                .collect();
+            (param.kind(), usages)
+        });
+        let mut params = Vec::new();
+        for param in param_list.params() {
+            let pat = match param.pat()?.kind() {
+                ast::PatKind::BindPat(it) if !it.is_ref() && it.pat().is_none() => it,
+                _ => return None,
+            };
+            let usages = local_usages(&analyzer, &root, &pat);
+            params.push(Local { pat, usages });
+        }
+        let locals = body
+            .syntax()
+            .descendants()
+            .filter_map(ast::BindPat::cast)
+            .filter(|it| !is_in_nested_fn(it.syntax(), &body))
+            .map(|pat| {
+                let usages = local_usages(&analyzer, &root, &pat);
+                Local { pat, usages }
+            })
+            .collect();
+
+        let is_self_contained = body.syntax().descendants().all(|node| {
+            if node.kind() == MACRO_CALL {
+                return false;
+            }
+            let path = match ast::Path::cast(node) {
+                Some(it) => it,
+                None => return true,
+            };
+            // qualifiers are checked together with the path they belong to
+            if path.syntax().parent().and_then(ast::Path::cast).is_some() {
+                return true;
+            }
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, path.syntax(), None);
+            match analyzer.resolve_path(db, &path) {
+                Some(PathResolution::LocalBinding(_)) => true,
+                _ => false,
+            }
+        });
+
+        Some(Callee {
+            file_id,
+            module: func.module(db),
+            is_self_contained,
+            root,
+            fn_def,
+            body,
+            self_param,
+            params,
+            locals,
+            returns,
+        })
+    }
+
+    /// Builds the code replacing `call`, which is in `module`. Paths in the
+    /// body are not re-qualified, so calls from other modules are only
+    /// inlined if the body does not refer to any items.
+    fn inline(&self, module: hir::Module, call: &CallSite) -> Option<String> {
+        if module != self.module && !self.is_self_contained {
+            return None;
+        }
+        let (receiver, args) = call.receiver_and_args(self.self_param.is_some())?;
+        if args.len() != self.params.len() {
+            return None;
+        }
+        let mut taken = names_at_call_site(call);
+        let mut edits: Vec<(TextRange, String)> = Vec::new();
+        let mut lets = Vec::new();
+
+        if let Some((kind, usages)) = &self.self_param {
+            let receiver = receiver?;
+            // A method call borrows its receiver, while a call like
+            // `S::get(&s)` already passes the value of `self`.
+            let is_method_call = match call {
+                CallSite::Method(_) => true,
+                CallSite::Call(_) => false,
+            };
+            let direct = is_simple(&receiver)
+                && (!is_method_call
+                    || *kind == SelfParamKind::Owned
+                    || usages.iter().all(|it| is_auto_deref(it.syntax())));
+            let name = if direct {
+                receiver.syntax().text().to_string()
+            } else {
+                let name = fresh_name("this", &mut taken);
+                let borrow = match kind {
+                    _ if !is_method_call => "",
+                    SelfParamKind::Owned => "",
+                    SelfParamKind::Ref => "&",
+                    SelfParamKind::MutRef => "&mut ",
+                };
+                lets.push(format!("let {} = {}{};", name, borrow, receiver.syntax().text()));
+                name
+            };
+            for usage in usages {
+                edits.push((usage.syntax().text_range(), name.clone()));
+            }
+        }
+
+        for (param, arg) in self.params.iter().zip(args.iter()) {
+            let direct = is_simple(arg) && param.usages.len() <= 1 && !param.pat.is_mutable();
+            let name = if direct {
+                arg.syntax().text().to_string()
+            } else {
+                let name = fresh_name(&param.pat.name()?.text(), &mut taken);
+                let mut_kw = if param.pat.is_mutable() { "mut " } else { "" };
+                lets.push(format!("let {}{} = {};", mut_kw, name, arg.syntax().text()));
+                name
+            };
+            for usage in param.usages.iter() {
+                edits.push((*usage, name.clone()));
+            }
+        }
+
+        for local in self.locals.iter() {
+            let name = local.pat.name()?;
+            if !taken.contains(name.text().as_str()) {
+                taken.insert(name.text().to_string());
+                continue;
+            }
+            let new_name = fresh_name(&name.text(), &mut taken);
+            edits.push((name.syntax().text_range(), new_name.clone()));
+            for usage in local.usages.iter() {
+                edits.push((*usage, new_name.clone()));
+            }
+        }
+
+        let statements: Vec<_> = self.body.statements().collect();
+        let tail = self.body.expr();
+        if lets.is_empty() && self.returns.is_empty() && statements.is_empty() {
+            let tail = match tail {
+                Some(it) => it,
+                None => return Some("()".to_string()),
+            };
+            let text = self.render(tail.syntax().text_range(), edits);
+            let needs_parens = call.syntax().parent().and_then(ast::Expr::cast).is_some()
+                && match tail.syntax().kind() {
+                    BIN_EXPR | RANGE_EXPR | CAST_EXPR | PREFIX_EXPR | LAMBDA_EXPR => true,
+                    _ => false,
+                };
+            return Some(if needs_parens { format!("({})", text) } else { text });
+        }
+
+        let mut prefix = String::new();
+        if !self.returns.is_empty() {
+            prefix = format!("{}: loop ", RETURN_LABEL);
+            for ret in self.returns.iter() {
+                let return_kw = ret.syntax().first_token()?.text_range();
+                edits.push((return_kw, format!("break {}", RETURN_LABEL)));
+            }
+            match &tail {
+                Some(tail) => {
+                    let range = tail.syntax().text_range();
+                    let start = TextRange::offset_len(range.start(), 0.into());
+                    let end = TextRange::offset_len(range.end(), 0.into());
+                    edits.push((start, format!("break {} ", RETURN_LABEL)));
+                    edits.push((end, ";".to_string()));
+                }
+                None => {
+                    let last = statements.last()?.syntax().clone();
+                    let indent = leading_indent(&last).unwrap_or_default();
+                    let end = TextRange::offset_len(last.text_range().end(), 0.into());
+                    edits.push((end, format!("\n{}break {};", indent, RETURN_LABEL)));
+                }
+            }
+        }
+
+        let first = match statements.first() {
+            Some(it) => it.syntax().clone(),
+            None => tail?.syntax().clone(),
+        };
+        let lets: String = match leading_indent(&first) {
+            Some(indent) => lets.iter().map(|it| format!("\n{}{}", indent, it)).collect(),
+            None => lets.iter().map(|it| format!(" {}", it)).collect(),
+        };
+        let mut block = self.render(self.body.syntax().text_range(), edits);
+        block.insert_str(1, &lets);
+
+        let fn_indent = leading_indent(self.fn_def.syntax()).unwrap_or_default();
+        let call_indent =
+            call.syntax().ancestors().find_map(|it| leading_indent(&it)).unwrap_or_default();
+        let lines = block.lines().enumerate().map(|(i, line)| {
+            if i == 0 {
+                line.to_string()
+            } else if line.starts_with(fn_indent.as_str()) {
+                format!("{}{}", call_indent, &line[fn_indent.len()..])
+            } else {
+                format!("{}{}", call_indent, line)
+            }
+        });
+        Some(format!("{}{}", prefix, lines.collect::<Vec<_>>().join("\n")))
+    }
+
+    /// Finds all the calls of the function, with their replacements, if the
+    /// function can be removed afterwards: it must be private, only called
+    /// in its own file and not recursive.
+    fn all_calls(
+        &self,
+        db: &impl HirDatabase,
+        func: hir::Function,
+    ) -> Option<Vec<(TextRange, String)>> {
+        if self.fn_def.visibility().is_some() {
+            return None;
+        }
+        let has_out_of_line_modules = self
+            .root
+            .descendants()
+            .filter_map(ast::Module::cast)
+            .any(|it| it.item_list().is_none());
+        if has_out_of_line_modules {
+            return None;
+        }
+        let name = self.fn_def.name()?;
+        let fn_range = self.fn_def.syntax().text_range();
+
+        let mut res: Vec<(TextRange, String)> = Vec::new();
+        for name_ref in self.root.descendants().filter_map(ast::NameRef::cast) {
+            if name_ref.text() != name.text() {
+                continue;
+            }
+            if resolve_function(db, self.file_id, &name_ref) != Some(func) {
+                continue;
+            }
+            if name_ref.syntax().text_range().is_subrange(&fn_range) {
+                return None;
+            }
+            let call = CallSite::from_name_ref(&name_ref)?;
+            let module =
+                source_binder::module_from_child_node(db, self.file_id, name_ref.syntax())?;
+            res.push((call.syntax().text_range(), self.inline(module, &call)?));
+        }
+        res.sort_by_key(|(range, _)| range.start());
+        let overlaps = res.windows(2).any(|it| it[0].0.end() > it[1].0.start());
+        if overlaps {
+            return None;
+        }
+        Some(res)
+    }
+
+    /// The range of the function together with the whitespace following it.
+    fn removal_range(&self) -> TextRange {
+        let range = self.fn_def.syntax().text_range();
+        match self
+            .fn_def
+            .syntax()
+            .next_sibling_or_token()
+            .and_then(|it| ast::Whitespace::cast(it.as_token()?.clone()))
+        {
+            Some(ws) => TextRange::from_to(range.start(), ws.syntax().text_range().end()),
+            None => range,
+        }
+    }
+
+    /// Returns the text of the function in `range`, with the `edits` which lie
+    /// inside of the range applied.
+    fn render(&self, range: TextRange, mut edits: Vec<(TextRange, String)>) -> String {
+        edits.retain(|(it, _)| it.is_subrange(&range));
+        // Replacements starting at the same offset as an insertion must be
+        // applied first, so that the insertion stays in front of them.
+        edits.sort_by_key(|(it, _)| (it.start(), it.end()));
+        let mut text = self.root.text().slice(range).to_string();
+        for (edit_range, replacement) in edits.into_iter().rev() {
+            let start = (edit_range.start() - range.start()).to_usize();
+            let end = (edit_range.end() - range.start()).to_usize();
+            text.replace_range(start..end, &replacement);
+        }
+        text
+    }
+}
+
+/// Finds the usages of a local of the inlined function. Only usages as paths
+/// are kept, as `find_all_refs` also reports fields with the same name.
+fn local_usages(
+    analyzer: &hir::SourceAnalyzer,
+    root: &SyntaxNode,
+    pat: &ast::BindPat,
+) -> Vec<TextRange> {
+    analyzer
+        .find_all_refs(pat)
+        .into_iter()
+        .map(|it| it.range)
+        .filter(|&range| {
+            let node = find_covering_element(root, range).ancestors().find_map(ast::NameRef::cast);
+            node.and_then(|it| it.syntax().parent()).map(|it| it.kind()) == Some(PATH_SEGMENT)
+        })
+        .collect()
+}
+
+/// The names which must not be introduced by the inlined body: the locals of
+/// the function containing the call and the names used in its arguments.
+fn names_at_call_site(call: &CallSite) -> HashSet<String> {
+    let mut res = HashSet::new();
+    if let Some(fn_def) = call.syntax().ancestors().find_map(ast::FnDef::cast) {
+        for pat in fn_def.syntax().descendants().filter_map(ast::BindPat::cast) {
+            if let Some(name) = pat.name() {
+                res.insert(name.text().to_string());
+            }
+        }
+    }
+    for name_ref in call.syntax().descendants().filter_map(ast::NameRef::cast) {
+        res.insert(name_ref.text().to_string());
+    }
+    res
+}
+
+/// Returns `name`, or `name` with the smallest numeric suffix which makes it
+/// unique, and marks the result as taken.
+fn fresh_name(name: &str, taken: &mut HashSet<String>) -> String {
+    let mut res = name.to_string();
+    let mut i = 1;
+    while taken.contains(&res) {
+        res = format!("{}{}", name, i);
+        i += 1;
+    }
+    taken.insert(res.clone());
+    res
+}
+
+fn is_self(path_expr: &ast::PathExpr) -> bool {
+    match path_expr.path() {
+        Some(path) => {
+            path.qualifier().is_none()
+                && path.segment().and_then(|it| it.kind()) == Some(PathSegmentKind::SelfKw)
+        }
+        None => false,
+    }
+}
+
+fn is_in_nested_fn(node: &SyntaxNode, body: &ast::Block) -> bool {
+    node.ancestors().take_while(|it| it != body.syntax()).any(|it| it.kind() == FN_DEF)
+}
+
+/// Checks if an argument can be substituted for its parameter without
+/// changing the meaning of the code.
+fn is_simple(expr: &ast::Expr) -> bool {
+    match expr.syntax().kind() {
+        PATH_EXPR | LITERAL => true,
+        _ => false,
+    }
+}
+
+/// Checks if a reference substituted for `expr` is dereferenced
+/// automatically, because `expr` is the receiver of a method call or of a
+/// field access.
+fn is_auto_deref(expr: &SyntaxNode) -> bool {
+    match expr.parent() {
+        Some(parent) => match parent.kind() {
+            FIELD_EXPR | METHOD_CALL_EXPR => parent.first_child().as_ref() == Some(expr),
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_nth_action};
+
+    #[test]
+    fn inline_expression_function() {
+        check_assist(
+            inline_function,
+            "
+fn add(a: i32, b: i32) -> i32 { a + b }
+fn main() {
+    let x = 1;
+    let y = ad<|>d(x, 2) * 3;
+}",
+            "
+fn add(a: i32, b: i32) -> i32 { a + b }
+fn main() {
+    let x = 1;
+    let y = <|>(x + 2) * 3;
+}",
+        );
+    }
+
+    #[test]
+    fn inline_method_with_bindings_and_clashing_locals() {
+        check_assist(
+            inline_function,
+            "
+struct S { n: i32 }
+impl S {
+    fn get(&self, k: i32) -> i32 {
+        let n = self.n;
+        n * k + k
+    }
+}
+fn main() {
+    let s = S { n: 1 };
+    let n = 2;
+    s.g<|>et(n + 1);
+}",
+            "
+struct S { n: i32 }
+impl S {
+    fn get(&self, k: i32) -> i32 {
+        let n = self.n;
+        n * k + k
+    }
+}
+fn main() {
+    let s = S { n: 1 };
+    let n = 2;
+    <|>{
+        let k = n + 1;
+        let n1 = s.n;
+        n1 * k + k
+    };
+}",
+        );
+    }
+
+    #[test]
+    fn inline_method_binds_complex_receiver() {
+        check_assist(
+            inline_function,
+            "
+struct S { n: i32 }
+fn take(s: &S) -> i32 { s.n }
+impl S {
+    fn get(&self) -> i32 {
+        take(self)
+    }
+}
+fn make() -> S { S { n: 1 } }
+fn main() {
+    let n = make().g<|>et();
+}",
+            "
+struct S { n: i32 }
+fn take(s: &S) -> i32 { s.n }
+impl S {
+    fn get(&self) -> i32 {
+        take(self)
+    }
+}
+fn make() -> S { S { n: 1 } }
+fn main() {
+    let n = <|>{
+        let this = &make();
+        take(this)
+    };
+}",
+        );
+    }
+
+    #[test]
+    fn inline_method_called_as_function() {
+        let before = "
+struct S { n: i32 }
+fn take(s: &S) -> i32 { s.n }
+impl S {
+    fn get(&self) -> i32 {
+        take(self)
+    }
+}
+fn main() {
+    let s = S { n: 1 };
+    let r = &s;
+    let n = S::g<|>et(&s) + S::get(r);
+}";
+        check_assist(
+            inline_function,
+            before,
+            "
+struct S { n: i32 }
+fn take(s: &S) -> i32 { s.n }
+impl S {
+    fn get(&self) -> i32 {
+        take(self)
+    }
+}
+fn main() {
+    let s = S { n: 1 };
+    let r = &s;
+    let n = <|>{
+        let this = &s;
+        take(this)
+    } + S::get(r);
+}",
+        );
+        check_assist(
+            inline_function,
+            &before.replace("S::g<|>et(&s) + S::get(r)", "S::get(&s) + S::g<|>et(r)"),
+            "
+struct S { n: i32 }
+fn take(s: &S) -> i32 { s.n }
+impl S {
+    fn get(&self) -> i32 {
+        take(self)
+    }
+}
+fn main() {
+    let s = S { n: 1 };
+    let r = &s;
+    let n = S::get(&s) + <|>take(r);
+}",
+        );
+    }
+
+    #[test]
+    fn inline_function_with_early_return() {
+        check_assist(
+            inline_function,
+            "
+fn sign(x: i32) -> i32 {
+    if x < 0 {
+        return -1;
+    }
+    1
+}
+fn main() {
+    let s = si<|>gn(5);
+}",
+            "
+fn sign(x: i32) -> i32 {
+    if x < 0 {
+        return -1;
+    }
+    1
+}
+fn main() {
+    let s = <|>'inline: loop {
+        if 5 < 0 {
+            break 'inline -1;
+        }
+        break 'inline 1;
+    };
+}",
+        );
+    }
+
+    #[test]
+    fn inline_all_calls_and_remove_function() {
+        check_assist_nth_action(
+            inline_function,
+            "
+fn double(x: i32) -> i32 { x * 2 }
+fn main() {
+    let a = dou<|>ble(1);
+    let b = double(a) + 1;
+}",
+            "
+fn main() {
+    let a = <|>1 * 2;
+    let b = (a * 2) + 1;
+}",
+            1,
+        );
+    }
+
+    #[test]
+    fn inline_function_not_applicable_with_try() {
+        check_assist_not_applicable(
+            inline_function,
+            "
+fn parse(s: &str) -> Option<i32> {
+    let n = s.parse().ok()?;
+    Some(n)
+}
+fn main() {
+    let n = pa<|>rse(\"1\");
+}",
+        );
+    }
+
+    #[test]
+    fn inline_function_not_applicable_for_generic_functions() {
+        check_assist_not_applicable(
+            inline_function,
+            "
+fn id<T>(t: T) -> T { t }
+fn main() {
+    let n = i<|>d(1);
+}",
+        );
+        check_assist_not_applicable(
+            inline_function,
+            "
+struct W<T>(T);
+impl<T> W<T> {
+    fn get(self) -> T { self.0 }
+}
+fn main() {
+    let n = W(1).g<|>et();
+}",
+        );
+    }
+
+    #[test]
+    fn inline_function_from_other_module() {
+        check_assist(
+            inline_function,
+            "
+mod m {
+    pub fn double(x: i32) -> i32 { x * 2 }
+}
+fn main() {
+    let n = m::dou<|>ble(1);
+}",
+            "
+mod m {
+    pub fn double(x: i32) -> i32 { x * 2 }
+}
+fn main() {
+    let n = <|>1 * 2;
+}",
+        );
+        check_assist_not_applicable(
+            inline_function,
+            "
+mod m {
+    const FACTOR: i32 = 2;
+    pub fn scale(x: i32) -> i32 { x * FACTOR }
+}
+fn main() {
+    let n = m::sca<|>le(1);
+}",
+        );
+    }
+}
@@ -0,0 +1,143 @@
+use hir::db::HirDatabase;
+use ra_syntax::{
+    ast::{self, AstNode, BinOp, PrefixOp},
+    TextRange, T,
+};
+
+use crate::{Assist, AssistCtx, AssistId};
+
+/// Inverts the condition of an `if` with an `else` block, and swaps the two
+/// blocks.
+pub(crate) fn invert_if(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let if_expr: ast::IfExpr = ctx.node_at_offset()?;
+    let if_range = if_keyword_range(&if_expr)?;
+    if !ctx.frange.range.is_subrange(&if_range) {
+        return None;
+    }
+    let cond = if_expr.condition()?;
+    if cond.pat().is_some() {
+        return None;
+    }
+    let cond_expr = cond.expr()?;
+    let then_block = if_expr.then_branch()?;
+    let else_block = match if_expr.else_branch()? {
+        ast::ElseBranch::Block(it) => it,
+        ast::ElseBranch::IfExpr(_) => return None,
+    };
+
+    ctx.add_action(AssistId("invert_if"), "invert if", |edit| {
+        edit.target(if_range);
+        edit.replace(cond_expr.syntax().text_range(), invert_boolean_expression(&cond_expr));
+        edit.replace(then_block.syntax().text_range(), else_block.syntax().text());
+        edit.replace(else_block.syntax().text_range(), then_block.syntax().text());
+    });
+
+    ctx.build()
+}
+
+pub(crate) fn if_keyword_range(if_expr: &ast::IfExpr) -> Option<TextRange> {
+    let token = if_expr.syntax().first_token()?;
+    if token.kind() != T![if] {
+        return None;
+    }
+    Some(token.text_range())
+}
+
+/// Returns the negation of `expr`: `!` is removed or added, and `==` and `!=`
+/// are swapped. Other comparisons are negated as a whole, as they are not
+/// inverted for floating point numbers.
+pub(crate) fn invert_boolean_expression(expr: &ast::Expr) -> String {
+    match expr.kind() {
+        ast::ExprKind::BinExpr(e) => {
+            let new_op = match e.op_kind() {
+                Some(BinOp::EqualityTest) => "!=",
+                Some(BinOp::NegatedEqualityTest) => "==",
+                _ => return format!("!({})", expr.syntax().text()),
+            };
+            let (lhs, rhs) = match (e.lhs(), e.rhs()) {
+                (Some(lhs), Some(rhs)) => (lhs, rhs),
+                _ => return format!("!({})", expr.syntax().text()),
+            };
+            format!("{} {} {}", lhs.syntax().text(), new_op, rhs.syntax().text())
+        }
+        ast::ExprKind::PrefixExpr(e) if e.op_kind() == Some(PrefixOp::Not) => {
+            match e.expr().map(|it| it.kind()) {
+                Some(ast::ExprKind::ParenExpr(it)) => match it.expr() {
+                    Some(inner) => inner.syntax().text().to_string(),
+                    None => it.syntax().text().to_string(),
+                },
+                Some(_) | None => {
+                    e.expr().map(|it| it.syntax().text().to_string()).unwrap_or_default()
+                }
+            }
+        }
+        ast::ExprKind::PathExpr(_)
+        | ast::ExprKind::CallExpr(_)
+        | ast::ExprKind::MethodCallExpr(_)
+        | ast::ExprKind::FieldExpr(_)
+        | ast::ExprKind::ParenExpr(_)
+        | ast::ExprKind::Literal(_)
+        | ast::ExprKind::MacroCall(_) => format!("!{}", expr.syntax().text()),
+        _ => format!("!({})", expr.syntax().text()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    #[test]
+    fn invert_if_swaps_blocks() {
+        check_assist(
+            invert_if,
+            "fn f(x: bool) { i<|>f x { 1 } else { 2 } }",
+            "fn f(x: bool) { i<|>f !x { 2 } else { 1 } }",
+        )
+    }
+
+    #[test]
+    fn invert_if_removes_negation() {
+        check_assist(
+            invert_if,
+            "fn f(x: bool, y: bool) { <|>if !(x && y) { 1 } else { 2 } }",
+            "fn f(x: bool, y: bool) { <|>if x && y { 2 } else { 1 } }",
+        )
+    }
+
+    #[test]
+    fn invert_if_comparisons() {
+        check_assist(
+            invert_if,
+            "fn f(x: u32) { <|>if x == 1 { 1 } else { 2 } }",
+            "fn f(x: u32) { <|>if x != 1 { 2 } else { 1 } }",
+        );
+        check_assist(
+            invert_if,
+            "fn f(x: f32) { <|>if x < 1.0 { 1 } else { 2 } }",
+            "fn f(x: f32) { <|>if !(x < 1.0) { 2 } else { 1 } }",
+        );
+    }
+
+    #[test]
+    fn invert_if_not_applicable_without_else_block() {
+        check_assist_not_applicable(invert_if, "fn f(x: bool) { <|>if x { 1 } }");
+        check_assist_not_applicable(
+            invert_if,
+            "fn f(x: bool) { <|>if x { 1 } else if true { 2 } else { 3 } }",
+        );
+    }
+
+    #[test]
+    fn invert_if_not_applicable_for_if_let() {
+        check_assist_not_applicable(
+            invert_if,
+            "fn f(x: Option<u32>) { <|>if let Some(y) = x { 1 } else { 2 } }",
+        );
+    }
+
+    #[test]
+    fn invert_if_target() {
+        check_assist_target(invert_if, "fn f(x: bool) { <|>if x { 1 } else { 2 } }", "if");
+    }
+}
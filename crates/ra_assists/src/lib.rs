@@ -8,13 +8,16 @@
 mod assist_ctx;
 mod marks;
 pub mod ast_editor;
+pub mod search;
+mod utils;
 
 use itertools::Itertools;
 
 use hir::db::HirDatabase;
-use ra_db::FileRange;
+use ra_db::{FileId, FileRange, SourceRootId};
 use ra_syntax::{TextRange, TextUnit};
 use ra_text_edit::TextEdit;
+use relative_path::RelativePathBuf;
 
 pub(crate) use crate::assist_ctx::{Assist, AssistCtx};
 
@@ -32,7 +35,13 @@ pub struct AssistLabel {
 
 #[derive(Debug, Clone)]
 pub struct AssistAction {
+    /// Edit of the file the assist was invoked in.
     pub edit: TextEdit,
+    /// Edits of other files, for assists which update references across the
+    /// workspace.
+    pub other_file_edits: Vec<(FileId, TextEdit)>,
+    /// Files created by the assist, with their contents.
+    pub new_files: Vec<(SourceRootId, RelativePathBuf, String)>,
     pub cursor_position: Option<TextUnit>,
     pub target: Option<TextRange>,
 }
@@ -89,6 +98,10 @@ where
 mod add_derive;
 mod add_explicit_type;
 mod add_impl;
+mod generate_accessors;
+mod convert_tuple_struct;
+mod extract_struct_from_enum_variant;
+mod move_item;
 mod flip_comma;
 mod flip_binexpr;
 mod change_visibility;
@@ -96,8 +109,15 @@ mod fill_match_arms;
 mod merge_match_arms;
 mod introduce_variable;
 mod inline_local_variable;
+mod inline_function;
+mod extract_function;
 mod replace_if_let_with_match;
+mod invert_if;
+mod convert_to_guarded_return;
+mod wrap_return_type;
+mod replace_unwrap_with_try;
 mod split_import;
+pub mod organize_imports;
 mod remove_dbg;
 pub mod auto_import;
 mod add_missing_impl_members;
@@ -108,6 +128,16 @@ fn all_assists<DB: HirDatabase>() -> &'static [fn(AssistCtx<DB>) -> Option<Assis
         add_derive::add_derive,
         add_explicit_type::add_explicit_type,
         add_impl::add_impl,
+        add_impl::add_trait_impl,
+        generate_accessors::generate_new,
+        generate_accessors::generate_getters,
+        generate_accessors::generate_setters,
+        convert_tuple_struct::convert_tuple_struct_to_named_struct,
+        convert_tuple_struct::convert_named_struct_to_tuple_struct,
+        extract_struct_from_enum_variant::extract_struct_from_enum_variant,
+        move_item::move_item_to_module,
+        move_item::move_item_to_new_file,
+        move_item::move_module_to_file,
         change_visibility::change_visibility,
         fill_match_arms::fill_match_arms,
         merge_match_arms::merge_match_arms,
@@ -115,12 +145,19 @@ fn all_assists<DB: HirDatabase>() -> &'static [fn(AssistCtx<DB>) -> Option<Assis
         flip_binexpr::flip_binexpr,
         introduce_variable::introduce_variable,
         replace_if_let_with_match::replace_if_let_with_match,
+        invert_if::invert_if,
+        convert_to_guarded_return::convert_to_guarded_return,
+        wrap_return_type::wrap_return_type,
+        replace_unwrap_with_try::replace_unwrap_with_try,
         split_import::split_import,
+        organize_imports::organize_imports,
         remove_dbg::remove_dbg,
         auto_import::auto_import,
         add_missing_impl_members::add_missing_impl_members,
         add_missing_impl_members::add_missing_default_members,
         inline_local_variable::inline_local_varialbe,
+        inline_function::inline_function,
+        extract_function::extract_function,
         move_guard::move_guard_to_arm_body,
         move_guard::move_arm_cond_to_match_guard,
     ]
@@ -129,9 +166,9 @@ fn all_assists<DB: HirDatabase>() -> &'static [fn(AssistCtx<DB>) -> Option<Assis
 #[cfg(test)]
 mod helpers {
     use hir::mock::MockDatabase;
-    use ra_db::FileRange;
+    use ra_db::{FileRange, SourceDatabase};
     use ra_syntax::TextRange;
-    use test_utils::{add_cursor, assert_eq_text, extract_offset, extract_range};
+    use test_utils::{add_cursor, assert_eq_text, extract_offset, extract_range, parse_fixture};
 
     use crate::{Assist, AssistCtx};
 
@@ -197,6 +234,45 @@ mod helpers {
         assert_eq_text!(after, &actual);
     }
 
+    /// Checks an assist which may edit or create several files. `before` and
+    /// `after` are multi-file fixtures; the cursor is only marked in `before`.
+    pub(crate) fn check_assist_in_files(
+        assist: fn(AssistCtx<MockDatabase>) -> Option<Assist>,
+        before: &str,
+        after: &str,
+    ) {
+        let (db, position) = MockDatabase::with_position(before);
+        let frange = FileRange {
+            file_id: position.file_id,
+            range: TextRange::offset_len(position.offset, 0.into()),
+        };
+        let assist =
+            AssistCtx::with_ctx(&db, frange, true, assist).expect("code action is not applicable");
+        let labels_actions = match assist {
+            Assist::Unresolved(_) => unreachable!(),
+            Assist::Resolved(labels_actions) => labels_actions,
+        };
+
+        let (_, action) = labels_actions.get(0).expect("expect assist action at index");
+        for entry in parse_fixture(after) {
+            let new_file =
+                action.new_files.iter().find(|(_, path, _)| format!("/{}", path) == entry.meta);
+            if let Some((_, _, text)) = new_file {
+                assert_eq_text!(&entry.text, text);
+                continue;
+            }
+            let file_id = db.file_id_of(&entry.meta);
+            let mut actual = db.file_text(file_id).to_string();
+            if file_id == position.file_id {
+                actual = action.edit.apply(&actual);
+            }
+            for (_, edit) in action.other_file_edits.iter().filter(|(id, _)| *id == file_id) {
+                actual = edit.apply(&actual);
+            }
+            assert_eq_text!(&entry.text, &actual);
+        }
+    }
+
     pub(crate) fn check_assist_range_nth_action(
         assist: fn(AssistCtx<MockDatabase>) -> Option<Assist>,
         before: &str,
@@ -328,5 +404,4 @@ mod tests {
         assert_eq!(assists.next().expect("expected assist").0.label, "introduce variable");
         assert_eq!(assists.next().expect("expected assist").0.label, "replace with match");
     }
-
 }
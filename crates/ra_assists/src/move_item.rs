@@ -0,0 +1,1203 @@
+//! Assists moving an item to another module, possibly in a new file, and
+//! moving the contents of an inline module to a file of its own.
+//!
+//! Items are moved into a child module of their module. The names the item
+//! uses from its old module are imported with `use super::..`, and the paths
+//! referring to it across the workspace get the name of the child module
+//! inserted before its own name, as in `crate::m::S` becoming
+//! `crate::m::child::S`.
+
+use std::iter::{once, successors};
+
+use hir::{
+    db::HirDatabase, source_binder, AdtDef, ImplItem, Module, ModuleDef, ModuleSource,
+    PathResolution, VariantDef,
+};
+use ra_db::{FileId, SourceDatabase, SourceRootId};
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    ast::{self, AstNode, NameOwner, PathSegmentKind, VisibilityOwner},
+    Direction, SyntaxElement,
+    SyntaxKind::*,
+    SyntaxNode, TextRange, TextUnit, T,
+};
+use relative_path::{RelativePath, RelativePathBuf};
+
+use crate::{
+    assist_ctx::AssistBuilder, ast_editor::AstBuilder, organize_imports::extend_to_whitespace,
+    search, utils::to_snake_case, Assist, AssistCtx, AssistId,
+};
+
+/// Moves the item at the cursor into one of the child modules of its module.
+/// There is an action for each child module.
+pub(crate) fn move_item_to_module(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let item = item_at_cursor(&ctx)?;
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let module = source_binder::module_from_child_node(db, file_id, item.syntax())?;
+    let mut targets: Vec<_> = module
+        .children(db)
+        .filter(|it| !is_declared_by(db, *it, &item))
+        .filter_map(|it| Some((it.name(db)?.to_string(), it)))
+        .collect();
+    targets.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    let usages = ItemUsages::find(db, file_id, module, &item, ctx.should_compute_edit())?;
+    let mut has_actions = false;
+    for (name, target) in targets {
+        let target = Target::Module(target);
+        let moved = match move_item(db, file_id, module, &item, &usages, &target) {
+            Some(it) => it,
+            None => continue,
+        };
+        has_actions = true;
+        ctx.add_action(
+            AssistId("move_item_to_module"),
+            format!("move item to module `{}`", name),
+            |edit| {
+                edit.target(item.syntax().text_range());
+                moved.apply(edit);
+            },
+        );
+    }
+    if !has_actions {
+        return None;
+    }
+    ctx.build()
+}
+
+/// Moves the item at the cursor into a new child module, stored in a file
+/// named after the item.
+pub(crate) fn move_item_to_new_file(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let item = item_at_cursor(&ctx)?;
+    if item.syntax().kind() == MODULE {
+        return None;
+    }
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let module = source_binder::module_from_child_node(db, file_id, item.syntax())?;
+    let name = to_snake_case(item_name(&item)?.text());
+    if module.scope(db).entries().any(|(it, res)| it.to_string() == name && res.def.types.is_some())
+    {
+        return None;
+    }
+    let (source_root, dir) = child_module_dir(db, module)?;
+    let path = new_module_path(db, source_root, &dir, &name)?;
+    let target = Target::NewFile { name, source_root, path: path.clone() };
+    let usages = ItemUsages::find(db, file_id, module, &item, ctx.should_compute_edit())?;
+    let moved = move_item(db, file_id, module, &item, &usages, &target)?;
+
+    ctx.add_action(
+        AssistId("move_item_to_new_file"),
+        format!("move item to new file `{}`", path),
+        |edit| {
+            edit.target(item.syntax().text_range());
+            moved.apply(edit);
+        },
+    );
+    ctx.build()
+}
+
+/// Moves the contents of the inline module at the cursor to a new file, so
+/// that `mod foo { .. }` becomes `mod foo;`.
+pub(crate) fn move_module_to_file(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let module_ast = ctx.node_at_offset::<ast::Module>()?;
+    let item_list = module_ast.item_list()?;
+    if ctx.frange.range.start() >= item_list.syntax().text_range().start() {
+        return None;
+    }
+    let name = module_ast.name()?;
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let parent = source_binder::module_from_child_node(db, file_id, module_ast.syntax())?;
+    let (source_root, dir) = child_module_dir(db, parent)?;
+    let path = new_module_path(db, source_root, &dir, name.text())?;
+
+    let text = db.file_text(file_id);
+    let l_curly = item_list.syntax().first_child_or_token()?.text_range();
+    let r_curly = item_list.syntax().last_child_or_token()?.text_range();
+    let inner = text[TextRange::from_to(l_curly.end(), r_curly.start())].trim();
+    let indent = leading_indent(module_ast.syntax()).unwrap_or_default();
+    let mut contents = reindent(inner, &format!("{}    ", indent), "");
+    if !contents.is_empty() {
+        contents.push('\n');
+    }
+
+    ctx.add_action(
+        AssistId("move_module_to_file"),
+        format!("move module to file `{}`", path),
+        |edit| {
+            edit.target(module_ast.syntax().text_range());
+            let name_end = name.syntax().text_range().end();
+            edit.replace(TextRange::from_to(name_end, r_curly.end()), ";");
+            edit.create_file(source_root, path, contents);
+        },
+    );
+    ctx.build()
+}
+
+enum Target {
+    Module(Module),
+    NewFile { name: String, source_root: SourceRootId, path: RelativePathBuf },
+}
+
+/// The edits moving an item, computed before the action is added.
+struct MovedItem {
+    edits: Vec<(FileId, TextRange, String)>,
+    new_file: Option<(SourceRootId, RelativePathBuf, String)>,
+}
+
+impl MovedItem {
+    fn apply(self, edit: &mut AssistBuilder) {
+        for (file_id, range, text) in self.edits {
+            edit.replace_in(file_id, range, text);
+        }
+        if let Some((source_root, path, text)) = self.new_file {
+            edit.create_file(source_root, path, text);
+        }
+    }
+}
+
+/// The visibility an item needs after it is moved, given where it is used.
+#[derive(Default, Clone, Copy)]
+struct RequiredVisibility {
+    in_crate: bool,
+    in_other_crates: bool,
+}
+
+impl RequiredVisibility {
+    fn add(&mut self, in_other_crate: bool) {
+        if in_other_crate {
+            self.in_other_crates = true;
+        } else {
+            self.in_crate = true;
+        }
+    }
+
+    fn keyword(self) -> Option<&'static str> {
+        if self.in_other_crates {
+            Some("pub")
+        } else if self.in_crate {
+            Some("pub(crate)")
+        } else {
+            None
+        }
+    }
+}
+
+/// Finds the item whose header is at the cursor. Only the items of a module
+/// can be moved, and neither imports nor `mod foo;` declarations.
+fn item_at_cursor(ctx: &AssistCtx<impl HirDatabase>) -> Option<ast::ModuleItem> {
+    let item = ctx.node_at_offset::<ast::ModuleItem>()?;
+    let parent = item.syntax().parent()?;
+    let is_module_level = parent.kind() == SOURCE_FILE
+        || (parent.kind() == ITEM_LIST && parent.parent().map(|it| it.kind()) == Some(MODULE));
+    if !is_module_level {
+        return None;
+    }
+    let header_end = match item.kind() {
+        ast::ModuleItemKind::UseItem(_) | ast::ModuleItemKind::ExternCrateItem(_) => return None,
+        ast::ModuleItemKind::Module(it) => {
+            it.item_list()?;
+            it.name()?.syntax().text_range().end()
+        }
+        ast::ModuleItemKind::ImplBlock(it) => it.item_list()?.syntax().text_range().start(),
+        _ => item_name(&item)?.syntax().text_range().end(),
+    };
+    if ctx.frange.range.start() > header_end {
+        return None;
+    }
+    Some(item)
+}
+
+fn item_name(item: &ast::ModuleItem) -> Option<ast::Name> {
+    match item.kind() {
+        ast::ModuleItemKind::StructDef(it) => it.name(),
+        ast::ModuleItemKind::EnumDef(it) => it.name(),
+        ast::ModuleItemKind::FnDef(it) => it.name(),
+        ast::ModuleItemKind::TraitDef(it) => it.name(),
+        ast::ModuleItemKind::TypeAliasDef(it) => it.name(),
+        ast::ModuleItemKind::ConstDef(it) => it.name(),
+        ast::ModuleItemKind::StaticDef(it) => it.name(),
+        ast::ModuleItemKind::Module(it) => it.name(),
+        ast::ModuleItemKind::ImplBlock(_)
+        | ast::ModuleItemKind::UseItem(_)
+        | ast::ModuleItemKind::ExternCrateItem(_) => None,
+    }
+}
+
+fn item_visibility(item: &ast::ModuleItem) -> Option<ast::Visibility> {
+    match item.kind() {
+        ast::ModuleItemKind::StructDef(it) => it.visibility(),
+        ast::ModuleItemKind::EnumDef(it) => it.visibility(),
+        ast::ModuleItemKind::FnDef(it) => it.visibility(),
+        ast::ModuleItemKind::TraitDef(it) => it.visibility(),
+        ast::ModuleItemKind::TypeAliasDef(it) => it.visibility(),
+        ast::ModuleItemKind::ConstDef(it) => it.visibility(),
+        ast::ModuleItemKind::StaticDef(it) => it.visibility(),
+        ast::ModuleItemKind::Module(it) => it.visibility(),
+        ast::ModuleItemKind::ImplBlock(_)
+        | ast::ModuleItemKind::UseItem(_)
+        | ast::ModuleItemKind::ExternCrateItem(_) => None,
+    }
+}
+
+fn is_declared_by(db: &impl HirDatabase, module: Module, item: &ast::ModuleItem) -> bool {
+    module
+        .declaration_source(db)
+        .map_or(false, |it| it.ast.syntax().text_range() == item.syntax().text_range())
+}
+
+fn is_in(db: &impl HirDatabase, module: Module, ancestor: Module) -> bool {
+    module.path_to_root(db).contains(&ancestor)
+}
+
+/// The usages of the moved item across the workspace. They don't depend on
+/// where the item is moved, so they are searched once for all the targets.
+#[derive(Default)]
+struct ItemUsages {
+    def: Option<ModuleDef>,
+    /// The paths referring to the item through its old module.
+    refs: Vec<ItemRef>,
+    /// The private methods of a moved inherent impl or the private fields of
+    /// a moved struct, along with the modules using them.
+    members: Vec<(SyntaxNode, Vec<Module>)>,
+}
+
+/// A path referring to the moved item through its old module: qualified by
+/// the module, unqualified inside of it, or imported from it.
+struct ItemRef {
+    file_id: FileId,
+    name_ref: ast::NameRef,
+    path: ast::Path,
+    module: Module,
+    use_tree: Option<ast::UseTree>,
+}
+
+impl ItemUsages {
+    /// Resolves `item`, defined in `module`, and searches the workspace for
+    /// its usages if `compute_edit` is set. Otherwise, the usages are left
+    /// empty and the edits only tell that the item can be moved.
+    fn find(
+        db: &impl HirDatabase,
+        file_id: FileId,
+        module: Module,
+        item: &ast::ModuleItem,
+        compute_edit: bool,
+    ) -> Option<ItemUsages> {
+        let def = match item_name(item) {
+            Some(name) => {
+                let analyzer =
+                    hir::SourceAnalyzer::new(db, file_id, &item.syntax().parent()?, None);
+                let path = AstBuilder::<ast::Path>::from_text(name.text());
+                match analyzer.resolve_path(db, &path)? {
+                    PathResolution::Def(def) => Some(def),
+                    _ => return None,
+                }
+            }
+            None => None,
+        };
+        let mut res = ItemUsages { def, ..Default::default() };
+        if !compute_edit {
+            return Some(res);
+        }
+        if let (Some(name), Some(def)) = (item_name(item), def) {
+            res.refs = find_item_refs(db, file_id, item, module, name.text(), def);
+        }
+        res.members = match (item.kind(), def) {
+            (ast::ModuleItemKind::ImplBlock(imp), _) => impl_items_users(db, file_id, module, &imp),
+            (ast::ModuleItemKind::StructDef(strukt), Some(ModuleDef::Struct(it))) => {
+                struct_fields_users(db, &strukt, it)
+            }
+            _ => Vec::new(),
+        };
+        Some(res)
+    }
+}
+
+/// Computes the edits moving `item`, defined in `module`, to `target`.
+fn move_item(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    module: Module,
+    item: &ast::ModuleItem,
+    usages: &ItemUsages,
+    target: &Target,
+) -> Option<MovedItem> {
+    let name = item_name(item).map(|it| it.text().to_string());
+    let def = usages.def;
+    let target_module = match target {
+        Target::Module(it) => Some(*it),
+        Target::NewFile { .. } => None,
+    };
+    let target_scope = match target_module {
+        Some(it) => Some(module_scope(db, it)?),
+        None => None,
+    };
+    if let (Some(name), Some(scope)) = (&name, &target_scope) {
+        // The target module must not have another item with the same name.
+        match (resolve_in(db, scope, name), def) {
+            (None, _) => (),
+            (Some(PathResolution::Def(it)), Some(def)) if it == def => (),
+            _ => return None,
+        }
+    }
+    let target_name = match target {
+        Target::Module(it) => it.name(db)?.to_string(),
+        Target::NewFile { name, .. } => name.clone(),
+    };
+
+    let mut edits = Vec::new();
+    let (item_visibility_needed, module_visibility_needed) =
+        update_references(db, module, &usages.refs, target_module, &target_name, &mut edits)?;
+    let imports = imports_from_parent(db, file_id, module, item, &target_scope)?;
+
+    let text = db.file_text(file_id);
+    let mut item_edits = relative_path_edits(item);
+    if let (Some(vis), None) = (item_visibility_needed.keyword(), item_visibility(item)) {
+        let offset = visibility_offset(item.syntax());
+        item_edits.push((TextRange::offset_len(offset, 0.into()), format!("{} ", vis)));
+    }
+    item_edits.extend(members_visibility(db, module, &usages.members, target_module));
+    let item_text = render(&text, item.syntax().text_range(), item_edits);
+    let old_indent = leading_indent(item.syntax()).unwrap_or_default();
+    let mut block = reindent(&item_text, &old_indent, "");
+    if !imports.is_empty() {
+        let imports = if imports.len() == 1 {
+            imports[0].clone()
+        } else {
+            format!("{{{}}}", imports.join(", "))
+        };
+        block = format!("use super::{};\n\n{}", imports, block);
+    }
+
+    let mut new_file = None;
+    match target {
+        Target::Module(target) => {
+            edits.push((file_id, deletion_range(item.syntax()), String::new()));
+            edits.push(insertion_into(db, *target, &block)?);
+            if let Some(vis) = module_visibility_needed.keyword() {
+                let decl = target.declaration_source(db)?;
+                if decl.ast.visibility().is_none() {
+                    let offset = visibility_offset(decl.ast.syntax());
+                    let range = TextRange::offset_len(offset, 0.into());
+                    edits.push((decl.file_id.original_file(db), range, format!("{} ", vis)));
+                }
+            }
+        }
+        Target::NewFile { name, source_root, path } => {
+            let vis = module_visibility_needed.keyword().map(|it| format!("{} ", it));
+            let decl = format!("{}mod {};", vis.unwrap_or_default(), name);
+            edits.push((file_id, item.syntax().text_range(), decl));
+            new_file = Some((*source_root, path.clone(), format!("{}\n", block)));
+        }
+    }
+    Some(MovedItem { edits, new_file })
+}
+
+/// Finds the paths referring to `def`, the moved item defined in `module`,
+/// through its module. Other paths, like the ones using an import of the
+/// item, don't change when it is moved.
+fn find_item_refs(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    item: &ast::ModuleItem,
+    module: Module,
+    name: &str,
+    def: ModuleDef,
+) -> Vec<ItemRef> {
+    let item_range = item.syntax().text_range();
+    let mut res = Vec::new();
+    for (ref_file_id, name_ref) in search::find_name_refs(db, name) {
+        let range = name_ref.syntax().text_range();
+        if ref_file_id == file_id && range.is_subrange(&item_range) {
+            continue;
+        }
+        let path = match search::path_of_name_ref(&name_ref) {
+            Some(it) => it,
+            None => continue,
+        };
+        let ref_module = match source_binder::module_from_child_node(db, ref_file_id, path.syntax())
+        {
+            Some(it) => it,
+            None => continue,
+        };
+        let analyzer = hir::SourceAnalyzer::new(db, ref_file_id, path.syntax(), None);
+        let use_tree = path.syntax().ancestors().find_map(ast::UseTree::cast);
+        let qualifier = path.qualifier().map(|it| it.syntax().text().to_string());
+        let (resolution, qualifier) = match &use_tree {
+            Some(tree) => {
+                let segments: Vec<_> = use_tree_prefix(tree).into_iter().chain(qualifier).collect();
+                let full_path = segments.iter().map(String::as_str).chain(once(name));
+                let full_path = full_path.collect::<Vec<_>>().join("::");
+                let resolution =
+                    analyzer.resolve_path(db, &AstBuilder::<ast::Path>::from_text(&full_path));
+                let qualifier = if segments.is_empty() { None } else { Some(segments.join("::")) };
+                (resolution, qualifier)
+            }
+            None => (analyzer.resolve_path(db, &path), qualifier),
+        };
+        if resolution != Some(PathResolution::Def(def)) {
+            continue;
+        }
+        let is_qualified_by_module = match qualifier {
+            Some(qualifier) => {
+                let qualifier = AstBuilder::<ast::Path>::from_text(&qualifier);
+                analyzer.resolve_path(db, &qualifier)
+                    == Some(PathResolution::Def(ModuleDef::Module(module)))
+            }
+            // Outside of the old module, an unqualified name is imported,
+            // and the import is updated instead.
+            None => use_tree.is_none() && ref_module == module,
+        };
+        if is_qualified_by_module {
+            res.push(ItemRef {
+                file_id: ref_file_id,
+                name_ref,
+                path,
+                module: ref_module,
+                use_tree,
+            });
+        }
+    }
+    res
+}
+
+/// Updates the paths referring to the moved item, which is defined in
+/// `module`, and returns the visibility the item and its new module need.
+fn update_references(
+    db: &impl HirDatabase,
+    module: Module,
+    refs: &[ItemRef],
+    target: Option<Module>,
+    target_name: &str,
+    edits: &mut Vec<(FileId, TextRange, String)>,
+) -> Option<(RequiredVisibility, RequiredVisibility)> {
+    let krate = module.krate(db)?;
+    let mut item_visibility = RequiredVisibility::default();
+    let mut module_visibility = RequiredVisibility::default();
+    for item_ref in refs {
+        if let (Some(tree), Some(target)) = (&item_ref.use_tree, target) {
+            // The item is not imported into its own module anymore.
+            if item_ref.module == target
+                && tree.path() == Some(item_ref.path.clone())
+                && tree.alias().is_none()
+            {
+                edits.push((item_ref.file_id, use_tree_deletion_range(tree), String::new()));
+                continue;
+            }
+        }
+        let offset = item_ref.name_ref.syntax().text_range().start();
+        let insert = TextRange::offset_len(offset, 0.into());
+        edits.push((item_ref.file_id, insert, format!("{}::", target_name)));
+
+        let in_other_crate = item_ref.module.krate(db) != Some(krate);
+        if !target.map_or(false, |it| is_in(db, item_ref.module, it)) {
+            item_visibility.add(in_other_crate);
+        }
+        if !is_in(db, item_ref.module, module) {
+            module_visibility.add(in_other_crate);
+        }
+    }
+    Some((item_visibility, module_visibility))
+}
+
+/// Makes the private `members` of a moved item visible to their users outside
+/// of the target module.
+fn members_visibility(
+    db: &impl HirDatabase,
+    module: Module,
+    members: &[(SyntaxNode, Vec<Module>)],
+    target: Option<Module>,
+) -> Vec<(TextRange, String)> {
+    let krate = module.krate(db);
+    let mut res = Vec::new();
+    for (node, users) in members {
+        let mut visibility = RequiredVisibility::default();
+        for &user in users {
+            if !target.map_or(false, |target| is_in(db, user, target)) {
+                visibility.add(user.krate(db) != krate);
+            }
+        }
+        if let Some(vis) = visibility.keyword() {
+            let offset = visibility_offset(node);
+            res.push((TextRange::offset_len(offset, 0.into()), format!("{} ", vis)));
+        }
+    }
+    res
+}
+
+/// Finds the private methods of a moved inherent impl and the modules which
+/// call them.
+fn impl_items_users(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    module: Module,
+    imp: &ast::ImplBlock,
+) -> Vec<(SyntaxNode, Vec<Module>)> {
+    let mut res = Vec::new();
+    let item_list = match imp.item_list() {
+        Some(it) if imp.target_trait().is_none() => it,
+        _ => return res,
+    };
+    let imp_range = imp.syntax().text_range();
+    for fn_def in item_list.impl_items().filter_map(|it| ast::FnDef::cast(it.syntax().clone())) {
+        let name = match fn_def.name() {
+            Some(it) if fn_def.visibility().is_none() => it,
+            _ => continue,
+        };
+        let function = source_binder::function_from_module(db, module, &fn_def);
+        let mut users = Vec::new();
+        for (ref_file_id, name_ref) in search::find_name_refs(db, name.text()) {
+            if ref_file_id == file_id && name_ref.syntax().text_range().is_subrange(&imp_range) {
+                continue;
+            }
+            let analyzer = hir::SourceAnalyzer::new(db, ref_file_id, name_ref.syntax(), None);
+            let is_ref = match name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast) {
+                Some(call) => analyzer.resolve_method_call(&call) == Some(function),
+                None => search::path_of_name_ref(&name_ref)
+                    .and_then(|path| analyzer.resolve_path(db, &path))
+                    .map_or(false, |it| {
+                        it == PathResolution::AssocItem(ImplItem::Method(function))
+                    }),
+            };
+            if !is_ref {
+                continue;
+            }
+            users.extend(source_binder::module_from_child_node(db, ref_file_id, name_ref.syntax()));
+        }
+        res.push((fn_def.syntax().clone(), users));
+    }
+    res
+}
+
+/// Finds the private fields of a moved struct and the modules which use them
+/// in constructors, patterns and field accesses.
+fn struct_fields_users(
+    db: &impl HirDatabase,
+    strukt: &ast::StructDef,
+    hir_struct: hir::Struct,
+) -> Vec<(SyntaxNode, Vec<Module>)> {
+    let fields: Vec<(String, SyntaxNode)> = match strukt.kind() {
+        ast::StructKind::Named(list) => list
+            .fields()
+            .filter(|it| it.visibility().is_none())
+            .filter_map(|it| Some((it.name()?.text().to_string(), it.syntax().clone())))
+            .collect(),
+        ast::StructKind::Tuple(list) => list
+            .fields()
+            .enumerate()
+            .filter(|(_, it)| it.visibility().is_none())
+            .map(|(idx, it)| (idx.to_string(), it.syntax().clone()))
+            .collect(),
+        ast::StructKind::Unit => Vec::new(),
+    };
+    let name = match hir_struct.name(db) {
+        Some(it) if !fields.is_empty() => it.to_string(),
+        _ => return Vec::new(),
+    };
+    let user = |file_id: FileId, node: &SyntaxNode| {
+        source_binder::module_from_child_node(db, file_id, node)
+    };
+
+    // Constructors and patterns need all the fields.
+    let usages = search::find_constructor_usages(db, &[&name, "Self"], |res| match res {
+        PathResolution::Def(ModuleDef::Struct(it)) => it == hir_struct,
+        PathResolution::SelfType(imp) => {
+            imp.target_ty(db).as_adt().map(|(adt, _)| adt) == Some(AdtDef::Struct(hir_struct))
+        }
+        _ => false,
+    });
+    let all_fields: Vec<_> =
+        usages.iter().filter_map(|(file_id, usage)| user(*file_id, usage.syntax())).collect();
+
+    let mut field_exprs = Vec::new();
+    for (field_name, _) in fields.iter().filter(|(it, _)| it.parse::<usize>().is_err()) {
+        field_exprs.extend(search::find_name_refs(db, field_name).into_iter().filter_map(
+            |(file_id, name_ref)| {
+                Some((file_id, name_ref.syntax().parent().and_then(ast::FieldExpr::cast)?))
+            },
+        ));
+    }
+    if let ast::StructKind::Tuple(list) = strukt.kind() {
+        for file_id in search::files_with_tuple_fields(db, list.fields().count()) {
+            let parse = db.parse(file_id);
+            let tuple_field_exprs = parse
+                .tree()
+                .syntax()
+                .descendants()
+                .filter_map(ast::FieldExpr::cast)
+                .filter(|it| it.index_token().is_some());
+            field_exprs.extend(tuple_field_exprs.map(|it| (file_id, it)));
+        }
+    }
+    let mut field_users = vec![all_fields; fields.len()];
+    for (file_id, field_expr) in field_exprs {
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, field_expr.syntax(), None);
+        let field = match analyzer.resolve_field(&field_expr) {
+            Some(it) if it.parent_def(db) == VariantDef::Struct(hir_struct) => it,
+            _ => continue,
+        };
+        let field_name = field.name(db).to_string();
+        if let Some(idx) = fields.iter().position(|(it, _)| *it == field_name) {
+            field_users[idx].extend(user(file_id, field_expr.syntax()));
+        }
+    }
+    fields.into_iter().map(|(_, node)| node).zip(field_users).collect()
+}
+
+/// Finds the names the moved item uses from the scope of its old module,
+/// which must be imported into the target module. Returns `None` if the
+/// target module has a different item with one of these names.
+fn imports_from_parent(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    module: Module,
+    item: &ast::ModuleItem,
+    target_scope: &Option<(FileId, SyntaxNode)>,
+) -> Option<Vec<String>> {
+    let scope = module.scope(db);
+    let own_name = item_name(item).map(|it| it.text().to_string());
+    let mut candidates = Vec::new();
+    for node in item.syntax().descendants() {
+        if nesting_depth(item, &node) != 0 || node.ancestors().any(|it| it.kind() == USE_ITEM) {
+            continue;
+        }
+        if let Some(path) = ast::Path::cast(node.clone()) {
+            if path.qualifier().is_some() {
+                continue;
+            }
+            let name_ref = match path.segment().and_then(|it| it.kind()) {
+                Some(PathSegmentKind::Name(it)) => it,
+                _ => continue,
+            };
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, path.syntax(), None);
+            match analyzer.resolve_path(db, &path) {
+                Some(PathResolution::Def(ModuleDef::BuiltinType(_))) | None => (),
+                Some(PathResolution::Def(def)) => {
+                    candidates.push((name_ref.text().to_string(), def))
+                }
+                Some(_) => (),
+            }
+        } else if let Some(call) = ast::MethodCallExpr::cast(node) {
+            // A trait must be in scope for its methods to be called.
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, call.syntax(), None);
+            let tr = match analyzer.resolve_method_call(&call).and_then(|it| it.parent_trait(db)) {
+                Some(it) => it,
+                None => continue,
+            };
+            let def = ModuleDef::Trait(tr);
+            if let Some((name, _)) = scope.entries().find(|(_, res)| res.def.types == Some(def)) {
+                candidates.push((name.to_string(), def));
+            }
+        }
+    }
+
+    let mut res = Vec::new();
+    for (name, def) in candidates {
+        if Some(&name) == own_name.as_ref() || res.contains(&name) {
+            continue;
+        }
+        let in_parent_scope = scope.entries().any(|(it, res)| {
+            it.to_string() == name && (res.def.types == Some(def) || res.def.values == Some(def))
+        });
+        if !in_parent_scope {
+            continue;
+        }
+        if let Some(target_scope) = target_scope {
+            match resolve_in(db, target_scope, &name) {
+                None => (),
+                Some(PathResolution::Def(it)) if it == def => continue,
+                Some(_) => return None,
+            }
+        }
+        res.push(name);
+    }
+    res.sort();
+    Some(res)
+}
+
+/// Fixes the paths of the moved item which are relative to its old module:
+/// `self::` becomes `super::`, and `super::` becomes `super::super::`.
+fn relative_path_edits(item: &ast::ModuleItem) -> Vec<(TextRange, String)> {
+    let mut res = Vec::new();
+    for path in item.syntax().descendants().filter_map(ast::Path::cast) {
+        if path.qualifier().is_some() || is_in_use_tree_list(&path) {
+            continue;
+        }
+        let depth = nesting_depth(item, path.syntax());
+        let segment_range = path.syntax().text_range();
+        // A `self` expression refers to the receiver of a method instead.
+        let is_module_path =
+            path.syntax().parent().map_or(false, |it| it.kind() == PATH || it.kind() == USE_TREE);
+        match path.segment().and_then(|it| it.kind()) {
+            Some(PathSegmentKind::SelfKw) if depth == 0 && is_module_path => {
+                res.push((segment_range, "super".to_string()))
+            }
+            Some(PathSegmentKind::SuperKw) => {
+                let supers = successors(Some(path.clone()), |it| {
+                    it.syntax().parent().and_then(ast::Path::cast)
+                })
+                .take_while(|it| match it.segment().and_then(|it| it.kind()) {
+                    Some(PathSegmentKind::SuperKw) => true,
+                    _ => false,
+                })
+                .count();
+                if supers >= depth {
+                    let start = TextRange::offset_len(segment_range.start(), 0.into());
+                    res.push((start, "super::".to_string()));
+                }
+            }
+            _ => (),
+        }
+    }
+    res
+}
+
+/// The number of modules of the moved item around `node`, including the
+/// item itself if it is a module.
+fn nesting_depth(item: &ast::ModuleItem, node: &SyntaxNode) -> usize {
+    let mut depth = 0;
+    for it in node.ancestors() {
+        if it.kind() == MODULE && it != *node {
+            depth += 1;
+        }
+        if it == *item.syntax() {
+            break;
+        }
+    }
+    depth
+}
+
+fn is_in_use_tree_list(path: &ast::Path) -> bool {
+    let top =
+        successors(Some(path.syntax().clone()), |it| it.parent().filter(|it| it.kind() == PATH))
+            .last();
+    top.and_then(|it| it.parent())
+        .and_then(|it| it.parent())
+        .map_or(false, |it| it.kind() == USE_TREE_LIST)
+}
+
+/// The node whose scope is the scope of `module`.
+fn module_scope(db: &impl HirDatabase, module: Module) -> Option<(FileId, SyntaxNode)> {
+    let src = module.definition_source(db);
+    let node = match src.ast {
+        ModuleSource::SourceFile(it) => it.syntax().clone(),
+        ModuleSource::Module(it) => it.item_list()?.syntax().clone(),
+    };
+    Some((src.file_id.original_file(db), node))
+}
+
+fn resolve_in(
+    db: &impl HirDatabase,
+    (file_id, node): &(FileId, SyntaxNode),
+    name: &str,
+) -> Option<PathResolution> {
+    let analyzer = hir::SourceAnalyzer::new(db, *file_id, node, None);
+    analyzer.resolve_path(db, &AstBuilder::<ast::Path>::from_text(name))
+}
+
+/// The segments of the paths of the use trees around `tree`, as in `a::b`
+/// for `c` in `use a::{b::{c, d}}`.
+fn use_tree_prefix(tree: &ast::UseTree) -> Vec<String> {
+    let parents = successors(Some(tree.clone()), |it| {
+        it.syntax().parent()?.parent().and_then(ast::UseTree::cast)
+    });
+    let mut res: Vec<_> =
+        parents.skip(1).filter_map(|it| Some(it.path()?.syntax().text().to_string())).collect();
+    res.reverse();
+    res
+}
+
+/// Builds the edit inserting `block` at the end of the `target` module.
+fn insertion_into(
+    db: &impl HirDatabase,
+    target: Module,
+    block: &str,
+) -> Option<(FileId, TextRange, String)> {
+    let src = target.definition_source(db);
+    let file_id = src.file_id.original_file(db);
+    match src.ast {
+        ModuleSource::Module(module) => {
+            let outer_indent = leading_indent(module.syntax()).unwrap_or_default();
+            let indent = format!("{}    ", outer_indent);
+            let r_curly = module.item_list()?.syntax().last_child_or_token()?;
+            let ws = r_curly.prev_sibling_or_token().filter(|it| it.kind() == WHITESPACE);
+            let prev = match &ws {
+                Some(ws) => ws.prev_sibling_or_token()?,
+                None => r_curly.prev_sibling_or_token()?,
+            };
+            let has_newline = ws
+                .as_ref()
+                .and_then(|it| it.as_token())
+                .map_or(false, |it| it.text().contains('\n'));
+            let separator = if prev.kind() == T!['{'] { "\n" } else { "\n\n" };
+            let suffix = if has_newline { String::new() } else { format!("\n{}", outer_indent) };
+            let text = format!("{}{}{}{}", separator, indent, reindent(block, "", &indent), suffix);
+            Some((file_id, TextRange::offset_len(prev.text_range().end(), 0.into()), text))
+        }
+        ModuleSource::SourceFile(file) => {
+            let text = db.file_text(file_id);
+            let separator = match text.as_str() {
+                "" => "",
+                it if it.ends_with('\n') => "\n",
+                _ => "\n\n",
+            };
+            let end = file.syntax().text_range().end();
+            Some((
+                file_id,
+                TextRange::offset_len(end, 0.into()),
+                format!("{}{}\n", separator, block),
+            ))
+        }
+    }
+}
+
+/// The range to delete to remove an item, along with the whitespace which
+/// separates it from the next one.
+fn deletion_range(node: &SyntaxNode) -> TextRange {
+    let next = node
+        .next_sibling_or_token()
+        .filter(|it| it.kind() == WHITESPACE)
+        .and_then(|ws| ws.next_sibling_or_token());
+    match next {
+        Some(next) if next.kind() != T!['}'] => {
+            TextRange::from_to(node.text_range().start(), next.text_range().start())
+        }
+        _ => extend_to_whitespace(node, Direction::Prev),
+    }
+}
+
+/// The range to delete to remove a use tree, along with the comma which
+/// separates it from its neighbours.
+fn use_tree_deletion_range(tree: &ast::UseTree) -> TextRange {
+    if let Some(use_item) = tree.syntax().parent().and_then(ast::UseItem::cast) {
+        return deletion_range(use_item.syntax());
+    }
+    let range = tree.syntax().text_range();
+    let element = SyntaxElement::from(tree.syntax().clone());
+    if let Some(comma) =
+        non_trivia_sibling(&element, Direction::Next).filter(|it| it.kind() == T![,])
+    {
+        let end = non_trivia_sibling(&comma, Direction::Next)
+            .map_or(comma.text_range().end(), |it| it.text_range().start());
+        return TextRange::from_to(range.start(), end);
+    }
+    match non_trivia_sibling(&element, Direction::Prev).filter(|it| it.kind() == T![,]) {
+        Some(comma) => TextRange::from_to(comma.text_range().start(), range.end()),
+        None => range,
+    }
+}
+
+fn non_trivia_sibling(element: &SyntaxElement, direction: Direction) -> Option<SyntaxElement> {
+    let step = |it: &SyntaxElement| match direction {
+        Direction::Next => it.next_sibling_or_token(),
+        Direction::Prev => it.prev_sibling_or_token(),
+    };
+    successors(step(element), step).find(|it| it.kind() != WHITESPACE)
+}
+
+/// The offset where the visibility of an item goes, after its attributes and
+/// doc comments.
+fn visibility_offset(item: &SyntaxNode) -> TextUnit {
+    item.children_with_tokens()
+        .find(|it| match it.kind() {
+            ATTR | COMMENT | WHITESPACE => false,
+            _ => true,
+        })
+        .map_or(item.text_range().start(), |it| it.text_range().start())
+}
+
+/// Returns the text of `range` with `edits` applied. Insertions are given as
+/// empty ranges.
+fn render(text: &str, range: TextRange, mut edits: Vec<(TextRange, String)>) -> String {
+    edits.sort_by_key(|(range, _)| range.start());
+    let mut res = String::new();
+    let mut offset = range.start();
+    for (edit_range, replace_with) in edits {
+        res.push_str(&text[TextRange::from_to(offset, edit_range.start())]);
+        res.push_str(&replace_with);
+        offset = edit_range.end();
+    }
+    res.push_str(&text[TextRange::from_to(offset, range.end())]);
+    res
+}
+
+/// Changes the indentation of all lines but the first one from `old_indent`
+/// to `new_indent`.
+fn reindent(text: &str, old_indent: &str, new_indent: &str) -> String {
+    let mut lines = text.lines();
+    let mut res = lines.next().unwrap_or_default().to_string();
+    for line in lines {
+        res.push('\n');
+        let line = if line.starts_with(old_indent) { &line[old_indent.len()..] } else { line };
+        if !line.trim().is_empty() {
+            res.push_str(new_indent);
+            res.push_str(line);
+        }
+    }
+    res
+}
+
+/// The directory of the files of the child modules of `module`.
+fn child_module_dir(
+    db: &impl HirDatabase,
+    module: Module,
+) -> Option<(SourceRootId, RelativePathBuf)> {
+    let mut inline_names = Vec::new();
+    let mut file_module = module;
+    while let ModuleSource::Module(_) = file_module.definition_source(db).ast {
+        inline_names.push(file_module.name(db)?.to_string());
+        file_module = file_module.parent(db)?;
+    }
+    let file_id = file_module.definition_source(db).file_id.original_file(db);
+    let path = db.file_relative_path(file_id);
+    let root = RelativePathBuf::default();
+    let mut dir = path.parent().unwrap_or(&root).to_owned();
+    if file_module.parent(db).is_some() && path.file_stem() != Some("mod") {
+        dir = dir.join(path.file_stem()?);
+    }
+    for name in inline_names.iter().rev() {
+        dir = dir.join(name);
+    }
+    Some((db.file_source_root(file_id), dir))
+}
+
+/// The path of the file of a new module named `name`, if neither this path
+/// nor `name/mod.rs` exist.
+fn new_module_path(
+    db: &impl HirDatabase,
+    source_root: SourceRootId,
+    dir: &RelativePath,
+    name: &str,
+) -> Option<RelativePathBuf> {
+    let path = dir.join(format!("{}.rs", name));
+    let files = &db.source_root(source_root).files;
+    if files.contains_key(&path) || files.contains_key(&dir.join(format!("{}/mod.rs", name))) {
+        return None;
+    }
+    Some(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist_in_files, check_assist_not_applicable};
+
+    #[test]
+    fn move_struct_to_inline_module() {
+        check_assist_in_files(
+            move_item_to_module,
+            r#"
+//- /lib.rs
+struct Point<|> {
+    x: i32,
+}
+
+fn origin() -> Point {
+    Point { x: 0 }
+}
+
+mod geometry {
+    fn f() {}
+}
+"#,
+            r#"
+//- /lib.rs
+fn origin() -> geometry::Point {
+    geometry::Point { x: 0 }
+}
+
+mod geometry {
+    fn f() {}
+
+    pub(crate) struct Point {
+        pub(crate) x: i32,
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn move_impl_keeps_self_expressions() {
+        check_assist_in_files(
+            move_item_to_module,
+            r#"
+//- /lib.rs
+struct Point {
+    x: i32,
+}
+
+impl<|> Point {
+    fn x(&self) -> i32 {
+        self.x
+    }
+}
+
+mod geometry {
+    fn f() {}
+}
+"#,
+            r#"
+//- /lib.rs
+struct Point {
+    x: i32,
+}
+
+mod geometry {
+    fn f() {}
+
+    use super::Point;
+
+    impl Point {
+        fn x(&self) -> i32 {
+            self.x
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn move_item_updates_imports() {
+        check_assist_in_files(
+            move_item_to_module,
+            r#"
+//- /lib.rs
+mod shapes;
+mod app;
+
+//- /app.rs
+use crate::shapes::Circle;
+
+fn f(c: Circle) -> f64 {
+    c.r
+}
+
+//- /shapes.rs
+mod circle;
+
+pub struct Circle<|> {
+    pub r: f64,
+}
+
+//- /shapes/circle.rs
+fn unit() {}
+"#,
+            r#"
+//- /lib.rs
+mod shapes;
+mod app;
+
+//- /app.rs
+use crate::shapes::circle::Circle;
+
+fn f(c: Circle) -> f64 {
+    c.r
+}
+
+//- /shapes.rs
+pub(crate) mod circle;
+
+//- /shapes/circle.rs
+fn unit() {}
+
+pub struct Circle {
+    pub r: f64,
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn move_fn_to_new_file() {
+        check_assist_in_files(
+            move_item_to_new_file,
+            r#"
+//- /lib.rs
+struct Config;
+
+fn helper() -> u32 {
+    1
+}
+
+fn parse_config<|>() -> Config {
+    let _ = self::helper();
+    Config
+}
+
+fn main() {
+    parse_config();
+}
+"#,
+            r#"
+//- /lib.rs
+struct Config;
+
+fn helper() -> u32 {
+    1
+}
+
+mod parse_config;
+
+fn main() {
+    parse_config::parse_config();
+}
+//- /parse_config.rs
+use super::Config;
+
+pub(crate) fn parse_config() -> Config {
+    let _ = super::helper();
+    Config
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn move_inline_module_to_file() {
+        check_assist_in_files(
+            move_module_to_file,
+            r#"
+//- /lib.rs
+mod foo<|> {
+    fn bar() {}
+
+    struct S;
+}
+"#,
+            r#"
+//- /lib.rs
+mod foo;
+//- /foo.rs
+fn bar() {}
+
+struct S;
+"#,
+        );
+    }
+
+    #[test]
+    fn move_item_not_applicable_in_body() {
+        check_assist_not_applicable(
+            move_item_to_new_file,
+            "
+            fn foo() {
+                let x<|> = 1;
+            }
+            ",
+        );
+    }
+
+    #[test]
+    fn move_item_not_applicable_to_imports() {
+        check_assist_not_applicable(
+            move_item_to_new_file,
+            "
+            use std::<|>fmt;
+            ",
+        );
+    }
+}
@@ -0,0 +1,660 @@
+//! Merges, sorts and deduplicates the imports of a module and removes the
+//! unused ones. The unused imports are also reported as diagnostics.
+
+use std::iter::{self, successors};
+
+use hir::{
+    db::HirDatabase, source_binder, ImplItem, ModuleDef, ModuleSource, PathResolution, Source,
+};
+use ra_db::FileId;
+use ra_fmt::leading_indent;
+use ra_syntax::{
+    ast::{self, AstNode, AttrsOwner, NameOwner, VisibilityOwner},
+    Direction, SmolStr,
+    SyntaxKind::*,
+    SyntaxNode, TextRange,
+};
+
+use crate::{ast_editor::AstBuilder, Assist, AssistCtx, AssistId};
+
+pub(crate) fn organize_imports(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let use_item = ctx.node_at_offset::<ast::UseItem>()?;
+    let scope = use_item.syntax().parent()?;
+    let use_items = organizable_use_items(&scope);
+    if !use_items.contains(&use_item) {
+        return None;
+    }
+    let unused = unused_in_scope(ctx.db, ctx.frange.file_id, &scope, &use_items);
+
+    let mut trie = UseTrie::default();
+    for item in use_items.iter() {
+        for import in flatten(&item.use_tree()?)? {
+            if !unused.contains(&import.tree) {
+                trie.insert(&import.path, import.alias);
+            }
+        }
+    }
+
+    let first = use_items.first()?;
+    let last = use_items.last()?;
+    let indent = leading_indent(first.syntax()).unwrap_or_default();
+    let text = trie.render_items(&indent);
+    let range =
+        TextRange::from_to(first.syntax().text_range().start(), last.syntax().text_range().end());
+    let root = scope.ancestors().last()?;
+    if root.text().slice(range).to_string() == text {
+        return None;
+    }
+
+    ctx.add_action(AssistId("organize_imports"), "organize imports", |edit| {
+        edit.target(range);
+        if text.is_empty() {
+            edit.delete(extend_to_whitespace(first.syntax(), Direction::Next));
+        } else {
+            edit.replace(first.syntax().text_range(), text);
+        }
+        for item in use_items[1..].iter() {
+            edit.delete(extend_to_whitespace(item.syntax(), Direction::Prev));
+        }
+        edit.set_cursor(first.syntax().text_range().start());
+    });
+    ctx.build()
+}
+
+/// Finds the imports in `file_id` which are not used in their module. The
+/// leaves of the use trees are returned.
+pub fn unused_imports(db: &impl HirDatabase, file_id: FileId) -> Vec<ast::UseTree> {
+    let file = db.parse(file_id).tree();
+    let modules = file.syntax().descendants().filter_map(ast::Module::cast);
+    let scopes = iter::once(file.syntax().clone())
+        .chain(modules.filter_map(|it| it.item_list()).map(|it| it.syntax().clone()));
+
+    let mut res = Vec::new();
+    for scope in scopes {
+        let use_items = organizable_use_items(&scope);
+        res.extend(unused_in_scope(db, file_id, &scope, &use_items));
+    }
+    res
+}
+
+/// The `use` items of the module which can be rewritten: the private ones
+/// without attributes.
+fn organizable_use_items(scope: &SyntaxNode) -> Vec<ast::UseItem> {
+    scope
+        .children()
+        .filter_map(ast::UseItem::cast)
+        .filter(|it| it.visibility().is_none() && it.attrs().next().is_none())
+        .collect()
+}
+
+/// An imported name: a leaf of a use tree.
+struct Import {
+    tree: ast::UseTree,
+    path: Vec<SmolStr>,
+    alias: Option<SmolStr>,
+}
+
+impl Import {
+    /// The name the import brings into scope, `None` for globs.
+    fn name(&self) -> Option<&SmolStr> {
+        if self.path.last()? == "*" {
+            return None;
+        }
+        self.alias.as_ref().or_else(|| self.path.last())
+    }
+}
+
+/// Flattens `tree` into the imports of its leaves. Returns `None` for trees
+/// which can't be rewritten, like the ones with leading `::`.
+fn flatten(tree: &ast::UseTree) -> Option<Vec<Import>> {
+    fn go(tree: &ast::UseTree, prefix: &[SmolStr], acc: &mut Vec<Import>) -> Option<()> {
+        let mut path = prefix.to_vec();
+        if let Some(it) = tree.path() {
+            let segments: Vec<ast::PathSegment> =
+                successors(Some(it), |it| it.qualifier()).filter_map(|it| it.segment()).collect();
+            for segment in segments.into_iter().rev() {
+                if segment.has_colon_colon() {
+                    return None;
+                }
+                path.push(segment.syntax().text().to_string().into());
+            }
+        }
+        if tree.has_star() {
+            path.push("*".into());
+            acc.push(Import { tree: tree.clone(), path, alias: None });
+        } else if let Some(list) = tree.use_tree_list() {
+            for it in list.use_trees() {
+                go(&it, &path, acc)?;
+            }
+        } else {
+            if path.last()? == "self" {
+                path.pop();
+                path.last()?;
+            }
+            let alias = tree.alias().map(|it| match it.name() {
+                Some(name) => name.text().clone(),
+                None => "_".into(),
+            });
+            acc.push(Import { tree: tree.clone(), path, alias });
+        }
+        Some(())
+    }
+
+    let mut res = Vec::new();
+    go(tree, &[], &mut res)?;
+    Some(res)
+}
+
+/// Finds the imports of `use_items` which are not used in `scope`.
+///
+/// An import is used if a path starting with its name resolves to an item, if
+/// a path like `self::A` or `super::A` in a child module refers to it through
+/// the module, if its name appears in a macro call or an attribute, or, for
+/// traits, if a
+/// method of the trait is called. Paths which don't resolve count as uses, so
+/// that imports are only reported when it is certain they are unused. For the
+/// same reason, traits count as used when the scope contains macro calls,
+/// method calls which don't resolve or qualified paths which don't resolve,
+/// like `u32::from_str`.
+fn unused_in_scope(
+    db: &impl HirDatabase,
+    file_id: FileId,
+    scope: &SyntaxNode,
+    use_items: &[ast::UseItem],
+) -> Vec<ast::UseTree> {
+    let imports: Vec<Import> = use_items
+        .iter()
+        .filter_map(|it| flatten(&it.use_tree()?))
+        .flatten()
+        .filter(|it| it.name().map_or(false, |name| name != "_"))
+        .collect();
+    if imports.is_empty() || has_child_glob_import(db, file_id, scope) {
+        return Vec::new();
+    }
+    let in_use_items =
+        |node: &SyntaxNode| use_items.iter().any(|it| node.ancestors().any(|a| &a == it.syntax()));
+    let module = source_binder::module_from_child_node(db, file_id, scope);
+
+    let mut used_names = Vec::new();
+    let mut used_traits = Vec::new();
+    let mut may_use_any_trait = false;
+    for node in scope.descendants().filter(|it| !in_use_items(it)) {
+        if node.kind() == MACRO_CALL {
+            may_use_any_trait = true;
+        } else if node.kind() == TOKEN_TREE {
+            for token in node.descendants_with_tokens().filter_map(|it| it.into_token()) {
+                if token.kind() == IDENT {
+                    used_names.push(token.text().clone());
+                }
+            }
+        } else if let Some(call) = ast::MethodCallExpr::cast(node.clone()) {
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, call.syntax(), None);
+            match analyzer.resolve_method_call(&call) {
+                Some(func) => used_traits.extend(function_trait(db, func)),
+                None => may_use_any_trait = true,
+            }
+        } else if let Some(path) = ast::Path::cast(node.clone()) {
+            if path.qualifier().is_none()
+                || path.syntax().parent().map(|it| it.kind()) == Some(PATH)
+            {
+                continue;
+            }
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, path.syntax(), None);
+            match analyzer.resolve_path(db, &path) {
+                Some(PathResolution::AssocItem(ImplItem::Method(func))) => {
+                    used_traits.extend(function_trait(db, func))
+                }
+                Some(_) => (),
+                None => may_use_any_trait = true,
+            }
+        } else if let Some(name_ref) = ast::NameRef::cast(node.clone()) {
+            if !imports.iter().any(|it| it.name() == Some(name_ref.text())) {
+                continue;
+            }
+            let path = match name_ref.syntax().ancestors().find_map(ast::Path::cast) {
+                Some(it) => it,
+                None => continue,
+            };
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, path.syntax(), None);
+            if let Some(qualifier) = path.qualifier() {
+                if !is_module_relative(&qualifier) {
+                    continue;
+                }
+                match analyzer.resolve_path(db, &qualifier) {
+                    Some(PathResolution::Def(ModuleDef::Module(it))) if Some(it) == module => {
+                        used_names.push(name_ref.text().clone())
+                    }
+                    _ => (),
+                }
+                continue;
+            }
+            match analyzer.resolve_path(db, &path) {
+                Some(PathResolution::LocalBinding(_))
+                | Some(PathResolution::GenericParam(_))
+                | Some(PathResolution::SelfType(_)) => (),
+                _ => used_names.push(name_ref.text().clone()),
+            }
+        }
+    }
+
+    let mut res = Vec::new();
+    for import in imports {
+        let name = match import.name() {
+            Some(it) => it,
+            None => continue,
+        };
+        if used_names.contains(name) {
+            continue;
+        }
+        let path = AstBuilder::<ast::Path>::from_text(&import.path.join("::"));
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, import.tree.syntax(), None);
+        match analyzer.resolve_path(db, &path) {
+            Some(PathResolution::Def(ModuleDef::Trait(tr)))
+                if may_use_any_trait || used_traits.contains(&tr) => {}
+            _ => res.push(import.tree),
+        }
+    }
+    res
+}
+
+/// Checks if `path` only consists of `self`, `super` and `crate`.
+fn is_module_relative(path: &ast::Path) -> bool {
+    successors(Some(path.clone()), |it| it.qualifier()).all(|it| {
+        match it.segment().and_then(|it| it.kind()) {
+            Some(ast::PathSegmentKind::SelfKw)
+            | Some(ast::PathSegmentKind::SuperKw)
+            | Some(ast::PathSegmentKind::CrateKw) => true,
+            _ => false,
+        }
+    })
+}
+
+/// The trait which declares or implements `func`, if any.
+fn function_trait(db: &impl HirDatabase, func: hir::Function) -> Option<hir::Trait> {
+    func.parent_trait(db).or_else(|| Some(func.impl_block(db)?.target_trait_ref(db)?.trait_))
+}
+
+/// Checks if a child module of `scope` imports everything from its parent
+/// with `use super::*`, which also brings the private imports of the parent
+/// into scope. Out-of-line child modules are searched in their own files.
+fn has_child_glob_import(db: &impl HirDatabase, file_id: FileId, scope: &SyntaxNode) -> bool {
+    scope.descendants().any(|node| {
+        if let Some(tree) = ast::UseTree::cast(node.clone()) {
+            return tree.has_star()
+                && tree.path().map_or(false, |it| it.syntax().text().to_string() == "super");
+        }
+        let module = match ast::Module::cast(node) {
+            Some(it) if it.has_semi() => it,
+            _ => return false,
+        };
+        let name = module.name().map(|it| it.text().to_string());
+        let parent = source_binder::module_from_child_node(db, file_id, module.syntax());
+        let child = parent
+            .into_iter()
+            .flat_map(|it| it.children(db))
+            .find(|child| child.name(db).map(|it| it.to_string()) == name);
+        match child.map(|it| it.definition_source(db)) {
+            Some(Source { file_id: child_file_id, ast: ModuleSource::SourceFile(file) }) => {
+                has_child_glob_import(db, child_file_id.original_file(db), file.syntax())
+            }
+            _ => false,
+        }
+    })
+}
+
+/// The range of `node` together with the whitespace next to it in
+/// `direction`.
+pub(crate) fn extend_to_whitespace(node: &SyntaxNode, direction: Direction) -> TextRange {
+    let range = node.text_range();
+    let ws = match direction {
+        Direction::Next => node.next_sibling_or_token(),
+        Direction::Prev => node.prev_sibling_or_token(),
+    };
+    match ws.filter(|it| it.kind() == WHITESPACE) {
+        Some(ws) => match direction {
+            Direction::Next => TextRange::from_to(range.start(), ws.text_range().end()),
+            Direction::Prev => TextRange::from_to(ws.text_range().start(), range.end()),
+        },
+        None => range,
+    }
+}
+
+/// The imports of a module merged by common prefixes.
+#[derive(Default)]
+struct UseTrie {
+    /// Whether the path leading to this node is imported itself.
+    is_leaf: bool,
+    aliases: Vec<SmolStr>,
+    children: Vec<(SmolStr, UseTrie)>,
+}
+
+impl UseTrie {
+    fn insert(&mut self, path: &[SmolStr], alias: Option<SmolStr>) {
+        let (first, rest) = match path.split_first() {
+            Some(it) => it,
+            None => {
+                match alias {
+                    Some(alias) => {
+                        if !self.aliases.contains(&alias) {
+                            self.aliases.push(alias);
+                        }
+                    }
+                    None => self.is_leaf = true,
+                }
+                return;
+            }
+        };
+        let idx = match self.children.iter().position(|(segment, _)| segment == first) {
+            Some(idx) => idx,
+            None => {
+                self.children.push((first.clone(), UseTrie::default()));
+                self.children.len() - 1
+            }
+        };
+        self.children[idx].1.insert(rest, alias);
+    }
+
+    /// Renders the top-level tries as `use` items: the imports from `std`,
+    /// `core` and `alloc` first, then the ones from other crates and then the
+    /// ones from the current crate, with blank lines between the groups.
+    fn render_items(&self, indent: &str) -> String {
+        let mut groups: [Vec<String>; 3] = Default::default();
+        for (segment, child) in self.sorted_children() {
+            let group = match segment.as_str() {
+                "std" | "core" | "alloc" => 0,
+                "crate" | "self" | "super" => 2,
+                _ => 1,
+            };
+            for entry in child.entries_for(segment) {
+                groups[group].push(format!("use {};", entry));
+            }
+        }
+        let groups: Vec<String> = groups
+            .iter()
+            .filter(|it| !it.is_empty())
+            .map(|it| it.join(&format!("\n{}", indent)))
+            .collect();
+        groups.join(&format!("\n\n{}", indent))
+    }
+
+    /// The entries of the use tree list for the children of this trie.
+    fn entries(&self) -> Vec<String> {
+        let mut res = Vec::new();
+        if self.is_leaf {
+            res.push("self".to_string());
+        }
+        let mut aliases = self.aliases.clone();
+        aliases.sort();
+        res.extend(aliases.into_iter().map(|it| format!("self as {}", it)));
+        for (segment, child) in self.sorted_children() {
+            res.extend(child.entries_for(segment));
+        }
+        res
+    }
+
+    /// The entries for this trie as the child `segment` of its parent: one
+    /// entry per import if this is a leaf, else a single, possibly nested,
+    /// entry.
+    fn entries_for(&self, segment: &str) -> Vec<String> {
+        if self.children.is_empty() {
+            let mut res = Vec::new();
+            if self.is_leaf {
+                res.push(segment.to_string());
+            }
+            let mut aliases = self.aliases.clone();
+            aliases.sort();
+            res.extend(aliases.into_iter().map(|it| format!("{} as {}", segment, it)));
+            return res;
+        }
+        let entries = self.entries();
+        let entry = if entries.len() == 1 && !entries[0].starts_with("self") {
+            format!("{}::{}", segment, entries[0])
+        } else {
+            format!("{}::{{{}}}", segment, entries.join(", "))
+        };
+        vec![entry]
+    }
+
+    fn sorted_children(&self) -> Vec<(&SmolStr, &UseTrie)> {
+        let mut res: Vec<_> = self.children.iter().map(|(segment, it)| (segment, it)).collect();
+        res.sort_by_key(|(segment, _)| segment_key(segment));
+        res
+    }
+}
+
+/// Sorts modules and functions before types, types before constants and
+/// macros and globs last, like rustfmt does.
+fn segment_key(segment: &str) -> (u8, &str) {
+    let kind = if segment == "*" {
+        3
+    } else if segment.starts_with(|c: char| c.is_lowercase() || c == '_') {
+        0
+    } else if segment.chars().any(|c| c.is_lowercase()) {
+        1
+    } else {
+        2
+    };
+    (kind, segment)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable};
+
+    #[test]
+    fn organize_imports_merges_and_removes_unused() {
+        check_assist(
+            organize_imports,
+            "
+mod foo {
+    pub struct A;
+    pub struct B;
+    pub fn c() {}
+}
+mod bar {
+    pub struct D;
+}
+use crate::foo::B;
+use crate::bar::D;<|>
+use crate::foo::{A, c};
+use crate::foo::A;
+
+fn main() {
+    let _ = A;
+    c();
+    let _: B;
+}",
+            "
+mod foo {
+    pub struct A;
+    pub struct B;
+    pub fn c() {}
+}
+mod bar {
+    pub struct D;
+}
+<|>use crate::foo::{c, A, B};
+
+fn main() {
+    let _ = A;
+    c();
+    let _: B;
+}",
+        );
+    }
+
+    #[test]
+    fn organize_imports_groups() {
+        check_assist(
+            organize_imports,
+            "
+use crate::foo::Foo;
+use std::fmt;<|>
+use itertools::Itertools;
+use std::collections::HashMap;
+
+mod foo {
+    pub struct Foo;
+}
+
+fn main() {
+    let _: HashMap = Foo;
+    fmt::foo();
+    Itertools::bar();
+}",
+            "
+<|>use std::{collections::HashMap, fmt};
+
+use itertools::Itertools;
+
+use crate::foo::Foo;
+
+mod foo {
+    pub struct Foo;
+}
+
+fn main() {
+    let _: HashMap = Foo;
+    fmt::foo();
+    Itertools::bar();
+}",
+        );
+    }
+
+    #[test]
+    fn organize_imports_keeps_traits_used_for_methods() {
+        check_assist(
+            organize_imports,
+            "
+mod t {
+    pub trait Tr { fn m(&self) {} }
+    impl Tr for u32 {}
+    pub struct S;
+}
+use crate::t::{Tr, S};<|>
+
+fn main() {
+    1u32.m();
+}",
+            "
+mod t {
+    pub trait Tr { fn m(&self) {} }
+    impl Tr for u32 {}
+    pub struct S;
+}
+<|>use crate::t::Tr;
+
+fn main() {
+    1u32.m();
+}",
+        );
+    }
+
+    #[test]
+    fn organize_imports_keeps_traits_when_macros_are_called() {
+        check_assist(
+            organize_imports,
+            "
+mod t {
+    pub trait Tr { fn write_fmt(&mut self) {} }
+    pub struct S;
+}
+use crate::t::{Tr, S};<|>
+
+fn main() {
+    write!(w, \"{}\", 1);
+}",
+            "
+mod t {
+    pub trait Tr { fn write_fmt(&mut self) {} }
+    pub struct S;
+}
+<|>use crate::t::Tr;
+
+fn main() {
+    write!(w, \"{}\", 1);
+}",
+        );
+    }
+
+    #[test]
+    fn organize_imports_keeps_traits_used_through_paths() {
+        check_assist(
+            organize_imports,
+            "
+mod t {
+    pub trait Parse { fn parse(s: &str) -> Self; }
+    impl Parse for u32 { fn parse(s: &str) -> u32 { 0 } }
+    pub struct S;
+}
+use crate::t::{Parse, S};<|>
+
+fn main() {
+    let _ = u32::parse(\"1\");
+}",
+            "
+mod t {
+    pub trait Parse { fn parse(s: &str) -> Self; }
+    impl Parse for u32 { fn parse(s: &str) -> u32 { 0 } }
+    pub struct S;
+}
+<|>use crate::t::Parse;
+
+fn main() {
+    let _ = u32::parse(\"1\");
+}",
+        );
+    }
+
+    #[test]
+    fn organize_imports_keeps_imports_used_through_module_paths() {
+        check_assist(
+            organize_imports,
+            "
+mod t {
+    pub struct A;
+    pub struct B;
+    pub struct C;
+}
+use crate::t::{A, B, C};<|>
+
+mod inner {
+    use super::A;
+}
+
+fn main() {
+    let _ = self::B;
+}",
+            "
+mod t {
+    pub struct A;
+    pub struct B;
+    pub struct C;
+}
+<|>use crate::t::{A, B};
+
+mod inner {
+    use super::A;
+}
+
+fn main() {
+    let _ = self::B;
+}",
+        );
+    }
+
+    #[test]
+    fn organize_imports_not_applicable_when_organized() {
+        check_assist_not_applicable(
+            organize_imports,
+            "
+use std::{collections::HashMap, fmt};<|>
+
+fn main() {
+    let _: HashMap;
+    fmt::foo();
+}",
+        );
+    }
+}
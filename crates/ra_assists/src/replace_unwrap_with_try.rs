@@ -0,0 +1,170 @@
+use hir::{db::HirDatabase, source_binder, AdtDef, CallableDef, SourceAnalyzer, Ty};
+use ra_syntax::{
+    ast::{self, AstNode},
+    SyntaxKind::LAMBDA_EXPR,
+};
+
+use crate::{Assist, AssistCtx, AssistId};
+
+/// Replaces `x.unwrap()` with `x?`, when `x` is a `Result` or an `Option` and
+/// the enclosing function returns the same kind of value. For `Result`, the
+/// error types must be the same.
+pub(crate) fn replace_unwrap_with_try(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let call = ctx.node_at_offset::<ast::MethodCallExpr>()?;
+    let name_ref = call.name_ref()?;
+    if name_ref.text().as_str() != "unwrap"
+        || !ctx.frange.range.is_subrange(&name_ref.syntax().text_range())
+        || call.arg_list()?.args().next().is_some()
+    {
+        return None;
+    }
+    let receiver = call.expr()?;
+    let fn_def = call.syntax().ancestors().find_map(ast::FnDef::cast)?;
+    // In a closure, `?` would return from the closure.
+    let in_closure = call
+        .syntax()
+        .ancestors()
+        .take_while(|it| it != fn_def.syntax())
+        .any(|it| it.kind() == LAMBDA_EXPR);
+    if in_closure {
+        return None;
+    }
+
+    let db = ctx.db;
+    let file_id = ctx.frange.file_id;
+    let analyzer = SourceAnalyzer::new(db, file_id, call.syntax(), None);
+    let receiver_ty = analyzer.type_of(db, &receiver)?;
+    let module = source_binder::module_from_child_node(db, file_id, fn_def.syntax())?;
+    let function = source_binder::function_from_module(db, module, &fn_def);
+    let sig = db.callable_item_signature(CallableDef::Function(function));
+    if !is_compatible(db, &receiver_ty, sig.ret()) {
+        return None;
+    }
+
+    ctx.add_action(AssistId("replace_unwrap_with_try"), "replace unwrap with ?", |edit| {
+        let range = call.syntax().text_range();
+        edit.target(range);
+        edit.replace(range, format!("{}?", receiver.syntax().text()));
+        edit.set_cursor(range.start());
+    });
+
+    ctx.build()
+}
+
+fn is_compatible(db: &impl HirDatabase, receiver_ty: &Ty, ret_ty: &Ty) -> bool {
+    let (receiver_enum, receiver_substs, ret_substs) = match (receiver_ty.as_adt(), ret_ty.as_adt())
+    {
+        (Some((AdtDef::Enum(a), a_substs)), Some((AdtDef::Enum(b), b_substs))) if a == b => {
+            (a, a_substs, b_substs)
+        }
+        _ => return false,
+    };
+    match receiver_enum.name(db).map(|it| it.to_string()) {
+        Some(ref name) if name == "Option" => true,
+        Some(ref name) if name == "Result" => match (receiver_substs.get(1), ret_substs.get(1)) {
+            (Some(Ty::Unknown), Some(_)) | (Some(_), Some(Ty::Unknown)) => true,
+            (Some(a), Some(b)) => a == b,
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_target};
+
+    fn with_prelude(text: &str) -> String {
+        let prelude = r#"
+enum Result<T, E> { Ok(T), Err(E) }
+impl<T, E> Result<T, E> {
+    fn unwrap(self) -> T { loop {} }
+}
+enum Option<T> { Some(T), None }
+impl<T> Option<T> {
+    fn unwrap(self) -> T { loop {} }
+}
+fn parse() -> Result<u32, ()> { Result::Ok(1) }
+fn first() -> Option<u32> { Option::None }
+"#;
+        format!("{}{}", prelude, text)
+    }
+
+    #[test]
+    fn replace_unwrap_of_result() {
+        check_assist(
+            replace_unwrap_with_try,
+            &with_prelude(
+                r#"
+fn foo() -> Result<u32, ()> {
+    let x = parse().unw<|>rap();
+    Result::Ok(x)
+}
+"#,
+            ),
+            &with_prelude(
+                r#"
+fn foo() -> Result<u32, ()> {
+    let x = <|>parse()?;
+    Result::Ok(x)
+}
+"#,
+            ),
+        );
+    }
+
+    #[test]
+    fn replace_unwrap_of_option() {
+        check_assist(
+            replace_unwrap_with_try,
+            &with_prelude(
+                r#"
+fn foo() -> Option<u32> {
+    first().<|>unwrap();
+}
+"#,
+            ),
+            &with_prelude(
+                r#"
+fn foo() -> Option<u32> {
+    <|>first()?;
+}
+"#,
+            ),
+        );
+    }
+
+    #[test]
+    fn replace_unwrap_not_applicable_for_incompatible_return_types() {
+        check_assist_not_applicable(
+            replace_unwrap_with_try,
+            &with_prelude("fn foo() -> u32 { parse().<|>unwrap() }"),
+        );
+        check_assist_not_applicable(
+            replace_unwrap_with_try,
+            &with_prelude("fn foo() -> Result<u32, ()> { first().<|>unwrap(); }"),
+        );
+        check_assist_not_applicable(
+            replace_unwrap_with_try,
+            &with_prelude("fn foo() -> Result<u32, u8> { parse().<|>unwrap(); }"),
+        );
+    }
+
+    #[test]
+    fn replace_unwrap_not_applicable_in_closure() {
+        check_assist_not_applicable(
+            replace_unwrap_with_try,
+            &with_prelude("fn foo() -> Result<u32, ()> { let f = || parse().<|>unwrap(); }"),
+        );
+    }
+
+    #[test]
+    fn replace_unwrap_target() {
+        check_assist_target(
+            replace_unwrap_with_try,
+            &with_prelude("fn foo() -> Option<u32> { first().<|>unwrap(); }"),
+            "first().unwrap()",
+        );
+    }
+}
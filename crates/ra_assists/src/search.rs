@@ -0,0 +1,195 @@
+//! Helpers for assists which need to look at the whole workspace rather than
+//! at the current file only.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use hir::{db::HirDatabase, PathResolution};
+use ra_db::{CheckCanceled, FileId, SourceDatabase};
+use ra_syntax::{ast, AstNode, SyntaxNode, TextRange};
+
+/// Returns all files of the local (non-library) crates of the workspace.
+fn workspace_files(db: &impl HirDatabase) -> Vec<FileId> {
+    let crate_graph = db.crate_graph();
+    let mut roots: Vec<_> = crate_graph
+        .iter()
+        .map(|krate| db.file_source_root(crate_graph.crate_root(krate)))
+        .collect();
+    roots.sort();
+    roots.dedup();
+
+    let mut res = Vec::new();
+    for root in roots {
+        let source_root = db.source_root(root);
+        if source_root.is_library {
+            continue;
+        }
+        res.extend(source_root.files.values().copied());
+    }
+    res.sort();
+    res
+}
+
+/// Returns the files of the workspace which may access one of the first
+/// `count` fields of a tuple struct, that is which contain a dot followed by
+/// one of their indices. Tuple fields have no name to search for, so this
+/// is the filter used before parsing the files.
+pub(crate) fn files_with_tuple_fields(db: &impl HirDatabase, count: usize) -> Vec<FileId> {
+    workspace_files(db)
+        .into_iter()
+        .filter(|&file_id| {
+            db.check_canceled();
+            db.file_text(file_id).split('.').skip(1).any(|rest| {
+                let rest = rest.trim_start();
+                let len = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or_else(|| rest.len());
+                rest[..len].parse::<usize>().map_or(false, |idx| idx < count)
+            })
+        })
+        .collect()
+}
+
+/// Finds all name references spelled `name` in the workspace. The references
+/// are not resolved: callers are expected to check that they point to the
+/// right definition.
+pub(crate) fn find_name_refs(db: &impl HirDatabase, name: &str) -> Vec<(FileId, ast::NameRef)> {
+    hir::search::find_name_refs(db, workspace_files(db), name)
+}
+
+/// Returns the path whose last segment is `name_ref`, if any.
+pub(crate) fn path_of_name_ref(name_ref: &ast::NameRef) -> Option<ast::Path> {
+    let segment = name_ref.syntax().parent().and_then(ast::PathSegment::cast)?;
+    Some(segment.parent_path())
+}
+
+/// A usage of a struct or of an enum variant.
+pub(crate) enum Usage {
+    /// `S(a, b)`
+    Call(ast::CallExpr),
+    /// `S` used as a function value, as in `.map(S)`
+    Value(ast::PathExpr),
+    /// `S { a, b }`
+    StructLit(ast::StructLit),
+    /// `S(a, b)` in a pattern
+    TupleStructPat(ast::TupleStructPat),
+    /// `S { a, b }` in a pattern
+    StructPat(ast::StructPat),
+    /// `s.0` or `s.a`
+    Field(ast::FieldExpr),
+}
+
+impl Usage {
+    pub(crate) fn syntax(&self) -> &SyntaxNode {
+        match self {
+            Usage::Call(it) => it.syntax(),
+            Usage::Value(it) => it.syntax(),
+            Usage::StructLit(it) => it.syntax(),
+            Usage::TupleStructPat(it) => it.syntax(),
+            Usage::StructPat(it) => it.syntax(),
+            Usage::Field(it) => it.syntax(),
+        }
+    }
+}
+
+/// Finds the constructors and the patterns in the workspace whose path ends
+/// with one of `names` and resolves to the definition accepted by `is_target`.
+pub(crate) fn find_constructor_usages(
+    db: &impl HirDatabase,
+    names: &[&str],
+    is_target: impl Fn(PathResolution) -> bool,
+) -> Vec<(FileId, Usage)> {
+    let mut res = Vec::new();
+    for (file_id, name_ref) in names.iter().flat_map(|name| find_name_refs(db, name)) {
+        let path = match path_of_name_ref(&name_ref) {
+            Some(it) => it,
+            None => continue,
+        };
+        let parent = match path.syntax().parent() {
+            Some(it) => it,
+            None => continue,
+        };
+        let usage = if let Some(path_expr) = ast::PathExpr::cast(parent.clone()) {
+            match path_expr.syntax().parent().and_then(ast::CallExpr::cast) {
+                Some(call) => Usage::Call(call),
+                None => Usage::Value(path_expr),
+            }
+        } else if let Some(lit) = ast::StructLit::cast(parent.clone()) {
+            Usage::StructLit(lit)
+        } else if let Some(pat) = ast::TupleStructPat::cast(parent.clone()) {
+            Usage::TupleStructPat(pat)
+        } else if let Some(pat) = ast::StructPat::cast(parent) {
+            Usage::StructPat(pat)
+        } else {
+            continue;
+        };
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, path.syntax(), None);
+        if analyzer.resolve_path(db, &path).map_or(false, &is_target) {
+            res.push((file_id, usage));
+        }
+    }
+    res
+}
+
+/// Computes the replacements of `usages`, file by file and innermost usages
+/// first. Returns `None` if `f` fails to convert one of the usages.
+pub(crate) fn edit_usages(
+    db: &impl HirDatabase,
+    usages: Vec<(FileId, Usage)>,
+    mut f: impl FnMut(&UsageEdits, FileId, &Usage) -> Option<(TextRange, String)>,
+) -> Option<Vec<(FileId, TextRange, String)>> {
+    let mut by_file = BTreeMap::new();
+    for (file_id, usage) in usages {
+        by_file.entry(file_id).or_insert_with(Vec::new).push(usage);
+    }
+    let mut res = Vec::new();
+    for (file_id, mut usages) in by_file {
+        usages.sort_by_key(|usage| usage.syntax().text_range().len());
+        let mut edits = UsageEdits::new(db, file_id);
+        for usage in usages.iter() {
+            let (range, text) = f(&edits, file_id, usage)?;
+            edits.replace(range, text);
+        }
+        res.extend(edits.into_edits().into_iter().map(|(range, text)| (file_id, range, text)));
+    }
+    Some(res)
+}
+
+/// Collects the replacements of the usages found in a single file.
+///
+/// Usages may be nested, as in `S(S(1))`. They should be added innermost
+/// first: the replacement of an outer usage is built with `render`, which
+/// applies the replacements already made inside of it.
+pub struct UsageEdits {
+    text: Arc<String>,
+    edits: Vec<(TextRange, String)>,
+}
+
+impl UsageEdits {
+    pub fn new(db: &impl HirDatabase, file_id: FileId) -> UsageEdits {
+        UsageEdits { text: db.file_text(file_id), edits: Vec::new() }
+    }
+
+    /// Returns the text of `range` with the replacements inside it applied.
+    pub fn render(&self, range: TextRange) -> String {
+        let mut inner: Vec<_> = self.edits.iter().filter(|(r, _)| r.is_subrange(&range)).collect();
+        inner.sort_by_key(|(r, _)| r.start());
+        let mut res = String::new();
+        let mut offset = range.start();
+        for (r, text) in inner {
+            res.push_str(&self.text[TextRange::from_to(offset, r.start())]);
+            res.push_str(text);
+            offset = r.end();
+        }
+        res.push_str(&self.text[TextRange::from_to(offset, range.end())]);
+        res
+    }
+
+    /// Replaces `range`, superseding the replacements made inside of it.
+    pub fn replace(&mut self, range: TextRange, text: String) {
+        self.edits.retain(|(r, _)| !r.is_subrange(&range));
+        self.edits.push((range, text));
+    }
+
+    /// Returns the replacements, none of which overlap.
+    pub fn into_edits(self) -> Vec<(TextRange, String)> {
+        self.edits
+    }
+}
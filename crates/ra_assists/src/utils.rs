@@ -0,0 +1,60 @@
+//! Helpers shared by several assists.
+
+use hir::{db::HirDatabase, source_binder, Module};
+use ra_db::FileId;
+use ra_syntax::SyntaxNode;
+
+/// Returns the path to the item named `name`, defined in `def_module`, from
+/// the module of `node`.
+pub(crate) fn item_path(
+    db: &impl HirDatabase,
+    def_module: Module,
+    file_id: FileId,
+    node: &SyntaxNode,
+    name: &str,
+) -> Option<String> {
+    let module = source_binder::module_from_child_node(db, file_id, node)?;
+    if module == def_module {
+        return Some(name.to_string());
+    }
+    let def_crate = def_module.krate(db)?;
+    let krate = module.krate(db)?;
+    let mut segments = vec![if krate == def_crate {
+        "crate".to_string()
+    } else {
+        krate.dependencies(db).into_iter().find(|dep| dep.krate == def_crate)?.name.to_string()
+    }];
+    let modules = def_module.path_to_root(db).into_iter().rev();
+    segments.extend(modules.filter_map(|it| it.name(db)).map(|it| it.to_string()));
+    segments.push(name.to_string());
+    Some(segments.join("::"))
+}
+
+/// Converts a `CamelCase` name, like the name of a type, to `snake_case`.
+pub(crate) fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut res = String::new();
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_is_lower = chars.get(i + 1).map_or(false, |it| it.is_lowercase());
+            if prev.is_lowercase() || prev.is_numeric() || (prev.is_uppercase() && next_is_lower) {
+                res.push('_');
+            }
+        }
+        res.extend(c.to_lowercase());
+    }
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::to_snake_case;
+
+    #[test]
+    fn snake_case_names() {
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("LineItem"), "line_item");
+        assert_eq!(to_snake_case("parse_config"), "parse_config");
+    }
+}
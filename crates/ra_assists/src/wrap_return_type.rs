@@ -0,0 +1,258 @@
+use hir::{
+    db::HirDatabase, source_binder, ApplicationTy, Body, Expr, ExprId, Function, InferenceResult,
+    Ty, TypeCtor,
+};
+use ra_syntax::{
+    ast::{self, AstNode},
+    SyntaxNode, TextRange, TextUnit,
+};
+
+use crate::{Assist, AssistCtx, AssistId};
+
+/// Changes the return type `T` of a function to `Result<T, ()>` or
+/// `Option<T>`, and wraps the values it returns in `Ok` or `Some`.
+pub(crate) fn wrap_return_type(mut ctx: AssistCtx<impl HirDatabase>) -> Option<Assist> {
+    let ret_type = ctx.node_at_offset::<ast::RetType>()?;
+    let type_ref = ret_type.type_ref()?;
+    let fn_def = ret_type.syntax().parent().and_then(ast::FnDef::cast)?;
+    if fn_def.body().is_none() || is_wrapped(&type_ref) {
+        return None;
+    }
+
+    let db = ctx.db;
+    let module = source_binder::module_from_child_node(db, ctx.frange.file_id, fn_def.syntax())?;
+    let function = source_binder::function_from_module(db, module, &fn_def);
+    let root = fn_def.syntax().ancestors().last()?;
+    let returned = returned_values(db, function, &root)?;
+    let ty = type_ref.syntax().text().to_string();
+    let type_range = type_ref.syntax().text_range();
+
+    let wrappers = [
+        ("wrap_return_type_in_result", "Result", "Ok", ", ()"),
+        ("wrap_return_type_in_option", "Option", "Some", ""),
+    ];
+    for &(id, wrapper, ctor, error_ty) in wrappers.iter() {
+        ctx.add_action(AssistId(id), format!("wrap return type in {}", wrapper), |edit| {
+            edit.target(type_range);
+            edit.replace(type_range, format!("{}<{}{}>", wrapper, ty, error_ty));
+            for value in returned.iter() {
+                match value {
+                    Returned::Value(range, text) => {
+                        edit.replace(*range, format!("{}({})", ctor, text))
+                    }
+                    Returned::BareReturn(range) => {
+                        edit.replace(*range, format!("return {}(())", ctor))
+                    }
+                }
+            }
+            // The error type is a placeholder, the cursor is put on it.
+            let prefix = match error_ty {
+                "" => String::new(),
+                _ => format!("{}<{}, ", wrapper, ty),
+            };
+            edit.set_cursor(type_range.start() + TextUnit::of_str(&prefix));
+        });
+    }
+
+    ctx.build()
+}
+
+fn is_wrapped(type_ref: &ast::TypeRef) -> bool {
+    let segment = ast::PathType::cast(type_ref.syntax().clone())
+        .and_then(|it| it.path())
+        .and_then(|it| it.segment())
+        .and_then(|it| it.name_ref());
+    match segment {
+        Some(name_ref) => match name_ref.text().as_str() {
+            "Result" | "Option" => true,
+            _ => false,
+        },
+        None => false,
+    }
+}
+
+enum Returned {
+    /// A returned value, with its text.
+    Value(TextRange, String),
+    /// A `return` without value.
+    BareReturn(TextRange),
+}
+
+/// Finds the values returned by `function`: the operands of `return`, and the
+/// tail expression of its body, looking through blocks, `if` and `match`.
+fn returned_values(
+    db: &impl HirDatabase,
+    function: Function,
+    root: &SyntaxNode,
+) -> Option<Vec<Returned>> {
+    let body = function.body(db);
+    let source_map = function.body_source_map(db);
+    let infer = function.infer(db);
+
+    let mut exprs = Vec::new();
+    collect_returns(&body, body.body_expr(), &mut exprs);
+    collect_tails(&body, &infer, body.body_expr(), &mut exprs);
+
+    let mut res = Vec::new();
+    for (expr, is_bare_return) in exprs {
+        // Returns from macro expansions can't be edited.
+        let node = source_map.expr_syntax(expr)?.to_node(root);
+        let range = node.text_range();
+        if is_bare_return {
+            res.push(Returned::BareReturn(range));
+        } else {
+            res.push(Returned::Value(range, node.text().to_string()));
+        }
+    }
+    res.sort_by_key(|it| returned_range(it).start());
+    let overlapping = res
+        .iter()
+        .zip(res.iter().skip(1))
+        .any(|(a, b)| returned_range(a).end() > returned_range(b).start());
+    if overlapping {
+        return None;
+    }
+    Some(res)
+}
+
+fn returned_range(returned: &Returned) -> TextRange {
+    match returned {
+        Returned::Value(range, _) | Returned::BareReturn(range) => *range,
+    }
+}
+
+/// Collects the `return`s of the body, except those of closures. Bare
+/// `return`s are flagged.
+fn collect_returns(body: &Body, expr: ExprId, acc: &mut Vec<(ExprId, bool)>) {
+    match &body[expr] {
+        Expr::Lambda { .. } => return,
+        Expr::Return { expr: Some(value) } => acc.push((*value, false)),
+        Expr::Return { expr: None } => acc.push((expr, true)),
+        _ => (),
+    }
+    body[expr].walk_child_exprs(|child| collect_returns(body, child, acc));
+}
+
+fn collect_tails(
+    body: &Body,
+    infer: &InferenceResult,
+    expr: ExprId,
+    acc: &mut Vec<(ExprId, bool)>,
+) {
+    match &body[expr] {
+        Expr::Block { tail: Some(tail), .. } => collect_tails(body, infer, *tail, acc),
+        Expr::If { then_branch, else_branch: Some(else_branch), .. } => {
+            collect_tails(body, infer, *then_branch, acc);
+            collect_tails(body, infer, *else_branch, acc);
+        }
+        Expr::Match { arms, .. } => {
+            for arm in arms {
+                collect_tails(body, infer, arm.expr, acc);
+            }
+        }
+        // `return`s are collected separately, and blocks without tail or `if`
+        // without `else` can't have a non unit type.
+        Expr::Return { .. } | Expr::Block { tail: None, .. } | Expr::If { .. } => (),
+        _ => match &infer[expr] {
+            Ty::Apply(ApplicationTy { ctor: TypeCtor::Never, .. }) => (),
+            _ => acc.push((expr, false)),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::{check_assist, check_assist_not_applicable, check_assist_nth_action};
+
+    #[test]
+    fn wrap_return_type_in_result() {
+        check_assist(
+            wrap_return_type,
+            r#"
+fn foo(x: u32) -> u<|>32 {
+    if x == 0 {
+        return 1;
+    }
+    let f = |y: u32| -> u32 { return y; };
+    match x {
+        1 => 2,
+        _ => {
+            let y = x + 1;
+            y
+        }
+    }
+}
+"#,
+            r#"
+fn foo(x: u32) -> Result<u32, <|>()> {
+    if x == 0 {
+        return Ok(1);
+    }
+    let f = |y: u32| -> u32 { return y; };
+    match x {
+        1 => Ok(2),
+        _ => {
+            let y = x + 1;
+            Ok(y)
+        }
+    }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn wrap_return_type_in_option() {
+        check_assist_nth_action(
+            wrap_return_type,
+            r#"
+fn foo(x: bool) -> <|>u32 {
+    if x { 1 } else { 2 }
+}
+"#,
+            r#"
+fn foo(x: bool) -> <|>Option<u32> {
+    if x { Some(1) } else { Some(2) }
+}
+"#,
+            1,
+        );
+    }
+
+    #[test]
+    fn wrap_return_type_skips_diverging_tails() {
+        check_assist(
+            wrap_return_type,
+            r#"
+fn foo(x: bool) -> <|>u32 {
+    if x {
+        return 1;
+    }
+    loop {}
+}
+"#,
+            r#"
+fn foo(x: bool) -> Result<u32, <|>()> {
+    if x {
+        return Ok(1);
+    }
+    loop {}
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn wrap_return_type_not_applicable_when_wrapped() {
+        check_assist_not_applicable(
+            wrap_return_type,
+            r#"
+enum Option<T> { Some(T), None }
+fn foo() -> <|>Option<u32> {
+    Option::Some(1)
+}
+"#,
+        );
+    }
+}
@@ -1,11 +1,12 @@
 mod analysis_stats;
 mod analysis_bench;
+mod ssr;
 
 use std::{error::Error, io::Read};
 
 use clap::{App, Arg, SubCommand};
 use flexi_logger::Logger;
-use ra_ide_api::{file_structure, Analysis};
+use ra_ide_api::{file_structure, Analysis, FilePosition, LineCol};
 use ra_prof::profile;
 use ra_syntax::{AstNode, SourceFile};
 
@@ -21,6 +22,34 @@ fn main() -> Result<()> {
             SubCommand::with_name("highlight")
                 .arg(Arg::with_name("rainbow").short("r").long("rainbow")),
         )
+        .subcommand(
+            SubCommand::with_name("expand-macro").arg(
+                Arg::with_name("position")
+                    .required(true)
+                    .value_name("LINE:COLUMN")
+                    .help("expand the macro call at this location of stdin, counting from 0"),
+            ),
+        )
+        .subcommand(
+            SubCommand::with_name("ssr")
+                .arg(Arg::with_name("resolve-paths").long("resolve-paths").help(
+                    "match paths by the definitions they resolve to, rather than by spelling",
+                ))
+                .arg(
+                    Arg::with_name("path")
+                        .long("path")
+                        .takes_value(true)
+                        .value_name("PATH")
+                        .help("project to rewrite"),
+                )
+                .arg(
+                    Arg::with_name("rule")
+                        .required(true)
+                        .multiple(true)
+                        .value_name("RULE")
+                        .help("rules like `foo($a, $b) ==>> $a.foo($b)`, applied in order"),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("analysis-stats")
                 .arg(Arg::with_name("verbose").short("v").long("verbose"))
@@ -70,6 +99,22 @@ fn main() -> Result<()> {
             let html = analysis.highlight_as_html(file_id, matches.is_present("rainbow")).unwrap();
             println!("{}", html);
         }
+        ("expand-macro", Some(matches)) => {
+            let (line, column) = rsplit_at_char(matches.value_of("position").unwrap(), ':')?;
+            let (analysis, file_id) = Analysis::from_single_file(read_stdin()?);
+            let offset = analysis
+                .file_line_index(file_id)?
+                .offset(LineCol { line: line.parse()?, col_utf16: column.parse()? });
+            match analysis.expand_macro(FilePosition { file_id, offset })? {
+                Some(expanded) => println!("// {}!\n{}", expanded.name, expanded.expansion),
+                None => return Err("no macro call at this location".into()),
+            }
+        }
+        ("ssr", Some(matches)) => {
+            let path = matches.value_of("path").unwrap_or(".");
+            let rules = matches.values_of("rule").unwrap().collect::<Vec<_>>();
+            ssr::run(path.as_ref(), &rules, matches.is_present("resolve-paths"))?;
+        }
         ("analysis-stats", Some(matches)) => {
             let verbose = matches.is_present("verbose");
             let memory_usage = matches.is_present("memory-usage");
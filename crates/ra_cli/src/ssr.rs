@@ -0,0 +1,44 @@
+use std::{collections::HashMap, path::Path, sync::Arc};
+
+use ra_db::SourceDatabase;
+use ra_ide_api::{AnalysisChange, FileId};
+
+use crate::Result;
+
+/// Applies the structural search and replace rules one after another to the
+/// project at `path` and writes the changed files back to disk.
+pub(crate) fn run(path: &Path, rules: &[&str], resolve_paths: bool) -> Result<()> {
+    let (mut host, roots) = ra_batch::load_cargo(path)?;
+    let mut paths = HashMap::new();
+    for (source_root_id, project_root) in roots.iter() {
+        if project_root.is_member() {
+            for (rel_path, file_id) in &host.raw_database().source_root(*source_root_id).files {
+                paths.insert(*file_id, rel_path.to_path(project_root.path()));
+            }
+        }
+    }
+
+    let mut changed: Vec<FileId> = Vec::new();
+    for rule in rules {
+        let analysis = host.analysis();
+        let source_change = analysis.structural_search_replace(rule, resolve_paths)??;
+        let mut change = AnalysisChange::new();
+        for edit in source_change.source_file_edits {
+            let text = edit.edit.apply(&analysis.file_text(edit.file_id)?);
+            change.change_file(edit.file_id, Arc::new(text));
+            if !changed.contains(&edit.file_id) {
+                changed.push(edit.file_id);
+            }
+        }
+        drop(analysis);
+        host.apply_change(change);
+    }
+
+    let analysis = host.analysis();
+    for file_id in changed {
+        let path = &paths[&file_id];
+        std::fs::write(path, analysis.file_text(file_id)?.as_bytes())?;
+        println!("{}", path.display());
+    }
+    Ok(())
+}
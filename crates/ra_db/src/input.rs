@@ -164,6 +164,11 @@ impl CrateGraph {
         self.arena[&crate_id].dependencies.iter()
     }
 
+    /// Checks if `from` depends on `to`, directly or through other crates.
+    pub fn depends_on(&self, from: CrateId, to: CrateId) -> bool {
+        self.dfs_find(to, from, &mut FxHashSet::default())
+    }
+
     /// Extends this crate graph by adding a complete disjoint second crate
     /// graph.
     pub fn extend(&mut self, other: CrateGraph) {
@@ -220,4 +225,17 @@ mod tests {
         assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
         assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
     }
+
+    #[test]
+    fn it_finds_transitive_dependencies() {
+        let mut graph = CrateGraph::default();
+        let crate1 = graph.add_crate_root(FileId(1u32), Edition2018);
+        let crate2 = graph.add_crate_root(FileId(2u32), Edition2018);
+        let crate3 = graph.add_crate_root(FileId(3u32), Edition2018);
+        assert!(graph.add_dep(crate1, SmolStr::new("crate2"), crate2).is_ok());
+        assert!(graph.add_dep(crate2, SmolStr::new("crate3"), crate3).is_ok());
+        assert!(graph.depends_on(crate1, crate3));
+        assert!(!graph.depends_on(crate3, crate1));
+        assert!(!graph.depends_on(crate1, crate1));
+    }
 }
@@ -20,7 +20,7 @@ pub enum AdtDef {
 impl_froms!(AdtDef: Struct, Union, Enum);
 
 impl AdtDef {
-    pub(crate) fn krate(self, db: &impl HirDatabase) -> Option<Crate> {
+    pub fn krate(self, db: &impl HirDatabase) -> Option<Crate> {
         match self {
             AdtDef::Struct(s) => s.module(db),
             AdtDef::Union(s) => s.module(db),
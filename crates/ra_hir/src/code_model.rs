@@ -1,5 +1,6 @@
 pub(crate) mod src;
 pub(crate) mod docs;
+mod display;
 
 use std::sync::Arc;
 
@@ -25,7 +26,7 @@ use crate::{
     traits::{TraitData, TraitItem},
     ty::{
         primitive::{FloatBitness, FloatTy, IntBitness, IntTy, Signedness},
-        InferenceResult, TraitRef,
+        GenericPredicate, InferenceResult, TraitRef,
     },
     type_ref::Mutability,
     type_ref::TypeRef,
@@ -62,6 +63,16 @@ impl Crate {
             .collect()
     }
 
+    /// The crates which depend on this one, directly or through other crates.
+    pub fn reverse_dependencies(self, db: &impl DefDatabase) -> Vec<Crate> {
+        let crate_graph = db.crate_graph();
+        crate_graph
+            .iter()
+            .filter(|&crate_id| crate_graph.depends_on(crate_id, self.crate_id))
+            .map(|crate_id| Crate { crate_id })
+            .collect()
+    }
+
     pub fn root_module(self, db: &impl DefDatabase) -> Option<Module> {
         let module_id = db.crate_def_map(self).root();
         let module = Module { krate: self, module_id };
@@ -617,7 +628,7 @@ impl Function {
         self.data(db).name.clone()
     }
 
-    pub(crate) fn body_source_map(self, db: &impl HirDatabase) -> Arc<BodySourceMap> {
+    pub fn body_source_map(self, db: &impl HirDatabase) -> Arc<BodySourceMap> {
         db.body_with_source_map(self.into()).1
     }
 
@@ -815,6 +826,24 @@ impl Trait {
         TraitRef::for_trait(db, self)
     }
 
+    /// The traits which are bounds on `Self` in the declaration of the trait,
+    /// like `Bar` in `trait Foo: Bar`.
+    pub fn super_traits(self, db: &impl HirDatabase) -> Vec<Trait> {
+        db.generic_predicates(self.into())
+            .iter()
+            .filter_map(|pred| match pred {
+                GenericPredicate::Implemented(trait_ref) => Some(trait_ref),
+                GenericPredicate::Error => None,
+            })
+            .filter(|trait_ref| match trait_ref.self_ty() {
+                Ty::Param { idx: 0, .. } => true,
+                _ => false,
+            })
+            .map(|trait_ref| trait_ref.trait_)
+            .filter(|&tr| tr != self)
+            .collect()
+    }
+
     pub fn is_auto(self, db: &impl DefDatabase) -> bool {
         self.trait_data(db).is_auto()
     }
@@ -0,0 +1,142 @@
+//! Renders signatures of items, with generic parameters and where clauses
+//! normalized: bounds on type parameters are shown in the where clause.
+
+use std::fmt;
+
+use ra_syntax::ast::{self, AstNode};
+
+use crate::{
+    db::HirDatabase,
+    generics::{GenericDef, HasGenericParams},
+    name::SELF_TYPE,
+    ty::{
+        display::{HirDisplay, HirFormatter},
+        GenericPredicate,
+    },
+    Enum, HasSource, ImplBlock, Name, Struct, Trait, TypeAlias, Union,
+};
+
+impl HirDisplay for Struct {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "struct {}", self.name(f.db).unwrap_or_else(Name::missing))?;
+        write_generic_params(GenericDef::from(*self), &lifetimes(&self.source(f.db).ast), f)?;
+        write_where_clause(GenericDef::from(*self), f)
+    }
+}
+
+impl HirDisplay for Union {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "union {}", self.name(f.db).unwrap_or_else(Name::missing))?;
+        write_generic_params(GenericDef::from(*self), &lifetimes(&self.source(f.db).ast), f)?;
+        write_where_clause(GenericDef::from(*self), f)
+    }
+}
+
+impl HirDisplay for Enum {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "enum {}", self.name(f.db).unwrap_or_else(Name::missing))?;
+        write_generic_params(GenericDef::from(*self), &lifetimes(&self.source(f.db).ast), f)?;
+        write_where_clause(GenericDef::from(*self), f)
+    }
+}
+
+impl HirDisplay for Trait {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "trait {}", self.name(f.db).unwrap_or_else(Name::missing))?;
+        write_generic_params(GenericDef::from(*self), &lifetimes(&self.source(f.db).ast), f)?;
+        write_where_clause(GenericDef::from(*self), f)
+    }
+}
+
+impl HirDisplay for TypeAlias {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "type {}", self.name(f.db))?;
+        write_generic_params(GenericDef::from(*self), &lifetimes(&self.source(f.db).ast), f)?;
+        if self.type_ref(f.db).is_some() {
+            write!(f, " = {}", self.ty(f.db).display(f.db))?;
+        }
+        write_where_clause(GenericDef::from(*self), f)
+    }
+}
+
+impl HirDisplay for ImplBlock {
+    fn hir_fmt(&self, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+        write!(f, "impl")?;
+        write_generic_params(GenericDef::from(*self), &lifetimes(&self.source(f.db).ast), f)?;
+        write!(f, " ")?;
+        if let Some(trait_ref) = self.target_trait_ref(f.db) {
+            write!(f, "{}", trait_ref.trait_.name(f.db).unwrap_or_else(Name::missing))?;
+            if trait_ref.substs.len() > 1 {
+                write!(f, "<")?;
+                f.write_joined(&trait_ref.substs[1..], ", ")?;
+                write!(f, ">")?;
+            }
+            write!(f, " for ")?;
+        }
+        write!(f, "{}", self.target_ty(f.db).display(f.db))?;
+        write_where_clause(GenericDef::from(*self), f)
+    }
+}
+
+/// Lifetimes are not tracked by `GenericParams`, so they are taken from the
+/// source.
+fn lifetimes(node: &impl ast::TypeParamsOwner) -> Vec<String> {
+    node.type_param_list()
+        .into_iter()
+        .flat_map(|it| it.lifetime_params())
+        .map(|it| it.syntax().text().to_string())
+        .collect()
+}
+
+/// Writes the lifetimes and the own type parameters of `def`, together with
+/// the defaults of the latter.
+fn write_generic_params(
+    def: GenericDef,
+    lifetimes: &[String],
+    f: &mut HirFormatter<impl HirDatabase>,
+) -> fmt::Result {
+    let generic_params = def.generic_params(f.db);
+    let params = generic_params.params.iter().filter(|it| it.name != SELF_TYPE).collect::<Vec<_>>();
+    if lifetimes.is_empty() && params.is_empty() {
+        return Ok(());
+    }
+    let defaults = f.db.generic_defaults(def);
+    write!(f, "<")?;
+    for (idx, lifetime) in lifetimes.iter().enumerate() {
+        if idx > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", lifetime)?;
+    }
+    for (idx, param) in params.iter().enumerate() {
+        if idx > 0 || !lifetimes.is_empty() {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}", param.name)?;
+        if param.default.is_some() {
+            if let Some(ty) = defaults.get(param.idx as usize) {
+                write!(f, " = {}", ty.display(f.db))?;
+            }
+        }
+    }
+    write!(f, ">")
+}
+
+fn write_where_clause(def: GenericDef, f: &mut HirFormatter<impl HirDatabase>) -> fmt::Result {
+    let predicates = f.db.generic_predicates(def);
+    let mut first = true;
+    for predicate in predicates.iter() {
+        let trait_ref = match predicate {
+            GenericPredicate::Implemented(it) => it,
+            GenericPredicate::Error => continue,
+        };
+        if first {
+            write!(f, "\nwhere ")?;
+            first = false;
+        } else {
+            write!(f, ",\n      ")?;
+        }
+        trait_ref.hir_fmt(f)?;
+    }
+    Ok(())
+}
@@ -9,7 +9,7 @@ use ra_syntax::{
         self, ArgListOwner, ArrayExprKind, LiteralKind, LoopBodyOwner, NameOwner,
         TryBlockBodyOwner, TypeAscriptionOwner,
     },
-    AstNode, AstPtr, SyntaxNodePtr,
+    AstNode, AstPtr, SyntaxNodePtr, T,
 };
 use test_utils::tested_by;
 
@@ -19,11 +19,12 @@ use crate::{
     ty::primitive::{FloatTy, IntTy, UncertainFloatTy, UncertainIntTy},
     type_ref::{Mutability, TypeRef},
     DefWithBody, Either, HasSource, HirDatabase, HirFileId, MacroCallLoc, MacroFileKind, Name,
-    Path, Resolver,
+    Path, Resolver, Source,
 };
 
-pub use self::scope::ExprScopes;
+pub use self::{match_check::MissingPat, scope::ExprScopes};
 
+pub(crate) mod match_check;
 pub(crate) mod scope;
 pub(crate) mod validation;
 
@@ -57,6 +58,9 @@ pub struct Body {
 #[derive(Default, Debug, Eq, PartialEq)]
 pub struct BodySourceMap {
     expr_map: FxHashMap<SyntaxNodePtr, ExprId>,
+    /// Expressions from macro expansions, which have no place in the original
+    /// file.
+    expansion_expr_map: FxHashMap<(HirFileId, SyntaxNodePtr), ExprId>,
     expr_map_back: ArenaMap<ExprId, SyntaxNodePtr>,
     pat_map: FxHashMap<PatPtr, PatId>,
     pat_map_back: ArenaMap<PatId, PatPtr>,
@@ -128,23 +132,29 @@ impl Index<PatId> for Body {
 }
 
 impl BodySourceMap {
-    pub(crate) fn expr_syntax(&self, expr: ExprId) -> Option<SyntaxNodePtr> {
+    pub fn expr_syntax(&self, expr: ExprId) -> Option<SyntaxNodePtr> {
         self.expr_map_back.get(expr).cloned()
     }
 
-    pub(crate) fn syntax_expr(&self, ptr: SyntaxNodePtr) -> Option<ExprId> {
+    pub fn syntax_expr(&self, ptr: SyntaxNodePtr) -> Option<ExprId> {
         self.expr_map.get(&ptr).cloned()
     }
 
-    pub(crate) fn node_expr(&self, node: &ast::Expr) -> Option<ExprId> {
+    pub fn node_expr(&self, node: &ast::Expr) -> Option<ExprId> {
         self.expr_map.get(&SyntaxNodePtr::new(node.syntax())).cloned()
     }
 
-    pub(crate) fn pat_syntax(&self, pat: PatId) -> Option<PatPtr> {
+    pub fn pat_syntax(&self, pat: PatId) -> Option<PatPtr> {
         self.pat_map_back.get(pat).cloned()
     }
 
-    pub(crate) fn node_pat(&self, node: &ast::Pat) -> Option<PatId> {
+    /// Like `node_expr`, for an expression from a macro expansion in the body.
+    pub fn expansion_node_expr(&self, node: Source<&ast::Expr>) -> Option<ExprId> {
+        let key = (node.file_id, SyntaxNodePtr::new(node.ast.syntax()));
+        self.expansion_expr_map.get(&key).cloned()
+    }
+
+    pub fn node_pat(&self, node: &ast::Pat) -> Option<PatId> {
         self.pat_map.get(&Either::A(AstPtr::new(node))).cloned()
     }
 
@@ -533,6 +543,8 @@ where
         if self.current_file_id == self.original_file_id {
             self.source_map.expr_map.insert(syntax_ptr, id);
             self.source_map.expr_map_back.insert(id, syntax_ptr);
+        } else {
+            self.source_map.expansion_expr_map.insert((self.current_file_id, syntax_ptr), id);
         }
         id
     }
@@ -843,7 +855,7 @@ where
                     LiteralKind::Byte => {
                         Literal::Int(Default::default(), UncertainIntTy::Known(IntTy::u8()))
                     }
-                    LiteralKind::Bool => Literal::Bool(Default::default()),
+                    LiteralKind::Bool => Literal::Bool(e.token().kind() == T![true]),
                     LiteralKind::Char => Literal::Char(Default::default()),
                 };
                 self.alloc_expr(Expr::Literal(lit), syntax_ptr)
@@ -871,6 +883,12 @@ where
                                     std::mem::replace(&mut self.current_file_id, file_id);
                                 let id = self.collect_expr(expr);
                                 self.current_file_id = old_file_id;
+                                // The expansion is recorded under the macro call,
+                                // so that it is found from the original file.
+                                if self.current_file_id == self.original_file_id {
+                                    self.source_map.expr_map.insert(syntax_ptr, id);
+                                    self.source_map.expr_map_back.insert(id, syntax_ptr);
+                                }
                                 return id;
                             }
                         }
@@ -970,8 +988,10 @@ where
                 Pat::Struct { path, args: fields }
             }
 
-            // FIXME: implement
-            ast::PatKind::LiteralPat(_) => Pat::Missing,
+            ast::PatKind::LiteralPat(p) => match p.literal() {
+                Some(lit) => Pat::Lit(self.collect_expr(lit.into())),
+                None => Pat::Missing,
+            },
             ast::PatKind::SlicePat(_) | ast::PatKind::RangePat(_) => Pat::Missing,
         };
         let ptr = AstPtr::new(&pat);
@@ -0,0 +1,396 @@
+//! Finds the arms `match` expressions are missing.
+//!
+//! This follows the usefulness algorithm from "Warnings for pattern matching"
+//! by Luc Maranget: a pattern is useful after some other patterns if it
+//! matches a value which none of them matches, and a `match` is exhaustive if
+//! `_` isn't useful after its arms.
+//!
+//! Patterns are deconstructed into constructors and the patterns of their
+//! fields. The constructors are the booleans, the tuples, the structs and the
+//! enum variants. The values of the other types can't be enumerated, so their
+//! literals are opaque: they never cover all the values of their type.
+
+use std::sync::Arc;
+
+use crate::{
+    adt::VariantDef,
+    expr::{BindingAnnotation, Body, Expr, ExprId, Literal, MatchArm, Pat, PatId},
+    resolve::{Resolution, Resolver},
+    ty::{ApplicationTy, InferenceResult, Substs, Ty, TypeCtor},
+    AdtDef, HirDatabase, ModuleDef,
+};
+
+/// The pattern of an arm which a `match` expression is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MissingPat {
+    /// `_`, or a binding.
+    Wild,
+    Bool(bool),
+    Tuple(Vec<MissingPat>),
+    /// A struct or an enum variant, with the patterns of all of its fields.
+    Variant(VariantDef, Vec<MissingPat>),
+}
+
+/// The maximal number of patterns a type is expanded into when looking for
+/// missing arms. Beyond it, the fields of the constructors are left as `_`.
+const MAX_EXPANDED_PATTERNS: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Constructor {
+    Bool(bool),
+    Tuple(usize),
+    Variant(VariantDef),
+}
+
+#[derive(Debug, Clone)]
+enum Pattern {
+    Wild,
+    Constructor(Constructor, Vec<Pattern>),
+    /// A pattern which matches some of the values of a type without
+    /// constructors, like an integer literal.
+    Opaque,
+}
+
+pub(crate) struct MatchChecker<'a, DB> {
+    db: &'a DB,
+    body: Arc<Body>,
+    infer: Arc<InferenceResult>,
+    resolver: Resolver,
+    scrutinee: ExprId,
+    arms: Vec<MatchArm>,
+}
+
+impl<'a, DB: HirDatabase> MatchChecker<'a, DB> {
+    /// Creates a checker for `match_expr`, or returns `None` if it isn't a
+    /// `match` expression.
+    pub(crate) fn new(
+        db: &'a DB,
+        body: Arc<Body>,
+        infer: Arc<InferenceResult>,
+        match_expr: ExprId,
+    ) -> Option<MatchChecker<'a, DB>> {
+        let (scrutinee, arms) = match &body[match_expr] {
+            Expr::Match { expr, arms } => (*expr, arms.clone()),
+            _ => return None,
+        };
+        let resolver = super::resolver_for_expr(body.clone(), db, match_expr);
+        Some(MatchChecker { db, body, infer, resolver, scrutinee, arms })
+    }
+
+    /// Returns the patterns of the arms to add to make the `match` exhaustive,
+    /// leaving out the arms for which `ignore_arm` returns `true`.
+    ///
+    /// The type of the scrutinee is expanded into the patterns of its
+    /// constructors, and so are the elements of tuples and the fields of
+    /// generic enums, like the `T` of `Option<T>`. The other fields are `_`.
+    pub(crate) fn missing_arms(
+        &self,
+        ignore_arm: impl Fn(usize) -> bool,
+    ) -> Option<Vec<MissingPat>> {
+        let ty = self.infer[self.scrutinee].clone();
+        if ty == Ty::Unknown {
+            return None;
+        }
+        let rows: Vec<_> = self
+            .arms
+            .iter()
+            .enumerate()
+            .filter(|(idx, arm)| arm.guard.is_none() && !ignore_arm(*idx))
+            .flat_map(|(_, arm)| arm.pats.iter())
+            .filter_map(|&pat| self.lower_pat(pat))
+            .map(|pat| vec![pat])
+            .collect();
+        let mut res = Vec::new();
+        for candidate in self.expand(&ty) {
+            let pattern = to_pattern(&candidate);
+            if self.is_useful(&rows, &[pattern], &[ty.clone()])? {
+                res.push(candidate);
+            }
+        }
+        Some(res)
+    }
+
+    fn lower_pat(&self, pat: PatId) -> Option<Pattern> {
+        let res = match &self.body[pat] {
+            Pat::Wild => Pattern::Wild,
+            Pat::Bind { mode, name, subpat } => match subpat {
+                Some(subpat) => return self.lower_pat(*subpat),
+                // An identifier refers to a unit struct, a unit variant or a
+                // constant if there is one with this name.
+                None if *mode == BindingAnnotation::Unannotated => {
+                    let path = name.clone().into();
+                    match self
+                        .resolver
+                        .resolve_path_without_assoc_items(self.db, &path)
+                        .take_values()
+                    {
+                        Some(Resolution::Def(ModuleDef::EnumVariant(it))) => {
+                            self.variant_pattern(it.into())
+                        }
+                        Some(Resolution::Def(ModuleDef::Struct(it))) => {
+                            self.variant_pattern(it.into())
+                        }
+                        Some(Resolution::Def(ModuleDef::Const(_))) => Pattern::Opaque,
+                        _ => Pattern::Wild,
+                    }
+                }
+                None => Pattern::Wild,
+            },
+            Pat::Ref { pat, .. } => return self.lower_pat(*pat),
+            Pat::Tuple(args) => {
+                let arity = strip_references(&self.infer[pat]).as_tuple()?.len();
+                if arity != args.len() {
+                    return None;
+                }
+                Pattern::Constructor(Constructor::Tuple(arity), self.lower_pats(args)?)
+            }
+            Pat::TupleStruct { args, .. } => {
+                let variant = self.infer.variant_resolution_for_pat(pat)?;
+                if variant.fields(self.db).len() != args.len() {
+                    return None;
+                }
+                Pattern::Constructor(Constructor::Variant(variant), self.lower_pats(args)?)
+            }
+            Pat::Struct { args, .. } => {
+                let variant = self.infer.variant_resolution_for_pat(pat)?;
+                let fields = variant
+                    .fields(self.db)
+                    .iter()
+                    .map(|field| {
+                        let name = field.name(self.db);
+                        match args.iter().find(|it| it.name == name) {
+                            Some(it) => self.lower_pat(it.pat),
+                            None => Some(Pattern::Wild),
+                        }
+                    })
+                    .collect::<Option<_>>()?;
+                Pattern::Constructor(Constructor::Variant(variant), fields)
+            }
+            Pat::Path(_) => match self.infer.variant_resolution_for_pat(pat) {
+                Some(variant) => self.variant_pattern(variant),
+                None => Pattern::Opaque,
+            },
+            Pat::Lit(expr) => match &self.body[*expr] {
+                Expr::Literal(Literal::Bool(value)) => {
+                    Pattern::Constructor(Constructor::Bool(*value), Vec::new())
+                }
+                _ => Pattern::Opaque,
+            },
+            Pat::Range { .. } | Pat::Slice { .. } | Pat::Missing => return None,
+        };
+        Some(res)
+    }
+
+    fn lower_pats(&self, pats: &[PatId]) -> Option<Vec<Pattern>> {
+        pats.iter().map(|&pat| self.lower_pat(pat)).collect()
+    }
+
+    fn variant_pattern(&self, variant: VariantDef) -> Pattern {
+        let arity = variant.fields(self.db).len();
+        Pattern::Constructor(Constructor::Variant(variant), vec![Pattern::Wild; arity])
+    }
+
+    /// Returns whether `v` matches a value which none of `rows` matches.
+    /// `tys` are the types of the columns.
+    fn is_useful(&self, rows: &[Vec<Pattern>], v: &[Pattern], tys: &[Ty]) -> Option<bool> {
+        let (head, rest) = match v.split_first() {
+            Some(it) => it,
+            None => return Some(rows.is_empty()),
+        };
+        let (ty, rest_tys) = (&tys[0], &tys[1..]);
+        match head {
+            Pattern::Constructor(ctor, args) => {
+                let v: Vec<_> = args.iter().chain(rest).cloned().collect();
+                self.is_useful_specialized(rows, *ctor, &v, ty, rest_tys)
+            }
+            Pattern::Wild | Pattern::Opaque => {
+                let used: Vec<_> = rows
+                    .iter()
+                    .filter_map(|row| match &row[0] {
+                        Pattern::Constructor(ctor, _) => Some(*ctor),
+                        _ => None,
+                    })
+                    .collect();
+                match self.constructors(ty) {
+                    Some(all) if all.iter().all(|it| used.contains(it)) => {
+                        for ctor in all {
+                            let wilds = vec![Pattern::Wild; self.arity(ctor)];
+                            let v: Vec<_> = wilds.into_iter().chain(rest.iter().cloned()).collect();
+                            if self.is_useful_specialized(rows, ctor, &v, ty, rest_tys)? {
+                                return Some(true);
+                            }
+                        }
+                        Some(false)
+                    }
+                    // The constructors of an unknown type can't be listed.
+                    None if !used.is_empty() => None,
+                    _ => {
+                        let rows: Vec<_> = rows
+                            .iter()
+                            .filter(|row| match row[0] {
+                                Pattern::Wild => true,
+                                _ => false,
+                            })
+                            .map(|row| row[1..].to_vec())
+                            .collect();
+                        self.is_useful(&rows, rest, rest_tys)
+                    }
+                }
+            }
+        }
+    }
+
+    /// Checks the usefulness of `v`, a vector starting with the fields of
+    /// `ctor`, against the rows which match `ctor`.
+    fn is_useful_specialized(
+        &self,
+        rows: &[Vec<Pattern>],
+        ctor: Constructor,
+        v: &[Pattern],
+        ty: &Ty,
+        rest_tys: &[Ty],
+    ) -> Option<bool> {
+        let arity = self.arity(ctor);
+        let rows: Vec<_> = rows
+            .iter()
+            .filter_map(|row| {
+                let fields = match &row[0] {
+                    Pattern::Constructor(it, fields) if *it == ctor => fields.clone(),
+                    Pattern::Wild => vec![Pattern::Wild; arity],
+                    _ => return None,
+                };
+                Some(fields.into_iter().chain(row[1..].iter().cloned()).collect())
+            })
+            .collect();
+        let tys: Vec<_> =
+            self.field_types(ty, ctor).into_iter().chain(rest_tys.iter().cloned()).collect();
+        self.is_useful(&rows, v, &tys)
+    }
+
+    /// All the constructors of `ty`, or `None` if its values can't be
+    /// enumerated.
+    fn constructors(&self, ty: &Ty) -> Option<Vec<Constructor>> {
+        let res = match strip_references(ty) {
+            Ty::Apply(ApplicationTy { ctor, parameters }) => match ctor {
+                TypeCtor::Bool => vec![Constructor::Bool(true), Constructor::Bool(false)],
+                TypeCtor::Tuple { .. } => vec![Constructor::Tuple(parameters.len())],
+                TypeCtor::Adt(AdtDef::Struct(it)) => vec![Constructor::Variant((*it).into())],
+                TypeCtor::Adt(AdtDef::Enum(it)) => it
+                    .variants(self.db)
+                    .into_iter()
+                    .map(|it| Constructor::Variant(it.into()))
+                    .collect(),
+                TypeCtor::Never => Vec::new(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    fn arity(&self, ctor: Constructor) -> usize {
+        match ctor {
+            Constructor::Bool(_) => 0,
+            Constructor::Tuple(arity) => arity,
+            Constructor::Variant(it) => it.fields(self.db).len(),
+        }
+    }
+
+    fn field_types(&self, ty: &Ty, ctor: Constructor) -> Vec<Ty> {
+        let ty = strip_references(ty);
+        match ctor {
+            Constructor::Bool(_) => Vec::new(),
+            Constructor::Tuple(arity) => match ty.as_tuple() {
+                Some(parameters) => parameters.to_vec(),
+                None => vec![Ty::Unknown; arity],
+            },
+            Constructor::Variant(variant) => {
+                let substs = ty.as_adt().map_or_else(Substs::empty, |(_, it)| it.clone());
+                variant.fields(self.db).iter().map(|it| it.ty(self.db).subst(&substs)).collect()
+            }
+        }
+    }
+
+    /// Expands `ty` into the patterns of its constructors, in declaration
+    /// order.
+    fn expand(&self, ty: &Ty) -> Vec<MissingPat> {
+        let ctors = match self.constructors(ty) {
+            Some(it) => it,
+            None => return vec![MissingPat::Wild],
+        };
+        let mut res = Vec::new();
+        for ctor in ctors {
+            let columns: Vec<Vec<MissingPat>> = match ctor {
+                Constructor::Bool(value) => {
+                    res.push(MissingPat::Bool(value));
+                    continue;
+                }
+                Constructor::Tuple(_) => {
+                    self.field_types(ty, ctor).iter().map(|it| self.expand(it)).collect()
+                }
+                Constructor::Variant(variant) => {
+                    let tys = self.field_types(ty, ctor);
+                    let fields = variant.fields(self.db);
+                    // Only the fields whose type is a type parameter, like the
+                    // one of `Some` in `Option<T>`, are expanded.
+                    fields
+                        .iter()
+                        .zip(tys.iter())
+                        .map(|(field, ty)| match field.ty(self.db) {
+                            Ty::Param { .. } => self.expand(ty),
+                            _ => vec![MissingPat::Wild],
+                        })
+                        .collect()
+                }
+            };
+            let count = columns.iter().map(|it| it.len()).product::<usize>();
+            let fields = if res.len() + count > MAX_EXPANDED_PATTERNS {
+                vec![vec![MissingPat::Wild; columns.len()]]
+            } else {
+                product(columns)
+            };
+            res.extend(fields.into_iter().map(|fields| match ctor {
+                Constructor::Variant(variant) => MissingPat::Variant(variant, fields),
+                _ => MissingPat::Tuple(fields),
+            }));
+        }
+        res
+    }
+}
+
+fn to_pattern(pat: &MissingPat) -> Pattern {
+    match pat {
+        MissingPat::Wild => Pattern::Wild,
+        MissingPat::Bool(value) => Pattern::Constructor(Constructor::Bool(*value), Vec::new()),
+        MissingPat::Tuple(fields) => Pattern::Constructor(
+            Constructor::Tuple(fields.len()),
+            fields.iter().map(to_pattern).collect(),
+        ),
+        MissingPat::Variant(variant, fields) => Pattern::Constructor(
+            Constructor::Variant(*variant),
+            fields.iter().map(to_pattern).collect(),
+        ),
+    }
+}
+
+/// All the combinations of one pattern from each column.
+fn product(columns: Vec<Vec<MissingPat>>) -> Vec<Vec<MissingPat>> {
+    columns.into_iter().fold(vec![Vec::new()], |acc, column| {
+        acc.iter()
+            .flat_map(|prefix| {
+                column.iter().map(move |pat| {
+                    let mut combination = prefix.clone();
+                    combination.push(pat.clone());
+                    combination
+                })
+            })
+            .collect()
+    })
+}
+
+fn strip_references(mut ty: &Ty) -> &Ty {
+    while let Some((inner, _)) = ty.as_reference() {
+        ty = inner;
+    }
+    ty
+}
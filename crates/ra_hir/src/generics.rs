@@ -83,7 +83,10 @@ impl GenericParams {
             GenericDef::Trait(it) => {
                 // traits get the Self type as an implicit first type parameter
                 generics.params.push(GenericParam { idx: start, name: SELF_TYPE, default: None });
-                generics.fill(&it.source(db).ast, start + 1);
+                let src = it.source(db);
+                generics.fill(&src.ast, start + 1);
+                // supertraits are bounds on the Self type
+                generics.fill_bounds(&src.ast, TypeRef::Path(SELF_TYPE.into()));
             }
             GenericDef::TypeAlias(it) => generics.fill(&it.source(db).ast, start),
             GenericDef::ImplBlock(it) => generics.fill(&it.source(db).ast, start),
@@ -102,6 +105,14 @@ impl GenericParams {
         }
     }
 
+    fn fill_bounds(&mut self, node: &impl TypeBoundsOwner, type_ref: TypeRef) {
+        for bound in
+            node.type_bound_list().iter().flat_map(|type_bound_list| type_bound_list.bounds())
+        {
+            self.add_where_predicate_from_bound(bound, type_ref.clone());
+        }
+    }
+
     fn fill_params(&mut self, params: ast::TypeParamList, start: u32) {
         for (idx, type_param) in params.type_params().enumerate() {
             let name = type_param.name().map_or_else(Name::missing, |it| it.as_name());
@@ -111,13 +122,7 @@ impl GenericParams {
             self.params.push(param);
 
             let type_ref = TypeRef::Path(name.into());
-            for bound in type_param
-                .type_bound_list()
-                .iter()
-                .flat_map(|type_bound_list| type_bound_list.bounds())
-            {
-                self.add_where_predicate_from_bound(bound, type_ref.clone());
-            }
+            self.fill_bounds(&type_param, type_ref);
         }
     }
 
@@ -55,6 +55,14 @@ impl HirFileId {
         }
     }
 
+    /// The macro call which produced the file, if it is a macro expansion.
+    pub(crate) fn macro_file(self) -> Option<MacroFile> {
+        match self.0 {
+            HirFileIdRepr::File(_) => None,
+            HirFileIdRepr::Macro(it) => Some(it),
+        }
+    }
+
     pub(crate) fn parse_or_expand_query(
         db: &impl AstDatabase,
         file_id: HirFileId,
@@ -87,8 +95,10 @@ impl HirFileId {
             })
             .ok()?;
         match macro_file.macro_file_kind {
-            MacroFileKind::Items => Some(Parse::to_syntax(mbe::token_tree_to_ast_item_list(&tt))),
-            MacroFileKind::Expr => mbe::token_tree_to_expr(&tt).ok().map(Parse::to_syntax),
+            MacroFileKind::Items => Some(Parse::to_syntax(mbe::token_tree_to_ast_item_list(&tt).0)),
+            MacroFileKind::Expr => {
+                mbe::token_tree_to_expr(&tt).ok().map(|(parse, _)| Parse::to_syntax(parse))
+            }
         }
     }
 }
@@ -101,8 +111,8 @@ enum HirFileIdRepr {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct MacroFile {
-    macro_call_id: MacroCallId,
-    macro_file_kind: MacroFileKind,
+    pub(crate) macro_call_id: MacroCallId,
+    pub(crate) macro_file_kind: MacroFileKind,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -24,6 +24,7 @@ pub mod db;
 pub mod mock;
 mod path;
 pub mod source_binder;
+pub mod search;
 
 mod source_id;
 mod ids;
@@ -57,7 +58,7 @@ use crate::{
 pub use self::{
     adt::{AdtDef, VariantDef},
     either::Either,
-    expr::ExprScopes,
+    expr::{Body, BodySourceMap, Expr, ExprId, ExprScopes, MissingPat, Pat, PatId, Statement},
     generics::{GenericParam, GenericParams, HasGenericParams},
     ids::{HirFileId, MacroCallId, MacroCallLoc, MacroDefId, MacroFile},
     impl_block::{ImplBlock, ImplItem},
@@ -65,9 +66,14 @@ pub use self::{
     nameres::{ImportId, Namespace, PerNs},
     path::{Path, PathKind},
     resolve::Resolution,
-    source_binder::{PathResolution, ScopeEntryWithSyntax, SourceAnalyzer},
+    source_binder::{Expansion, PathResolution, ScopeEntryWithSyntax, SourceAnalyzer},
     source_id::{AstIdMap, ErasedFileAstId},
-    ty::{display::HirDisplay, ApplicationTy, CallableDef, Substs, TraitRef, Ty, TypeCtor},
+    traits::TraitItem,
+    ty::{
+        display::HirDisplay,
+        primitive::{IntBitness, IntTy, Signedness, UncertainIntTy},
+        ApplicationTy, CallableDef, InferenceResult, Substs, TraitRef, Ty, TypeCtor,
+    },
     type_ref::Mutability,
 };
 
@@ -0,0 +1,46 @@
+//! Text based search for the names of items. The results are not resolved:
+//! this is a cheap first pass, and callers are expected to check that the
+//! references point to the right definition.
+//!
+//! The files to search are given by the caller, as the IDE and the assists
+//! don't look at the same set of files.
+
+use ra_db::{CheckCanceled, FileId, SourceDatabase};
+use ra_syntax::{ast, AstNode};
+
+/// Returns the files among `files` which contain `text`. Searches for a name
+/// use this as a filter, so that only the files mentioning the name are
+/// parsed.
+pub fn files_containing(
+    db: &impl SourceDatabase,
+    files: impl IntoIterator<Item = FileId>,
+    text: &str,
+) -> Vec<FileId> {
+    files
+        .into_iter()
+        .filter(|&file_id| {
+            db.check_canceled();
+            db.file_text(file_id).contains(text)
+        })
+        .collect()
+}
+
+/// Finds all name references spelled `name` in `files`.
+pub fn find_name_refs(
+    db: &impl SourceDatabase,
+    files: impl IntoIterator<Item = FileId>,
+    name: &str,
+) -> Vec<(FileId, ast::NameRef)> {
+    let mut res = Vec::new();
+    for file_id in files_containing(db, files, name) {
+        let parse = db.parse(file_id);
+        let name_refs = parse
+            .tree()
+            .syntax()
+            .descendants()
+            .filter_map(ast::NameRef::cast)
+            .filter(|name_ref| name_ref.text().as_str() == name);
+        res.extend(name_refs.map(|name_ref| (file_id, name_ref)));
+    }
+    res
+}
@@ -9,27 +9,28 @@ use std::sync::Arc;
 
 use ra_db::{FileId, FilePosition};
 use ra_syntax::{
-    algo::find_node_at_offset,
+    algo::{find_covering_element, find_node_at_offset},
     ast::{self, AstNode, NameOwner},
     AstPtr,
     SyntaxKind::*,
-    SyntaxNode, SyntaxNodePtr, TextRange, TextUnit,
+    SyntaxNode, SyntaxNodePtr, SyntaxToken, TextRange, TextUnit,
 };
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use crate::{
     expr::{
         self,
+        match_check::{MatchChecker, MissingPat},
         scope::{ExprScopes, ScopeId},
-        BodySourceMap,
+        Body, BodySourceMap,
     },
-    ids::LocationCtx,
+    ids::{LocationCtx, MacroCallId, MacroCallLoc, MacroFileKind},
     name,
     path::{PathKind, PathSegment},
     ty::method_resolution::implements_trait,
     AsName, AstId, Const, Crate, DefWithBody, Either, Enum, Function, HirDatabase, HirFileId,
-    MacroDef, Module, ModuleDef, Name, Path, PerNs, Resolution, Resolver, Static, Struct, Trait,
-    Ty,
+    MacroDef, Module, ModuleDef, Name, Path, PerNs, Resolution, Resolver, Source, Static, Struct,
+    Trait, Ty,
 };
 
 /// Locates the module by `FileId`. Picks topmost module in the file.
@@ -126,6 +127,16 @@ pub fn trait_from_module(
     Trait { id: ctx.to_def(trait_def) }
 }
 
+pub fn function_from_module(
+    db: &impl HirDatabase,
+    module: Module,
+    fn_def: &ast::FnDef,
+) -> Function {
+    let file_id = module.definition_source(db).file_id;
+    let ctx = LocationCtx::new(db, module, file_id);
+    Function { id: ctx.to_def(fn_def) }
+}
+
 fn try_get_resolver_for_node(
     db: &impl HirDatabase,
     file_id: FileId,
@@ -175,6 +186,7 @@ fn def_with_body_from_child_node(
 #[derive(Debug)]
 pub struct SourceAnalyzer {
     resolver: Resolver,
+    body: Option<Arc<Body>>,
     body_source_map: Option<Arc<BodySourceMap>>,
     infer: Option<Arc<crate::ty::InferenceResult>>,
     scopes: Option<Arc<crate::expr::ExprScopes>>,
@@ -230,9 +242,11 @@ impl SourceAnalyzer {
                 None => scope_for(&scopes, &source_map, &node),
                 Some(offset) => scope_for_offset(&scopes, &source_map, offset),
             };
-            let resolver = expr::resolver_for_scope(def.body(db), db, scope);
+            let body = def.body(db);
+            let resolver = expr::resolver_for_scope(body.clone(), db, scope);
             SourceAnalyzer {
                 resolver,
+                body: Some(body),
                 body_source_map: Some(source_map),
                 infer: Some(def.infer(db)),
                 scopes: Some(scopes),
@@ -243,6 +257,7 @@ impl SourceAnalyzer {
                     .ancestors()
                     .find_map(|node| try_get_resolver_for_node(db, file_id, &node))
                     .unwrap_or_default(),
+                body: None,
                 body_source_map: None,
                 infer: None,
                 scopes: None,
@@ -278,6 +293,28 @@ impl SourceAnalyzer {
         self.infer.as_ref()?.field_resolution(expr_id)
     }
 
+    /// Like `resolve_method_call`, for a method call from a macro expansion in
+    /// the body.
+    pub fn resolve_expanded_method_call(
+        &self,
+        call: Source<&ast::MethodCallExpr>,
+    ) -> Option<Function> {
+        let expr = Source { file_id: call.file_id, ast: &call.ast.clone().into() };
+        let expr_id = self.body_source_map.as_ref()?.expansion_node_expr(expr)?;
+        self.infer.as_ref()?.method_resolution(expr_id)
+    }
+
+    /// Like `resolve_field`, for a field access from a macro expansion in the
+    /// body.
+    pub fn resolve_expanded_field(
+        &self,
+        field: Source<&ast::FieldExpr>,
+    ) -> Option<crate::StructField> {
+        let expr = Source { file_id: field.file_id, ast: &field.ast.clone().into() };
+        let expr_id = self.body_source_map.as_ref()?.expansion_node_expr(expr)?;
+        self.infer.as_ref()?.field_resolution(expr_id)
+    }
+
     pub fn resolve_struct_literal(&self, struct_lit: &ast::StructLit) -> Option<crate::VariantDef> {
         let expr_id = self.body_source_map.as_ref()?.node_expr(&struct_lit.clone().into())?;
         self.infer.as_ref()?.variant_resolution_for_expr(expr_id)
@@ -288,6 +325,21 @@ impl SourceAnalyzer {
         self.infer.as_ref()?.variant_resolution_for_pat(pat_id)
     }
 
+    /// Returns the patterns of the arms `match_expr` is missing, without
+    /// counting the arms for which `ignore_arm` returns `true`.
+    pub fn missing_match_arms(
+        &self,
+        db: &impl HirDatabase,
+        match_expr: &ast::MatchExpr,
+        ignore_arm: impl Fn(&ast::MatchArm) -> bool,
+    ) -> Option<Vec<MissingPat>> {
+        let expr_id = self.body_source_map.as_ref()?.node_expr(&match_expr.clone().into())?;
+        let checker = MatchChecker::new(db, self.body.clone()?, self.infer.clone()?, expr_id)?;
+        let arms: Vec<_> =
+            match_expr.match_arm_list().into_iter().flat_map(|it| it.arms()).collect();
+        checker.missing_arms(|idx| arms.get(idx).map_or(false, |it| ignore_arm(it)))
+    }
+
     pub fn resolve_macro_call(
         &self,
         db: &impl HirDatabase,
@@ -297,6 +349,21 @@ impl SourceAnalyzer {
         self.resolver.resolve_path_as_macro(db, &path)
     }
 
+    /// Expands `macro_call`, if the macro resolves in this scope.
+    pub fn expand(
+        &self,
+        db: &impl HirDatabase,
+        macro_call: Source<&ast::MacroCall>,
+    ) -> Option<Expansion> {
+        let def = self.resolve_macro_call(db, macro_call.ast)?.id;
+        let ast_id = db
+            .ast_id_map(macro_call.file_id)
+            .ast_id(macro_call.ast)
+            .with_file_id(macro_call.file_id);
+        let macro_call_id = MacroCallLoc { def, ast_id }.id(db);
+        Some(Expansion { macro_call_id, macro_file_kind: to_macro_file_kind(macro_call.ast) })
+    }
+
     pub fn resolve_hir_path(
         &self,
         db: &impl HirDatabase,
@@ -306,16 +373,22 @@ impl SourceAnalyzer {
     }
 
     pub fn resolve_path(&self, db: &impl HirDatabase, path: &ast::Path) -> Option<PathResolution> {
+        // Paths from macro expansions are not in the source map, so they are
+        // only resolved by name.
         if let Some(path_expr) = path.syntax().parent().and_then(ast::PathExpr::cast) {
-            let expr_id = self.body_source_map.as_ref()?.node_expr(&path_expr.into())?;
-            if let Some(assoc) = self.infer.as_ref()?.assoc_resolutions_for_expr(expr_id) {
-                return Some(PathResolution::AssocItem(assoc));
+            let source_map = self.body_source_map.as_ref()?;
+            if let Some(expr_id) = source_map.node_expr(&path_expr.into()) {
+                if let Some(assoc) = self.infer.as_ref()?.assoc_resolutions_for_expr(expr_id) {
+                    return Some(PathResolution::AssocItem(assoc));
+                }
             }
         }
         if let Some(path_pat) = path.syntax().parent().and_then(ast::PathPat::cast) {
-            let pat_id = self.body_source_map.as_ref()?.node_pat(&path_pat.into())?;
-            if let Some(assoc) = self.infer.as_ref()?.assoc_resolutions_for_pat(pat_id) {
-                return Some(PathResolution::AssocItem(assoc));
+            let source_map = self.body_source_map.as_ref()?;
+            if let Some(pat_id) = source_map.node_pat(&path_pat.into()) {
+                if let Some(assoc) = self.infer.as_ref()?.assoc_resolutions_for_pat(pat_id) {
+                    return Some(PathResolution::AssocItem(assoc));
+                }
             }
         }
         let hir_path = crate::Path::from_ast(path.clone())?;
@@ -402,6 +475,18 @@ impl SourceAnalyzer {
         )
     }
 
+    /// Finds the method implementing the trait method `func` when it is called
+    /// on a receiver of type `ty`.
+    pub fn resolve_impl_method(
+        &self,
+        db: &impl HirDatabase,
+        ty: Ty,
+        func: Function,
+    ) -> Option<Function> {
+        let canonical = crate::ty::Canonical { value: ty, num_vars: 0 };
+        crate::ty::method_resolution::lookup_impl_method(&canonical, db, &self.resolver, func)
+    }
+
     pub fn autoderef<'a>(
         &'a self,
         db: &'a impl HirDatabase,
@@ -440,6 +525,21 @@ impl SourceAnalyzer {
         implements_trait(&canonical_ty, db, &self.resolver, krate, std_future_trait)
     }
 
+    /// Checks that particular type `ty` implements the `Copy` lang item.
+    pub fn impls_copy(&self, db: &impl HirDatabase, ty: Ty) -> bool {
+        let krate = match self.resolver.krate() {
+            Some(krate) => krate,
+            _ => return false,
+        };
+        let copy_trait = match db.lang_item(krate, "copy".into()) {
+            Some(crate::lang_item::LangItemTarget::Trait(trait_)) => trait_,
+            _ => return false,
+        };
+
+        let canonical_ty = crate::ty::Canonical { value: ty, num_vars: 0 };
+        implements_trait(&canonical_ty, db, &self.resolver, krate, copy_trait)
+    }
+
     #[cfg(test)]
     pub(crate) fn body_source_map(&self) -> Arc<BodySourceMap> {
         self.body_source_map.clone().unwrap()
@@ -456,6 +556,97 @@ impl SourceAnalyzer {
     }
 }
 
+/// An expanded macro call. Identifiers which were passed to the macro can be
+/// mapped between the call and the expansion.
+#[derive(Debug)]
+pub struct Expansion {
+    macro_call_id: MacroCallId,
+    macro_file_kind: MacroFileKind,
+}
+
+impl Expansion {
+    /// Returns the expansion which produced `file_id`, if it is a macro file.
+    pub fn from_file(file_id: HirFileId) -> Option<Expansion> {
+        let macro_file = file_id.macro_file()?;
+        Some(Expansion {
+            macro_call_id: macro_file.macro_call_id,
+            macro_file_kind: macro_file.macro_file_kind,
+        })
+    }
+
+    pub fn file_id(&self) -> HirFileId {
+        self.macro_call_id.as_file(self.macro_file_kind)
+    }
+
+    /// The range of the macro call in the file it is written in.
+    pub fn call_range(&self, db: &impl HirDatabase) -> Source<TextRange> {
+        let loc = self.macro_call_id.loc(db);
+        let range = loc.ast_id.to_node(db).syntax().text_range();
+        Source { file_id: loc.ast_id.file_id(), ast: range }
+    }
+
+    /// Maps an identifier in the macro call to the first token it was
+    /// expanded to.
+    pub fn map_token_down(
+        &self,
+        db: &impl HirDatabase,
+        token: Source<&SyntaxToken>,
+    ) -> Option<Source<SyntaxToken>> {
+        let (file_id, arg, token_map) = self.macro_arg(db)?;
+        let arg_range = arg.syntax().text_range();
+        if token.file_id != file_id || !token.ast.text_range().is_subrange(&arg_range) {
+            return None;
+        }
+        let token_id = token_map.token_by_range(token.ast.text_range() - arg_range.start())?;
+        let range = self.expansion_map(db)?.range_by_token(token_id)?;
+
+        let file_id = self.file_id();
+        let root = db.parse_or_expand(file_id)?;
+        let token = find_covering_element(&root, range).into_token()?;
+        Some(Source { file_id, ast: token })
+    }
+
+    /// Maps the range of an identifier in the expansion to the range of the
+    /// token in the macro call it was expanded from.
+    pub fn map_range_up(
+        &self,
+        db: &impl HirDatabase,
+        range: TextRange,
+    ) -> Option<Source<TextRange>> {
+        let token_id = self.expansion_map(db)?.token_by_range(range)?;
+        let (file_id, arg, token_map) = self.macro_arg(db)?;
+        let range = token_map.relative_range_of(token_id)? + arg.syntax().text_range().start();
+        Some(Source { file_id, ast: range })
+    }
+
+    fn macro_arg(
+        &self,
+        db: &impl HirDatabase,
+    ) -> Option<(HirFileId, ast::TokenTree, mbe::TokenMap)> {
+        let loc = self.macro_call_id.loc(db);
+        let arg = loc.ast_id.to_node(db).token_tree()?;
+        let (_, token_map) = mbe::ast_to_token_tree(&arg)?;
+        Some((loc.ast_id.file_id(), arg, token_map))
+    }
+
+    fn expansion_map(&self, db: &impl HirDatabase) -> Option<mbe::RevTokenMap> {
+        let tt = db.macro_expand(self.macro_call_id).ok()?;
+        let map = match self.macro_file_kind {
+            MacroFileKind::Items => mbe::token_tree_to_ast_item_list(&tt).1,
+            MacroFileKind::Expr => mbe::token_tree_to_expr(&tt).ok()?.1,
+        };
+        Some(map)
+    }
+}
+
+/// Macro calls in item position are expanded to items, the rest to expressions.
+fn to_macro_file_kind(macro_call: &ast::MacroCall) -> MacroFileKind {
+    match macro_call.syntax().parent().map(|it| it.kind()) {
+        Some(SOURCE_FILE) | Some(MACRO_ITEMS) | Some(ITEM_LIST) => MacroFileKind::Items,
+        _ => MacroFileKind::Expr,
+    }
+}
+
 fn scope_for(
     scopes: &ExprScopes,
     source_map: &BodySourceMap,
@@ -20,7 +20,8 @@ use crate::{db::HirDatabase, type_ref::Mutability, AdtDef, GenericParams, Name,
 use display::{HirDisplay, HirFormatter};
 
 pub(crate) use autoderef::autoderef;
-pub(crate) use infer::{infer_query, InferTy, InferenceResult};
+pub use infer::InferenceResult;
+pub(crate) use infer::{infer_query, InferTy};
 pub use lower::CallableDef;
 pub(crate) use lower::{
     callable_item_sig, generic_defaults_query, generic_predicates_query, type_for_def,
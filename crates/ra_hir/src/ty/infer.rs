@@ -658,8 +658,12 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
         subpats: &[PatId],
         expected: &Ty,
         default_bm: BindingMode,
+        id: PatId,
     ) -> Ty {
         let (ty, def) = self.resolve_variant(path);
+        if let Some(variant) = def {
+            self.write_variant_resolution(id.into(), variant);
+        }
 
         self.unify(&ty, expected);
 
@@ -767,7 +771,7 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
                 Ty::apply_one(TypeCtor::Ref(*mutability), subty)
             }
             Pat::TupleStruct { path: ref p, args: ref subpats } => {
-                self.infer_tuple_struct_pat(p.as_ref(), subpats, expected, default_bm)
+                self.infer_tuple_struct_pat(p.as_ref(), subpats, expected, default_bm, pat)
             }
             Pat::Struct { path: ref p, args: ref fields } => {
                 self.infer_struct_pat(p.as_ref(), fields, expected, default_bm, pat)
@@ -775,8 +779,18 @@ impl<'a, D: HirDatabase> InferenceContext<'a, D> {
             Pat::Path(path) => {
                 // FIXME use correct resolver for the surrounding expression
                 let resolver = self.resolver.clone();
+                let variant =
+                    match resolver.resolve_path_without_assoc_items(self.db, &path).take_values() {
+                        Some(Def(ModuleDef::EnumVariant(it))) => Some(it.into()),
+                        Some(Def(ModuleDef::Struct(it))) => Some(it.into()),
+                        _ => None,
+                    };
+                if let Some(variant) = variant {
+                    self.write_variant_resolution(pat.into(), variant);
+                }
                 self.infer_path_expr(&resolver, &path, pat.into()).unwrap_or(Ty::Unknown)
             }
+            Pat::Lit(expr) => self.infer_expr(*expr, &Expectation::has_type(expected.clone())),
             Pat::Bind { mode, name: _name, subpat } => {
                 let mode = if mode == &BindingAnnotation::Unannotated {
                     default_bm
@@ -2,12 +2,15 @@
 //! For details about how this works in rustc, see the method lookup page in the
 //! [rustc guide](https://rust-lang.github.io/rustc-guide/method-lookup.html)
 //! and the corresponding code mostly in librustc_typeck/check/method/probe.rs.
-use std::sync::Arc;
+use std::{iter, sync::Arc};
 
 use arrayvec::ArrayVec;
 use rustc_hash::FxHashMap;
 
-use super::{autoderef, lower, Canonical, InEnvironment, TraitEnvironment, TraitRef};
+use super::{
+    autoderef, lower, Canonical, GenericPredicate, InEnvironment, Obligation, Substs,
+    TraitEnvironment, TraitRef,
+};
 use crate::{
     generics::HasGenericParams,
     impl_block::{ImplBlock, ImplId, ImplItem},
@@ -68,6 +71,15 @@ impl CrateImplBlocks {
         )
     }
 
+    pub fn all_impls<'a>(&'a self) -> impl Iterator<Item = ImplBlock> + 'a {
+        self.impls.values().chain(self.impls_by_trait.values()).flat_map(|i| i.iter()).map(
+            move |(module_id, impl_id)| {
+                let module = Module { krate: self.krate, module_id: *module_id };
+                ImplBlock::from_id(module, *impl_id)
+            },
+        )
+    }
+
     fn collect_recursive(&mut self, db: &impl HirDatabase, module: Module) {
         let module_impl_blocks = db.impls_in_module(module);
 
@@ -269,6 +281,107 @@ pub(crate) fn implements_trait(
     solution.is_some()
 }
 
+/// Finds the method of an impl block which implements the trait method `func`
+/// for a receiver of type `ty`. The receiver is autoderefed like for method
+/// resolution, and the where clauses of the impl are checked with the trait
+/// solver, so blanket impls are found as well.
+///
+/// Returns `None` if `ty` does not select a single impl, or if the impl uses
+/// the default body of the trait method.
+pub(crate) fn lookup_impl_method(
+    ty: &Canonical<Ty>,
+    db: &impl HirDatabase,
+    resolver: &Resolver,
+    func: Function,
+) -> Option<Function> {
+    let trait_ = func.parent_trait(db)?;
+    let name = func.name(db);
+    let (self_ty, found) = lookup_method(ty, db, &name, resolver)?;
+    if found != func {
+        return None;
+    }
+
+    let krate = resolver.krate()?;
+    let env = lower::trait_env(db, resolver);
+    let trait_krate = trait_.module(db).krate(db)?;
+    let mut impls = Vec::new();
+    for krate in iter::once(trait_krate).chain(trait_krate.reverse_dependencies(db)) {
+        for impl_block in db.impls_in_crate(krate).lookup_impl_blocks_for_trait(trait_) {
+            if impl_applies(db, &env, krate, impl_block, &self_ty) {
+                impls.push(impl_block);
+            }
+        }
+    }
+    let impl_block = match impls.as_slice() {
+        [it] => *it,
+        _ => return None,
+    };
+    impl_block.items(db).into_iter().find_map(|item| match item {
+        ImplItem::Method(it) if it.name(db) == name => Some(it),
+        _ => None,
+    })
+}
+
+/// Checks whether `impl_block` is an impl for `ty`, i.e. whether its self type
+/// matches `ty` and its where clauses hold.
+fn impl_applies(
+    db: &impl HirDatabase,
+    env: &Arc<TraitEnvironment>,
+    krate: Crate,
+    impl_block: ImplBlock,
+    ty: &Ty,
+) -> bool {
+    let mut substs = vec![None; impl_block.generic_params(db).count_params_including_parent()];
+    if !match_impl_ty(&impl_block.target_ty(db), ty, &mut substs) {
+        return false;
+    }
+    // parameters which only appear in the where clauses are left to the solver
+    let mut num_vars = 0;
+    let substs: Substs = substs
+        .into_iter()
+        .map(|it| {
+            it.unwrap_or_else(|| {
+                num_vars += 1;
+                Ty::Bound(num_vars - 1)
+            })
+        })
+        .collect::<Vec<_>>()
+        .into();
+    db.generic_predicates(impl_block.into()).iter().all(|pred| match pred.clone().subst(&substs) {
+        GenericPredicate::Implemented(trait_ref) => {
+            let obligation = Obligation::Trait(trait_ref);
+            let goal = Canonical { num_vars, value: InEnvironment::new(env.clone(), obligation) };
+            db.trait_solve(krate, goal).is_some()
+        }
+        GenericPredicate::Error => true,
+    })
+}
+
+/// Matches `ty` against the self type of an impl, binding the type parameters
+/// of the impl in `substs`.
+fn match_impl_ty(impl_ty: &Ty, ty: &Ty, substs: &mut [Option<Ty>]) -> bool {
+    match (impl_ty, ty) {
+        (Ty::Param { idx, .. }, _) => match substs.get_mut(*idx as usize) {
+            Some(Some(bound)) => bound == ty,
+            Some(slot) => {
+                *slot = Some(ty.clone());
+                true
+            }
+            None => false,
+        },
+        (Ty::Apply(impl_ty), Ty::Apply(ty)) => {
+            impl_ty.ctor == ty.ctor
+                && impl_ty.parameters.len() == ty.parameters.len()
+                && impl_ty
+                    .parameters
+                    .iter()
+                    .zip(ty.parameters.iter())
+                    .all(|(impl_ty, ty)| match_impl_ty(impl_ty, ty, substs))
+        }
+        _ => impl_ty == ty,
+    }
+}
+
 impl Ty {
     // This would be nicer if it just returned an iterator, but that runs into
     // lifetime problems, because we need to borrow temp `CrateImplBlocks`.
@@ -1113,6 +1113,63 @@ fn test() {
     );
 }
 
+#[test]
+fn infer_literal_pattern() {
+    assert_snapshot_matches!(
+        infer(r#"
+enum E { A, B(u32) }
+
+fn test(x: i32, b: bool, e: E) {
+    match &x {
+        1 => (),
+        _ => (),
+    };
+    match b {
+        true => (),
+        false => (),
+    };
+    match e {
+        E::A => (),
+        E::B(2) => (),
+        E::B(_) => (),
+    };
+}
+"#),
+        @r###"
+[31; 32) 'x': i32
+[39; 40) 'b': bool
+[48; 49) 'e': E
+[54; 262) '{     ...  }; }': ()
+[60; 110) 'match ...     }': ()
+[66; 68) '&x': &i32
+[67; 68) 'x': i32
+[79; 80) '1': i32
+[79; 80) '1': i32
+[84; 86) '()': ()
+[96; 97) '_': &i32
+[101; 103) '()': ()
+[116; 172) 'match ...     }': ()
+[122; 123) 'b': bool
+[134; 138) 'true': bool
+[134; 138) 'true': bool
+[142; 144) '()': ()
+[154; 159) 'false': bool
+[154; 159) 'false': bool
+[163; 165) '()': ()
+[178; 259) 'match ...     }': ()
+[184; 185) 'e': E
+[196; 200) 'E::A': E
+[204; 206) '()': ()
+[216; 223) 'E::B(2)': E
+[221; 222) '2': u32
+[221; 222) '2': u32
+[227; 229) '()': ()
+[239; 246) 'E::B(_)': E
+[244; 245) '_': u32
+[250; 252) '()': ()"###
+    );
+}
+
 #[test]
 fn infer_struct_generics() {
     assert_snapshot_matches!(
@@ -2637,6 +2694,7 @@ fn main() {
    ⋮
    ⋮[156; 182) '{     ...,2); }': ()
    ⋮[166; 167) 'x': Foo
+   ⋮[170; 179) 'foo!(1,2)': Foo
     "###
     );
 }
@@ -3141,6 +3199,59 @@ fn test<T: Trait>(t: T) { (*t)<|>; }
     assert_eq!(t, "i128");
 }
 
+#[test]
+fn generic_param_env_super_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Super { fn foo(&self) -> u128; }
+trait Sub: Super {}
+fn test<T: Sub>(t: T) { t.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "u128");
+}
+
+#[test]
+fn generic_param_env_super_trait_where_clause() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Super { fn foo(&self) -> u128; }
+trait Sub where Self: Super {}
+fn test<T>(t: T) where T: Sub { t.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "u128");
+}
+
+#[test]
+fn generic_param_env_transitive_super_trait() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait A { fn foo(&self) -> u128; }
+trait B: A {}
+trait C: B {}
+fn test<T: C>(t: T) { t.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "u128");
+}
+
+#[test]
+fn generic_param_env_super_trait_not_met() {
+    let t = type_at(
+        r#"
+//- /main.rs
+trait Super { fn foo(&self) -> u128; }
+trait Sub {}
+fn test<T: Sub>(t: T) { t.foo()<|>; }
+"#,
+    );
+    assert_eq!(t, "{unknown}");
+}
+
 fn type_at_pos(db: &MockDatabase, pos: FilePosition) -> String {
     let file = db.parse(pos.file_id).ok().unwrap();
     let expr = algo::find_node_at_offset::<ast::Expr>(file.syntax(), pos.offset).unwrap();
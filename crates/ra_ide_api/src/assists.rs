@@ -1,6 +1,6 @@
 use ra_db::{FilePosition, FileRange};
 
-use crate::{db::RootDatabase, SourceChange, SourceFileEdit};
+use crate::{db::RootDatabase, FileSystemEdit, SourceChange, SourceFileEdit};
 
 pub use ra_assists::AssistId;
 
@@ -16,10 +16,28 @@ pub(crate) fn assists(db: &RootDatabase, frange: FileRange) -> Vec<Assist> {
         .map(|(label, action)| {
             let file_id = frange.file_id;
             let file_edit = SourceFileEdit { file_id, edit: action.edit };
+            let file_edits = std::iter::once(file_edit)
+                .chain(
+                    action
+                        .other_file_edits
+                        .into_iter()
+                        .map(|(file_id, edit)| SourceFileEdit { file_id, edit }),
+                )
+                .collect();
+            let file_system_edits = action
+                .new_files
+                .into_iter()
+                .map(|(source_root, path, initial_contents)| FileSystemEdit::CreateFile {
+                    source_root,
+                    path,
+                    initial_contents,
+                })
+                .collect();
             let id = label.id;
-            let change = SourceChange::source_file_edit(label.label, file_edit).with_cursor_opt(
-                action.cursor_position.map(|offset| FilePosition { offset, file_id }),
-            );
+            let change = SourceChange::from_edits(label.label, file_edits, file_system_edits)
+                .with_cursor_opt(
+                    action.cursor_position.map(|offset| FilePosition { offset, file_id }),
+                );
             Assist { id, change }
         })
         .collect()
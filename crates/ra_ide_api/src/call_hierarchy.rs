@@ -0,0 +1,289 @@
+//! Entry point for call-hierarchy
+
+use hir::{source_binder, CallableDef, Expr, HasSource};
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    algo::find_node_at_offset,
+    ast::{self, DocCommentsOwner},
+    AstNode, TextRange,
+};
+
+use crate::{
+    db::RootDatabase,
+    display::ShortLabel,
+    name_ref_kind::{classify_name_ref, NameRefKind},
+    references::find_name_refs,
+    FilePosition, NavigationTarget, RangeInfo,
+};
+
+/// A function participating in a call hierarchy, together with the ranges of
+/// the calls.
+///
+/// For incoming calls, `target` is the caller and `ranges` point into the
+/// caller's file. For outgoing calls, `target` is the callee and `ranges` point
+/// into the file of the function the hierarchy was requested for.
+#[derive(Debug, Clone)]
+pub struct CallItem {
+    pub target: NavigationTarget,
+    pub ranges: Vec<TextRange>,
+}
+
+pub(crate) fn call_hierarchy(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let RangeInfo { range, info: func } = function_at(db, position)?;
+    Some(RangeInfo::new(range, vec![NavigationTarget::from_def_source(db, func)]))
+}
+
+pub(crate) fn incoming_calls(db: &RootDatabase, position: FilePosition) -> Option<Vec<CallItem>> {
+    let func = function_at(db, position)?.info;
+    let name = func.name(db).to_string();
+
+    let mut calls = CallLocations::default();
+    for (file_id, name_ref) in find_name_refs(db, &name, |kind| as_function(kind) == Some(func)) {
+        if !is_call(&name_ref) {
+            continue;
+        }
+        let caller = match name_ref.syntax().ancestors().find_map(ast::FnDef::cast) {
+            Some(it) => it,
+            None => continue,
+        };
+        let nav = NavigationTarget::from_named(
+            file_id,
+            &caller,
+            caller.doc_comment_text(),
+            caller.short_label(),
+        );
+        calls.add(nav, name_ref.syntax().text_range());
+    }
+    Some(calls.finish())
+}
+
+pub(crate) fn outgoing_calls(db: &RootDatabase, position: FilePosition) -> Option<Vec<CallItem>> {
+    let func = function_at(db, position)?.info;
+    let src = func.source(db);
+    let root = src.ast.syntax().ancestors().last()?;
+    let body = func.body(db);
+    let source_map = func.body_source_map(db);
+    let infer = func.infer(db);
+
+    let mut calls = CallLocations::default();
+    for (expr_id, expr) in body.exprs() {
+        let (callee, range) = match expr {
+            Expr::Call { callee, .. } => {
+                let callee_fn = match infer[*callee].as_callable() {
+                    Some((CallableDef::Function(it), _)) => it,
+                    _ => continue,
+                };
+                match source_map.expr_syntax(*callee) {
+                    Some(ptr) => (callee_fn, ptr.range()),
+                    None => continue,
+                }
+            }
+            Expr::MethodCall { .. } => {
+                let callee_fn = match infer.method_resolution(expr_id) {
+                    Some(it) => it,
+                    None => continue,
+                };
+                let ptr = match source_map.expr_syntax(expr_id) {
+                    Some(it) => it,
+                    None => continue,
+                };
+                let range = ast::MethodCallExpr::cast(ptr.to_node(&root))
+                    .and_then(|call| call.name_ref())
+                    .map(|name_ref| name_ref.syntax().text_range())
+                    .unwrap_or_else(|| ptr.range());
+                (callee_fn, range)
+            }
+            _ => continue,
+        };
+        calls.add(NavigationTarget::from_def_source(db, callee), range);
+    }
+    Some(calls.finish())
+}
+
+/// Finds the function which is either defined or referenced at the position.
+fn function_at(db: &RootDatabase, position: FilePosition) -> Option<RangeInfo<hir::Function>> {
+    let parse = db.parse(position.file_id);
+    let syntax = parse.tree().syntax().clone();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(&syntax, position.offset) {
+        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+        let func = as_function(classify_name_ref(db, &analyzer, &name_ref)?)?;
+        return Some(RangeInfo::new(name_ref.syntax().text_range(), func));
+    }
+    let name = find_node_at_offset::<ast::Name>(&syntax, position.offset)?;
+    let fn_def = name.syntax().parent().and_then(ast::FnDef::cast)?;
+    let module = source_binder::module_from_child_node(db, position.file_id, fn_def.syntax())?;
+    let func = source_binder::function_from_module(db, module, &fn_def);
+    Some(RangeInfo::new(name.syntax().text_range(), func))
+}
+
+fn as_function(kind: NameRefKind) -> Option<hir::Function> {
+    match kind {
+        NameRefKind::Method(func)
+        | NameRefKind::Def(hir::ModuleDef::Function(func))
+        | NameRefKind::AssocItem(hir::ImplItem::Method(func)) => Some(func),
+        _ => None,
+    }
+}
+
+/// Checks that the name ref is the name of a called function or method, and
+/// not, for example, a function passed as an argument.
+fn is_call(name_ref: &ast::NameRef) -> bool {
+    if name_ref.syntax().parent().and_then(ast::MethodCallExpr::cast).is_some() {
+        return true;
+    }
+    name_ref
+        .syntax()
+        .ancestors()
+        .find_map(ast::PathExpr::cast)
+        .and_then(|path_expr| path_expr.syntax().parent())
+        .and_then(ast::CallExpr::cast)
+        .is_some()
+}
+
+/// Groups call ranges by the function they belong to.
+#[derive(Default)]
+struct CallLocations {
+    items: Vec<CallItem>,
+}
+
+impl CallLocations {
+    fn add(&mut self, target: NavigationTarget, range: TextRange) {
+        let existing = self.items.iter_mut().find(|it| {
+            it.target.file_id() == target.file_id() && it.target.full_range() == target.full_range()
+        });
+        match existing {
+            Some(item) => item.ranges.push(range),
+            None => self.items.push(CallItem { target, ranges: vec![range] }),
+        }
+    }
+
+    fn finish(mut self) -> Vec<CallItem> {
+        for item in self.items.iter_mut() {
+            item.ranges.sort_by_key(|range| range.start());
+        }
+        self.items.sort_by_key(|item| (item.target.file_id(), item.ranges[0].start()));
+        self.items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::analysis_and_position;
+
+    fn check_incoming(fixture: &str, expected: &[&str]) {
+        let (analysis, pos) = analysis_and_position(fixture);
+        let calls = analysis.incoming_calls(pos).unwrap().unwrap();
+        check_calls(calls, expected);
+    }
+
+    fn check_outgoing(fixture: &str, expected: &[&str]) {
+        let (analysis, pos) = analysis_and_position(fixture);
+        let calls = analysis.outgoing_calls(pos).unwrap().unwrap();
+        check_calls(calls, expected);
+    }
+
+    fn check_calls(calls: Vec<crate::CallItem>, expected: &[&str]) {
+        let actual = calls
+            .iter()
+            .map(|call| format!("{} {:?}", call.target.debug_render(), call.ranges))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn test_call_hierarchy_on_ref() {
+        let (analysis, pos) = analysis_and_position(
+            "
+            //- /lib.rs
+            fn callee() {}
+            fn caller() {
+                call<|>ee();
+            }
+            ",
+        );
+        let navs = analysis.call_hierarchy(pos).unwrap().unwrap().info;
+        assert_eq!(navs.len(), 1);
+        navs[0].assert_match("callee FN_DEF FileId(1) [0; 14) [3; 9)");
+    }
+
+    #[test]
+    fn test_incoming_calls_with_multiple_callers() {
+        check_incoming(
+            "
+            //- /lib.rs
+            fn call<|>ee() {}
+            fn caller1() {
+                callee();
+            }
+            fn caller2() {
+                callee();
+                callee();
+            }
+            ",
+            &[
+                "caller1 FN_DEF FileId(1) [15; 45) [18; 25) [[34; 40)]",
+                "caller2 FN_DEF FileId(1) [46; 90) [49; 56) [[65; 71), [79; 85)]",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_incoming_calls_in_another_file() {
+        check_incoming(
+            "
+            //- /lib.rs
+            mod foo;
+            fn call<|>ee() {}
+            //- /foo.rs
+            fn caller() {
+                crate::callee();
+                let f = crate::callee;
+            }
+            ",
+            &["caller FN_DEF FileId(2) [0; 63) [3; 9) [[25; 31)]"],
+        );
+    }
+
+    #[test]
+    fn test_incoming_calls_of_method() {
+        check_incoming(
+            "
+            //- /lib.rs
+            struct S;
+            impl S {
+                fn call<|>ee(&self) {}
+            }
+            fn caller(s: S) {
+                s.callee();
+            }
+            ",
+            &["caller FN_DEF FileId(1) [45; 80) [48; 54) [[69; 75)]"],
+        );
+    }
+
+    #[test]
+    fn test_outgoing_calls() {
+        check_outgoing(
+            "
+            //- /lib.rs
+            struct S;
+            impl S {
+                fn meth(&self) {}
+            }
+            fn callee() {}
+            fn call<|>er(s: S) {
+                callee();
+                s.meth();
+                callee();
+            }
+            ",
+            &[
+                "callee FN_DEF FileId(1) [43; 57) [46; 52) [[80; 86), [108; 114)]",
+                "meth FN_DEF FileId(1) [23; 40) [26; 30) [[96; 100)]",
+            ],
+        );
+    }
+}
@@ -1,8 +1,9 @@
+use hir::HasSource;
 use ra_db::SourceDatabase;
 use ra_syntax::{
-    algo::find_node_at_offset,
+    algo::{ancestors_at_offset, find_node_at_offset},
     ast::{self, ArgListOwner},
-    AstNode, SyntaxNode, TextUnit,
+    AstNode, NodeOrToken, SyntaxKind, SyntaxNode, TextUnit, T,
 };
 use test_utils::tested_by;
 
@@ -13,29 +14,82 @@ pub(crate) fn call_info(db: &RootDatabase, position: FilePosition) -> Option<Cal
     let parse = db.parse(position.file_id);
     let syntax = parse.tree().syntax().clone();
 
-    // Find the calling expression and it's NameRef
+    // Find the calling expression
     let calling_node = FnCallNode::with_node(&syntax, position.offset)?;
-    let name_ref = calling_node.name_ref()?;
 
-    let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
-    let function = match &calling_node {
+    let analyzer = hir::SourceAnalyzer::new(db, position.file_id, calling_node.syntax(), None);
+    let (signature, has_self) = match &calling_node {
         FnCallNode::CallExpr(expr) => {
             //FIXME: apply subst
             let (callable_def, _subst) = analyzer.type_of(db, &expr.expr()?)?.as_callable()?;
             match callable_def {
-                hir::CallableDef::Function(it) => it,
-                //FIXME: handle other callables
-                _ => return None,
+                hir::CallableDef::Function(it) => {
+                    (FunctionSignature::from_hir(db, it), it.data(db).has_self_param())
+                }
+                hir::CallableDef::Struct(it) => (FunctionSignature::from_struct(db, it)?, false),
+                hir::CallableDef::EnumVariant(it) => {
+                    (FunctionSignature::from_enum_variant(db, it)?, false)
+                }
             }
         }
-        FnCallNode::MethodCallExpr(expr) => analyzer.resolve_method_call(&expr)?,
+        FnCallNode::MethodCallExpr(expr) => {
+            let function = analyzer.resolve_method_call(&expr)?;
+            (FunctionSignature::from_hir(db, function), function.data(db).has_self_param())
+        }
+        FnCallNode::MacroCall(call) => {
+            let macro_def = analyzer.resolve_macro_call(db, call)?;
+            let signature = FunctionSignature::from_macro(db, macro_def)?;
+            // The rules are the parameters, and the active one is the rule the
+            // invocation expands with
+            let active_parameter = matching_rule(db, macro_def, call);
+            return Some(CallInfo { signature, active_parameter, generic_args: false });
+        }
+        FnCallNode::TypeArgList(type_arg_list) => {
+            let function = match type_arg_list.syntax().parent()?.kind() {
+                SyntaxKind::METHOD_CALL_EXPR => {
+                    let call =
+                        type_arg_list.syntax().parent().and_then(ast::MethodCallExpr::cast)?;
+                    analyzer.resolve_method_call(&call)?
+                }
+                _ => {
+                    let path = type_arg_list.syntax().ancestors().find_map(ast::Path::cast)?;
+                    match analyzer.resolve_path(db, &path)? {
+                        hir::PathResolution::Def(hir::ModuleDef::Function(it))
+                        | hir::PathResolution::AssocItem(hir::ImplItem::Method(it)) => it,
+                        _ => return None,
+                    }
+                }
+            };
+            let mut call_info = CallInfo {
+                signature: FunctionSignature::from_hir(db, function),
+                active_parameter: None,
+                generic_args: true,
+            };
+            let num_params = call_info.parameters().len();
+            if num_params > 0 {
+                // Lifetimes are usually left out, in which case the arguments
+                // start at the first type parameter
+                let skipped_lifetimes = if type_arg_list.lifetime_args().next().is_some() {
+                    0
+                } else {
+                    call_info.parameters().iter().take_while(|it| it.starts_with('\'')).count()
+                };
+                let param = skipped_lifetimes
+                    + type_arg_list
+                        .syntax()
+                        .children()
+                        .take_while(|arg| arg.text_range().end() < position.offset)
+                        .count();
+                call_info.active_parameter = Some(std::cmp::min(param, num_params - 1));
+            }
+            return Some(call_info);
+        }
     };
 
-    let mut call_info = CallInfo::new(db, function);
+    let mut call_info = CallInfo { signature, active_parameter: None, generic_args: false };
 
     // If we have a calling expression let's find which argument we are on
     let num_params = call_info.parameters().len();
-    let has_self = function.data(db).has_self_param();
 
     if num_params == 1 {
         if !has_self {
@@ -73,13 +127,53 @@ pub(crate) fn call_info(db: &RootDatabase, position: FilePosition) -> Option<Cal
     Some(call_info)
 }
 
-enum FnCallNode {
+fn matching_rule(
+    db: &RootDatabase,
+    macro_def: hir::MacroDef,
+    call: &ast::MacroCall,
+) -> Option<usize> {
+    let (definition, _) = mbe::ast_to_token_tree(&macro_def.source(db).ast.token_tree()?)?;
+    let rules = mbe::MacroRules::parse(&definition).ok()?;
+    let (invocation, _) = mbe::ast_to_token_tree(&call.token_tree()?)?;
+    rules.matching_rule(&invocation)
+}
+
+pub(crate) enum FnCallNode {
     CallExpr(ast::CallExpr),
     MethodCallExpr(ast::MethodCallExpr),
+    MacroCall(ast::MacroCall),
+    /// The generic arguments of a function or method, `foo::<T>`
+    TypeArgList(ast::TypeArgList),
 }
 
 impl FnCallNode {
     fn with_node(syntax: &SyntaxNode, offset: TextUnit) -> Option<FnCallNode> {
+        // In nested calls, the innermost list of arguments the offset is in
+        // decides the call, rather than the innermost call.
+        let in_args = ancestors_at_offset(syntax, offset)
+            .filter(|node| match node.kind() {
+                SyntaxKind::ARG_LIST | SyntaxKind::TOKEN_TREE | SyntaxKind::TYPE_ARG_LIST => {
+                    is_between_delimiters(node, offset)
+                }
+                _ => false,
+            })
+            .find_map(|node| match node.kind() {
+                SyntaxKind::ARG_LIST => {
+                    let call = node.parent()?;
+                    if let Some(expr) = ast::CallExpr::cast(call.clone()) {
+                        return Some(FnCallNode::CallExpr(expr));
+                    }
+                    ast::MethodCallExpr::cast(call).map(FnCallNode::MethodCallExpr)
+                }
+                SyntaxKind::TOKEN_TREE => {
+                    node.parent().and_then(ast::MacroCall::cast).map(FnCallNode::MacroCall)
+                }
+                _ => ast::TypeArgList::cast(node).map(FnCallNode::TypeArgList),
+            });
+        if in_args.is_some() {
+            return in_args;
+        }
+
         if let Some(expr) = find_node_at_offset::<ast::CallExpr>(syntax, offset) {
             return Some(FnCallNode::CallExpr(expr));
         }
@@ -89,36 +183,83 @@ impl FnCallNode {
         None
     }
 
-    fn name_ref(&self) -> Option<ast::NameRef> {
-        match self {
-            FnCallNode::CallExpr(call_expr) => Some(match call_expr.expr()?.kind() {
-                ast::ExprKind::PathExpr(path_expr) => path_expr.path()?.segment()?.name_ref()?,
-                _ => return None,
-            }),
+    /// Returns the call or the method call whose callee is `name_ref`.
+    pub(crate) fn with_callee(name_ref: &ast::NameRef) -> Option<FnCallNode> {
+        let parent = name_ref.syntax().parent()?;
+        if let Some(call) = ast::MethodCallExpr::cast(parent.clone()) {
+            return Some(FnCallNode::MethodCallExpr(call));
+        }
+        let path = ast::PathSegment::cast(parent)?.parent_path();
+        let path_expr = path.syntax().parent().and_then(ast::PathExpr::cast)?;
+        let call = path_expr.syntax().parent().and_then(ast::CallExpr::cast)?;
+        Some(FnCallNode::CallExpr(call))
+    }
 
-            FnCallNode::MethodCallExpr(call_expr) => {
-                call_expr.syntax().children().filter_map(ast::NameRef::cast).nth(0)
-            }
+    /// Maps the arguments of the call to the parameters of the callee. The
+    /// first element is the argument passed as `self` when a method is called
+    /// like a function, as in `Foo::method(foo, a)`; the second one holds the
+    /// arguments of the other parameters.
+    pub(crate) fn param_args(&self, has_self: bool) -> Option<(Option<ast::Expr>, Vec<ast::Expr>)> {
+        let mut args = self.arg_list()?.args();
+        let receiver = match self {
+            FnCallNode::CallExpr(_) if has_self => Some(args.next()?),
+            _ => None,
+        };
+        Some((receiver, args.collect()))
+    }
+
+    pub(crate) fn syntax(&self) -> &SyntaxNode {
+        match self {
+            FnCallNode::CallExpr(it) => it.syntax(),
+            FnCallNode::MethodCallExpr(it) => it.syntax(),
+            FnCallNode::MacroCall(it) => it.syntax(),
+            FnCallNode::TypeArgList(it) => it.syntax(),
         }
     }
 
-    fn arg_list(&self) -> Option<ast::ArgList> {
+    pub(crate) fn arg_list(&self) -> Option<ast::ArgList> {
         match self {
             FnCallNode::CallExpr(expr) => expr.arg_list(),
             FnCallNode::MethodCallExpr(expr) => expr.arg_list(),
+            FnCallNode::MacroCall(_) | FnCallNode::TypeArgList(_) => None,
         }
     }
 }
 
-impl CallInfo {
-    fn new(db: &RootDatabase, function: hir::Function) -> Self {
-        let signature = FunctionSignature::from_hir(db, function);
-
-        CallInfo { signature, active_parameter: None }
+/// Checks that `offset` is after the opening delimiter of `node` and before
+/// the closing one, which may be missing while typing.
+fn is_between_delimiters(node: &SyntaxNode, offset: TextUnit) -> bool {
+    let mut tokens = node.children_with_tokens().filter_map(|it| it.into_token());
+    let open = match tokens.find(|it| match it.kind() {
+        T!['('] | T!['['] | T!['{'] | T![<] => true,
+        _ => false,
+    }) {
+        Some(it) => it,
+        None => return false,
+    };
+    if offset < open.text_range().end() {
+        return false;
+    }
+    match node.last_child_or_token() {
+        Some(NodeOrToken::Token(close)) if close.text_range() != open.text_range() => {
+            match close.kind() {
+                T![')'] | T![']'] | T!['}'] | T![>] => offset <= close.text_range().start(),
+                _ => offset <= node.text_range().end(),
+            }
+        }
+        _ => offset <= node.text_range().end(),
     }
+}
 
-    fn parameters(&self) -> &[String] {
-        &self.signature.parameters
+impl CallInfo {
+    /// The parameters `active_parameter` refers to: the type parameters for
+    /// generic arguments, and the parameters of the signature otherwise.
+    pub fn parameters(&self) -> &[String] {
+        if self.generic_args {
+            &self.signature.generic_parameters
+        } else {
+            &self.signature.parameters
+        }
     }
 }
 
@@ -426,6 +567,101 @@ By default this method stops actor's `Context`."#
         );
     }
 
+    #[test]
+    fn test_fn_signature_nested_calls() {
+        let info = call_info(
+            r#"fn foo(x: u32, y: u32) -> u32 {x + y}
+fn bar(z: u32) -> u32 { z }
+fn main() { foo(bar(1)<|>, 2); }"#,
+        );
+
+        assert_eq!(info.parameters(), ["x: u32", "y: u32"]);
+        assert_eq!(info.active_parameter, Some(0));
+
+        let info = call_info(
+            r#"fn foo(x: u32, y: u32) -> u32 {x + y}
+fn bar(z: u32) -> u32 { z }
+fn main() { foo(1, ba<|>r(2)); }"#,
+        );
+
+        assert_eq!(info.parameters(), ["x: u32", "y: u32"]);
+        assert_eq!(info.active_parameter, Some(1));
+    }
+
+    #[test]
+    fn test_signature_for_tuple_struct() {
+        let info = call_info(
+            r#"
+/// A tuple struct
+struct TS<T>(u32, T);
+
+fn main() {
+    let s = TS(0, <|>);
+}"#,
+        );
+
+        assert_eq!(info.parameters(), ["u32", "T"]);
+        assert_eq!(info.active_parameter, Some(1));
+        assert_eq!(info.label(), "struct TS<T>(u32, T)");
+        assert_eq!(info.doc().map(|it| it.into()), Some("A tuple struct".to_string()));
+    }
+
+    #[test]
+    fn test_signature_for_enum_variant() {
+        let info = call_info(
+            r#"
+enum E {
+    /// A variant
+    A(i32, i32),
+    B,
+}
+
+fn main() {
+    let a = E::A(<|>);
+}"#,
+        );
+
+        assert_eq!(info.parameters(), ["i32", "i32"]);
+        assert_eq!(info.active_parameter, Some(0));
+        assert_eq!(info.label(), "E::A(i32, i32)");
+        assert_eq!(info.doc().map(|it| it.into()), Some("A variant".to_string()));
+    }
+
+    #[test]
+    fn test_signature_for_macro() {
+        let info = call_info(
+            r#"
+macro_rules! id {
+    ($x:expr) => { $x };
+    ($x:expr, $y:expr) => { $y };
+}
+
+fn main() {
+    id!(1, <|>2);
+}"#,
+        );
+
+        assert_eq!(info.parameters(), ["($x:expr)", "($x:expr, $y:expr)"]);
+        assert_eq!(info.active_parameter, Some(1));
+        assert_eq!(info.label(), "macro_rules! id { ($x:expr); ($x:expr, $y:expr) }");
+    }
+
+    #[test]
+    fn test_signature_for_generic_args() {
+        let info = call_info(
+            r#"
+fn foo<'a, T: Clone, U>(x: &'a T, y: U) {}
+
+fn main() {
+    foo::<u32, <|>>();
+}"#,
+        );
+
+        assert_eq!(info.parameters(), ["'a", "T: Clone", "U"]);
+        assert_eq!(info.active_parameter, Some(2));
+        assert_eq!(info.label(), "fn foo<'a, T: Clone, U>(x: &'a T, y: U)");
+    }
+
     #[test]
     fn call_info_bad_offset() {
         covers!(call_info_bad_offset);
@@ -0,0 +1,611 @@
+//! Changes the parameters of a function: they can be reordered, added, removed
+//! or renamed. The call sites are updated across the workspace, as are the
+//! declaration and the other implementations when the function is a trait
+//! method.
+
+use std::{collections::BTreeMap, fmt};
+
+use hir::{db::HirDatabase, source_binder, HasSource};
+use ra_assists::search::UsageEdits;
+use ra_db::SourceDatabase;
+use ra_syntax::{
+    algo::{find_covering_element, find_node_at_offset},
+    ast::{self, NameOwner},
+    AstNode,
+    SyntaxKind::IDENT,
+    TextRange,
+};
+use ra_text_edit::TextEditBuilder;
+
+use crate::{
+    call_info::FnCallNode,
+    db::{LineIndexDatabase, RootDatabase},
+    name_ref_kind::{classify_name_ref, NameRefKind},
+    references::{find_name_refs, local_files},
+    symbol_index::SymbolsDatabase,
+    FileId, FilePosition, SourceChange, SourceFileEdit,
+};
+
+/// The expression passed to an added parameter without a default value.
+const PLACEHOLDER: &str = "unimplemented!()";
+
+/// A parameter of the new signature of a function. The parameters of the
+/// current signature which are not listed are removed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureParam {
+    /// The parameter at `index` in the current signature, not counting
+    /// `self`, renamed to `new_name` if it is given.
+    Existing { index: usize, new_name: Option<String> },
+    /// A new parameter. The call sites pass `default_value`, or a placeholder
+    /// if there is none.
+    Added { name: String, ty: String, default_value: Option<String> },
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct ChangeSignatureError(String);
+
+impl fmt::Display for ChangeSignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Can not change the signature: {}", self.0)
+    }
+}
+
+impl std::error::Error for ChangeSignatureError {}
+
+pub(crate) fn change_signature(
+    db: &RootDatabase,
+    position: FilePosition,
+    new_params: &[SignatureParam],
+) -> Result<Option<SourceChange>, ChangeSignatureError> {
+    let function = match function_at(db, position) {
+        Some(it) => it,
+        None => return Ok(None),
+    };
+    let has_self = function.data(db).has_self_param();
+    let sig = db.callable_item_signature(hir::CallableDef::Function(function));
+    let param_count = sig.params().len() - if has_self { 1 } else { 0 };
+    let is_valid = |param: &SignatureParam| match param {
+        SignatureParam::Existing { index, .. } => *index < param_count,
+        SignatureParam::Added { .. } => true,
+    };
+    if !new_params.iter().all(is_valid) {
+        return Ok(None);
+    }
+    let functions = related_functions(db, function);
+
+    let name = function.name(db).to_string();
+    let call_refs = find_name_refs(db, &name, |kind| match kind {
+        NameRefKind::Method(it)
+        | NameRefKind::Def(hir::ModuleDef::Function(it))
+        | NameRefKind::AssocItem(hir::ImplItem::Method(it)) => functions.contains(&it),
+        _ => false,
+    });
+    let mut calls = Vec::new();
+    let mut non_calls = Vec::new();
+    for (file_id, name_ref) in call_refs.iter() {
+        match FnCallNode::with_callee(name_ref) {
+            Some(call) => calls.push((*file_id, call)),
+            None => non_calls.push(describe_location(db, *file_id, name_ref.syntax().text_range())),
+        }
+    }
+    if !non_calls.is_empty() {
+        return Err(ChangeSignatureError(format!(
+            "`{}` is used without being called at {}",
+            name,
+            non_calls.join(", ")
+        )));
+    }
+    let macro_usages = find_macro_usages(db, &name);
+    if !macro_usages.is_empty() {
+        return Err(ChangeSignatureError(format!(
+            "`{}` may be called inside a macro at {}",
+            name,
+            macro_usages.join(", ")
+        )));
+    }
+
+    let mut edits = BTreeMap::new();
+    let mut definitions = Vec::new();
+    for &function in functions.iter() {
+        let src = function.source(db);
+        let file_id = src.file_id.original_file(db);
+        if hir::HirFileId::from(file_id) != src.file_id {
+            // The function is defined by a macro.
+            return Ok(None);
+        }
+        let params = match src.ast.param_list() {
+            Some(it) => it.params().collect::<Vec<_>>(),
+            None => return Ok(None),
+        };
+        let file = edits.entry(file_id).or_insert_with(|| UsageEdits::new(db, file_id));
+        rename_in_body(db, file_id, &src.ast, &params, new_params, file)?;
+        definitions.push((file_id, src.ast));
+    }
+
+    // Nested calls are updated first, so that the outer calls include them.
+    calls.sort_by_key(|(_, call)| call.syntax().text_range().len());
+    for (file_id, call) in calls {
+        let file = edits.entry(file_id).or_insert_with(|| UsageEdits::new(db, file_id));
+        let arg_list = match call.arg_list() {
+            Some(it) => it,
+            None => continue,
+        };
+        let (receiver, args) = match call.param_args(has_self) {
+            Some(it) => it,
+            None => continue,
+        };
+        let mut new_args: Vec<_> =
+            receiver.iter().map(|it| file.render(it.syntax().text_range())).collect();
+        new_args.extend(new_params.iter().map(|param| match param {
+            SignatureParam::Existing { index, .. } => match args.get(*index) {
+                Some(arg) => file.render(arg.syntax().text_range()),
+                None => PLACEHOLDER.to_string(),
+            },
+            SignatureParam::Added { default_value, .. } => {
+                default_value.clone().unwrap_or_else(|| PLACEHOLDER.to_string())
+            }
+        }));
+        file.replace(arg_list.syntax().text_range(), format!("({})", new_args.join(", ")));
+    }
+
+    for (file_id, fn_def) in definitions {
+        let file = edits.entry(file_id).or_insert_with(|| UsageEdits::new(db, file_id));
+        let (range, text) = match new_param_list(&fn_def, new_params, file) {
+            Some(it) => it,
+            None => return Ok(None),
+        };
+        file.replace(range, text);
+    }
+
+    let source_file_edits = edits
+        .into_iter()
+        .map(|(file_id, file)| {
+            let mut edit = TextEditBuilder::default();
+            for (range, text) in file.into_edits() {
+                edit.replace(range, text);
+            }
+            SourceFileEdit { file_id, edit: edit.finish() }
+        })
+        .collect();
+    Ok(Some(SourceChange::source_file_edits("change signature", source_file_edits)))
+}
+
+/// Returns the range of the parameter list of `fn_def` and its replacement.
+fn new_param_list(
+    fn_def: &ast::FnDef,
+    new_params: &[SignatureParam],
+    file: &UsageEdits,
+) -> Option<(TextRange, String)> {
+    let param_list = fn_def.param_list()?;
+    let params = param_list.params().collect::<Vec<_>>();
+    let mut new_list: Vec<_> =
+        param_list.self_param().iter().map(|it| it.syntax().text().to_string()).collect();
+    for param in new_params {
+        let text = match param {
+            SignatureParam::Existing { index, new_name } => {
+                let param = params.get(*index)?;
+                match (new_name, bind_pat(param)) {
+                    (Some(new_name), Some(pat)) => {
+                        let name_range = pat.name()?.syntax().text_range();
+                        let range = param.syntax().text_range();
+                        format!(
+                            "{}{}{}",
+                            file.render(TextRange::from_to(range.start(), name_range.start())),
+                            new_name,
+                            file.render(TextRange::from_to(name_range.end(), range.end())),
+                        )
+                    }
+                    _ => param.syntax().text().to_string(),
+                }
+            }
+            SignatureParam::Added { name, ty, .. } => format!("{}: {}", name, ty),
+        };
+        new_list.push(text);
+    }
+    Some((param_list.syntax().text_range(), format!("({})", new_list.join(", "))))
+}
+
+/// Finds the identifiers spelled `name` in the arguments of macro calls and
+/// returns their locations. Macro arguments are not resolved, so the calls
+/// they contain can't be updated.
+fn find_macro_usages(db: &RootDatabase, name: &str) -> Vec<String> {
+    let mut res = Vec::new();
+    for file_id in hir::search::files_containing(db, local_files(db), name) {
+        let parse = db.parse(file_id);
+        let idents = parse
+            .tree()
+            .syntax()
+            .descendants()
+            .filter_map(ast::MacroCall::cast)
+            .filter_map(|it| it.token_tree())
+            .flat_map(|it| it.syntax().descendants_with_tokens())
+            .filter_map(|it| it.into_token())
+            .filter(|it| it.kind() == IDENT && it.text() == name);
+        res.extend(idents.map(|it| describe_location(db, file_id, it.text_range())));
+    }
+    res
+}
+
+/// Describes where `range` starts, as `path:line:column`.
+fn describe_location(db: &RootDatabase, file_id: FileId, range: TextRange) -> String {
+    let line_col = db.line_index(file_id).line_col(range.start());
+    format!("{}:{}:{}", db.file_relative_path(file_id), line_col.line + 1, line_col.col_utf16 + 1)
+}
+
+/// Finds the function whose signature is changed: the one called at the
+/// position, or the one whose signature contains it.
+fn function_at(db: &RootDatabase, position: FilePosition) -> Option<hir::Function> {
+    let parse = db.parse(position.file_id);
+    let syntax = parse.tree().syntax().clone();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(&syntax, position.offset) {
+        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+        return match classify_name_ref(db, &analyzer, &name_ref)? {
+            NameRefKind::Method(it)
+            | NameRefKind::Def(hir::ModuleDef::Function(it))
+            | NameRefKind::AssocItem(hir::ImplItem::Method(it)) => Some(it),
+            _ => None,
+        };
+    }
+    let fn_def = find_node_at_offset::<ast::FnDef>(&syntax, position.offset)?;
+    if let Some(body) = fn_def.body() {
+        if body.syntax().text_range().contains(position.offset) {
+            return None;
+        }
+    }
+    let module = source_binder::module_from_child_node(db, position.file_id, fn_def.syntax())?;
+    Some(source_binder::function_from_module(db, module, &fn_def))
+}
+
+/// Returns `function` along with the functions which must keep the same
+/// signature: if it is a trait method, these are the declaration in the trait
+/// and the implementations in the workspace.
+fn related_functions(db: &RootDatabase, function: hir::Function) -> Vec<hir::Function> {
+    let name = function.name(db);
+    let tr = match function.parent_trait(db) {
+        Some(it) => it,
+        None => match function.impl_block(db).and_then(|it| it.target_trait_ref(db)) {
+            Some(trait_ref) => trait_ref.trait_,
+            None => return vec![function],
+        },
+    };
+    let mut res: Vec<_> = tr
+        .items(db)
+        .into_iter()
+        .filter_map(|item| match item {
+            hir::TraitItem::Function(it) if it.name(db) == name => Some(it),
+            _ => None,
+        })
+        .collect();
+    for &root in db.local_roots().iter() {
+        for krate in hir::Crate::source_root_crates(db, root) {
+            for imp in db.impls_in_crate(krate).lookup_impl_blocks_for_trait(tr) {
+                for item in imp.items(db) {
+                    match item {
+                        hir::ImplItem::Method(it) if it.name(db) == name && !res.contains(&it) => {
+                            res.push(it)
+                        }
+                        _ => (),
+                    }
+                }
+            }
+        }
+    }
+    if !res.contains(&function) {
+        res.push(function);
+    }
+    res
+}
+
+/// Renames the usages of the renamed parameters in the body of a function.
+/// Fails if a new name is already used in the function, as the renamed
+/// parameter would clash with or shadow what the name refers to.
+fn rename_in_body(
+    db: &RootDatabase,
+    file_id: FileId,
+    fn_def: &ast::FnDef,
+    params: &[ast::Param],
+    new_params: &[SignatureParam],
+    file: &mut UsageEdits,
+) -> Result<(), ChangeSignatureError> {
+    for new_param in new_params {
+        let (pat, new_name) = match new_param {
+            SignatureParam::Existing { index, new_name: Some(new_name) } => {
+                match params.get(*index).and_then(bind_pat) {
+                    Some(pat) => (pat, new_name),
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        if pat.name().map_or(false, |it| it.text() == new_name.as_str()) {
+            continue;
+        }
+        if is_name_used(fn_def, new_name) {
+            return Err(ChangeSignatureError(format!(
+                "`{}` is already used in `{}`",
+                new_name,
+                fn_def.name().map_or_else(String::new, |it| it.text().to_string()),
+            )));
+        }
+        let root = pat.syntax().ancestors().last().unwrap();
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, pat.syntax(), None);
+        for reference in analyzer.find_all_refs(&pat) {
+            // A shorthand field `S { a }` must keep the name of the field.
+            let is_shorthand_field = find_covering_element(&root, reference.range)
+                .ancestors()
+                .find_map(ast::NamedField::cast)
+                .map_or(false, |it| it.expr().is_none());
+            let text = if is_shorthand_field {
+                format!("{}: {}", reference.name, new_name)
+            } else {
+                new_name.clone()
+            };
+            file.replace(reference.range, text);
+        }
+    }
+    Ok(())
+}
+
+/// Checks if `name` is bound or referred to in the parameters or the body of
+/// `fn_def`.
+fn is_name_used(fn_def: &ast::FnDef, name: &str) -> bool {
+    let nodes = fn_def
+        .param_list()
+        .into_iter()
+        .map(|it| it.syntax().clone())
+        .chain(fn_def.body().map(|it| it.syntax().clone()));
+    nodes.flat_map(|node| node.descendants().collect::<Vec<_>>()).any(|node| {
+        match (ast::Name::cast(node.clone()), ast::NameRef::cast(node)) {
+            (Some(it), _) => it.text() == name,
+            (_, Some(it)) => it.text() == name,
+            _ => false,
+        }
+    })
+}
+
+fn bind_pat(param: &ast::Param) -> Option<ast::BindPat> {
+    match param.pat()?.kind() {
+        ast::PatKind::BindPat(it) => Some(it),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use test_utils::{assert_eq_text, parse_fixture};
+
+    use super::SignatureParam::{self, *};
+    use crate::mock_analysis::{analysis_and_position, MockAnalysis};
+
+    fn check(new_params: &[SignatureParam], before: &str, after: &str) {
+        let (mock, position) = MockAnalysis::with_files_and_position(before);
+        let expected: Vec<_> = parse_fixture(after)
+            .into_iter()
+            .map(|entry| (mock.id_of(&entry.meta), entry.text))
+            .collect();
+        let analysis = mock.analysis();
+        let change = analysis.change_signature(position, new_params).unwrap().unwrap().unwrap();
+        for (file_id, expected) in expected {
+            let mut text = analysis.file_text(file_id).unwrap().to_string();
+            for edit in change.source_file_edits.iter().filter(|it| it.file_id == file_id) {
+                text = edit.edit.apply(&text);
+            }
+            assert_eq_text!(&expected, &text);
+        }
+    }
+
+    fn check_error(new_params: &[SignatureParam], before: &str, error: &str) {
+        let (analysis, position) = analysis_and_position(before);
+        let err = analysis.change_signature(position, new_params).unwrap().unwrap_err();
+        assert_eq!(err.to_string(), error);
+    }
+
+    fn existing(index: usize) -> SignatureParam {
+        Existing { index, new_name: None }
+    }
+
+    #[test]
+    fn reorders_and_removes_params() {
+        check(
+            &[existing(2), existing(0)],
+            r#"
+//- /lib.rs
+fn foo<|>(a: i32, b: bool, c: char) {}
+
+fn main() {
+    foo(1, true, 'c');
+    foo(2, false, 'd');
+}
+"#,
+            r#"
+//- /lib.rs
+fn foo(c: char, a: i32) {}
+
+fn main() {
+    foo('c', 1);
+    foo('d', 2);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn adds_and_renames_params() {
+        check(
+            &[
+                Existing { index: 0, new_name: Some("count".to_string()) },
+                Added { name: "flag".to_string(), ty: "bool".to_string(), default_value: None },
+                Added {
+                    name: "name".to_string(),
+                    ty: "&str".to_string(),
+                    default_value: Some("\"x\"".to_string()),
+                },
+            ],
+            r#"
+//- /lib.rs
+struct S { n: u32 }
+
+fn new(n: u32) -> S {
+    if n > 0 {
+        return new<|>(n - 1);
+    }
+    S { n }
+}
+"#,
+            r#"
+//- /lib.rs
+struct S { n: u32 }
+
+fn new(count: u32, flag: bool, name: &str) -> S {
+    if count > 0 {
+        return new(count - 1, unimplemented!(), "x");
+    }
+    S { n: count }
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn updates_method_calls_and_nested_calls() {
+        check(
+            &[existing(1), existing(0)],
+            r#"
+//- /lib.rs
+struct S;
+
+impl S {
+    fn f<|>(&self, a: u32, b: u32) -> u32 {
+        a + b
+    }
+}
+
+fn main() {
+    let s = S;
+    s.f(1, s.f(2, 3));
+    S::f(&s, 4, 5);
+}
+"#,
+            r#"
+//- /lib.rs
+struct S;
+
+impl S {
+    fn f(&self, b: u32, a: u32) -> u32 {
+        a + b
+    }
+}
+
+fn main() {
+    let s = S;
+    s.f(s.f(3, 2), 1);
+    S::f(&s, 5, 4);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn updates_trait_impls_in_other_files() {
+        check(
+            &[existing(1), existing(0)],
+            r#"
+//- /lib.rs
+mod imp;
+
+pub trait Shape {
+    fn scale<|>(&mut self, x: f64, y: f64);
+}
+
+//- /imp.rs
+use crate::Shape;
+
+struct Square;
+
+impl Shape for Square {
+    fn scale(&mut self, x: f64, _y: f64) {}
+}
+
+fn grow() {
+    Square.scale(2.0, 3.0);
+}
+"#,
+            r#"
+//- /lib.rs
+mod imp;
+
+pub trait Shape {
+    fn scale(&mut self, y: f64, x: f64);
+}
+
+//- /imp.rs
+use crate::Shape;
+
+struct Square;
+
+impl Shape for Square {
+    fn scale(&mut self, _y: f64, x: f64) {}
+}
+
+fn grow() {
+    Square.scale(3.0, 2.0);
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn rejects_references_which_are_not_calls() {
+        check_error(
+            &[existing(0)],
+            r#"
+//- /lib.rs
+fn foo<|>(a: i32, b: i32) -> i32 { a + b }
+
+fn main() {
+    foo(1, 2);
+    let f = foo;
+    Some(1).map(|it| foo(it, 2)).map(foo);
+}
+"#,
+            "Can not change the signature: `foo` is used without being called at \
+             lib.rs:5:13, lib.rs:6:38",
+        );
+    }
+
+    #[test]
+    fn rejects_calls_inside_macros() {
+        check_error(
+            &[existing(0)],
+            r#"
+//- /lib.rs
+macro_rules! id {
+    ($e:expr) => { $e };
+}
+
+fn foo<|>(a: i32, b: i32) -> i32 { a + b }
+
+fn main() {
+    foo(1, 2);
+    let x = id!(foo(3, 4));
+}
+"#,
+            "Can not change the signature: `foo` may be called inside a macro at lib.rs:9:17",
+        );
+    }
+
+    #[test]
+    fn rejects_renames_clashing_with_locals() {
+        check_error(
+            &[Existing { index: 0, new_name: Some("b".to_string()) }],
+            r#"
+//- /lib.rs
+fn foo<|>(a: i32) -> i32 {
+    let b = 1;
+    a + b
+}
+"#,
+            "Can not change the signature: `b` is already used in `foo`",
+        );
+    }
+}
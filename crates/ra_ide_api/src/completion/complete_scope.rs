@@ -169,6 +169,47 @@ mod tests {
         );
     }
 
+    #[test]
+    fn completes_bindings_in_macro_arguments() {
+        assert_debug_snapshot_matches!(
+            do_reference_completion(
+                r"
+                macro_rules! foo { ($e:expr) => { $e } }
+                fn quux(x: i32) {
+                    let y = 92;
+                    foo!(1 + <|>);
+                }
+                "
+            ),
+            @r###"[
+    CompletionItem {
+        label: "quux",
+        source_range: [153; 153),
+        delete: [153; 153),
+        insert: "quux($0)",
+        kind: Function,
+        detail: "fn quux(x: i32)",
+    },
+    CompletionItem {
+        label: "x",
+        source_range: [153; 153),
+        delete: [153; 153),
+        insert: "x",
+        kind: Binding,
+        detail: "i32",
+    },
+    CompletionItem {
+        label: "y",
+        source_range: [153; 153),
+        delete: [153; 153),
+        insert: "y",
+        kind: Binding,
+        detail: "i32",
+    },
+]"###
+        );
+    }
+
     #[test]
     fn completes_bindings_from_if_let() {
         assert_debug_snapshot_matches!(
@@ -3,7 +3,7 @@ use ra_syntax::{
     algo::{find_covering_element, find_node_at_offset},
     ast, AstNode, Parse, SourceFile,
     SyntaxKind::*,
-    SyntaxNode, SyntaxToken, TextRange, TextUnit,
+    SyntaxNode, SyntaxToken, TextRange, TextUnit, T,
 };
 use ra_text_edit::AtomTextEdit;
 
@@ -93,6 +93,17 @@ impl<'a> CompletionContext<'a> {
             let edit = AtomTextEdit::insert(offset, "intellijRulezz".to_string());
             original_parse.reparse(&edit).tree().to_owned()
         };
+        // Arguments of macro calls are token trees, so when we are inside of
+        // one, both files are reparsed with the arguments as an expression.
+        let (file, original_file) = match parse_macro_args(&file, offset) {
+            Some(file) => {
+                let original_file = original_parse.tree();
+                let original_file =
+                    parse_macro_args(&original_file, offset).unwrap_or(original_file);
+                (file, original_file)
+            }
+            None => (file, original_parse.tree()),
+        };
 
         // First, let's try to complete a reference to some declaration.
         if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(file.syntax(), offset) {
@@ -102,7 +113,7 @@ impl<'a> CompletionContext<'a> {
                 self.is_param = true;
                 return;
             }
-            self.classify_name_ref(original_parse.tree(), name_ref);
+            self.classify_name_ref(original_file.clone(), name_ref);
         }
 
         // Otherwise, see if this is a declaration. We can use heuristics to
@@ -121,8 +132,7 @@ impl<'a> CompletionContext<'a> {
                 return;
             }
             if name.syntax().ancestors().find_map(ast::FieldPatList::cast).is_some() {
-                self.struct_lit_pat =
-                    find_node_at_offset(original_parse.tree().syntax(), self.offset);
+                self.struct_lit_pat = find_node_at_offset(original_file.syntax(), self.offset);
             }
         }
     }
@@ -235,6 +245,34 @@ impl<'a> CompletionContext<'a> {
     }
 }
 
+/// Blanks out the `path!` of expression-like macro calls around `offset`, so
+/// that their arguments are reparsed as a tuple or an array expression with
+/// the same text ranges.
+fn parse_macro_args(file: &SourceFile, offset: TextUnit) -> Option<SourceFile> {
+    let token = file.syntax().token_at_offset(offset).left_biased()?;
+    let mut text = file.syntax().text().to_string();
+    let mut changed = false;
+    for macro_call in token.parent().ancestors().filter_map(ast::MacroCall::cast) {
+        match macro_call.syntax().parent().map(|it| it.kind()) {
+            Some(SOURCE_FILE) | Some(ITEM_LIST) | Some(MACRO_ITEMS) | None => break,
+            _ => (),
+        }
+        let token_tree = macro_call.token_tree()?;
+        match token_tree.syntax().first_child_or_token().map(|it| it.kind()) {
+            Some(T!['(']) | Some(T!['[']) => (),
+            _ => break,
+        }
+        let start = macro_call.syntax().text_range().start().to_usize();
+        let end = token_tree.syntax().text_range().start().to_usize();
+        text.replace_range(start..end, &" ".repeat(end - start));
+        changed = true;
+    }
+    if !changed {
+        return None;
+    }
+    Some(SourceFile::parse(&text).tree())
+}
+
 fn find_node_with_range<N: AstNode>(syntax: &SyntaxNode, range: TextRange) -> Option<N> {
     find_covering_element(syntax, range).ancestors().find_map(N::cast)
 }
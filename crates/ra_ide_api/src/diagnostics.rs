@@ -5,12 +5,17 @@ use hir::{
     source_binder,
 };
 use itertools::Itertools;
-use ra_assists::ast_editor::{AstBuilder, AstEditor};
+use ra_assists::{
+    ast_editor::{AstBuilder, AstEditor},
+    organize_imports,
+};
 use ra_db::SourceDatabase;
 use ra_prof::profile;
 use ra_syntax::{
     ast::{self, AstNode, NamedField},
-    Location, SyntaxNode, TextRange, T,
+    Direction, Location,
+    SyntaxKind::*,
+    SyntaxNode, TextRange, T,
 };
 use ra_text_edit::{TextEdit, TextEditBuilder};
 
@@ -38,6 +43,7 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
         check_unnecessary_braces_in_use_statement(&mut res, file_id, &node);
         check_struct_shorthand_initialization(&mut res, file_id, &node);
     }
+    check_unused_imports(&mut res, db, file_id);
     let res = RefCell::new(res);
     let mut sink = DiagnosticSink::new(|d| {
         res.borrow_mut().push(Diagnostic {
@@ -49,7 +55,11 @@ pub(crate) fn diagnostics(db: &RootDatabase, file_id: FileId) -> Vec<Diagnostic>
     })
     .on::<hir::diagnostics::UnresolvedModule, _>(|d| {
         let source_root = db.file_source_root(d.file().original_file(db));
-        let create_file = FileSystemEdit::CreateFile { source_root, path: d.candidate.clone() };
+        let create_file = FileSystemEdit::CreateFile {
+            source_root,
+            path: d.candidate.clone(),
+            initial_contents: String::new(),
+        };
         let fix = SourceChange::file_system_edit("create module", create_file);
         res.borrow_mut().push(Diagnostic {
             range: d.highlight_range(),
@@ -97,15 +107,7 @@ fn check_unnecessary_braces_in_use_statement(
     let use_tree_list = ast::UseTreeList::cast(node.clone())?;
     if let Some((single_use_tree,)) = use_tree_list.use_trees().collect_tuple() {
         let range = use_tree_list.syntax().text_range();
-        let edit =
-            text_edit_for_remove_unnecessary_braces_with_self_in_use_statement(&single_use_tree)
-                .unwrap_or_else(|| {
-                    let to_replace = single_use_tree.syntax().text().to_string();
-                    let mut edit_builder = TextEditBuilder::default();
-                    edit_builder.delete(range);
-                    edit_builder.insert(range.start(), to_replace);
-                    edit_builder.finish()
-                });
+        let edit = text_edit_for_remove_braces(&single_use_tree, range);
 
         acc.push(Diagnostic {
             range,
@@ -121,6 +123,19 @@ fn check_unnecessary_braces_in_use_statement(
     Some(())
 }
 
+/// Replaces the use tree list at `range` with `single_use_tree`, its only
+/// remaining tree.
+fn text_edit_for_remove_braces(single_use_tree: &ast::UseTree, range: TextRange) -> TextEdit {
+    text_edit_for_remove_unnecessary_braces_with_self_in_use_statement(single_use_tree)
+        .unwrap_or_else(|| {
+            let to_replace = single_use_tree.syntax().text().to_string();
+            let mut edit_builder = TextEditBuilder::default();
+            edit_builder.delete(range);
+            edit_builder.insert(range.start(), to_replace);
+            edit_builder.finish()
+        })
+}
+
 fn text_edit_for_remove_unnecessary_braces_with_self_in_use_statement(
     single_use_tree: &ast::UseTree,
 ) -> Option<TextEdit> {
@@ -136,6 +151,70 @@ fn text_edit_for_remove_unnecessary_braces_with_self_in_use_statement(
     None
 }
 
+fn check_unused_imports(acc: &mut Vec<Diagnostic>, db: &RootDatabase, file_id: FileId) {
+    for use_tree in organize_imports::unused_imports(db, file_id) {
+        let edit = text_edit_for_remove_use_tree(&use_tree);
+        acc.push(Diagnostic {
+            range: use_tree.syntax().text_range(),
+            message: "Unused import".to_string(),
+            severity: Severity::WeakWarning,
+            fix: Some(SourceChange::source_file_edit(
+                "remove unused import",
+                SourceFileEdit { file_id, edit },
+            )),
+        });
+    }
+}
+
+fn text_edit_for_remove_use_tree(use_tree: &ast::UseTree) -> TextEdit {
+    // If the tree is the only one in its list, the enclosing tree goes too.
+    let mut use_tree = use_tree.clone();
+    while let Some(list) = use_tree.syntax().parent().and_then(ast::UseTreeList::cast) {
+        if list.use_trees().count() != 1 {
+            break;
+        }
+        use_tree = list.parent_use_tree();
+    }
+    // If a single tree remains, the braces go as well.
+    if let Some(list) = use_tree.syntax().parent().and_then(ast::UseTreeList::cast) {
+        if let Some((first, second)) = list.use_trees().collect_tuple() {
+            let remaining = if first == use_tree { second } else { first };
+            return text_edit_for_remove_braces(&remaining, list.syntax().text_range());
+        }
+    }
+
+    let node = match use_tree.syntax().parent().and_then(ast::UseItem::cast) {
+        Some(use_item) => use_item.syntax().clone(),
+        None => use_tree.syntax().clone(),
+    };
+    let is_separator = |kind| kind == WHITESPACE || kind == T![,];
+    let next: Vec<_> = node
+        .siblings_with_tokens(Direction::Next)
+        .skip(1)
+        .take_while(|it| is_separator(it.kind()))
+        .collect();
+    let prev = node
+        .siblings_with_tokens(Direction::Prev)
+        .skip(1)
+        .take_while(|it| is_separator(it.kind()))
+        .last();
+    let range = node.text_range();
+    let range = if node.kind() == USE_ITEM || next.iter().any(|it| it.kind() == T![,]) {
+        match next.last() {
+            Some(it) => TextRange::from_to(range.start(), it.text_range().end()),
+            None => range,
+        }
+    } else {
+        match prev {
+            Some(it) => TextRange::from_to(it.text_range().start(), range.end()),
+            None => range,
+        }
+    };
+    let mut edit_builder = TextEditBuilder::default();
+    edit_builder.delete(range);
+    edit_builder.finish()
+}
+
 fn check_struct_shorthand_initialization(
     acc: &mut Vec<Diagnostic>,
     file_id: FileId,
@@ -174,7 +253,7 @@ mod tests {
     use ra_syntax::SourceFile;
     use test_utils::assert_eq_text;
 
-    use crate::mock_analysis::single_file;
+    use crate::mock_analysis::{single_file, MockAnalysis};
 
     use super::*;
 
@@ -302,6 +381,82 @@ mod tests {
         check_no_diagnostic(content);
     }
 
+    #[test]
+    fn test_unused_import_in_list() {
+        let before = r"
+            use crate::foo::{A, B};
+            mod foo { pub struct A; pub struct B; }
+            fn main() { let _ = A; }
+        ";
+        let after = r"
+            use crate::foo::A;
+            mod foo { pub struct A; pub struct B; }
+            fn main() { let _ = A; }
+        ";
+        check_apply_diagnostic_fix(before, after);
+    }
+
+    #[test]
+    fn test_unused_import_in_list_with_self() {
+        let before = r"
+            use crate::foo::{self, B};
+            mod foo { pub struct B; }
+            fn main() { foo::B; }
+        ";
+        let after = r"
+            use crate::foo;
+            mod foo { pub struct B; }
+            fn main() { foo::B; }
+        ";
+        check_apply_diagnostic_fix(before, after);
+    }
+
+    #[test]
+    fn test_unused_import_item() {
+        let before = r"
+            use crate::foo::B;
+            mod foo { pub struct B; }
+            fn main() {}
+        ";
+        let after = r"
+            mod foo { pub struct B; }
+            fn main() {}
+        ";
+        check_apply_diagnostic_fix(before, after);
+    }
+
+    #[test]
+    fn test_used_imports_no_diagnostic() {
+        let content = r"
+            use crate::foo::{A, Tr};
+            mod foo {
+                pub struct A;
+                pub trait Tr { fn m(&self) {} }
+                impl Tr for A {}
+            }
+            fn main() { A.m(); }
+        ";
+        check_no_diagnostic(content);
+    }
+
+    #[test]
+    fn test_imports_used_by_glob_import_in_out_of_line_module() {
+        let mock = MockAnalysis::with_files(
+            r"
+            //- /lib.rs
+            use crate::foo::A;
+            mod foo { pub struct A; }
+            mod tests;
+            //- /tests.rs
+            use super::*;
+            fn f() { let _ = A; }
+            ",
+        );
+        let file_id = mock.id_of("/lib.rs");
+        let diagnostics = mock.analysis().diagnostics(file_id).unwrap();
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    }
+
     #[test]
     fn test_unresolved_module_diagnostic() {
         let (analysis, file_id) = single_file("mod foo;");
@@ -321,6 +476,7 @@ mod tests {
        ⋮                            0,
        ⋮                        ),
        ⋮                        path: "foo.rs",
+       ⋮                        initial_contents: "",
        ⋮                    },
        ⋮                ],
        ⋮                cursor_position: None,
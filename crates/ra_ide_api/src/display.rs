@@ -11,12 +11,13 @@ use ra_syntax::{
     SyntaxKind::{ATTR, COMMENT},
 };
 
-pub use function_signature::FunctionSignature;
+pub use function_signature::{CallableKind, FunctionSignature};
 pub use navigation_target::NavigationTarget;
 pub use structure::{file_structure, StructureNode};
 
 pub(crate) use navigation_target::{description_from_symbol, docs_from_symbol};
 pub(crate) use short_label::ShortLabel;
+pub(crate) use structure::file_structure_with_macros;
 
 pub(crate) fn function_label(node: &ast::FnDef) -> String {
     FunctionSignature::from(node).to_string()
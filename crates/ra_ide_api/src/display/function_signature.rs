@@ -10,9 +10,20 @@ use crate::{
     display::{generic_parameters, where_predicates},
 };
 
+/// What a signature belongs to, which decides how it is rendered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CallableKind {
+    Function,
+    StructConstructor,
+    VariantConstructor,
+    Macro,
+}
+
 /// Contains information about a function signature
 #[derive(Debug)]
 pub struct FunctionSignature {
+    /// The kind of the callable
+    pub kind: CallableKind,
     /// Optional visibility
     pub visibility: Option<String>,
     /// Name of the function
@@ -21,7 +32,8 @@ pub struct FunctionSignature {
     pub doc: Option<Documentation>,
     /// Generic parameters
     pub generic_parameters: Vec<String>,
-    /// Parameters of the function
+    /// Parameters of the function, the field types of a constructor or the
+    /// patterns of the rules of a macro
     pub parameters: Vec<String>,
     /// Optional return type
     pub ret_type: Option<String>,
@@ -40,6 +52,80 @@ impl FunctionSignature {
         let ast_node = function.source(db).ast;
         FunctionSignature::from(&ast_node).with_doc_opt(doc)
     }
+
+    pub(crate) fn from_struct(db: &db::RootDatabase, st: hir::Struct) -> Option<Self> {
+        let node: ast::StructDef = st.source(db).ast;
+        let parameters = match node.kind() {
+            ast::StructKind::Tuple(fields) => tuple_fields(&fields),
+            _ => return None,
+        };
+        let sig = FunctionSignature {
+            kind: CallableKind::StructConstructor,
+            visibility: node.visibility().map(|n| n.syntax().text().to_string()),
+            name: node.name().map(|n| n.text().to_string()),
+            ret_type: None,
+            parameters,
+            generic_parameters: generic_parameters(&node),
+            where_predicates: where_predicates(&node),
+            doc: None,
+        };
+        Some(sig.with_doc_opt(st.docs(db)))
+    }
+
+    pub(crate) fn from_enum_variant(
+        db: &db::RootDatabase,
+        variant: hir::EnumVariant,
+    ) -> Option<Self> {
+        let node: ast::EnumVariant = variant.source(db).ast;
+        let parameters = match node.kind() {
+            ast::StructKind::Tuple(fields) => tuple_fields(&fields),
+            _ => return None,
+        };
+        let enum_name = node.parent_enum().name()?;
+        let sig = FunctionSignature {
+            kind: CallableKind::VariantConstructor,
+            visibility: None,
+            name: node.name().map(|n| format!("{}::{}", enum_name.text(), n.text())),
+            ret_type: None,
+            parameters,
+            generic_parameters: Vec::new(),
+            where_predicates: Vec::new(),
+            doc: None,
+        };
+        Some(sig.with_doc_opt(variant.docs(db)))
+    }
+
+    pub(crate) fn from_macro(db: &db::RootDatabase, macro_def: hir::MacroDef) -> Option<Self> {
+        let node: ast::MacroCall = macro_def.source(db).ast;
+        // Rules are `(pattern) => {expansion}` pairs of token trees
+        let parameters = node
+            .token_tree()?
+            .syntax()
+            .children()
+            .filter_map(ast::TokenTree::cast)
+            .step_by(2)
+            .map(|pattern| pattern.syntax().text().to_string())
+            .collect();
+        let sig = FunctionSignature {
+            kind: CallableKind::Macro,
+            visibility: None,
+            name: node.name().map(|n| n.text().to_string()),
+            ret_type: None,
+            parameters,
+            generic_parameters: Vec::new(),
+            where_predicates: Vec::new(),
+            doc: None,
+        };
+        Some(sig.with_doc_opt(macro_def.docs(db)))
+    }
+}
+
+fn tuple_fields(fields: &ast::PosFieldDefList) -> Vec<String> {
+    fields
+        .fields()
+        .filter_map(|field| field.type_ref())
+        .map(|type_ref| type_ref.syntax().text().to_string())
+        .collect()
 }
 
 impl From<&'_ ast::FnDef> for FunctionSignature {
@@ -57,6 +143,7 @@ impl From<&'_ ast::FnDef> for FunctionSignature {
         }
 
         FunctionSignature {
+            kind: CallableKind::Function,
             visibility: node.visibility().map(|n| n.syntax().text().to_string()),
             name: node.name().map(|n| n.text().to_string()),
             ret_type: node
@@ -79,7 +166,12 @@ impl Display for FunctionSignature {
         }
 
         if let Some(name) = &self.name {
-            write!(f, "fn {}", name)?;
+            match self.kind {
+                CallableKind::Function => write!(f, "fn {}", name)?,
+                CallableKind::StructConstructor => write!(f, "struct {}", name)?,
+                CallableKind::VariantConstructor => write!(f, "{}", name)?,
+                CallableKind::Macro => write!(f, "macro_rules! {}", name)?,
+            }
         }
 
         if !self.generic_parameters.is_empty() {
@@ -89,7 +181,12 @@ impl Display for FunctionSignature {
                 .to_fmt(f)?;
         }
 
-        join(self.parameters.iter()).separator(", ").surround_with("(", ")").to_fmt(f)?;
+        match self.kind {
+            CallableKind::Macro => {
+                join(self.parameters.iter()).separator("; ").surround_with(" { ", " }").to_fmt(f)?
+            }
+            _ => join(self.parameters.iter()).separator(", ").surround_with("(", ")").to_fmt(f)?,
+        }
 
         if let Some(t) = &self.ret_type {
             write!(f, " -> {}", t)?;
@@ -1,4 +1,4 @@
-use hir::{FieldSource, HasSource, ImplItem, ModuleSource};
+use hir::{FieldSource, HasSource, ImplItem, ModuleSource, Source};
 use ra_db::{FileId, SourceDatabase};
 use ra_syntax::{
     algo::visit::{visitor, Visitor},
@@ -9,7 +9,7 @@ use ra_syntax::{
 };
 
 use super::short_label::ShortLabel;
-use crate::{db::RootDatabase, FileSymbol};
+use crate::{db::RootDatabase, expand::original_range, FileSymbol};
 
 /// `NavigationTarget` represents and element in the editor's UI which you can
 /// click on to navigate to a particular piece of code.
@@ -154,14 +154,15 @@ impl NavigationTarget {
     pub(crate) fn from_field(db: &RootDatabase, field: hir::StructField) -> NavigationTarget {
         let src = field.source(db);
         let file_id = src.file_id.original_file(db);
-        match src.ast {
+        let nav = match src.ast {
             FieldSource::Named(it) => {
                 NavigationTarget::from_named(file_id, &it, it.doc_comment_text(), it.short_label())
             }
             FieldSource::Pos(it) => {
                 NavigationTarget::from_syntax(file_id, "".into(), None, it.syntax(), None, None)
             }
-        }
+        };
+        nav.map_to_original(db, src.file_id)
     }
 
     pub(crate) fn from_def_source<A, D>(db: &RootDatabase, def: D) -> NavigationTarget
@@ -176,6 +177,7 @@ impl NavigationTarget {
             src.ast.doc_comment_text(),
             src.ast.short_label(),
         )
+        .map_to_original(db, src.file_id)
     }
 
     pub(crate) fn from_adt_def(db: &RootDatabase, adt_def: hir::AdtDef) -> NavigationTarget {
@@ -214,13 +216,14 @@ impl NavigationTarget {
     ) -> NavigationTarget {
         let src = impl_block.source(db);
         NavigationTarget::from_syntax(
-            src.file_id.as_original_file(),
+            src.file_id.original_file(db),
             "impl".into(),
             None,
             src.ast.syntax(),
             None,
             None,
         )
+        .map_to_original(db, src.file_id)
     }
 
     pub(crate) fn from_impl_item(db: &RootDatabase, impl_item: hir::ImplItem) -> NavigationTarget {
@@ -240,6 +243,18 @@ impl NavigationTarget {
             src.ast.doc_comment_text(),
             None,
         )
+        .map_to_original(db, src.file_id)
+    }
+
+    /// Maps the ranges of a target from a macro expansion back to the macro
+    /// call.
+    fn map_to_original(mut self, db: &RootDatabase, file_id: hir::HirFileId) -> NavigationTarget {
+        let original = original_range(db, Source { file_id, ast: self.full_range });
+        self.file_id = original.file_id;
+        self.full_range = original.range;
+        self.focus_range =
+            self.focus_range.map(|it| original_range(db, Source { file_id, ast: it }).range);
+        self
     }
 
     #[cfg(test)]
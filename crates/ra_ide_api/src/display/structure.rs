@@ -1,5 +1,7 @@
-use crate::TextRange;
+use crate::{db::RootDatabase, FileId, TextRange};
 
+use hir::{db::AstDatabase, Source};
+use ra_db::SourceDatabase;
 use ra_syntax::{
     algo::visit::{visitor, Visitor},
     ast::{self, AttrsOwner, NameOwner, TypeAscriptionOwner, TypeParamsOwner},
@@ -18,16 +20,42 @@ pub struct StructureNode {
 }
 
 pub fn file_structure(file: &SourceFile) -> Vec<StructureNode> {
+    structure(file.syntax(), &mut |_| Vec::new())
+}
+
+/// Like `file_structure`, but also includes the items produced by the macro
+/// calls in the file. They span the whole macro call and are navigated to at
+/// the identifier their name is expanded from, if there is one.
+pub(crate) fn file_structure_with_macros(db: &RootDatabase, file_id: FileId) -> Vec<StructureNode> {
+    let parse = db.parse(file_id);
+    structure(parse.tree().syntax(), &mut |macro_call| {
+        expanded_structure(db, file_id, macro_call).unwrap_or_default()
+    })
+}
+
+/// Collects the structure of `root`. `expand` returns the structure of the
+/// items a macro call expands to.
+fn structure(
+    root: &SyntaxNode,
+    expand: &mut dyn FnMut(&ast::MacroCall) -> Vec<StructureNode>,
+) -> Vec<StructureNode> {
     let mut res = Vec::new();
     let mut stack = Vec::new();
 
-    for event in file.syntax().preorder() {
+    for event in root.preorder() {
         match event {
             WalkEvent::Enter(node) => {
                 if let Some(mut symbol) = structure_node(&node) {
                     symbol.parent = stack.last().copied();
                     stack.push(res.len());
                     res.push(symbol);
+                } else if let Some(macro_call) = ast::MacroCall::cast(node) {
+                    let parent = stack.last().copied();
+                    let offset = res.len();
+                    for mut symbol in expand(&macro_call) {
+                        symbol.parent = symbol.parent.map(|it| it + offset).or(parent);
+                        res.push(symbol);
+                    }
                 }
             }
             WalkEvent::Leave(node) => {
@@ -40,6 +68,36 @@ pub fn file_structure(file: &SourceFile) -> Vec<StructureNode> {
     res
 }
 
+fn expanded_structure(
+    db: &RootDatabase,
+    file_id: FileId,
+    macro_call: &ast::MacroCall,
+) -> Option<Vec<StructureNode>> {
+    // Only macro calls in item position expand to items.
+    match macro_call.syntax().parent()?.kind() {
+        SyntaxKind::SOURCE_FILE | SyntaxKind::ITEM_LIST => (),
+        _ => return None,
+    }
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, macro_call.syntax(), None);
+    let expansion = analyzer.expand(db, Source { file_id: file_id.into(), ast: macro_call })?;
+    let root = db.parse_or_expand(expansion.file_id())?;
+
+    let call_range = macro_call.syntax().text_range();
+    let path_range = macro_call.path().map_or(call_range, |it| it.syntax().text_range());
+    let res = structure(&root, &mut |_| Vec::new())
+        .into_iter()
+        .map(|mut node| {
+            node.navigation_range = expansion
+                .map_range_up(db, node.navigation_range)
+                .filter(|it| it.file_id == file_id.into())
+                .map_or(path_range, |it| it.ast);
+            node.node_range = call_range;
+            node
+        })
+        .collect();
+    Some(res)
+}
+
 fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
     fn decl<N: NameOwner + AttrsOwner>(node: N) -> Option<StructureNode> {
         decl_with_detail(node, None)
@@ -161,6 +219,7 @@ fn structure_node(node: &SyntaxNode) -> Option<StructureNode> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::mock_analysis::single_file;
     use insta::assert_debug_snapshot_matches;
 
     #[test]
@@ -389,6 +448,43 @@ fn very_obsolete() {}
         ),
         deprecated: true,
     },
+]"#
+                );
+    }
+
+    #[test]
+    fn test_file_structure_with_macros() {
+        let (analysis, file_id) = single_file(
+            r#"
+macro_rules! make_fn {
+    ($name:ident) => { fn $name() {} };
+}
+make_fn! { foo }
+"#,
+        );
+        let structure = analysis.file_structure_with_macros(file_id).unwrap();
+        assert_debug_snapshot_matches!(structure,
+        @r#"[
+    StructureNode {
+        parent: None,
+        label: "make_fn",
+        navigation_range: [14; 21),
+        node_range: [1; 65),
+        kind: MACRO_CALL,
+        detail: None,
+        deprecated: false,
+    },
+    StructureNode {
+        parent: None,
+        label: "foo",
+        navigation_range: [77; 80),
+        node_range: [66; 82),
+        kind: FN_DEF,
+        detail: Some(
+            "fn()",
+        ),
+        deprecated: false,
+    },
 ]"#
                 );
     }
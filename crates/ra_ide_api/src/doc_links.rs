@@ -0,0 +1,178 @@
+//! Resolves intra-doc links, like `[Foo]` or `[crate::bar::Baz]`, in
+//! documentation to the definitions they refer to.
+
+use format_buf::format;
+use ra_db::FileId;
+use ra_syntax::{ast, AstNode, SourceFile, SyntaxNode};
+
+use crate::{db::RootDatabase, NavigationTarget};
+
+/// A link in documentation which points to a definition.
+#[derive(Debug, Clone)]
+pub struct DocLink {
+    /// The path of the link target, as it appears in the rewritten docs.
+    pub target: String,
+    pub nav: NavigationTarget,
+}
+
+/// Rewrites links in `docs` which resolve from the scope of `node` into
+/// inline links with the path as the target, and returns the resolved links.
+///
+/// Code blocks, inline code and reference-style links are left as is.
+pub(crate) fn resolve_doc_links(
+    db: &RootDatabase,
+    file_id: FileId,
+    node: &SyntaxNode,
+    docs: &str,
+) -> (String, Vec<DocLink>) {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, node, None);
+    let mut links: Vec<DocLink> = Vec::new();
+    let mut in_code_block = false;
+    let mut lines = Vec::new();
+    for line in docs.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+            lines.push(line.to_string());
+            continue;
+        }
+        if in_code_block {
+            lines.push(line.to_string());
+            continue;
+        }
+        lines.push(rewrite_links(line, |path| {
+            if links.iter().any(|it| it.target == path) {
+                return true;
+            }
+            match resolve_path(db, &analyzer, path) {
+                Some(nav) => {
+                    links.push(DocLink { target: path.to_string(), nav });
+                    true
+                }
+                None => false,
+            }
+        }));
+    }
+    (lines.join("\n"), links)
+}
+
+fn resolve_path(
+    db: &RootDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    path: &str,
+) -> Option<NavigationTarget> {
+    let parse = SourceFile::parse(&format!("use {};", path));
+    if !parse.errors().is_empty() {
+        return None;
+    }
+    let path = parse.tree().syntax().descendants().find_map(ast::Path::cast)?;
+    let path = hir::Path::from_ast(path)?;
+    let resolution = analyzer.resolve_hir_path(db, &path);
+    match resolution.types.or(resolution.values)? {
+        hir::Resolution::Def(def) => NavigationTarget::from_def(db, def),
+        _ => None,
+    }
+}
+
+/// Rewrites every link in `line` for which `resolve` accepts the path into
+/// `[text](path)`.
+fn rewrite_links(line: &str, mut resolve: impl FnMut(&str) -> bool) -> String {
+    let mut buf = String::new();
+    let mut rest = line;
+    while let Some(idx) = rest.find(|c: char| c == '[' || c == '`') {
+        buf.push_str(&rest[..idx]);
+        rest = &rest[idx..];
+        if rest.starts_with('`') {
+            let end = rest[1..].find('`').map_or(rest.len(), |it| it + 2);
+            buf.push_str(&rest[..end]);
+            rest = &rest[end..];
+            continue;
+        }
+        let close = match rest.find(']') {
+            Some(it) => it,
+            None => break,
+        };
+        let text = &rest[1..close];
+        let after = &rest[close + 1..];
+        let (target, len) = if after.starts_with('(') {
+            match after.find(')') {
+                Some(end) => (Some(&after[1..end]), close + end + 2),
+                None => break,
+            }
+        } else if after.starts_with('[') {
+            // Reference-style links are resolved by markdown itself.
+            (None, after.find(']').map_or(close + 1, |end| close + end + 2))
+        } else if after.starts_with(':') {
+            // And so are their definitions.
+            (None, rest.len())
+        } else {
+            (Some(text.trim_matches('`')), close + 1)
+        };
+        match target.and_then(link_path).filter(|path| resolve(path)) {
+            Some(path) => format!(buf, "[{}]({})", text, path),
+            None => buf.push_str(&rest[..len]),
+        }
+        rest = &rest[len..];
+    }
+    buf.push_str(rest);
+    buf
+}
+
+/// Strips rustdoc disambiguators, like `struct@Foo`, `foo()` or `foo!`, and
+/// checks that what remains looks like a path.
+fn link_path(target: &str) -> Option<&str> {
+    let target = match target.find('@') {
+        Some(idx) => &target[idx + 1..],
+        None => target,
+    };
+    let target = target.trim_end_matches("()").trim_end_matches('!');
+    let is_path = target.split("::").all(|segment| {
+        !segment.is_empty() && segment.chars().all(|c| c.is_alphanumeric() || c == '_')
+    });
+    if is_path {
+        Some(target)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rewrite_links;
+
+    fn check(line: &str, known: &[&str], expected: &str) {
+        let actual = rewrite_links(line, |path| known.contains(&path));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn rewrites_shortcut_links() {
+        check(
+            "See [Foo] and [`crate::bar::Baz`].",
+            &["Foo", "crate::bar::Baz"],
+            "See [Foo](Foo) and [`crate::bar::Baz`](crate::bar::Baz).",
+        );
+    }
+
+    #[test]
+    fn rewrites_inline_links_with_disambiguators() {
+        check(
+            "Calls [the function](fn@foo) and [bar!()].",
+            &["foo", "bar"],
+            "Calls [the function](foo) and [bar!()](bar).",
+        );
+    }
+
+    #[test]
+    fn keeps_unresolved_and_external_links() {
+        check(
+            "[Unknown], [docs](https://docs.rs) and [`Foo`][foo]",
+            &["Foo"],
+            "[Unknown], [docs](https://docs.rs) and [`Foo`][foo]",
+        );
+    }
+
+    #[test]
+    fn skips_inline_code() {
+        check("`let x = [Foo];` [Foo]", &["Foo"], "`let x = [Foo];` [Foo](Foo)");
+    }
+}
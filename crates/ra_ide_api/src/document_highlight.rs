@@ -0,0 +1,248 @@
+//! Finds the occurrences of the symbol under the cursor in the current file
+//! and classifies each of them as its definition, a read or a write.
+
+use hir::HasSource;
+use ra_db::{FileId, SourceDatabase};
+use ra_syntax::{
+    algo::find_node_at_offset,
+    ast::{self, BinOp, SelfParamKind},
+    AstNode, SmolStr,
+    SyntaxKind::*,
+    SyntaxNode, TextRange,
+};
+
+use crate::{
+    db::RootDatabase,
+    goto_definition::{name_definition, reference_definition, ReferenceResult},
+    FilePosition, NavigationTarget,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceAccess {
+    Definition,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentHighlight {
+    pub range: TextRange,
+    pub access: ReferenceAccess,
+}
+
+/// The range of the name of a definition, which identifies it.
+type Target = (FileId, TextRange);
+
+pub(crate) fn document_highlight(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<Vec<DocumentHighlight>> {
+    let file_id = position.file_id;
+    let parse = db.parse(file_id);
+    let syntax = parse.tree().syntax().clone();
+    let (text, target): (SmolStr, Target) =
+        if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(&syntax, position.offset) {
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+            (name_ref.text().clone(), name_ref_target(db, file_id, &analyzer, &name_ref)?)
+        } else {
+            let name = find_node_at_offset::<ast::Name>(&syntax, position.offset)?;
+            (name.text().clone(), name_target(db, file_id, &name)?)
+        };
+
+    let mut res = Vec::new();
+    for node in syntax.descendants() {
+        if let Some(name) = ast::Name::cast(node.clone()) {
+            if *name.text() == text && name_target(db, file_id, &name) == Some(target) {
+                let range = name.syntax().text_range();
+                res.push(DocumentHighlight { range, access: ReferenceAccess::Definition });
+            }
+        } else if let Some(name_ref) = ast::NameRef::cast(node) {
+            if *name_ref.text() != text {
+                continue;
+            }
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+            if name_ref_target(db, file_id, &analyzer, &name_ref) == Some(target) {
+                let range = name_ref.syntax().text_range();
+                let access = reference_access(db, &analyzer, &name_ref);
+                res.push(DocumentHighlight { range, access });
+            }
+        }
+    }
+    Some(res)
+}
+
+fn name_ref_target(
+    db: &RootDatabase,
+    file_id: FileId,
+    analyzer: &hir::SourceAnalyzer,
+    name_ref: &ast::NameRef,
+) -> Option<Target> {
+    match reference_definition(db, file_id, analyzer, name_ref) {
+        ReferenceResult::Exact(nav) => Some((nav.file_id(), nav.range())),
+        ReferenceResult::Approximate(_) => None,
+    }
+}
+
+fn name_target(db: &RootDatabase, file_id: FileId, name: &ast::Name) -> Option<Target> {
+    let parent = name.syntax().parent()?;
+    let nav = match ast::BindPat::cast(parent) {
+        Some(pat) => NavigationTarget::from_bind_pat(file_id, &pat),
+        None => name_definition(db, file_id, name)?.into_iter().next()?,
+    };
+    Some((nav.file_id(), nav.range()))
+}
+
+fn reference_access(
+    db: &RootDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    name_ref: &ast::NameRef,
+) -> ReferenceAccess {
+    let parent = match name_ref.syntax().parent() {
+        Some(it) => it,
+        None => return ReferenceAccess::Read,
+    };
+    // The field in a struct literal, like `x` in `Foo { x: 92 }`.
+    if parent.kind() == NAMED_FIELD {
+        return ReferenceAccess::Write;
+    }
+    // The expression which is accessed: a whole path or a field access.
+    let expr = match parent.kind() {
+        FIELD_EXPR => Some(parent),
+        PATH_SEGMENT => parent
+            .parent()
+            .filter(|path| path.parent().map(|it| it.kind()) == Some(PATH_EXPR))
+            .and_then(|path| path.parent()),
+        _ => None,
+    };
+    match expr {
+        Some(expr) if is_written(db, analyzer, &expr) => ReferenceAccess::Write,
+        _ => ReferenceAccess::Read,
+    }
+}
+
+/// Checks if `expr` is assigned to, mutably borrowed or used as the receiver
+/// of a `&mut self` method.
+fn is_written(db: &RootDatabase, analyzer: &hir::SourceAnalyzer, expr: &SyntaxNode) -> bool {
+    let parent = match expr.parent() {
+        Some(it) => it,
+        None => return false,
+    };
+    if let Some(bin_expr) = ast::BinExpr::cast(parent.clone()) {
+        let is_lhs = bin_expr.lhs().map_or(false, |lhs| lhs.syntax() == expr);
+        return is_lhs && bin_expr.op_kind().map_or(false, is_assignment);
+    }
+    if let Some(ref_expr) = ast::RefExpr::cast(parent.clone()) {
+        return ref_expr.is_mut();
+    }
+    if let Some(method_call) = ast::MethodCallExpr::cast(parent) {
+        let is_receiver = method_call.expr().map_or(false, |it| it.syntax() == expr);
+        return is_receiver
+            && analyzer
+                .resolve_method_call(&method_call)
+                .and_then(|func| func.source(db).ast.param_list()?.self_param())
+                .map_or(false, |self_param| self_param.kind() == SelfParamKind::MutRef);
+    }
+    false
+}
+
+fn is_assignment(op: BinOp) -> bool {
+    match op {
+        BinOp::Assignment
+        | BinOp::AddAssign
+        | BinOp::DivAssign
+        | BinOp::MulAssign
+        | BinOp::RemAssign
+        | BinOp::ShrAssign
+        | BinOp::ShlAssign
+        | BinOp::SubAssign
+        | BinOp::BitOrAssign
+        | BinOp::BitAndAssign
+        | BinOp::BitXorAssign => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::single_file_with_position;
+
+    fn check(fixture: &str, expected: &[&str]) {
+        let (analysis, position) = single_file_with_position(fixture);
+        let highlights = analysis.document_highlight(position).unwrap().unwrap();
+        let actual = highlights
+            .into_iter()
+            .map(|it| format!("{:?} {:?}", it.range, it.access))
+            .collect::<Vec<_>>();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn highlights_local_reads_and_writes() {
+        check(
+            r#"
+            fn foo(v: &mut Vec<u32>) {}
+            fn main() {
+                let mut x<|> = 0;
+                x = 1;
+                x += 2;
+                let r = &mut x;
+                let s = &x;
+                foo(x);
+            }
+            "#,
+            &[
+                "[89; 90) Definition",
+                "[112; 113) Write",
+                "[135; 136) Write",
+                "[172; 173) Write",
+                "[200; 201) Read",
+                "[223; 224) Read",
+            ],
+        );
+    }
+
+    #[test]
+    fn highlights_mut_method_receivers() {
+        check(
+            r#"
+            struct S;
+            impl S {
+                fn get(&self) {}
+                fn set(&mut self) {}
+            }
+            fn main() {
+                let s = S;
+                s<|>.get();
+                s.set();
+            }
+            "#,
+            &["[172; 173) Definition", "[195; 196) Read", "[220; 221) Write"],
+        );
+    }
+
+    #[test]
+    fn highlights_fields() {
+        check(
+            r#"
+            struct Foo { x<|>: u32 }
+            fn main() {
+                let mut foo = Foo { x: 0 };
+                foo.x = foo.x + 1;
+            }
+            "#,
+            &["[26; 27) Definition", "[95; 96) Write", "[123; 124) Write", "[131; 132) Read"],
+        );
+    }
+
+    #[test]
+    fn highlights_items() {
+        check(
+            r#"
+            fn foo() {}
+            fn bar() { foo<|>(); }
+            fn baz() { let foo = 92; foo; foo(); }
+            "#,
+            &["[16; 19) Definition", "[48; 51) Read"],
+        );
+    }
+}
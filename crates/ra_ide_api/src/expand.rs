@@ -0,0 +1,98 @@
+//! Maps identifiers inside of macro calls to the macro expansions, and ranges
+//! in the expansions back to the macro calls.
+
+use hir::Source;
+use ra_db::SourceDatabase;
+use ra_syntax::{ast, AstNode, SyntaxKind::IDENT, SyntaxToken, TextRange};
+
+use crate::{
+    db::RootDatabase,
+    name_ref_kind::{classify_expanded_name_ref, NameRefKind},
+    FilePosition, FileRange,
+};
+
+/// Expansions of recursive macros are followed at most this deep.
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+/// An identifier from a macro call, as it appears in the macro expansion.
+pub(crate) struct ExpandedToken {
+    /// The analyzer for the scope of the macro call in the original file.
+    pub(crate) analyzer: hir::SourceAnalyzer,
+    /// The token in the innermost expansion the identifier ends up in.
+    pub(crate) token: SyntaxToken,
+    /// The macro file of the expansion `token` is in.
+    pub(crate) file_id: hir::HirFileId,
+    /// The range of the identifier in the original file.
+    pub(crate) range: TextRange,
+}
+
+/// Follows the identifier at `position` into the expansions of the macro calls
+/// it is passed to. Returns `None` if it is not in a macro call, or if the
+/// macro does not use it.
+pub(crate) fn descend_into_macros(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<ExpandedToken> {
+    let parse = db.parse(position.file_id);
+    let token =
+        parse.tree().syntax().token_at_offset(position.offset).find(|it| it.kind() == IDENT)?;
+    let range = token.text_range();
+    let macro_call = token.parent().ancestors().find_map(ast::MacroCall::cast)?;
+    let analyzer =
+        hir::SourceAnalyzer::new(db, position.file_id, macro_call.syntax(), Some(position.offset));
+
+    let mut token = Source { file_id: position.file_id.into(), ast: token };
+    for _ in 0..MAX_EXPANSION_DEPTH {
+        let macro_call = match token.ast.parent().ancestors().find_map(ast::MacroCall::cast) {
+            Some(it) => it,
+            None => break,
+        };
+        let expansion =
+            match analyzer.expand(db, Source { file_id: token.file_id, ast: &macro_call }) {
+                Some(it) => it,
+                None => break,
+            };
+        let mapped =
+            expansion.map_token_down(db, Source { file_id: token.file_id, ast: &token.ast });
+        match mapped {
+            Some(it) => token = it,
+            None => break,
+        }
+    }
+    if token.file_id == position.file_id.into() {
+        return None;
+    }
+    Some(ExpandedToken { analyzer, token: token.ast, file_id: token.file_id, range })
+}
+
+impl ExpandedToken {
+    /// Returns the name reference the token is a part of, and what it refers
+    /// to.
+    pub(crate) fn classify(
+        &self,
+        db: &RootDatabase,
+    ) -> Option<(ast::NameRef, Option<NameRefKind>)> {
+        let name_ref = ast::NameRef::cast(self.token.parent())?;
+        let kind = classify_expanded_name_ref(
+            db,
+            &self.analyzer,
+            Source { file_id: self.file_id, ast: &name_ref },
+        );
+        Some((name_ref, kind))
+    }
+}
+
+/// Maps a range in a macro expansion to the file the macro is called from.
+/// Identifiers passed to the macro map to the identifiers in the call, and
+/// anything else to the whole call.
+pub(crate) fn original_range(db: &RootDatabase, range: Source<TextRange>) -> FileRange {
+    let mut range = range;
+    for _ in 0..MAX_EXPANSION_DEPTH {
+        let expansion = match hir::Expansion::from_file(range.file_id) {
+            Some(it) => it,
+            None => break,
+        };
+        range = expansion.map_range_up(db, range.ast).unwrap_or_else(|| expansion.call_range(db));
+    }
+    FileRange { file_id: range.file_id.original_file(db), range: range.ast }
+}
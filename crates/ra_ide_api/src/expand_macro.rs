@@ -0,0 +1,210 @@
+//! Expands the macro call under the cursor, including the macro calls in its
+//! expansion, and pretty-prints the result.
+
+use hir::{db::AstDatabase, HirFileId, Source};
+use ra_db::SourceDatabase;
+use ra_fmt::compute_ws;
+use ra_syntax::{
+    algo::find_node_at_offset, ast, AstNode, NodeOrToken, SyntaxKind, SyntaxKind::*, SyntaxNode,
+    SyntaxToken, T,
+};
+
+use crate::{db::RootDatabase, FilePosition};
+
+/// Nested macro calls are expanded at most this deep.
+const MAX_EXPANSION_DEPTH: usize = 32;
+
+#[derive(Debug)]
+pub struct ExpandedMacro {
+    /// The name of the expanded macro, without the `!`.
+    pub name: String,
+    pub expansion: String,
+}
+
+pub(crate) fn expand_macro(db: &RootDatabase, position: FilePosition) -> Option<ExpandedMacro> {
+    let parse = db.parse(position.file_id);
+    let macro_call = find_node_at_offset::<ast::MacroCall>(parse.tree().syntax(), position.offset)?;
+    let name = macro_call.path()?.segment()?.name_ref()?.text().to_string();
+
+    let analyzer = hir::SourceAnalyzer::new(db, position.file_id, macro_call.syntax(), None);
+    let file_id = position.file_id.into();
+    let expansion = analyzer.expand(db, Source { file_id, ast: &macro_call })?;
+    let expanded = db.parse_or_expand(expansion.file_id())?;
+
+    let mut tokens = Vec::new();
+    collect_tokens(db, &analyzer, expansion.file_id(), &expanded, 0, &mut tokens);
+    Some(ExpandedMacro { name, expansion: insert_whitespaces(&tokens) })
+}
+
+/// Collects the tokens of `node`, replacing macro calls with the tokens of
+/// their expansions.
+fn collect_tokens(
+    db: &RootDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    file_id: HirFileId,
+    node: &SyntaxNode,
+    depth: usize,
+    acc: &mut Vec<SyntaxToken>,
+) {
+    for child in node.children_with_tokens() {
+        match child {
+            NodeOrToken::Token(token) => {
+                if !token.kind().is_trivia() {
+                    acc.push(token);
+                }
+            }
+            NodeOrToken::Node(child) => {
+                let expansion = ast::MacroCall::cast(child.clone())
+                    .filter(|_| depth < MAX_EXPANSION_DEPTH)
+                    .and_then(|call| analyzer.expand(db, Source { file_id, ast: &call }))
+                    .and_then(|expansion| {
+                        let file_id = expansion.file_id();
+                        Some((file_id, db.parse_or_expand(file_id)?))
+                    });
+                match expansion {
+                    Some((file_id, expanded)) => {
+                        collect_tokens(db, analyzer, file_id, &expanded, depth + 1, acc)
+                    }
+                    None => collect_tokens(db, analyzer, file_id, &child, depth, acc),
+                }
+            }
+        }
+    }
+}
+
+/// Macro expansions contain no whitespace, so it is inserted between the
+/// tokens: statements and items go on separate lines and blocks are indented.
+fn insert_whitespaces(tokens: &[SyntaxToken]) -> String {
+    let mut buf = String::new();
+    let mut indent: usize = 0;
+    let mut prev: Option<&SyntaxToken> = None;
+    for token in tokens {
+        let kind = token.kind();
+        if kind == T!['}'] {
+            indent = indent.saturating_sub(1);
+        }
+        if let Some(prev) = prev {
+            let newline = match (prev.kind(), kind) {
+                (T!['{'], T!['}']) => false,
+                (T!['{'], _) | (T![;], _) => true,
+                (_, T!['}']) => true,
+                (T!['}'], next) => !is_closing(next),
+                _ => false,
+            };
+            if newline {
+                buf.push('\n');
+                buf.push_str(&"    ".repeat(indent));
+            } else {
+                buf.push_str(ws_between(prev, token));
+            }
+        }
+        buf.push_str(token.text());
+        if kind == T!['{'] {
+            indent += 1;
+        }
+        prev = Some(token);
+    }
+    buf
+}
+
+/// Tokens which continue the expression or item a `}` ends.
+fn is_closing(kind: SyntaxKind) -> bool {
+    match kind {
+        T![')'] | T![']'] | T![,] | T![;] | T![.] | T![?] | T![else] => true,
+        _ => false,
+    }
+}
+
+fn ws_between(left: &SyntaxToken, right: &SyntaxToken) -> &'static str {
+    let parent_kind = |token: &SyntaxToken| token.parent().kind();
+    match right.kind() {
+        T![,] | T![;] | T![.] | T![?] | T![:] | T![::] => return "",
+        T!['('] | T!['['] => match left.kind() {
+            IDENT | T![')'] | T![']'] | T![!] | T![self] => return "",
+            T![>] if is_generic_list(parent_kind(left)) => return "",
+            _ => (),
+        },
+        T![!] if parent_kind(right) == MACRO_CALL => return "",
+        T![<] | T![>] if is_generic_list(parent_kind(right)) => return "",
+        _ => (),
+    }
+    match left.kind() {
+        T![.] | T![::] | T![#] => return "",
+        T![<] if is_generic_list(parent_kind(left)) => return "",
+        T![&] | T![*] | T![-] | T![!] => match parent_kind(left) {
+            REF_EXPR | PREFIX_EXPR | REFERENCE_TYPE | POINTER_TYPE | REF_PAT => return "",
+            _ => (),
+        },
+        _ => (),
+    }
+    compute_ws(left.kind(), right.kind())
+}
+
+fn is_generic_list(kind: SyntaxKind) -> bool {
+    match kind {
+        TYPE_ARG_LIST | TYPE_PARAM_LIST => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::analysis_and_position;
+
+    fn check_expand_macro(fixture: &str, name: &str, expected: &str) {
+        let (analysis, position) = analysis_and_position(fixture);
+        let expanded = analysis.expand_macro(position).unwrap().unwrap();
+        assert_eq!(expanded.name, name);
+        assert_eq!(expanded.expansion, expected.trim());
+    }
+
+    #[test]
+    fn expands_items() {
+        check_expand_macro(
+            r#"
+            //- /lib.rs
+            macro_rules! foo {
+                ($name:ident) => {
+                    struct $name;
+                    fn $name(x: &u32, v: Vec<u32>) -> u32 { let y = *x; y + v[0] }
+                };
+            }
+            fo<|>o!(Bar);
+            "#,
+            "foo",
+            r#"
+struct Bar;
+fn Bar(x: &u32, v: Vec<u32>) -> u32 {
+    let y = *x;
+    y + v[0]
+}
+"#,
+        );
+    }
+
+    #[test]
+    fn expands_nested_macro_calls() {
+        check_expand_macro(
+            r#"
+            //- /lib.rs
+            macro_rules! inner {
+                ($e:expr) => { $e + 1 };
+            }
+            macro_rules! outer {
+                ($e:expr) => { fn foo() -> u32 { if true { inner!($e) } else { 0 } } };
+            }
+            outer!(9<|>2);
+            "#,
+            "outer",
+            r#"
+fn foo() -> u32 {
+    if true {
+        92 + 1
+    } else {
+        0
+    }
+}
+"#,
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use hir::{db::AstDatabase, Source};
 use ra_db::SourceDatabase;
 use ra_syntax::{
     algo::find_covering_element,
@@ -7,12 +8,30 @@ use ra_syntax::{
     SyntaxNode, SyntaxToken, TextRange, TextUnit, TokenAtOffset, T,
 };
 
-use crate::{db::RootDatabase, FileRange};
+use crate::{db::RootDatabase, FilePosition, FileRange};
 
-// FIXME: restore macro support
 pub(crate) fn extend_selection(db: &RootDatabase, frange: FileRange) -> TextRange {
     let parse = db.parse(frange.file_id);
-    try_extend_selection(parse.tree().syntax(), frange.range).unwrap_or(frange.range)
+    let root = parse.tree().syntax().clone();
+    extend_in_macro_call(db, frange, &root)
+        .or_else(|| try_extend_selection(&root, frange.range))
+        .unwrap_or(frange.range)
+}
+
+/// Returns the ranges which are selected by extending the selection from the
+/// empty range at `position` until the whole file is selected.
+pub(crate) fn selection_ranges(db: &RootDatabase, position: FilePosition) -> Vec<TextRange> {
+    let mut range = TextRange::offset_len(position.offset, 0.into());
+    let mut res = vec![range];
+    loop {
+        let next = extend_selection(db, FileRange { file_id: position.file_id, range });
+        if next == range {
+            break;
+        }
+        res.push(next);
+        range = next;
+    }
+    res
 }
 
 fn try_extend_selection(root: &SyntaxNode, range: TextRange) -> Option<TextRange> {
@@ -30,6 +49,12 @@ fn try_extend_selection(root: &SyntaxNode, range: TextRange) -> Option<TextRange
         PARAM_LIST,
         ARG_LIST,
         ARRAY_EXPR,
+        TUPLE_EXPR,
+        TUPLE_TYPE,
+        TUPLE_PAT,
+        TUPLE_STRUCT_PAT,
+        SLICE_PAT,
+        WHERE_CLAUSE,
     ];
 
     if range.is_empty() {
@@ -43,6 +68,7 @@ fn try_extend_selection(root: &SyntaxNode, range: TextRange) -> Option<TextRange
             TokenAtOffset::Single(l) => {
                 if string_kinds.contains(&l.kind()) {
                     extend_single_word_in_comment_or_string(&l, offset)
+                        .or_else(|| string_content_range(&l))
                         .unwrap_or_else(|| l.text_range())
                 } else {
                     l.text_range()
@@ -55,6 +81,12 @@ fn try_extend_selection(root: &SyntaxNode, range: TextRange) -> Option<TextRange
     let node = match find_covering_element(root, range) {
         NodeOrToken::Token(token) => {
             if token.text_range() != range {
+                // Select the contents of a string before the quotes.
+                if let Some(content) = string_content_range(&token) {
+                    if range.is_subrange(&content) && range != content {
+                        return Some(content);
+                    }
+                }
                 return Some(token.text_range());
             }
             if let Some(comment) = ast::Comment::cast(token.clone()) {
@@ -82,6 +114,85 @@ fn try_extend_selection(root: &SyntaxNode, range: TextRange) -> Option<TextRange
     node.parent().map(|it| it.text_range())
 }
 
+/// Extends the selection of identifiers in the arguments of a macro call to the
+/// identifiers which make up the enclosing node in the macro expansion, like
+/// `b * c` for `b` in `foo!(a + b * c)` if the macro expands to an expression.
+fn extend_in_macro_call(
+    db: &RootDatabase,
+    frange: FileRange,
+    root: &SyntaxNode,
+) -> Option<TextRange> {
+    let range = frange.range;
+    if range.is_empty() {
+        return None;
+    }
+    let node = match find_covering_element(root, range) {
+        NodeOrToken::Token(token) => token.parent(),
+        NodeOrToken::Node(node) => node,
+    };
+    let tt = node.ancestors().find_map(ast::TokenTree::cast)?;
+    let macro_call = tt.syntax().ancestors().find_map(ast::MacroCall::cast)?;
+    let analyzer = hir::SourceAnalyzer::new(db, frange.file_id, macro_call.syntax(), None);
+    let file_id = frange.file_id.into();
+    let expansion = analyzer.expand(db, Source { file_id, ast: &macro_call })?;
+
+    // The identifiers of the arguments together with their ranges in the
+    // expansion.
+    let idents = macro_call
+        .token_tree()?
+        .syntax()
+        .descendants_with_tokens()
+        .filter_map(|it| it.into_token())
+        .filter(|it| it.kind() == IDENT)
+        .filter_map(|token| {
+            let mapped = expansion.map_token_down(db, Source { file_id, ast: &token })?;
+            Some((token.text_range(), mapped.ast.text_range()))
+        })
+        .collect::<Vec<_>>();
+    let expanded = cover(idents.iter().filter(|(it, _)| it.is_subrange(&range)).map(|it| it.1))?;
+
+    let expansion_root = db.parse_or_expand(expansion.file_id())?;
+    let node = match find_covering_element(&expansion_root, expanded) {
+        NodeOrToken::Token(token) => token.parent(),
+        NodeOrToken::Node(node) => node,
+    };
+    let target = if node.text_range() == expanded {
+        let node =
+            node.ancestors().take_while(|n| n.text_range() == node.text_range()).last().unwrap();
+        node.parent()?.text_range()
+    } else {
+        node.text_range()
+    };
+    let res = cover(idents.iter().filter(|(_, it)| it.is_subrange(&target)).map(|it| it.0))?;
+    if range.is_subrange(&res) && range != res {
+        Some(res)
+    } else {
+        None
+    }
+}
+
+fn cover(mut ranges: impl Iterator<Item = TextRange>) -> Option<TextRange> {
+    let first = ranges.next()?;
+    Some(ranges.fold(first, |acc, it| {
+        TextRange::from_to(acc.start().min(it.start()), acc.end().max(it.end()))
+    }))
+}
+
+/// Returns the range of a string literal without the quotes and prefixes.
+fn string_content_range(token: &SyntaxToken) -> Option<TextRange> {
+    if ![STRING, RAW_STRING, BYTE_STRING, RAW_BYTE_STRING].contains(&token.kind()) {
+        return None;
+    }
+    let text = token.text().as_str();
+    let start = text.find('"')? + 1;
+    let end = text.rfind('"')?;
+    if start > end {
+        return None;
+    }
+    let range = TextRange::from_to((start as u32).into(), (end as u32).into());
+    Some(range + token.text_range().start())
+}
+
 fn extend_single_word_in_comment_or_string(
     leaf: &SyntaxToken,
     offset: TextUnit,
@@ -143,7 +254,9 @@ fn pick_best<'a>(l: SyntaxToken, r: SyntaxToken) -> SyntaxToken {
     }
 }
 
-/// Extend list item selection to include nearby comma and whitespace.
+/// Extend list item selection to include nearby comma and whitespace. The
+/// comma after the item is preferred, so that the last item only gets the
+/// comma before it.
 fn extend_list_item(node: &SyntaxNode) -> Option<TextRange> {
     fn is_single_line_ws(node: &SyntaxToken) -> bool {
         node.kind() == WHITESPACE && !node.text().contains('\n')
@@ -161,9 +274,6 @@ fn extend_list_item(node: &SyntaxNode) -> Option<TextRange> {
             .filter(|node| node.kind() == T![,])
     }
 
-    if let Some(comma_node) = nearby_comma(node, Direction::Prev) {
-        return Some(TextRange::from_to(comma_node.text_range().start(), node.text_range().end()));
-    }
     if let Some(comma_node) = nearby_comma(node, Direction::Next) {
         // Include any following whitespace when comma if after list item.
         let final_node = comma_node
@@ -174,6 +284,9 @@ fn extend_list_item(node: &SyntaxNode) -> Option<TextRange> {
 
         return Some(TextRange::from_to(node.text_range().start(), final_node.text_range().end()));
     }
+    if let Some(comma_node) = nearby_comma(node, Direction::Prev) {
+        return Some(TextRange::from_to(comma_node.text_range().start(), node.text_range().end()));
+    }
 
     None
 }
@@ -199,6 +312,10 @@ fn adj_comments(comment: &ast::Comment, dir: Direction) -> ast::Comment {
             Some(token) => token,
         };
         if let Some(c) = ast::Comment::cast(token.clone()) {
+            // Doc comments and ordinary comments form separate blocks.
+            if c.kind() != comment.kind() {
+                break;
+            }
             res = c
         } else if token.kind() != WHITESPACE || token.text().contains("\n\n") {
             break;
@@ -213,6 +330,7 @@ mod tests {
     use test_utils::extract_offset;
 
     use super::*;
+    use crate::mock_analysis::single_file_with_range;
 
     fn do_check(before: &str, afters: &[&str]) {
         let (cursor, before) = extract_offset(before);
@@ -236,12 +354,14 @@ mod tests {
         do_check(r#"fn foo(<|>x: i32, y: i32) {}"#, &["x", "x: i32", "x: i32, "]);
         do_check(r#"fn foo(<|>x: i32,y: i32) {}"#, &["x", "x: i32", "x: i32,"]);
         do_check(r#"fn foo(x: i32, <|>y: i32) {}"#, &["y", "y: i32", ", y: i32"]);
-        do_check(r#"fn foo(x: i32, <|>y: i32, ) {}"#, &["y", "y: i32", ", y: i32"]);
+        do_check(r#"fn foo(x: i32, <|>y: i32, ) {}"#, &["y", "y: i32", "y: i32, "]);
         do_check(r#"fn foo(x: i32,<|>y: i32) {}"#, &["y", "y: i32", ",y: i32"]);
 
         do_check(r#"const FOO: [usize; 2] = [ 22<|> , 33];"#, &["22", "22 , "]);
         do_check(r#"const FOO: [usize; 2] = [ 22 , 33<|>];"#, &["33", ", 33"]);
-        do_check(r#"const FOO: [usize; 2] = [ 22 , 33<|> ,];"#, &["33", ", 33"]);
+        do_check(r#"const FOO: [usize; 2] = [ 22 , 33<|> ,];"#, &["33", "33 ,"]);
+        do_check(r#"fn foo() { (1, <|>2, 3); }"#, &["2", "2, "]);
+        do_check(r#"fn foo((a, <|>b): (i32, i32)) {}"#, &["b", ", b"]);
 
         do_check(
             r#"
@@ -258,7 +378,7 @@ const FOO: [usize; 2] = [
     22
     , 33<|>,
 ]"#,
-            &["33", ", 33"],
+            &["33", "33,"],
         );
     }
 
@@ -379,7 +499,46 @@ fn bar(){}
 
 " fn f<|>oo() {"
 "#,
-            &["foo", "\" fn foo() {\""],
+            &["foo", " fn foo() {", "\" fn foo() {\""],
+        );
+        do_check(
+            r##"fn bar() { r#"hel<|>lo world"#; }"##,
+            &["hello", "hello world", "r#\"hello world\"#"],
+        );
+        do_check(r#"fn bar() { b"<|> "; }"#, &[" ", "b\" \""]);
+    }
+
+    #[test]
+    fn test_extend_selection_doc_comment_block() {
+        do_check(
+            r#"
+// foo
+/// doc1
+/// d<|>oc2
+fn foo() {}
+"#,
+            &["doc2", "/// doc2", "/// doc1\n/// doc2"],
         );
     }
+
+    #[test]
+    fn test_extend_selection_in_macro_call() {
+        let (analysis, frange) = single_file_with_range(
+            "
+            macro_rules! id {
+                ($($tt:tt)*) => { $($tt)* };
+            }
+            fn main() {
+                id!(a + <|>b<|> * c);
+            }
+            ",
+        );
+        let text = analysis.file_text(frange.file_id).unwrap();
+        let mut range = frange.range;
+        for &after in &["b * c", "a + b * c", "(a + b * c)"] {
+            let frange = FileRange { file_id: frange.file_id, range };
+            range = analysis.extend_selection(frange).unwrap();
+            assert_eq!(after, &text[range.start().to_usize()..range.end().to_usize()]);
+        }
+    }
 }
@@ -0,0 +1,199 @@
+use hir::{ImplItem, TraitItem};
+use ra_db::{FileId, SourceDatabase};
+use ra_syntax::{
+    algo::find_node_at_offset,
+    ast, AstNode,
+    SyntaxKind::{self, *},
+};
+
+use crate::{
+    db::RootDatabase,
+    goto_definition::goto_definition,
+    name_ref_kind::{classify_name_ref, NameRefKind},
+    FilePosition, NavigationTarget, RangeInfo,
+};
+
+/// Goes to the declaration of an item. For the items of a trait impl, this is
+/// the corresponding item of the trait; for everything else, the declaration
+/// is the same as the definition.
+pub(crate) fn goto_declaration(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<Vec<NavigationTarget>>> {
+    let parse = db.parse(position.file_id);
+    let syntax = parse.tree().syntax().clone();
+    let declaration = if let Some(name_ref) =
+        find_node_at_offset::<ast::NameRef>(&syntax, position.offset)
+    {
+        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+        let item = match classify_name_ref(db, &analyzer, &name_ref) {
+            Some(NameRefKind::Method(it)) => Some(ImplItem::Method(it)),
+            Some(NameRefKind::AssocItem(it)) => Some(it),
+            Some(NameRefKind::Def(hir::ModuleDef::Function(it))) => Some(ImplItem::Method(it)),
+            Some(NameRefKind::Def(hir::ModuleDef::Const(it))) => Some(ImplItem::Const(it)),
+            Some(NameRefKind::Def(hir::ModuleDef::TypeAlias(it))) => Some(ImplItem::TypeAlias(it)),
+            _ => None,
+        };
+        item.and_then(|it| item_declaration(db, it))
+            .map(|nav| RangeInfo::new(name_ref.syntax().text_range(), vec![nav]))
+    } else if let Some(name) = find_node_at_offset::<ast::Name>(&syntax, position.offset) {
+        impl_item_declaration(db, position.file_id, &name)
+            .map(|nav| RangeInfo::new(name.syntax().text_range(), vec![nav]))
+    } else {
+        None
+    };
+    declaration.or_else(|| goto_definition(db, position))
+}
+
+/// Returns the declaration of an associated item: the item itself, if it is
+/// defined in a trait, or the item of the trait implemented by its impl block.
+fn item_declaration(db: &RootDatabase, item: ImplItem) -> Option<NavigationTarget> {
+    let (impl_block, name, kind) = match item {
+        ImplItem::Method(it) => {
+            if it.parent_trait(db).is_some() {
+                return Some(NavigationTarget::from_def_source(db, it));
+            }
+            (it.impl_block(db)?, it.name(db).to_string(), FN_DEF)
+        }
+        ImplItem::Const(it) => (it.impl_block(db)?, it.data(db).name().to_string(), CONST_DEF),
+        ImplItem::TypeAlias(it) => {
+            if it.parent_trait(db).is_some() {
+                return Some(NavigationTarget::from_def_source(db, it));
+            }
+            (it.impl_block(db)?, it.name(db).to_string(), TYPE_ALIAS_DEF)
+        }
+    };
+    let tr = impl_block.target_trait_ref(db)?.trait_;
+    trait_item(db, tr, &name, kind)
+}
+
+/// Returns the declaration of the item of a trait impl with the given `name`.
+fn impl_item_declaration(
+    db: &RootDatabase,
+    file_id: FileId,
+    name: &ast::Name,
+) -> Option<NavigationTarget> {
+    let item = name.syntax().parent()?;
+    let impl_block = item
+        .parent()
+        .filter(|it| it.kind() == ITEM_LIST)
+        .and_then(|it| it.parent())
+        .and_then(ast::ImplBlock::cast)?;
+    let path = match impl_block.target_trait()?.kind() {
+        ast::TypeRefKind::PathType(it) => it.path()?,
+        _ => return None,
+    };
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, impl_block.syntax(), None);
+    let tr = match analyzer.resolve_path(db, &path)? {
+        hir::PathResolution::Def(hir::ModuleDef::Trait(it)) => it,
+        _ => return None,
+    };
+    trait_item(db, tr, name.text(), item.kind())
+}
+
+/// Finds the item of the trait `tr` with the given name and syntax kind.
+fn trait_item(
+    db: &RootDatabase,
+    tr: hir::Trait,
+    name: &str,
+    kind: SyntaxKind,
+) -> Option<NavigationTarget> {
+    let nav = tr.items(db).into_iter().find_map(|item| match item {
+        TraitItem::Function(it) if kind == FN_DEF && it.name(db).to_string() == name => {
+            Some(NavigationTarget::from_def_source(db, it))
+        }
+        TraitItem::Const(it) if kind == CONST_DEF && it.data(db).name().to_string() == name => {
+            Some(NavigationTarget::from_def_source(db, it))
+        }
+        TraitItem::TypeAlias(it) if kind == TYPE_ALIAS_DEF && it.name(db).to_string() == name => {
+            Some(NavigationTarget::from_def_source(db, it))
+        }
+        _ => None,
+    })?;
+    Some(nav)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mock_analysis::analysis_and_position;
+
+    fn check_goto(fixture: &str, expected: &str) {
+        let (analysis, pos) = analysis_and_position(fixture);
+
+        let mut navs = analysis.goto_declaration(pos).unwrap().unwrap().info;
+        assert_eq!(navs.len(), 1);
+        let nav = navs.pop().unwrap();
+        nav.assert_match(expected);
+    }
+
+    #[test]
+    fn goto_declaration_from_impl_method() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                fn frobnicate(&self);
+            }
+            struct Foo;
+            impl Trait for Foo {
+                fn frobnicate<|>(&self) {}
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [18; 39) [21; 31)",
+        );
+    }
+
+    #[test]
+    fn goto_declaration_from_method_call() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                fn frobnicate(&self);
+            }
+            struct Foo;
+            impl Trait for Foo {
+                fn frobnicate(&self) {}
+            }
+            fn bar(foo: Foo) {
+                foo.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [18; 39) [21; 31)",
+        );
+    }
+
+    #[test]
+    fn goto_declaration_from_assoc_type() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                type Item;
+            }
+            struct Foo;
+            impl Trait for Foo {
+                type Item<|> = u32;
+            }
+            ",
+            "Item TYPE_ALIAS_DEF FileId(1) [18; 28) [23; 27)",
+        );
+    }
+
+    #[test]
+    fn goto_declaration_falls_back_to_definition() {
+        check_goto(
+            "
+            //- /lib.rs
+            struct Foo;
+            impl Foo {
+                fn frobnicate(&self) {}
+            }
+            fn bar(foo: Foo) {
+                foo.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [27; 50) [30; 40)",
+        );
+    }
+}
@@ -11,7 +11,8 @@ use ra_syntax::{
 use crate::{
     db::RootDatabase,
     display::ShortLabel,
-    name_ref_kind::{classify_name_ref, NameRefKind::*},
+    expand::descend_into_macros,
+    name_ref_kind::{classify_name_ref, NameRefKind, NameRefKind::*},
     FilePosition, NavigationTarget, RangeInfo,
 };
 
@@ -22,13 +23,20 @@ pub(crate) fn goto_definition(
     let parse = db.parse(position.file_id);
     let syntax = parse.tree().syntax().clone();
     if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(&syntax, position.offset) {
-        let navs = reference_definition(db, position.file_id, &name_ref).to_vec();
+        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+        let navs = reference_definition(db, position.file_id, &analyzer, &name_ref).to_vec();
         return Some(RangeInfo::new(name_ref.syntax().text_range(), navs.to_vec()));
     }
     if let Some(name) = find_node_at_offset::<ast::Name>(&syntax, position.offset) {
         let navs = name_definition(db, position.file_id, &name)?;
         return Some(RangeInfo::new(name.syntax().text_range(), navs));
     }
+    if let Some(expanded) = descend_into_macros(db, position) {
+        let (name_ref, kind) = expanded.classify(db)?;
+        let navs =
+            definition_of_kind(db, position.file_id, &expanded.analyzer, &name_ref, kind).to_vec();
+        return Some(RangeInfo::new(expanded.range, navs));
+    }
     None
 }
 
@@ -51,30 +59,43 @@ impl ReferenceResult {
 pub(crate) fn reference_definition(
     db: &RootDatabase,
     file_id: FileId,
+    analyzer: &hir::SourceAnalyzer,
     name_ref: &ast::NameRef,
 ) -> ReferenceResult {
-    use self::ReferenceResult::*;
+    let kind = classify_name_ref(db, analyzer, name_ref);
+    definition_of_kind(db, file_id, analyzer, name_ref, kind)
+}
 
-    let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+fn definition_of_kind(
+    db: &RootDatabase,
+    file_id: FileId,
+    analyzer: &hir::SourceAnalyzer,
+    name_ref: &ast::NameRef,
+    kind: Option<NameRefKind>,
+) -> ReferenceResult {
+    use self::ReferenceResult::*;
 
-    match classify_name_ref(db, &analyzer, name_ref) {
-        Some(Macro(mac)) => return Exact(NavigationTarget::from_macro_def(db, mac)),
-        Some(FieldAccess(field)) => return Exact(NavigationTarget::from_field(db, field)),
-        Some(AssocItem(assoc)) => return Exact(NavigationTarget::from_impl_item(db, assoc)),
-        Some(Method(func)) => return Exact(NavigationTarget::from_def_source(db, func)),
+    match kind {
         Some(Def(def)) => match NavigationTarget::from_def(db, def) {
             Some(nav) => return Exact(nav),
             None => return Approximate(vec![]),
         },
-        Some(SelfType(ty)) => {
-            if let Some((def_id, _)) = ty.as_adt() {
-                return Exact(NavigationTarget::from_adt_def(db, def_id));
-            }
+        Some(Method(func)) => {
+            // Prefer the implementation of a trait method for a concrete receiver.
+            let func = name_ref
+                .syntax()
+                .parent()
+                .and_then(ast::MethodCallExpr::cast)
+                .and_then(|call| impl_method(db, analyzer, &call, func))
+                .unwrap_or(func);
+            return Exact(NavigationTarget::from_def_source(db, func));
         }
         Some(Pat(pat)) => return Exact(NavigationTarget::from_pat(db, file_id, pat)),
         Some(SelfParam(par)) => return Exact(NavigationTarget::from_self_param(file_id, par)),
-        Some(GenericParam(_)) => {
-            // FIXME: go to the generic param def
+        Some(kind) => {
+            if let Some(nav) = item_definition(db, &kind) {
+                return Exact(nav);
+            }
         }
         None => {}
     };
@@ -87,6 +108,35 @@ pub(crate) fn reference_definition(
     Approximate(navs)
 }
 
+/// Finds the method of an impl block which implements the trait method `func`
+/// for the type of the receiver of `call`.
+fn impl_method(
+    db: &RootDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    call: &ast::MethodCallExpr,
+    func: hir::Function,
+) -> Option<hir::Function> {
+    let receiver_ty = analyzer.type_of(db, &call.expr()?)?;
+    analyzer.resolve_impl_method(db, receiver_ty, func)
+}
+
+/// Returns the definition of a name ref which does not refer to a local.
+pub(crate) fn item_definition(db: &RootDatabase, kind: &NameRefKind) -> Option<NavigationTarget> {
+    match *kind {
+        Macro(mac) => Some(NavigationTarget::from_macro_def(db, mac)),
+        FieldAccess(field) => Some(NavigationTarget::from_field(db, field)),
+        AssocItem(assoc) => Some(NavigationTarget::from_impl_item(db, assoc)),
+        Method(func) => Some(NavigationTarget::from_def_source(db, func)),
+        Def(def) => NavigationTarget::from_def(db, def),
+        SelfType(ref ty) => {
+            let (def_id, _) = ty.as_adt()?;
+            Some(NavigationTarget::from_adt_def(db, def_id))
+        }
+        // FIXME: go to the generic param def
+        Pat(_) | SelfParam(_) | GenericParam(_) => None,
+    }
+}
+
 pub(crate) fn name_definition(
     db: &RootDatabase,
     file_id: FileId,
@@ -288,6 +338,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_definition_works_in_macro_arguments() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! id {
+                ($($tt:tt)*) => { $($tt)* };
+            }
+            fn foo() {}
+            fn bar() {
+                id!(fo<|>o());
+            }
+            ",
+            "foo FN_DEF FileId(1) [53; 64) [56; 59)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_for_methods_in_macro_arguments() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! id {
+                ($($tt:tt)*) => { $($tt)* };
+            }
+            struct Foo;
+            impl Foo {
+                fn frobnicate(&self) {}
+            }
+            fn bar(foo: Foo) {
+                id!(foo.frob<|>nicate());
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [80; 103) [83; 93)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_maps_items_from_macros_to_the_call() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! make {
+                ($name:ident) => { struct $name; };
+            }
+            make!(Foo);
+            fn bar(foo: Fo<|>o) {}
+            ",
+            "Foo STRUCT_DEF FileId(1) [62; 73) [68; 71)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_for_locals_in_macro_arguments() {
+        check_goto(
+            "
+            //- /lib.rs
+            macro_rules! id {
+                ($($tt:tt)*) => { $($tt)* };
+            }
+            fn bar() {
+                let x = 1;
+                id!(x<|>);
+            }
+            ",
+            "x BIND_PAT FileId(1) [72; 73) [72; 73)",
+        );
+    }
+
     #[test]
     fn goto_definition_works_for_macros_from_other_crates() {
         covers!(goto_definition_works_for_macros);
@@ -330,6 +449,122 @@ mod tests {
         );
     }
 
+    #[test]
+    fn goto_definition_works_for_trait_methods_with_concrete_receiver() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                fn frobnicate(&self);
+            }
+            struct Foo;
+            impl Trait for Foo {
+                fn frobnicate(&self) {}
+            }
+            fn bar(foo: &Foo) {
+                foo.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [79; 102) [82; 92)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_for_trait_methods_from_blanket_impls() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Bar {}
+            trait Trait {
+                fn frobnicate(&self);
+            }
+            struct Foo;
+            impl Bar for Foo {}
+            impl<T: Bar> Trait for T {
+                fn frobnicate(&self) {}
+            }
+            fn bar(foo: Foo) {
+                foo.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [118; 141) [121; 131)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            trait Bar {}
+            trait Trait {
+                fn frobnicate(&self);
+            }
+            struct Foo;
+            impl Bar for Foo {}
+            impl<T: Bar> Trait for T {
+                fn frobnicate(&self) {}
+            }
+            fn bar<U: Bar>(u: U) {
+                u.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [118; 141) [121; 131)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_works_for_trait_methods_from_generic_types() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                fn frobnicate(&self);
+            }
+            struct Foo<T>(T);
+            impl Trait for Foo<u32> {
+                fn frobnicate(&self) {}
+            }
+            impl Trait for Foo<i64> {
+                fn frobnicate(&self) {}
+            }
+            fn bar(foo: Foo<i64>) {
+                foo.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [146; 169) [149; 159)",
+        );
+    }
+
+    #[test]
+    fn goto_definition_falls_back_to_trait_methods() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                fn frobnicate(&self) {}
+            }
+            struct Foo;
+            impl Trait for Foo {}
+            fn bar<T: Trait>(foo: Foo, t: T) {
+                foo.frobnicate();
+                t.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [18; 41) [21; 31)",
+        );
+        check_goto(
+            "
+            //- /lib.rs
+            trait Trait {
+                fn frobnicate(&self) {}
+            }
+            struct Foo;
+            impl Trait for Foo {}
+            fn bar(foo: Foo) {
+                foo.frobnicate<|>();
+            }
+            ",
+            "frobnicate FN_DEF FileId(1) [18; 41) [21; 31)",
+        );
+    }
+
     #[test]
     fn goto_definition_works_for_fields() {
         covers!(goto_definition_works_for_fields);
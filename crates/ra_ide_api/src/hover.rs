@@ -1,11 +1,16 @@
-use hir::{HasSource, HirDisplay};
-use ra_db::SourceDatabase;
+use std::fmt;
+
+use hir::{
+    source_binder, ApplicationTy, HasSource, HirDisplay, IntBitness, Signedness, Ty, TypeCtor,
+    UncertainIntTy,
+};
+use ra_db::{FileId, SourceDatabase};
 use ra_syntax::{
     algo::{
         ancestors_at_offset, find_covering_element, find_node_at_offset,
         visit::{visitor, Visitor},
     },
-    ast::{self, DocCommentsOwner},
+    ast::{self, DocCommentsOwner, VisibilityOwner},
     AstNode,
 };
 
@@ -15,6 +20,8 @@ use crate::{
         description_from_symbol, docs_from_symbol, rust_code_markup, rust_code_markup_with_doc,
         ShortLabel,
     },
+    doc_links::{resolve_doc_links, DocLink},
+    expand::descend_into_macros,
     name_ref_kind::{classify_name_ref, NameRefKind::*},
     FilePosition, FileRange, RangeInfo,
 };
@@ -24,6 +31,7 @@ use crate::{
 pub struct HoverResult {
     results: Vec<String>,
     exact: bool,
+    doc_links: Vec<DocLink>,
 }
 
 impl Default for HoverResult {
@@ -38,6 +46,7 @@ impl HoverResult {
             results: Vec::new(),
             // We assume exact by default
             exact: true,
+            doc_links: Vec::new(),
         }
     }
 
@@ -65,6 +74,12 @@ impl HoverResult {
         &self.results
     }
 
+    /// Links in the documentation which were resolved to definitions. The
+    /// markup refers to them by `DocLink::target`.
+    pub fn doc_links(&self) -> &[DocLink] {
+        &self.doc_links
+    }
+
     /// Returns the results converted into markup
     /// for displaying in a UI
     pub fn to_markup(&self) -> String {
@@ -99,13 +114,23 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
     let mut res = HoverResult::new();
 
     let mut range = None;
-    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(file.syntax(), position.offset) {
-        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
-
+    let name_ref = find_node_at_offset::<ast::NameRef>(file.syntax(), position.offset)
+        .map(|name_ref| {
+            let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+            let kind = classify_name_ref(db, &analyzer, &name_ref);
+            let range = name_ref.syntax().text_range();
+            (name_ref, kind, range)
+        })
+        .or_else(|| {
+            let expanded = descend_into_macros(db, position)?;
+            let (name_ref, kind) = expanded.classify(db)?;
+            Some((name_ref, kind, expanded.range))
+        });
+    if let Some((name_ref, kind, name_ref_range)) = name_ref {
         let mut no_fallback = false;
 
-        match classify_name_ref(db, &analyzer, &name_ref) {
-            Some(Method(it)) => res.extend(from_def_source(db, it)),
+        match kind {
+            Some(Method(it)) => from_function(db, &mut res, it),
             Some(Macro(it)) => {
                 let src = it.source(db);
                 res.extend(hover_text(src.ast.doc_comment_text(), None));
@@ -116,11 +141,11 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
                     res.extend(hover_text(it.doc_comment_text(), it.short_label()));
                 }
             }
-            Some(AssocItem(it)) => res.extend(match it {
-                hir::ImplItem::Method(it) => from_def_source(db, it),
-                hir::ImplItem::Const(it) => from_def_source(db, it),
-                hir::ImplItem::TypeAlias(it) => from_def_source(db, it),
-            }),
+            Some(AssocItem(it)) => match it {
+                hir::ImplItem::Method(it) => from_function(db, &mut res, it),
+                hir::ImplItem::Const(it) => from_const(db, &mut res, it),
+                hir::ImplItem::TypeAlias(it) => from_hir_display(db, &mut res, it),
+            },
             Some(Def(it)) => {
                 match it {
                     hir::ModuleDef::Module(it) => {
@@ -128,15 +153,15 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
                             res.extend(hover_text(it.doc_comment_text(), it.short_label()))
                         }
                     }
-                    hir::ModuleDef::Function(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::Struct(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::Union(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::Enum(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::EnumVariant(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::Const(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::Static(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::Trait(it) => res.extend(from_def_source(db, it)),
-                    hir::ModuleDef::TypeAlias(it) => res.extend(from_def_source(db, it)),
+                    hir::ModuleDef::Function(it) => from_function(db, &mut res, it),
+                    hir::ModuleDef::Struct(it) => from_hir_display(db, &mut res, it),
+                    hir::ModuleDef::Union(it) => from_hir_display(db, &mut res, it),
+                    hir::ModuleDef::Enum(it) => from_hir_display(db, &mut res, it),
+                    hir::ModuleDef::EnumVariant(it) => from_def_source(db, &mut res, it),
+                    hir::ModuleDef::Const(it) => from_const(db, &mut res, it),
+                    hir::ModuleDef::Static(it) => from_def_source(db, &mut res, it),
+                    hir::ModuleDef::Trait(it) => from_hir_display(db, &mut res, it),
+                    hir::ModuleDef::TypeAlias(it) => from_hir_display(db, &mut res, it),
                     hir::ModuleDef::BuiltinType(_) => {
                         // FIXME: hover for builtin Type ?
                     }
@@ -144,11 +169,11 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
             }
             Some(SelfType(ty)) => {
                 if let Some((adt_def, _)) = ty.as_adt() {
-                    res.extend(match adt_def {
-                        hir::AdtDef::Struct(it) => from_def_source(db, it),
-                        hir::AdtDef::Union(it) => from_def_source(db, it),
-                        hir::AdtDef::Enum(it) => from_def_source(db, it),
-                    })
+                    match adt_def {
+                        hir::AdtDef::Struct(it) => from_hir_display(db, &mut res, it),
+                        hir::AdtDef::Union(it) => from_hir_display(db, &mut res, it),
+                        hir::AdtDef::Enum(it) => from_hir_display(db, &mut res, it),
+                    }
                 }
             }
             Some(Pat(_)) | Some(SelfParam(_)) => {
@@ -172,30 +197,63 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
         }
 
         if !res.is_empty() {
-            range = Some(name_ref.syntax().text_range())
+            range = Some(name_ref_range)
         }
     } else if let Some(name) = find_node_at_offset::<ast::Name>(file.syntax(), position.offset) {
         if let Some(parent) = name.syntax().parent() {
+            let file_id = position.file_id;
+            let module = source_binder::module_from_child_node(db, file_id, &parent);
             let text = visitor()
                 .visit(|node: ast::StructDef| {
-                    hover_text(node.doc_comment_text(), node.short_label())
+                    let label = match module {
+                        Some(module) => {
+                            hir_label(db, source_binder::struct_from_module(db, module, &node))
+                        }
+                        None => node.short_label(),
+                    };
+                    hover_text(node.doc_comment_text(), label)
+                })
+                .visit(|node: ast::EnumDef| {
+                    let label = match module {
+                        Some(module) => {
+                            hir_label(db, source_binder::enum_from_module(db, module, &node))
+                        }
+                        None => node.short_label(),
+                    };
+                    hover_text(node.doc_comment_text(), label)
                 })
-                .visit(|node: ast::EnumDef| hover_text(node.doc_comment_text(), node.short_label()))
                 .visit(|node: ast::EnumVariant| {
                     hover_text(node.doc_comment_text(), node.short_label())
                 })
-                .visit(|node: ast::FnDef| hover_text(node.doc_comment_text(), node.short_label()))
+                .visit(|node: ast::FnDef| {
+                    let label = match module {
+                        Some(module) => function_label(
+                            db,
+                            source_binder::function_from_module(db, module, &node),
+                        ),
+                        None => node.short_label(),
+                    };
+                    hover_text(node.doc_comment_text(), label)
+                })
                 .visit(|node: ast::TypeAliasDef| {
                     hover_text(node.doc_comment_text(), node.short_label())
                 })
                 .visit(|node: ast::ConstDef| {
-                    hover_text(node.doc_comment_text(), node.short_label())
+                    let value = node.body().and_then(|body| eval_const(db, file_id, &body, 0));
+                    let label = node.short_label().map(|label| with_const_value(label, value));
+                    hover_text(node.doc_comment_text(), label)
                 })
                 .visit(|node: ast::StaticDef| {
                     hover_text(node.doc_comment_text(), node.short_label())
                 })
                 .visit(|node: ast::TraitDef| {
-                    hover_text(node.doc_comment_text(), node.short_label())
+                    let label = match module {
+                        Some(module) => {
+                            hir_label(db, source_binder::trait_from_module(db, module, &node))
+                        }
+                        None => node.short_label(),
+                    };
+                    hover_text(node.doc_comment_text(), label)
                 })
                 .visit(|node: ast::NamedFieldDef| {
                     hover_text(node.doc_comment_text(), node.short_label())
@@ -228,15 +286,91 @@ pub(crate) fn hover(db: &RootDatabase, position: FilePosition) -> Option<RangeIn
         return None;
     }
     let res = RangeInfo::new(range, res);
-    return Some(res);
+    Some(res)
+}
+
+fn from_def_source<A, D>(db: &RootDatabase, res: &mut HoverResult, def: D)
+where
+    D: HasSource<Ast = A>,
+    A: ast::DocCommentsOwner + ast::NameOwner + ShortLabel,
+{
+    let label = def.source(db).ast.short_label();
+    from_def_source_with_label(db, res, def, label)
+}
+
+fn from_hir_display<A, D>(db: &RootDatabase, res: &mut HoverResult, def: D)
+where
+    D: HasSource<Ast = A> + HirDisplay,
+    A: ast::DocCommentsOwner + ast::VisibilityOwner,
+{
+    let label = hir_label(db, def);
+    from_def_source_with_label(db, res, def, label)
+}
+
+fn from_function(db: &RootDatabase, res: &mut HoverResult, func: hir::Function) {
+    let label = function_label(db, func);
+    from_def_source_with_label(db, res, func, label)
+}
 
-    fn from_def_source<A, D>(db: &RootDatabase, def: D) -> Option<String>
-    where
-        D: HasSource<Ast = A>,
-        A: ast::DocCommentsOwner + ast::NameOwner + ShortLabel,
-    {
-        let src = def.source(db);
-        hover_text(src.ast.doc_comment_text(), src.ast.short_label())
+fn from_const(db: &RootDatabase, res: &mut HoverResult, konst: hir::Const) {
+    let src = konst.source(db);
+    let value =
+        src.ast.body().and_then(|body| eval_const(db, src.file_id.original_file(db), &body, 0));
+    let label = src.ast.short_label().map(|label| with_const_value(label, value));
+    from_def_source_with_label(db, res, konst, label)
+}
+
+/// Adds `label` together with the docs of `def`, with intra-doc links
+/// resolved from the scope of the definition.
+fn from_def_source_with_label<A, D>(
+    db: &RootDatabase,
+    res: &mut HoverResult,
+    def: D,
+    label: Option<String>,
+) where
+    D: HasSource<Ast = A>,
+    A: ast::DocCommentsOwner,
+{
+    let src = def.source(db);
+    let docs = src.ast.doc_comment_text().map(|docs| {
+        let file_id = src.file_id.original_file(db);
+        let (docs, links) = resolve_doc_links(db, file_id, src.ast.syntax(), &docs);
+        res.doc_links.extend(links);
+        docs
+    });
+    res.extend(hover_text(docs, label));
+}
+
+/// Unlike the short label, `HirDisplay` shows generic parameters with their
+/// defaults and the where clause.
+fn hir_label<A, D>(db: &RootDatabase, def: D) -> Option<String>
+where
+    D: HasSource<Ast = A> + HirDisplay,
+    A: ast::VisibilityOwner,
+{
+    let visibility = def.source(db).ast.visibility();
+    let visibility = visibility.map(|it| format!("{} ", it.syntax())).unwrap_or_default();
+    Some(format!("{}{}", visibility, def.display(db)))
+}
+
+/// Methods of traits and trait impls are shown below the trait or impl they
+/// come from.
+fn function_label(db: &RootDatabase, func: hir::Function) -> Option<String> {
+    let label = func.source(db).ast.short_label()?;
+    let container = match func.container(db) {
+        Some(hir::Container::Trait(it)) => it.display(db).to_string(),
+        Some(hir::Container::ImplBlock(it)) if it.target_trait(db).is_some() => {
+            it.display(db).to_string()
+        }
+        _ => return Some(label),
+    };
+    Some(format!("{}\n{}", container, label))
+}
+
+fn with_const_value(label: String, value: Option<ConstValue>) -> String {
+    match value {
+        Some(value) => format!("{} = {}", label, value),
+        None => label,
     }
 }
 
@@ -249,15 +383,188 @@ pub(crate) fn type_of(db: &RootDatabase, frange: FileRange) -> Option<String> {
         .take_while(|it| it.text_range() == leaf_node.text_range())
         .find(|it| ast::Expr::cast(it.clone()).is_some() || ast::Pat::cast(it.clone()).is_some())?;
     let analyzer = hir::SourceAnalyzer::new(db, frange.file_id, &node, None);
-    let ty = if let Some(ty) = ast::Expr::cast(node.clone()).and_then(|e| analyzer.type_of(db, &e))
-    {
-        ty
-    } else if let Some(ty) = ast::Pat::cast(node).and_then(|p| analyzer.type_of_pat(db, &p)) {
-        ty
+    let (ty, value) = if let Some(expr) = ast::Expr::cast(node.clone()) {
+        let ty = analyzer.type_of(db, &expr)?;
+        // The value of a literal is already in plain sight
+        let value = match expr.kind() {
+            ast::ExprKind::Literal(_) => None,
+            _ => eval_const(db, frange.file_id, &expr, 0),
+        };
+        (ty, value)
     } else {
+        (analyzer.type_of_pat(db, &ast::Pat::cast(node)?)?, None)
+    };
+    Some(with_const_value(ty.display(db).to_string(), value))
+}
+
+/// The value of a constant expression.
+enum ConstValue {
+    Int(i128),
+    Bool(bool),
+    /// Other literals, as written in the source.
+    Other(String),
+}
+
+impl fmt::Display for ConstValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ConstValue::Int(it) => write!(f, "{}", it),
+            ConstValue::Bool(it) => write!(f, "{}", it),
+            ConstValue::Other(it) => write!(f, "{}", it),
+        }
+    }
+}
+
+/// Evaluates literals, arithmetic and references to other constants. Integers
+/// are computed in the type of the expression, and a value which overflows it
+/// is not shown.
+fn eval_const(
+    db: &RootDatabase,
+    file_id: FileId,
+    expr: &ast::Expr,
+    depth: u32,
+) -> Option<ConstValue> {
+    let value = eval_unchecked(db, file_id, expr, depth)?;
+    if let ConstValue::Int(it) = value {
+        if !fits_int_type(db, file_id, expr, it) {
+            return None;
+        }
+    }
+    Some(value)
+}
+
+fn eval_unchecked(
+    db: &RootDatabase,
+    file_id: FileId,
+    expr: &ast::Expr,
+    depth: u32,
+) -> Option<ConstValue> {
+    // Guards against constants which refer to themselves
+    if depth > 32 {
         return None;
+    }
+    let value = match expr.kind() {
+        ast::ExprKind::Literal(it) => {
+            let text = it.token().text().to_string();
+            match it.kind() {
+                ast::LiteralKind::IntNumber { suffix } => {
+                    ConstValue::Int(parse_int(&text, suffix.as_ref().map(|it| it.as_str()))?)
+                }
+                ast::LiteralKind::Bool => ConstValue::Bool(text == "true"),
+                _ => ConstValue::Other(text),
+            }
+        }
+        ast::ExprKind::ParenExpr(it) => return eval_const(db, file_id, &it.expr()?, depth + 1),
+        ast::ExprKind::PrefixExpr(it) => {
+            let operand = it.expr()?;
+            let operand = match operand.kind() {
+                // `-128i8` is fine even though `128i8` is out of range
+                ast::ExprKind::Literal(_) => eval_unchecked(db, file_id, &operand, depth + 1)?,
+                _ => eval_const(db, file_id, &operand, depth + 1)?,
+            };
+            match (it.op_kind()?, operand) {
+                (ast::PrefixOp::Neg, ConstValue::Int(it)) => ConstValue::Int(it.checked_neg()?),
+                (ast::PrefixOp::Not, ConstValue::Bool(it)) => ConstValue::Bool(!it),
+                _ => return None,
+            }
+        }
+        ast::ExprKind::BinExpr(it) => {
+            let lhs = eval_const(db, file_id, &it.lhs()?, depth + 1)?;
+            let rhs = eval_const(db, file_id, &it.rhs()?, depth + 1)?;
+            match (lhs, rhs) {
+                (ConstValue::Int(lhs), ConstValue::Int(rhs)) => {
+                    eval_int_op(it.op_kind()?, lhs, rhs)?
+                }
+                (ConstValue::Bool(lhs), ConstValue::Bool(rhs)) => {
+                    ConstValue::Bool(match it.op_kind()? {
+                        ast::BinOp::BooleanOr | ast::BinOp::BitwiseOr => lhs || rhs,
+                        ast::BinOp::BooleanAnd | ast::BinOp::BitwiseAnd => lhs && rhs,
+                        ast::BinOp::BitwiseXor | ast::BinOp::NegatedEqualityTest => lhs != rhs,
+                        ast::BinOp::EqualityTest => lhs == rhs,
+                        _ => return None,
+                    })
+                }
+                _ => return None,
+            }
+        }
+        ast::ExprKind::PathExpr(it) => {
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, it.syntax(), None);
+            let konst = match analyzer.resolve_path(db, &it.path()?)? {
+                hir::PathResolution::Def(hir::ModuleDef::Const(it))
+                | hir::PathResolution::AssocItem(hir::ImplItem::Const(it)) => it,
+                _ => return None,
+            };
+            let src = konst.source(db);
+            return eval_const(db, src.file_id.original_file(db), &src.ast.body()?, depth + 1);
+        }
+        _ => return None,
     };
-    Some(ty.display(db).to_string())
+    Some(value)
+}
+
+fn eval_int_op(op: ast::BinOp, lhs: i128, rhs: i128) -> Option<ConstValue> {
+    let value = match op {
+        ast::BinOp::Addition => lhs.checked_add(rhs)?,
+        ast::BinOp::Subtraction => lhs.checked_sub(rhs)?,
+        ast::BinOp::Multiplication => lhs.checked_mul(rhs)?,
+        ast::BinOp::Division => lhs.checked_div(rhs)?,
+        ast::BinOp::Remainder => lhs.checked_rem(rhs)?,
+        ast::BinOp::LeftShift if rhs >= 0 && rhs < 128 => lhs.checked_shl(rhs as u32)?,
+        ast::BinOp::RightShift if rhs >= 0 && rhs < 128 => lhs.checked_shr(rhs as u32)?,
+        ast::BinOp::BitwiseXor => lhs ^ rhs,
+        ast::BinOp::BitwiseOr => lhs | rhs,
+        ast::BinOp::BitwiseAnd => lhs & rhs,
+        ast::BinOp::EqualityTest => return Some(ConstValue::Bool(lhs == rhs)),
+        ast::BinOp::NegatedEqualityTest => return Some(ConstValue::Bool(lhs != rhs)),
+        ast::BinOp::LesserTest => return Some(ConstValue::Bool(lhs < rhs)),
+        ast::BinOp::LesserEqualTest => return Some(ConstValue::Bool(lhs <= rhs)),
+        ast::BinOp::GreaterTest => return Some(ConstValue::Bool(lhs > rhs)),
+        ast::BinOp::GreaterEqualTest => return Some(ConstValue::Bool(lhs >= rhs)),
+        _ => return None,
+    };
+    Some(ConstValue::Int(value))
+}
+
+fn fits_int_type(db: &RootDatabase, file_id: FileId, expr: &ast::Expr, value: i128) -> bool {
+    let analyzer = hir::SourceAnalyzer::new(db, file_id, expr.syntax(), None);
+    let int_ty = match analyzer.type_of(db, expr) {
+        Some(Ty::Apply(ApplicationTy { ctor: TypeCtor::Int(it), .. })) => it,
+        _ => return false,
+    };
+    let (signedness, bits) = match int_ty {
+        UncertainIntTy::Known(it) => {
+            let bits = match it.bitness {
+                IntBitness::X8 => 8,
+                IntBitness::X16 => 16,
+                IntBitness::X32 => 32,
+                // FIXME: use the pointer width of the target
+                IntBitness::X64 | IntBitness::Xsize => 64,
+                IntBitness::X128 => 128,
+            };
+            (it.signedness, bits)
+        }
+        // Literals of an unconstrained type are `i32`
+        UncertainIntTy::Unknown => (Signedness::Signed, 32),
+    };
+    let (min, max) = match signedness {
+        Signedness::Signed => {
+            (i128::min_value() >> (128 - bits), i128::max_value() >> (128 - bits))
+        }
+        Signedness::Unsigned if bits == 128 => (0, i128::max_value()),
+        Signedness::Unsigned => (0, (1 << bits) - 1),
+    };
+    min <= value && value <= max
+}
+
+fn parse_int(text: &str, suffix: Option<&str>) -> Option<i128> {
+    let text = text.trim_end_matches(suffix.unwrap_or("")).replace('_', "");
+    let (digits, radix) = match text.get(..2) {
+        Some("0x") => (&text[2..], 16),
+        Some("0o") => (&text[2..], 8),
+        Some("0b") => (&text[2..], 2),
+        _ => (&text[..], 10),
+    };
+    i128::from_str_radix(digits, radix).ok()
 }
 
 #[cfg(test)]
@@ -421,7 +728,7 @@ mod tests {
                 const foo<|>: u32 = 0;
             }
         "#,
-            &["const foo: u32"],
+            &["const foo: u32 = 0"],
         );
 
         check_hover_result(
@@ -435,6 +742,132 @@ mod tests {
         );
     }
 
+    #[test]
+    fn hover_const_value() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const A: u32 = 0x10 >> 2;
+            const B: u32 = A * (3 + 1);
+            fn main() {
+                let b = B<|>;
+            }
+        "#,
+            &["const B: u32 = 16"],
+        );
+    }
+
+    #[test]
+    fn hover_const_value_in_declared_type() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const A: i8 = -128;
+            const B: i8 = A + 1;
+            fn main() {
+                let b = B<|>;
+            }
+        "#,
+            &["const B: i8 = -127"],
+        );
+
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const A: u8 = 200 + 100;
+            fn main() {
+                let a = A<|>;
+            }
+        "#,
+            &["const A: u8"],
+        );
+
+        check_hover_result(
+            r#"
+            //- /main.rs
+            const A: u32 = 1 - 2;
+            fn main() {
+                let a = A<|>;
+            }
+        "#,
+            &["const A: u32"],
+        );
+    }
+
+    #[test]
+    fn hover_shows_generic_defaults_and_where_clause() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            trait Tr {}
+            pub struct Foo<'a, T: Tr, U = u32> where U: Tr { t: &'a T, u: U }
+            fn main() {
+                let foo: Fo<|>o<(), u32>;
+            }
+        "#,
+            &["pub struct Foo<'a, T, U = u32>\nwhere T: Tr,\n      U: Tr"],
+        );
+    }
+
+    #[test]
+    fn hover_shows_trait_of_method() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            trait Shape<T> {
+                fn ar<|>ea(&self) -> T;
+            }
+        "#,
+            &["trait Shape<T>\nfn area(&self) -> T"],
+        );
+
+        check_hover_result(
+            r#"
+            //- /main.rs
+            trait Shape<T> {
+                fn area(&self) -> T;
+            }
+            struct Square;
+            impl Shape<u32> for Square {
+                fn ar<|>ea(&self) -> u32 { 0 }
+            }
+        "#,
+            &["impl Shape<u32> for Square\nfn area(&self) -> u32"],
+        );
+    }
+
+    #[test]
+    fn hover_resolves_intra_doc_links() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /main.rs
+            mod bar {
+                pub struct Baz;
+            }
+            /// Uses [`bar::Baz`] and [crate::bar::Baz], but not [Missing].
+            struct Foo;
+            fn main() {
+                let foo = Fo<|>o;
+            }
+            ",
+        );
+        let hover = analysis.hover(position).unwrap().unwrap();
+        assert_eq!(
+            trim_markup_opt(hover.info.first()),
+            Some(
+                "struct Foo\n```\n\nUses [`bar::Baz`](bar::Baz) and \
+                 [crate::bar::Baz](crate::bar::Baz), but not [Missing]."
+            )
+        );
+        let links = hover
+            .info
+            .doc_links()
+            .iter()
+            .map(|it| format!("{} {}", it.target, it.nav.name()))
+            .collect::<Vec<_>>();
+        assert_eq!(links, vec!["bar::Baz Baz", "crate::bar::Baz Baz"]);
+    }
+
     #[test]
     fn hover_some() {
         let (analysis, position) = single_file_with_position(
@@ -560,6 +993,21 @@ fn func(foo: i32) { if true { <|>foo; }; }
         assert_eq!("usize", &type_name);
     }
 
+    #[test]
+    fn test_type_of_for_const_expr() {
+        let (analysis, range) = single_file_with_range(
+            "
+            const A: u32 = 2;
+            fn main() {
+                let bar = <|>(A + 1) * 2 == 6<|>;
+            }
+            ",
+        );
+
+        let type_name = analysis.type_of(range).unwrap().unwrap();
+        assert_eq!("bool = true", &type_name);
+    }
+
     #[test]
     fn test_hover_infer_associated_method_result() {
         let (analysis, position) = single_file_with_position(
@@ -622,7 +1070,7 @@ fn func(foo: i32) { if true { <|>foo; }; }
             ",
         );
         let hover = analysis.hover(position).unwrap().unwrap();
-        assert_eq!(trim_markup_opt(hover.info.first()), Some("const C: u32"));
+        assert_eq!(trim_markup_opt(hover.info.first()), Some("const C: u32 = 1"));
         assert_eq!(hover.info.is_exact(), true);
     }
 
@@ -700,4 +1148,22 @@ fn func(foo: i32) { if true { <|>foo; }; }
         assert_eq!(trim_markup_opt(hover.info.first()), Some("i32"));
         assert_eq!(hover.info.is_exact(), true);
     }
+
+    #[test]
+    fn hover_shows_fn_signature_in_macro_arguments() {
+        check_hover_result(
+            r#"
+            //- /main.rs
+            macro_rules! id {
+                ($($tt:tt)*) => { $($tt)* };
+            }
+            pub fn foo() -> u32 { 1 }
+
+            fn main() {
+                let foo_test = id!(fo<|>o());
+            }
+        "#,
+            &["pub fn foo() -> u32"],
+        );
+    }
 }
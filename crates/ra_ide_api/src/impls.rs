@@ -1,4 +1,6 @@
-use hir::{db::HirDatabase, source_binder};
+use std::iter;
+
+use hir::{db::HirDatabase, source_binder, HasSource, ImplItem, TraitItem};
 use ra_db::SourceDatabase;
 use ra_syntax::{algo::find_node_at_offset, ast, AstNode};
 
@@ -13,6 +15,17 @@ pub(crate) fn goto_implementation(
 
     let module = source_binder::module_from_position(db, position)?;
 
+    if let Some(item) = find_node_at_offset::<ast::ImplItem>(&syntax, position.offset) {
+        let trait_def =
+            item.syntax().parent().and_then(|it| it.parent()).and_then(ast::TraitDef::cast);
+        if let Some(trait_def) = trait_def {
+            return Some(RangeInfo::new(
+                item.syntax().text_range(),
+                impls_for_trait_item(db, &trait_def, &item, module)?,
+            ));
+        }
+    }
+
     if let Some(nominal_def) = find_node_at_offset::<ast::NominalDef>(&syntax, position.offset) {
         return Some(RangeInfo::new(
             nominal_def.syntax().text_range(),
@@ -71,6 +84,69 @@ fn impls_for_trait(
     )
 }
 
+/// Finds the items which implement the trait item `node` in the impls of its
+/// trait.
+fn impls_for_trait_item(
+    db: &RootDatabase,
+    trait_def: &ast::TraitDef,
+    node: &ast::ImplItem,
+    module: hir::Module,
+) -> Option<Vec<NavigationTarget>> {
+    let tr = source_binder::trait_from_module(db, module, trait_def);
+    let range = node.syntax().text_range();
+    let item = tr.items(db).into_iter().find(|item| {
+        let item_range = match *item {
+            TraitItem::Function(it) => it.source(db).ast.syntax().text_range(),
+            TraitItem::Const(it) => it.source(db).ast.syntax().text_range(),
+            TraitItem::TypeAlias(it) => it.source(db).ast.syntax().text_range(),
+        };
+        item_range == range
+    })?;
+
+    Some(
+        trait_item_impls(db, tr, item)
+            .into_iter()
+            .map(|item| NavigationTarget::from_impl_item(db, item))
+            .collect(),
+    )
+}
+
+/// Returns the impls of the trait in its own crate and in the crates which
+/// depend on it.
+pub(crate) fn trait_impls(db: &RootDatabase, tr: hir::Trait) -> Vec<hir::ImplBlock> {
+    let krate = match tr.module(db).krate(db) {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    iter::once(krate)
+        .chain(krate.reverse_dependencies(db))
+        .flat_map(|krate| {
+            db.impls_in_crate(krate).lookup_impl_blocks_for_trait(tr).collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Returns the items which implement `item` of the trait `tr`. Like rustc,
+/// they are matched by kind and name.
+pub(crate) fn trait_item_impls(
+    db: &RootDatabase,
+    tr: hir::Trait,
+    item: TraitItem,
+) -> Vec<ImplItem> {
+    trait_impls(db, tr)
+        .into_iter()
+        .flat_map(|imp| imp.items(db))
+        .filter(|impl_item| match (item, *impl_item) {
+            (TraitItem::Function(it), ImplItem::Method(imp)) => it.name(db) == imp.name(db),
+            (TraitItem::Const(it), ImplItem::Const(imp)) => {
+                it.data(db).name() == imp.data(db).name()
+            }
+            (TraitItem::TypeAlias(it), ImplItem::TypeAlias(imp)) => it.name(db) == imp.name(db),
+            _ => false,
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use crate::mock_analysis::analysis_and_position;
@@ -173,4 +249,50 @@ mod tests {
             &["impl IMPL_BLOCK FileId(2) [0; 31)", "impl IMPL_BLOCK FileId(3) [0; 31)"],
         );
     }
+
+    #[test]
+    fn goto_implementation_for_trait_method() {
+        check_goto(
+            "
+            //- /lib.rs
+            trait T {
+                fn foo<|>(&self);
+                fn bar(&self);
+            }
+            struct Foo;
+            impl T for Foo {
+                fn foo(&self) {}
+                fn bar(&self) {}
+            }
+            mod a {
+                impl super::T for () {
+                    fn bar(&self) {}
+                    fn foo(&self) {}
+                }
+            }
+            ",
+            &[
+                "foo FN_DEF FileId(1) [83; 99) [86; 89)",
+                "foo FN_DEF FileId(1) [191; 207) [194; 197)",
+            ],
+        );
+    }
+
+    #[test]
+    fn goto_implementation_for_trait_method_in_dependent_crate() {
+        check_goto(
+            "
+            //- /lib.rs
+            struct Foo;
+            impl foo::T for Foo {
+                fn foo(&self) {}
+            }
+            //- /foo/lib.rs
+            pub trait T {
+                fn foo<|>(&self);
+            }
+            ",
+            &["foo FN_DEF FileId(1) [37; 53) [40; 43)"],
+        );
+    }
 }
@@ -19,18 +19,27 @@ mod status;
 mod completion;
 mod runnables;
 mod name_ref_kind;
+mod goto_declaration;
 mod goto_definition;
 mod goto_type_definition;
 mod extend_selection;
 mod hover;
 mod call_info;
+mod change_signature;
+mod call_hierarchy;
+mod doc_links;
+mod document_highlight;
+mod expand;
+mod expand_macro;
 mod syntax_highlighting;
 mod parent_module;
 mod references;
 mod impls;
+mod type_hierarchy;
 mod assists;
 mod diagnostics;
 mod syntax_tree;
+mod ssr;
 mod folding_ranges;
 mod line_index;
 mod line_index_utils;
@@ -59,18 +68,28 @@ use crate::{db::LineIndexDatabase, symbol_index::FileSymbol};
 
 pub use crate::{
     assists::{Assist, AssistId},
+    call_hierarchy::CallItem,
     change::{AnalysisChange, LibraryData},
+    change_signature::{ChangeSignatureError, SignatureParam},
     completion::{CompletionItem, CompletionItemKind, InsertTextFormat},
     diagnostics::Severity,
-    display::{file_structure, FunctionSignature, NavigationTarget, StructureNode},
+    display::{file_structure, CallableKind, FunctionSignature, NavigationTarget, StructureNode},
+    doc_links::DocLink,
+    document_highlight::{DocumentHighlight, ReferenceAccess},
+    expand_macro::ExpandedMacro,
     folding_ranges::{Fold, FoldKind},
     hover::HoverResult,
     inlay_hints::{InlayHint, InlayKind},
     line_index::{LineCol, LineIndex},
     line_index_utils::translate_offset_with_edit,
     references::ReferenceSearchResult,
-    runnables::{Runnable, RunnableKind},
-    syntax_highlighting::HighlightedRange,
+    runnables::{Runnable, RunnableKind, TestId},
+    ssr::SsrError,
+    symbol_index::SymbolKind,
+    syntax_highlighting::{
+        Highlight, HighlightModifier, HighlightModifiers, HighlightTag, HighlightedRange,
+    },
+    type_hierarchy::TypeHierarchy,
 };
 
 pub use hir::Documentation;
@@ -169,7 +188,7 @@ pub struct SourceFileEdit {
 
 #[derive(Debug)]
 pub enum FileSystemEdit {
-    CreateFile { source_root: SourceRootId, path: RelativePathBuf },
+    CreateFile { source_root: SourceRootId, path: RelativePathBuf, initial_contents: String },
     MoveFile { src: FileId, dst_source_root: SourceRootId, dst_path: RelativePathBuf },
 }
 
@@ -185,21 +204,31 @@ pub struct Diagnostic {
 pub struct Query {
     query: String,
     lowercased: String,
+    /// The lowercased segments before the name in a qualified query like
+    /// `module::Type::method`.
+    qualifier: Vec<String>,
     only_types: bool,
     libs: bool,
     exact: bool,
+    kinds: Vec<SymbolKind>,
     limit: usize,
 }
 
 impl Query {
     pub fn new(query: String) -> Query {
-        let lowercased = query.to_lowercase();
+        let mut segments: Vec<&str> = query.split("::").map(|it| it.trim()).collect();
+        let name = segments.pop().unwrap_or_default().to_string();
+        let qualifier =
+            segments.into_iter().filter(|it| !it.is_empty()).map(|it| it.to_lowercase()).collect();
+        let lowercased = name.to_lowercase();
         Query {
-            query,
+            query: name,
             lowercased,
+            qualifier,
             only_types: false,
             libs: false,
             exact: false,
+            kinds: Vec::new(),
             limit: usize::max_value(),
         }
     }
@@ -216,6 +245,11 @@ impl Query {
         self.exact = true;
     }
 
+    /// Restricts the search to symbols of the given kinds.
+    pub fn kinds(&mut self, kinds: &[SymbolKind]) {
+        self.kinds.extend_from_slice(kinds);
+    }
+
     pub fn limit(&mut self, limit: usize) {
         self.limit = limit
     }
@@ -237,6 +271,9 @@ impl<T> RangeInfo<T> {
 pub struct CallInfo {
     pub signature: FunctionSignature,
     pub active_parameter: Option<usize>,
+    /// Whether this is about the generic arguments of the call rather than
+    /// the regular ones
+    generic_args: bool,
 }
 
 /// `AnalysisHost` stores the current state of the world.
@@ -342,6 +379,12 @@ impl Analysis {
         self.with_db(|db| extend_selection::extend_selection(db, frange))
     }
 
+    /// Returns the ranges selected by repeatedly extending the selection from
+    /// the position, starting with the empty range at the position.
+    pub fn selection_ranges(&self, position: FilePosition) -> Cancelable<Vec<TextRange>> {
+        self.with_db(|db| extend_selection::selection_ranges(db, position))
+    }
+
     /// Returns position of the matching brace (all types of braces are
     /// supported).
     pub fn matching_brace(&self, position: FilePosition) -> Cancelable<Option<TextUnit>> {
@@ -362,6 +405,12 @@ impl Analysis {
         self.with_db(|db| syntax_tree::syntax_tree(&db, file_id, text_range))
     }
 
+    /// Expands the macro call at the position, together with the macro calls
+    /// in its expansion, and pretty-prints the result.
+    pub fn expand_macro(&self, position: FilePosition) -> Cancelable<Option<ExpandedMacro>> {
+        self.with_db(|db| expand_macro::expand_macro(db, position))
+    }
+
     /// Returns an edit to remove all newlines in the range, cleaning up minor
     /// stuff like trailing commas.
     pub fn join_lines(&self, frange: FileRange) -> Cancelable<SourceChange> {
@@ -407,6 +456,12 @@ impl Analysis {
         self.with_db(|db| file_structure(&db.parse(file_id).tree()))
     }
 
+    /// Like `file_structure`, but also includes the items produced by macro
+    /// calls in the file.
+    pub fn file_structure_with_macros(&self, file_id: FileId) -> Cancelable<Vec<StructureNode>> {
+        self.with_db(|db| display::file_structure_with_macros(db, file_id))
+    }
+
     /// Returns a list of the places in the file where type hints can be displayed.
     pub fn inlay_hints(&self, file_id: FileId) -> Cancelable<Vec<InlayHint>> {
         self.with_db(|db| inlay_hints::inlay_hints(db, file_id, &db.parse(file_id).tree()))
@@ -434,6 +489,15 @@ impl Analysis {
         self.with_db(|db| goto_definition::goto_definition(db, position))
     }
 
+    /// Returns the declaration of the item at `position`, which is the item of
+    /// the trait for the items of trait impls.
+    pub fn goto_declaration(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<Vec<NavigationTarget>>>> {
+        self.with_db(|db| goto_declaration::goto_declaration(db, position))
+    }
+
     pub fn goto_implementation(
         &self,
         position: FilePosition,
@@ -441,6 +505,15 @@ impl Analysis {
         self.with_db(|db| impls::goto_implementation(db, position))
     }
 
+    /// Returns the supertraits and implementing types of the trait, or the
+    /// implemented traits of the type at the position.
+    pub fn type_hierarchy(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<TypeHierarchy>>> {
+        self.with_db(|db| type_hierarchy::type_hierarchy(db, position))
+    }
+
     pub fn goto_type_definition(
         &self,
         position: FilePosition,
@@ -456,11 +529,39 @@ impl Analysis {
         self.with_db(|db| references::find_all_refs(db, position))
     }
 
+    /// Finds the occurrences of the symbol at the position in the same file,
+    /// classified as its definition, reads or writes.
+    pub fn document_highlight(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<Vec<DocumentHighlight>>> {
+        self.with_db(|db| document_highlight::document_highlight(db, position))
+    }
+
     /// Returns a short text describing element at position.
     pub fn hover(&self, position: FilePosition) -> Cancelable<Option<RangeInfo<HoverResult>>> {
         self.with_db(|db| hover::hover(db, position))
     }
 
+    /// Returns the function at the position, which is the root of the call
+    /// hierarchy.
+    pub fn call_hierarchy(
+        &self,
+        position: FilePosition,
+    ) -> Cancelable<Option<RangeInfo<Vec<NavigationTarget>>>> {
+        self.with_db(|db| call_hierarchy::call_hierarchy(db, position))
+    }
+
+    /// Finds all functions which call the function at the position.
+    pub fn incoming_calls(&self, position: FilePosition) -> Cancelable<Option<Vec<CallItem>>> {
+        self.with_db(|db| call_hierarchy::incoming_calls(db, position))
+    }
+
+    /// Finds all functions which are called by the function at the position.
+    pub fn outgoing_calls(&self, position: FilePosition) -> Cancelable<Option<Vec<CallItem>>> {
+        self.with_db(|db| call_hierarchy::outgoing_calls(db, position))
+    }
+
     /// Computes parameter information for the given call expression.
     pub fn call_info(&self, position: FilePosition) -> Cancelable<Option<CallInfo>> {
         self.with_db(|db| call_info::call_info(db, position))
@@ -482,8 +583,14 @@ impl Analysis {
     }
 
     /// Returns the set of possible targets to run for the current file.
-    pub fn runnables(&self, file_id: FileId) -> Cancelable<Vec<Runnable>> {
-        self.with_db(|db| runnables::runnables(db, file_id))
+    /// Functions with one of `test_attributes`, like `tokio::test`, are
+    /// considered tests in addition to `#[test]` ones.
+    pub fn runnables(
+        &self,
+        file_id: FileId,
+        test_attributes: &[String],
+    ) -> Cancelable<Vec<Runnable>> {
+        self.with_db(|db| runnables::runnables(db, file_id, test_attributes))
     }
 
     /// Computes syntax highlighting for the given file.
@@ -527,6 +634,27 @@ impl Analysis {
         self.with_db(|db| references::rename(db, position, new_name))
     }
 
+    /// Returns the edit which changes the parameters of the function at the
+    /// position, or called at the position, to `new_params`, and updates its
+    /// call sites.
+    pub fn change_signature(
+        &self,
+        position: FilePosition,
+        new_params: &[SignatureParam],
+    ) -> Cancelable<Result<Option<SourceChange>, ChangeSignatureError>> {
+        self.with_db(|db| change_signature::change_signature(db, position, new_params))
+    }
+
+    /// Replaces all code in the workspace which matches the structural search
+    /// and replace rule `query`, like `foo($a, $b) ==>> $a.foo($b)`.
+    pub fn structural_search_replace(
+        &self,
+        query: &str,
+        resolve_paths: bool,
+    ) -> Cancelable<Result<SourceChange, SsrError>> {
+        self.with_db(|db| ssr::structural_search_replace(db, query, resolve_paths))
+    }
+
     fn with_db<F: FnOnce(&db::RootDatabase) -> T + std::panic::UnwindSafe, T>(
         &self,
         f: F,
@@ -1,4 +1,4 @@
-use hir::Either;
+use hir::{Either, Source};
 use ra_syntax::{ast, AstNode, AstPtr};
 use test_utils::tested_by;
 
@@ -94,3 +94,25 @@ pub(crate) fn classify_name_ref(
 
     None
 }
+
+/// Classifies a name reference from a macro expansion in the scope of
+/// `analyzer`. Method calls and field accesses are resolved in the expansion,
+/// other names as if they were written at the macro call.
+pub(crate) fn classify_expanded_name_ref(
+    db: &RootDatabase,
+    analyzer: &hir::SourceAnalyzer,
+    name_ref: Source<&ast::NameRef>,
+) -> Option<NameRefKind> {
+    use NameRefKind::*;
+
+    let parent = name_ref.ast.syntax().parent()?;
+    if let Some(call) = ast::MethodCallExpr::cast(parent.clone()) {
+        let call = Source { file_id: name_ref.file_id, ast: &call };
+        return analyzer.resolve_expanded_method_call(call).map(Method);
+    }
+    if let Some(field) = ast::FieldExpr::cast(parent) {
+        let field = Source { file_id: name_ref.file_id, ast: &field };
+        return analyzer.resolve_expanded_field(field).map(FieldAccess);
+    }
+    classify_name_ref(db, analyzer, name_ref.ast)
+}
@@ -1,11 +1,20 @@
-use hir::{source_binder, Either, ModuleSource};
+use std::iter;
+
+use hir::{search, source_binder, Either, ModuleSource};
 use ra_db::SourceDatabase;
-use ra_syntax::{algo::find_node_at_offset, ast, AstNode, SourceFile, SyntaxNode};
+use ra_syntax::{
+    algo::find_node_at_offset, ast, AstNode, SourceFile, SyntaxKind::NAMED_FIELD_DEF, SyntaxNode,
+};
 use relative_path::{RelativePath, RelativePathBuf};
 
 use crate::{
-    db::RootDatabase, FileId, FilePosition, FileRange, FileSystemEdit, NavigationTarget,
-    SourceChange, SourceFileEdit, TextRange,
+    db::RootDatabase,
+    goto_definition::{item_definition, name_definition},
+    impls::trait_item_impls,
+    name_ref_kind::{classify_name_ref, NameRefKind},
+    symbol_index::SymbolsDatabase,
+    FileId, FilePosition, FileRange, FileSystemEdit, NavigationTarget, SourceChange,
+    SourceFileEdit, TextRange,
 };
 
 #[derive(Debug, Clone)]
@@ -49,8 +58,22 @@ pub(crate) fn find_all_refs(
     db: &RootDatabase,
     position: FilePosition,
 ) -> Option<ReferenceSearchResult> {
+    find_refs(db, position).map(|(refs, _)| refs)
+}
+
+/// Finds the references to the binding or the item at `position`, and the
+/// declarations of the other methods of the trait method family it belongs
+/// to. These declarations are renamed along with the references, but they
+/// aren't references themselves.
+fn find_refs(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<(ReferenceSearchResult, Vec<FileRange>)> {
     let parse = db.parse(position.file_id);
-    let (binding, analyzer) = find_binding(db, &parse.tree(), position)?;
+    let (binding, analyzer) = match find_binding(db, &parse.tree(), position) {
+        Some(it) => it,
+        None => return find_item_refs(db, &parse.tree(), position),
+    };
     let declaration = NavigationTarget::from_bind_pat(position.file_id, &binding);
 
     let references = analyzer
@@ -59,7 +82,7 @@ pub(crate) fn find_all_refs(
         .map(move |ref_desc| FileRange { file_id: position.file_id, range: ref_desc.range })
         .collect::<Vec<_>>();
 
-    return Some(ReferenceSearchResult { declaration, references });
+    return Some((ReferenceSearchResult { declaration, references }, Vec::new()));
 
     fn find_binding<'a>(
         db: &RootDatabase,
@@ -83,6 +106,156 @@ pub(crate) fn find_all_refs(
     }
 }
 
+/// Finds the references to the item at `position` in the local workspace. The
+/// references to a trait method and to the methods implementing it are the
+/// references to each of them.
+fn find_item_refs(
+    db: &RootDatabase,
+    source_file: &SourceFile,
+    position: FilePosition,
+) -> Option<(ReferenceSearchResult, Vec<FileRange>)> {
+    let (syntax, file_id, offset) = (source_file.syntax(), position.file_id, position.offset);
+    let (declaration, func) = if let Some(name) = find_node_at_offset::<ast::Name>(syntax, offset) {
+        let declaration = name_definition(db, file_id, &name)?.into_iter().next()?;
+        let func = name.syntax().parent().and_then(ast::FnDef::cast).and_then(|fn_def| {
+            let module = source_binder::module_from_child_node(db, file_id, fn_def.syntax())?;
+            Some(source_binder::function_from_module(db, module, &fn_def))
+        });
+        (declaration, func)
+    } else {
+        let name_ref = find_node_at_offset::<ast::NameRef>(syntax, offset)?;
+        let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
+        let kind = classify_name_ref(db, &analyzer, &name_ref)?;
+        let func = match kind {
+            NameRefKind::Method(it)
+            | NameRefKind::AssocItem(hir::ImplItem::Method(it))
+            | NameRefKind::Def(hir::ModuleDef::Function(it)) => Some(it),
+            _ => None,
+        };
+        (item_definition(db, &kind)?, func)
+    };
+
+    let target = |nav: &NavigationTarget| (nav.file_id(), nav.range());
+    let mut targets = vec![target(&declaration)];
+    let mut family = Vec::new();
+    for func in func.map(|it| trait_method_family(db, it)).unwrap_or_default() {
+        let nav = NavigationTarget::from_def_source(db, func);
+        if !targets.contains(&target(&nav)) {
+            family.push(FileRange { file_id: nav.file_id(), range: nav.range() });
+            targets.push(target(&nav));
+        }
+    }
+
+    let name = declaration.name();
+    let is_target =
+        |nav: Option<NavigationTarget>| nav.map_or(false, |nav| targets.contains(&target(&nav)));
+    let mut references: Vec<_> =
+        find_name_refs(db, name, |kind| is_target(item_definition(db, &kind)))
+            .into_iter()
+            .map(|(file_id, name_ref)| FileRange { file_id, range: name_ref.syntax().text_range() })
+            .collect();
+    if declaration.kind() == NAMED_FIELD_DEF {
+        references.extend(find_shorthand_field_pats(db, name, |field| {
+            is_target(Some(NavigationTarget::from_field(db, field)))
+        }));
+    }
+
+    Some((ReferenceSearchResult { declaration, references }, family))
+}
+
+/// Returns the trait method which `func` is or implements, together with all
+/// the methods implementing it.
+fn trait_method_family(db: &RootDatabase, func: hir::Function) -> Vec<hir::Function> {
+    let tr = match func.container(db) {
+        Some(hir::Container::Trait(it)) => it,
+        Some(hir::Container::ImplBlock(it)) => match it.target_trait_ref(db) {
+            Some(trait_ref) => trait_ref.trait_,
+            None => return Vec::new(),
+        },
+        None => return Vec::new(),
+    };
+    let name = func.name(db);
+    let trait_func = tr.items(db).into_iter().find_map(|item| match item {
+        hir::TraitItem::Function(it) if it.name(db) == name => Some(it),
+        _ => None,
+    });
+    let trait_func = match trait_func {
+        Some(it) => it,
+        None => return Vec::new(),
+    };
+    let impl_funcs = trait_item_impls(db, tr, hir::TraitItem::Function(trait_func))
+        .into_iter()
+        .filter_map(|item| match item {
+            hir::ImplItem::Method(it) => Some(it),
+            _ => None,
+        });
+    iter::once(trait_func).chain(impl_funcs).collect()
+}
+
+/// Finds all name references in the local workspace which are spelled as
+/// `name` and resolve to something accepted by `is_target`.
+///
+/// Files are filtered by text first, so only the candidate name refs are
+/// resolved through `hir`.
+pub(crate) fn find_name_refs(
+    db: &RootDatabase,
+    name: &str,
+    mut is_target: impl FnMut(NameRefKind) -> bool,
+) -> Vec<(FileId, ast::NameRef)> {
+    search::find_name_refs(db, local_files(db), name)
+        .into_iter()
+        .filter(|(file_id, name_ref)| {
+            let analyzer = hir::SourceAnalyzer::new(db, *file_id, name_ref.syntax(), None);
+            classify_name_ref(db, &analyzer, name_ref).map_or(false, &mut is_target)
+        })
+        .collect()
+}
+
+/// Finds the bindings of struct patterns like `S { x }`, which refer to the
+/// field `name` without a name ref.
+fn find_shorthand_field_pats(
+    db: &RootDatabase,
+    name: &str,
+    mut is_target: impl FnMut(hir::StructField) -> bool,
+) -> Vec<FileRange> {
+    let mut res = Vec::new();
+    for file_id in search::files_containing(db, local_files(db), name) {
+        let parse = db.parse(file_id);
+        let bind_pats = parse
+            .tree()
+            .syntax()
+            .descendants()
+            .filter_map(ast::FieldPatList::cast)
+            .flat_map(|it| it.bind_pats())
+            .filter_map(|it| Some((it.name()?, it)))
+            .filter(|(it, _)| it.text().as_str() == name);
+        for (pat_name, bind_pat) in bind_pats {
+            let struct_pat = match bind_pat.syntax().ancestors().find_map(ast::StructPat::cast) {
+                Some(it) => it,
+                None => continue,
+            };
+            let analyzer = hir::SourceAnalyzer::new(db, file_id, struct_pat.syntax(), None);
+            let field = analyzer.resolve_struct_pattern(&struct_pat).and_then(|variant| {
+                variant.fields(db).into_iter().find(|it| it.name(db).to_string() == name)
+            });
+            if field.map_or(false, &mut is_target) {
+                res.push(FileRange { file_id, range: pat_name.syntax().text_range() });
+            }
+        }
+    }
+    res
+}
+
+/// Returns the files of the local source roots, that is of the workspace
+/// without the libraries.
+pub(crate) fn local_files(db: &RootDatabase) -> Vec<FileId> {
+    let mut res = Vec::new();
+    for &root in db.local_roots().iter() {
+        res.extend(db.source_root(root).files.values().copied());
+    }
+    res
+}
+
 pub(crate) fn rename(
     db: &RootDatabase,
     position: FilePosition,
@@ -180,11 +353,13 @@ fn rename_reference(
     position: FilePosition,
     new_name: &str,
 ) -> Option<SourceChange> {
-    let refs = find_all_refs(db, position)?;
+    let (refs, family) = find_refs(db, position)?;
+    let renames_field = refs.declaration().kind() == NAMED_FIELD_DEF;
 
     let edit = refs
         .into_iter()
-        .map(|range| source_edit_from_fileid_range(range.file_id, range.range, new_name))
+        .chain(family)
+        .map(|range| rename_edit(db, range, renames_field, new_name))
         .collect::<Vec<_>>();
 
     if edit.is_empty() {
@@ -194,6 +369,52 @@ fn rename_reference(
     Some(SourceChange::source_file_edits("rename", edit))
 }
 
+/// Renames the name at `frange`. Shorthands like `S { x }` are expanded,
+/// because the field and the binding no longer have the same name.
+fn rename_edit(
+    db: &RootDatabase,
+    frange: FileRange,
+    renames_field: bool,
+    new_name: &str,
+) -> SourceFileEdit {
+    let parse = db.parse(frange.file_id);
+    let syntax = parse.tree().syntax().clone();
+    let mut range = frange.range;
+    let mut text = new_name.to_string();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(&syntax, range.start()) {
+        let is_shorthand = name_ref.syntax().text_range() == range
+            && name_ref
+                .syntax()
+                .parent()
+                .and_then(ast::NamedField::cast)
+                .map_or(false, |it| it.expr().is_none());
+        if is_shorthand {
+            text = if renames_field {
+                format!("{}: {}", new_name, name_ref.text())
+            } else {
+                format!("{}: {}", name_ref.text(), new_name)
+            };
+        }
+    } else if let Some(bind_pat) = find_node_at_offset::<ast::BindPat>(&syntax, range.start()) {
+        let is_shorthand = bind_pat.syntax().parent().and_then(ast::FieldPatList::cast).is_some();
+        if let (true, Some(name)) = (is_shorthand, bind_pat.name()) {
+            if name.syntax().text_range() == range {
+                // `ref x` becomes `new: ref x` or `x: ref new`
+                let pat_range = bind_pat.syntax().text_range();
+                let pat_text = bind_pat.syntax().text().to_string();
+                let prefix = &pat_text[..(range.start() - pat_range.start()).to_usize()];
+                text = if renames_field {
+                    format!("{}: {}", new_name, pat_text)
+                } else {
+                    format!("{}: {}{}", name.text(), prefix, new_name)
+                };
+                range = pat_range;
+            }
+        }
+    }
+    source_edit_from_fileid_range(frange.file_id, range, &text)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::{
@@ -244,6 +465,63 @@ mod tests {
         assert_eq!(refs.len(), 2);
     }
 
+    #[test]
+    fn test_find_all_refs_for_fn_across_files() {
+        let (analysis, position) = analysis_and_position(
+            "
+            //- /lib.rs
+            mod foo;
+            pub fn bar<|>() {}
+            fn main() { bar(); foo::baz(); }
+            //- /foo.rs
+            fn bar() {}
+            pub fn baz() { crate::bar(); bar(); }
+            ",
+        );
+        let refs = analysis.find_all_refs(position).unwrap().unwrap();
+        assert_eq!(format!("{:?}", refs.declaration().range()), "[16; 19)");
+        let mut refs = refs
+            .references()
+            .iter()
+            .map(|it| format!("{:?} {:?}", it.file_id, it.range))
+            .collect::<Vec<_>>();
+        refs.sort();
+        assert_eq!(refs, vec!["FileId(1) [37; 40)", "FileId(2) [34; 37)"]);
+    }
+
+    #[test]
+    fn test_find_all_refs_for_fields_and_methods() {
+        let code = r#"
+    struct Foo { x: u32 }
+    impl Foo { fn get(&self) -> u32 { self.x } }
+    fn main() {
+        let foo = Foo { x: 92 };
+        foo.get<|>() + foo.x + foo.get();
+    }"#;
+        assert_eq!(get_all_refs(code).len(), 3);
+        let code = code.replace("foo.x", "foo.x<|>").replace("get<|>", "get");
+        assert_eq!(get_all_refs(&code).len(), 4);
+    }
+
+    #[test]
+    fn test_find_all_refs_for_trait_method_leaves_out_impls() {
+        let code = r#"
+    trait Foo {
+        fn foo<|>(&self);
+    }
+    struct S;
+    impl Foo for S {
+        fn foo(&self) {}
+    }
+    fn main() {
+        S.foo();
+        Foo::foo(&S);
+    }"#;
+        let refs = get_all_refs(code);
+        let refs = refs.references().iter().map(|it| format!("{:?}", it.range)).collect::<Vec<_>>();
+        assert_eq!(refs, vec!["[138; 141)", "[158; 161)"]);
+    }
+
     fn get_all_refs(text: &str) -> ReferenceSearchResult {
         let (analysis, position) = single_file_with_position(text);
         analysis.find_all_refs(position).unwrap().unwrap()
@@ -325,6 +603,60 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_rename_trait_method_from_call() {
+        test_rename(
+            r#"
+    trait Foo {
+        fn foo(&self);
+    }
+    struct S;
+    impl Foo for S {
+        fn foo(&self) {}
+    }
+    fn main() {
+        S.foo<|>();
+    }"#,
+            "bar",
+            r#"
+    trait Foo {
+        fn bar(&self);
+    }
+    struct S;
+    impl Foo for S {
+        fn bar(&self) {}
+    }
+    fn main() {
+        S.bar();
+    }"#,
+        );
+    }
+
+    #[test]
+    fn test_rename_field_with_shorthands() {
+        test_rename(
+            r#"
+    struct S {
+        x<|>: u32,
+    }
+    fn foo(x: u32) -> u32 {
+        let S { x } = S { x };
+        let S { ref x } = S { x: 1 };
+        *x
+    }"#,
+            "y",
+            r#"
+    struct S {
+        y: u32,
+    }
+    fn foo(x: u32) -> u32 {
+        let S { y: x } = S { y: x };
+        let S { y: ref x } = S { y: 1 };
+        *x
+    }"#,
+        );
+    }
+
     #[test]
     fn test_rename_mod() {
         let (analysis, position) = analysis_and_position(
@@ -1,8 +1,11 @@
+use std::fmt;
+
 use itertools::Itertools;
 use ra_db::SourceDatabase;
 use ra_syntax::{
-    ast::{self, AstNode, AttrsOwner, ModuleItemOwner, NameOwner},
-    SyntaxNode, TextRange,
+    algo::visit::{visitor, Visitor},
+    ast::{self, AstNode, AttrsOwner, DocCommentsOwner, ModuleItemOwner, NameOwner},
+    SmolStr, SyntaxKind, SyntaxNode, TextRange, T,
 };
 
 use crate::{db::RootDatabase, FileId};
@@ -13,44 +16,109 @@ pub struct Runnable {
     pub kind: RunnableKind,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestId {
+    /// Only the name of the test is known, because it is not in a module.
+    Name(String),
+    /// The path of the test from the crate root, like `foo::tests::bar`.
+    Path(String),
+}
+
+impl fmt::Display for TestId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TestId::Name(name) => f.write_str(name),
+            TestId::Path(path) => f.write_str(path),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum RunnableKind {
-    Test { name: String },
+    Test { test_id: TestId },
     TestMod { path: String },
-    Bench { name: String },
+    Bench { test_id: TestId },
+    DocTest { test_id: TestId },
     Bin,
 }
 
-pub(crate) fn runnables(db: &RootDatabase, file_id: FileId) -> Vec<Runnable> {
+/// Code block attributes with which rustdoc runs the code as a doctest.
+const DOCTEST_ATTRIBUTES: &[&str] = &[
+    "",
+    "rust",
+    "should_panic",
+    "no_run",
+    "compile_fail",
+    "allow_fail",
+    "edition2015",
+    "edition2018",
+];
+
+/// Finds the runnables in the file. Functions are tests if they have the
+/// `#[test]` attribute or one of `test_attributes`, like `tokio::test`.
+pub(crate) fn runnables(
+    db: &RootDatabase,
+    file_id: FileId,
+    test_attributes: &[String],
+) -> Vec<Runnable> {
     let parse = db.parse(file_id);
-    parse.tree().syntax().descendants().filter_map(|i| runnable(db, file_id, i)).collect()
+    let mut res = Vec::new();
+    for node in parse.tree().syntax().descendants() {
+        res.extend(runnable(db, file_id, node.clone(), test_attributes));
+        res.extend(runnable_doctest(db, file_id, &node));
+    }
+    res
 }
 
-fn runnable(db: &RootDatabase, file_id: FileId, item: SyntaxNode) -> Option<Runnable> {
+fn runnable(
+    db: &RootDatabase,
+    file_id: FileId,
+    item: SyntaxNode,
+    test_attributes: &[String],
+) -> Option<Runnable> {
     if let Some(fn_def) = ast::FnDef::cast(item.clone()) {
-        runnable_fn(fn_def)
+        runnable_fn(db, file_id, fn_def, test_attributes)
     } else if let Some(m) = ast::Module::cast(item) {
-        runnable_mod(db, file_id, m)
+        runnable_mod(db, file_id, m, test_attributes)
     } else {
         None
     }
 }
 
-fn runnable_fn(fn_def: ast::FnDef) -> Option<Runnable> {
+fn runnable_fn(
+    db: &RootDatabase,
+    file_id: FileId,
+    fn_def: ast::FnDef,
+    test_attributes: &[String],
+) -> Option<Runnable> {
     let name = fn_def.name()?.text().clone();
     let kind = if name == "main" {
         RunnableKind::Bin
-    } else if fn_def.has_atom_attr("test") {
-        RunnableKind::Test { name: name.to_string() }
-    } else if fn_def.has_atom_attr("bench") {
-        RunnableKind::Bench { name: name.to_string() }
     } else {
-        return None;
+        let test_id = || match module_path(db, file_id, fn_def.syntax()) {
+            Some(mut path) => {
+                path.push(name.to_string());
+                TestId::Path(path.join("::"))
+            }
+            None => TestId::Name(name.to_string()),
+        };
+        if is_test_fn(&fn_def, test_attributes) {
+            RunnableKind::Test { test_id: test_id() }
+        } else if fn_def.has_atom_attr("bench") {
+            RunnableKind::Bench { test_id: test_id() }
+        } else {
+            return None;
+        }
     };
     Some(Runnable { range: fn_def.syntax().text_range(), kind })
 }
 
-fn runnable_mod(db: &RootDatabase, file_id: FileId, module: ast::Module) -> Option<Runnable> {
+fn runnable_mod(
+    db: &RootDatabase,
+    file_id: FileId,
+    module: ast::Module,
+    test_attributes: &[String],
+) -> Option<Runnable> {
     let has_test_function = module
         .item_list()?
         .items()
@@ -58,7 +126,7 @@ fn runnable_mod(db: &RootDatabase, file_id: FileId, module: ast::Module) -> Opti
             ast::ModuleItemKind::FnDef(it) => Some(it),
             _ => None,
         })
-        .any(|f| f.has_atom_attr("test"));
+        .any(|f| is_test_fn(&f, test_attributes));
     if !has_test_function {
         return None;
     }
@@ -69,6 +137,103 @@ fn runnable_mod(db: &RootDatabase, file_id: FileId, module: ast::Module) -> Opti
     Some(Runnable { range, kind: RunnableKind::TestMod { path } })
 }
 
+/// Finds items with code blocks in their documentation, which rustdoc runs as
+/// doctests.
+fn runnable_doctest(db: &RootDatabase, file_id: FileId, item: &SyntaxNode) -> Option<Runnable> {
+    let (name, docs) = visitor()
+        .visit(|it: ast::FnDef| name_and_docs(&it))
+        .visit(|it: ast::StructDef| name_and_docs(&it))
+        .visit(|it: ast::EnumDef| name_and_docs(&it))
+        .visit(|it: ast::TraitDef| name_and_docs(&it))
+        .visit(|it: ast::TypeAliasDef| name_and_docs(&it))
+        .visit(|it: ast::ConstDef| name_and_docs(&it))
+        .visit(|it: ast::StaticDef| name_and_docs(&it))
+        .visit(|it: ast::Module| name_and_docs(&it))
+        .accept(item)??;
+    if !has_doctest(&docs) {
+        return None;
+    }
+
+    // Rustdoc names the doctests of associated items after the type or the
+    // trait, like `foo::Bar::baz`.
+    let owner = item.parent().and_then(|it| it.parent()).and_then(|owner| {
+        if let Some(imp) = ast::ImplBlock::cast(owner.clone()) {
+            return Some(imp.target_type()?.syntax().text().to_string());
+        }
+        Some(ast::TraitDef::cast(owner)?.name()?.text().to_string())
+    });
+    let test_id = match module_path(db, file_id, item) {
+        // The path of a module already ends with its name.
+        Some(path) if item.kind() == SyntaxKind::MODULE => TestId::Path(path.join("::")),
+        Some(mut path) => {
+            path.extend(owner);
+            path.push(name.to_string());
+            TestId::Path(path.join("::"))
+        }
+        None => TestId::Name(name.to_string()),
+    };
+    Some(Runnable { range: item.text_range(), kind: RunnableKind::DocTest { test_id } })
+}
+
+fn name_and_docs<N: NameOwner + DocCommentsOwner>(node: &N) -> Option<(SmolStr, String)> {
+    Some((node.name()?.text().clone(), node.doc_comment_text()?))
+}
+
+/// Checks if the docs contain a code block which rustdoc runs.
+fn has_doctest(docs: &str) -> bool {
+    let mut in_code_block = false;
+    for line in docs.lines() {
+        let line = line.trim_start();
+        if !line.starts_with("```") {
+            continue;
+        }
+        if !in_code_block
+            && line[3..].split(',').all(|attr| DOCTEST_ATTRIBUTES.contains(&attr.trim()))
+        {
+            return true;
+        }
+        in_code_block = !in_code_block;
+    }
+    false
+}
+
+fn is_test_fn(fn_def: &ast::FnDef, test_attributes: &[String]) -> bool {
+    fn_def
+        .attrs()
+        .filter_map(|attr| attr_path(&attr))
+        .any(|path| path == "test" || test_attributes.iter().any(|it| *it == path))
+}
+
+/// Returns the path of the attribute, like `tokio::test` for
+/// `#[tokio::test(threaded_scheduler)]`.
+fn attr_path(attr: &ast::Attr) -> Option<String> {
+    let tt = attr.value()?;
+    let path = tt
+        .syntax()
+        .children_with_tokens()
+        .skip(1)
+        .take_while(|it| match it.kind() {
+            SyntaxKind::IDENT | T![:] | T![::] => true,
+            _ => false,
+        })
+        .filter_map(|it| it.into_token())
+        .map(|it| it.text().clone())
+        .join("");
+    if path.is_empty() {
+        None
+    } else {
+        Some(path)
+    }
+}
+
+/// Returns the names of the modules from the crate root to the module which
+/// contains `node`.
+fn module_path(db: &RootDatabase, file_id: FileId, node: &SyntaxNode) -> Option<Vec<String>> {
+    let module = hir::source_binder::module_from_child_node(db, file_id, node)?;
+    let path = module.path_to_root(db).into_iter().rev().filter_map(|it| it.name(db));
+    Some(path.map(|it| it.to_string()).collect())
+}
+
 #[cfg(test)]
 mod tests {
     use insta::assert_debug_snapshot_matches;
@@ -91,7 +256,7 @@ mod tests {
         fn test_foo() {}
         "#,
         );
-        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let runnables = analysis.runnables(pos.file_id, &[]).unwrap();
         assert_debug_snapshot_matches!(&runnables,
         @r#"[
     Runnable {
@@ -101,13 +266,17 @@ mod tests {
     Runnable {
         range: [22; 46),
         kind: Test {
-            name: "test_foo",
+            test_id: Path(
+                "test_foo",
+            ),
         },
     },
     Runnable {
         range: [47; 81),
         kind: Test {
-            name: "test_foo",
+            test_id: Path(
+                "test_foo",
+            ),
         },
     },
 ]"#
@@ -126,7 +295,7 @@ mod tests {
         }
         "#,
         );
-        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let runnables = analysis.runnables(pos.file_id, &[]).unwrap();
         assert_debug_snapshot_matches!(&runnables,
         @r#"[
     Runnable {
@@ -138,7 +307,9 @@ mod tests {
     Runnable {
         range: [28; 57),
         kind: Test {
-            name: "test_foo1",
+            test_id: Path(
+                "test_mod::test_foo1",
+            ),
         },
     },
 ]"#
@@ -159,7 +330,7 @@ mod tests {
         }
         "#,
         );
-        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let runnables = analysis.runnables(pos.file_id, &[]).unwrap();
         assert_debug_snapshot_matches!(&runnables,
         @r#"[
     Runnable {
@@ -171,7 +342,9 @@ mod tests {
     Runnable {
         range: [46; 79),
         kind: Test {
-            name: "test_foo1",
+            test_id: Path(
+                "foo::test_mod::test_foo1",
+            ),
         },
     },
 ]"#
@@ -194,7 +367,7 @@ mod tests {
         }
         "#,
         );
-        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let runnables = analysis.runnables(pos.file_id, &[]).unwrap();
         assert_debug_snapshot_matches!(&runnables,
         @r#"[
     Runnable {
@@ -206,7 +379,9 @@ mod tests {
     Runnable {
         range: [68; 105),
         kind: Test {
-            name: "test_foo1",
+            test_id: Path(
+                "foo::bar::test_mod::test_foo1",
+            ),
         },
     },
 ]"#
@@ -224,8 +399,104 @@ mod tests {
         }
         "#,
         );
-        let runnables = analysis.runnables(pos.file_id).unwrap();
+        let runnables = analysis.runnables(pos.file_id, &[]).unwrap();
         assert!(runnables.is_empty())
     }
 
+    #[test]
+    fn test_runnables_doc_test() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        /// ```
+        /// let x = 5;
+        /// ```
+        fn foo() {}
+
+        /// ```text
+        /// not rust
+        /// ```
+        fn bar() {}
+
+        struct Data;
+        impl Data {
+            /// ```
+            /// let x = 5;
+            /// ```
+            fn foo() {}
+        }
+        "#,
+        );
+        let runnables = analysis.runnables(pos.file_id, &[]).unwrap();
+        assert_debug_snapshot_matches!(&runnables,
+        @r#"[
+    Runnable {
+        range: [1; 51),
+        kind: DocTest {
+            test_id: Path(
+                "foo",
+            ),
+        },
+    },
+    Runnable {
+        range: [128; 182),
+        kind: DocTest {
+            test_id: Path(
+                "Data::foo",
+            ),
+        },
+    },
+]"#
+                );
+    }
+
+    #[test]
+    fn test_runnables_custom_test_attributes() {
+        let (analysis, pos) = analysis_and_position(
+            r#"
+        //- /lib.rs
+        <|> //empty
+        mod tests {
+            #[tokio::test]
+            fn test_foo() {}
+
+            #[test_case(1)]
+            fn test_bar() {}
+
+            #[other::attr]
+            fn not_a_test() {}
+        }
+        "#,
+        );
+        let test_attributes = vec!["tokio::test".to_string(), "test_case".to_string()];
+        let runnables = analysis.runnables(pos.file_id, &test_attributes).unwrap();
+        assert_debug_snapshot_matches!(&runnables,
+        @r#"[
+    Runnable {
+        range: [1; 147),
+        kind: TestMod {
+            path: "tests",
+        },
+    },
+    Runnable {
+        range: [25; 60),
+        kind: Test {
+            test_id: Path(
+                "tests::test_foo",
+            ),
+        },
+    },
+    Runnable {
+        range: [66; 102),
+        kind: Test {
+            test_id: Path(
+                "tests::test_bar",
+            ),
+        },
+    },
+]"#
+                );
+    }
+
 }
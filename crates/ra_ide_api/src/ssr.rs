@@ -0,0 +1,468 @@
+//! Structural search and replace.
+//!
+//! A rule like `foo($a, $b) ==>> $a.foo($b)` is parsed into a pattern and a
+//! template. Every piece of code in the workspace which has the same syntax
+//! tree as the pattern, with placeholders matching arbitrary subtrees, is
+//! replaced by the template with the placeholders substituted.
+
+use std::fmt;
+
+use ra_db::{CheckCanceled, SourceDatabase};
+use ra_syntax::{
+    ast, AstNode, NodeOrToken, SourceFile, SyntaxElement, SyntaxKind::*, SyntaxNode, TextRange,
+};
+use ra_text_edit::TextEditBuilder;
+
+use crate::{db::RootDatabase, FileId, SourceChange, SourceFileEdit};
+
+/// Placeholders are renamed to identifiers with this prefix before parsing.
+const PLACEHOLDER_PREFIX: &str = "__ssr_";
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct SsrError(String);
+
+impl fmt::Display for SsrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid rule: {}", self.0)
+    }
+}
+
+impl std::error::Error for SsrError {}
+
+#[derive(Debug)]
+struct SsrRule {
+    pattern: SyntaxNode,
+    template: SyntaxNode,
+}
+
+/// Subtrees of the code bound to the placeholders of the pattern, keyed by
+/// the placeholder identifier.
+type Bindings = Vec<(String, SyntaxNode)>;
+
+/// Applies the rule `query` to all files of the workspace. When
+/// `resolve_paths` is set, paths without placeholders match if they resolve
+/// to the same definition, rather than only if they are spelled the same.
+pub(crate) fn structural_search_replace(
+    db: &RootDatabase,
+    query: &str,
+    resolve_paths: bool,
+) -> Result<SourceChange, SsrError> {
+    let rule = parse_rule(query)?;
+    let mut edits = Vec::new();
+    for &root in db.local_roots().iter() {
+        let source_root = db.source_root(root);
+        for &file_id in source_root.files.values() {
+            db.check_canceled();
+            let matcher = Matcher { db, file_id, rule: &rule, resolve_paths };
+            let mut matches = Vec::new();
+            matcher.find_matches(db.parse(file_id).tree().syntax(), &mut matches);
+            if matches.is_empty() {
+                continue;
+            }
+            let mut builder = TextEditBuilder::default();
+            for (node, bindings) in matches {
+                builder.replace(node.text_range(), rule.render(&bindings));
+            }
+            edits.push(SourceFileEdit { file_id, edit: builder.finish() });
+        }
+    }
+    Ok(SourceChange::source_file_edits("structural search replace", edits))
+}
+
+fn parse_rule(query: &str) -> Result<SsrRule, SsrError> {
+    let mut parts = query.split("==>>");
+    let (pattern, template) = match (parts.next(), parts.next(), parts.next()) {
+        (Some(pattern), Some(template), None) => (pattern.trim(), template.trim()),
+        _ => return Err(SsrError("expected a rule like `pattern ==>> template`".to_string())),
+    };
+    let (pattern, kind) = {
+        let text = replace_placeholders(pattern)?;
+        let expr = FragmentKind::Expr.parse(&text).map(|it| (it, FragmentKind::Expr));
+        expr.or_else(|| FragmentKind::Type.parse(&text).map(|it| (it, FragmentKind::Type)))
+            .ok_or_else(|| SsrError(format!("`{}` is not an expression or a type", pattern)))?
+    };
+    let template = kind.parse(&replace_placeholders(template)?).ok_or_else(|| {
+        let expected = match kind {
+            FragmentKind::Expr => "an expression",
+            FragmentKind::Type => "a type",
+        };
+        SsrError(format!("`{}` is not {}", template, expected))
+    })?;
+
+    let rule = SsrRule { pattern, template };
+    if rule.placeholder(&rule.pattern).is_some() {
+        return Err(SsrError("the pattern can not be a single placeholder".to_string()));
+    }
+    let placeholder_names = |node: &SyntaxNode| {
+        let mut placeholders = Vec::new();
+        rule.collect_placeholders(node, &mut placeholders);
+        placeholders.iter().filter_map(|it| rule.placeholder(it)).collect::<Vec<_>>()
+    };
+    let defined = placeholder_names(&rule.pattern);
+    for name in placeholder_names(&rule.template) {
+        if !defined.contains(&name) {
+            let name = &name[PLACEHOLDER_PREFIX.len()..];
+            return Err(SsrError(format!("`${}` is not defined in the pattern", name)));
+        }
+    }
+    Ok(rule)
+}
+
+/// Renames every `$name` in `text` to a plain identifier, so that the text
+/// can be parsed as Rust.
+fn replace_placeholders(text: &str) -> Result<String, SsrError> {
+    let mut buf = String::new();
+    let mut rest = text;
+    while let Some(idx) = rest.find('$') {
+        buf.push_str(&rest[..idx]);
+        rest = &rest[idx + 1..];
+        let len = rest.find(|c: char| !(c.is_alphanumeric() || c == '_')).unwrap_or(rest.len());
+        if len == 0 {
+            return Err(SsrError("expected a placeholder name after `$`".to_string()));
+        }
+        buf.push_str(PLACEHOLDER_PREFIX);
+        buf.push_str(&rest[..len]);
+        rest = &rest[len..];
+    }
+    buf.push_str(rest);
+    Ok(buf)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FragmentKind {
+    Expr,
+    Type,
+}
+
+impl FragmentKind {
+    fn parse(self, text: &str) -> Option<SyntaxNode> {
+        let node = match self {
+            FragmentKind::Expr => {
+                let file = parse_file(&format!("fn __ssr() {{ {} }}", text))?;
+                let block = file.syntax().descendants().find_map(ast::Block::cast)?;
+                block.expr()?.syntax().clone()
+            }
+            FragmentKind::Type => {
+                let file = parse_file(&format!("type __Ssr = {};", text))?;
+                let alias = file.syntax().descendants().find_map(ast::TypeAliasDef::cast)?;
+                alias.type_ref()?.syntax().clone()
+            }
+        };
+        // The whole text must be a single fragment, not just its prefix.
+        if node.text().to_string() == text {
+            Some(node)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_file(text: &str) -> Option<SourceFile> {
+    let parse = SourceFile::parse(text);
+    if parse.errors().is_empty() {
+        Some(parse.tree())
+    } else {
+        None
+    }
+}
+
+impl SsrRule {
+    /// Returns the identifier of `node` if it is a placeholder.
+    fn placeholder(&self, node: &SyntaxNode) -> Option<String> {
+        let text = node.text().to_string();
+        if text.starts_with(PLACEHOLDER_PREFIX)
+            && text[PLACEHOLDER_PREFIX.len()..].chars().all(|c| c.is_alphanumeric() || c == '_')
+        {
+            Some(text)
+        } else {
+            None
+        }
+    }
+
+    /// Collects the outermost placeholder nodes under `node`.
+    fn collect_placeholders(&self, node: &SyntaxNode, acc: &mut Vec<SyntaxNode>) {
+        if self.placeholder(node).is_some() {
+            acc.push(node.clone());
+            return;
+        }
+        for child in node.children() {
+            self.collect_placeholders(&child, acc);
+        }
+    }
+
+    /// Returns the template text with the placeholders substituted.
+    fn render(&self, bindings: &Bindings) -> String {
+        let mut placeholders = Vec::new();
+        self.collect_placeholders(&self.template, &mut placeholders);
+        let start = self.template.text_range().start();
+        let mut buf = self.template.text().to_string();
+        for placeholder in placeholders.iter().rev() {
+            let name = self.placeholder(placeholder).unwrap();
+            let bound = match bindings.iter().find(|(it, _)| *it == name) {
+                Some((_, bound)) => bound,
+                None => continue,
+            };
+            let mut text = bound.text().to_string();
+            if needs_parens(placeholder, bound) {
+                text = format!("({})", text);
+            }
+            let range = placeholder.text_range();
+            let range = TextRange::from_to(range.start() - start, range.end() - start);
+            buf.replace_range(range.start().to_usize()..range.end().to_usize(), &text);
+        }
+        buf
+    }
+}
+
+struct Matcher<'a> {
+    db: &'a RootDatabase,
+    file_id: FileId,
+    rule: &'a SsrRule,
+    resolve_paths: bool,
+}
+
+impl<'a> Matcher<'a> {
+    /// Collects the outermost matches of the pattern under `node`. Code
+    /// inside a match is not searched for further matches.
+    fn find_matches(&self, node: &SyntaxNode, acc: &mut Vec<(SyntaxNode, Bindings)>) {
+        if node.kind() == self.rule.pattern.kind() {
+            let mut bindings = Vec::new();
+            if self.match_node(&self.rule.pattern, node, &mut bindings) {
+                acc.push((node.clone(), bindings));
+                return;
+            }
+        }
+        for child in node.children() {
+            self.find_matches(&child, acc);
+        }
+    }
+
+    fn match_node(&self, pattern: &SyntaxNode, code: &SyntaxNode, bindings: &mut Bindings) -> bool {
+        if let Some(name) = self.rule.placeholder(pattern) {
+            return match bindings.iter().find(|(it, _)| *it == name) {
+                Some((_, bound)) => same_tokens(bound, code),
+                None => {
+                    bindings.push((name, code.clone()));
+                    true
+                }
+            };
+        }
+        if pattern.kind() != code.kind() {
+            return false;
+        }
+        if self.resolve_paths && pattern.kind() == PATH {
+            if let Some(same) = self.paths_resolve_same(pattern, code) {
+                return same;
+            }
+        }
+        let mut pattern_children = non_trivia_children(pattern);
+        let mut code_children = non_trivia_children(code);
+        loop {
+            match (pattern_children.next(), code_children.next()) {
+                (None, None) => return true,
+                (Some(NodeOrToken::Node(pattern)), Some(NodeOrToken::Node(code))) => {
+                    if !self.match_node(&pattern, &code, bindings) {
+                        return false;
+                    }
+                }
+                (Some(NodeOrToken::Token(pattern)), Some(NodeOrToken::Token(code))) => {
+                    if pattern.kind() != code.kind() || pattern.text() != code.text() {
+                        return false;
+                    }
+                }
+                _ => return false,
+            }
+        }
+    }
+
+    /// Compares the definitions both paths resolve to from the scope of the
+    /// code. Returns `None` if neither resolves, in which case they are
+    /// compared structurally.
+    fn paths_resolve_same(&self, pattern: &SyntaxNode, code: &SyntaxNode) -> Option<bool> {
+        if pattern.text().to_string().contains(PLACEHOLDER_PREFIX) {
+            return None;
+        }
+        let pattern_path = hir::Path::from_ast(ast::Path::cast(pattern.clone())?)?;
+        let code_path = hir::Path::from_ast(ast::Path::cast(code.clone())?)?;
+        let analyzer = hir::SourceAnalyzer::new(self.db, self.file_id, code, None);
+        let resolve = |path: &hir::Path| {
+            let resolution = analyzer.resolve_hir_path(self.db, path);
+            resolution.types.or(resolution.values)
+        };
+        match (resolve(&pattern_path), resolve(&code_path)) {
+            (None, None) => None,
+            (expected, actual) => Some(expected == actual),
+        }
+    }
+}
+
+fn non_trivia_children(node: &SyntaxNode) -> impl Iterator<Item = SyntaxElement> {
+    node.children_with_tokens().filter(|it| !it.kind().is_trivia())
+}
+
+/// Checks that both nodes consist of the same tokens, ignoring whitespace and
+/// comments.
+fn same_tokens(left: &SyntaxNode, right: &SyntaxNode) -> bool {
+    let tokens = |node: &SyntaxNode| {
+        node.descendants_with_tokens()
+            .filter_map(|it| it.into_token())
+            .filter(|it| !it.kind().is_trivia())
+            .map(|it| it.text().clone())
+            .collect::<Vec<_>>()
+    };
+    tokens(left) == tokens(right)
+}
+
+/// Checks whether the expression bound to `placeholder` has to be wrapped in
+/// parentheses to keep its meaning in the template.
+fn needs_parens(placeholder: &SyntaxNode, bound: &SyntaxNode) -> bool {
+    if !ast::Expr::can_cast(bound.kind()) {
+        return false;
+    }
+    match bound.kind() {
+        PATH_EXPR | LITERAL | CALL_EXPR | METHOD_CALL_EXPR | FIELD_EXPR | INDEX_EXPR | TRY_EXPR
+        | PAREN_EXPR | TUPLE_EXPR | ARRAY_EXPR | BLOCK_EXPR | MACRO_CALL | STRUCT_LIT => {
+            return false
+        }
+        _ => (),
+    }
+    match placeholder.parent().map(|it| it.kind()) {
+        Some(ARG_LIST) | Some(PAREN_EXPR) | Some(TUPLE_EXPR) | Some(ARRAY_EXPR)
+        | Some(LET_STMT) | Some(NAMED_FIELD) | Some(BLOCK) | None => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insta::assert_debug_snapshot_matches;
+
+    use super::{parse_rule, SsrError};
+    use crate::mock_analysis::{single_file, MockAnalysis};
+
+    fn check(query: &str, before: &str, after: &str) {
+        check_with_resolution(query, false, before, after)
+    }
+
+    fn check_with_resolution(query: &str, resolve_paths: bool, before: &str, after: &str) {
+        let (analysis, file_id) = single_file(before);
+        let change = analysis.structural_search_replace(query, resolve_paths).unwrap().unwrap();
+        let mut text = analysis.file_text(file_id).unwrap().to_string();
+        for edit in change.source_file_edits {
+            text = edit.edit.apply(&text);
+        }
+        assert_eq!(text, after);
+    }
+
+    #[test]
+    fn replaces_function_call_with_method_call() {
+        check(
+            "foo($a, $b) ==>> $a.foo($b)",
+            "fn main() { foo(x, y); foo(1 + 2, bar(3, 4)); baz(x, y); }",
+            "fn main() { x.foo(y); (1 + 2).foo(bar(3, 4)); baz(x, y); }",
+        );
+    }
+
+    #[test]
+    fn ignores_whitespace_and_comments() {
+        check("foo($a) ==>> bar($a)", "fn main() { foo( /* x */ 92 ); }", "fn main() { bar(92); }");
+    }
+
+    #[test]
+    fn repeated_placeholders_must_match_the_same_code() {
+        check(
+            "$a == $a ==>> true",
+            "fn main() { if x.y == x . y {} if x == y {} }",
+            "fn main() { if true {} if x == y {} }",
+        );
+    }
+
+    #[test]
+    fn replaces_only_outermost_matches() {
+        check("foo($a) ==>> bar($a)", "fn main() { foo(foo(1)); }", "fn main() { bar(foo(1)); }");
+    }
+
+    #[test]
+    fn replaces_types() {
+        check(
+            "Option<Vec<$t>> ==>> Vec<$t>",
+            "fn foo(x: Option<Vec<u32>>) -> Option<Vec<String>> { None }",
+            "fn foo(x: Vec<u32>) -> Vec<String> { None }",
+        );
+    }
+
+    #[test]
+    fn matches_paths_by_resolution() {
+        let code = "
+mod m { pub fn foo(x: u32) {} }
+fn foo(x: u32) {}
+use m::foo as bar;
+fn main() { m::foo(1); bar(2); foo(3); }
+";
+        check_with_resolution(
+            "crate::m::foo($a) ==>> crate::m::baz($a)",
+            true,
+            code,
+            "
+mod m { pub fn foo(x: u32) {} }
+fn foo(x: u32) {}
+use m::foo as bar;
+fn main() { crate::m::baz(1); crate::m::baz(2); foo(3); }
+",
+        );
+        check_with_resolution("crate::m::foo($a) ==>> crate::m::baz($a)", false, code, code);
+    }
+
+    #[test]
+    fn searches_all_workspace_files() {
+        let analysis = MockAnalysis::with_files(
+            "
+            //- /lib.rs
+            mod foo;
+            fn main() { foo(1); }
+            //- /foo.rs
+            fn bar() { foo(2); }
+            ",
+        )
+        .analysis();
+        let change = analysis.structural_search_replace("foo($a) ==>> $a", false).unwrap().unwrap();
+        assert_eq!(change.source_file_edits.len(), 2);
+    }
+
+    #[test]
+    fn reports_invalid_rules() {
+        let errors: Vec<SsrError> = [
+            "foo($a)",
+            "foo($a) ==>> bar($b)",
+            "foo($) ==>> bar()",
+            "$a ==>> foo($a)",
+            "fn foo() ==>> bar()",
+            "foo($a) ==>> struct Foo;",
+        ]
+        .iter()
+        .map(|query| parse_rule(query).unwrap_err())
+        .collect();
+        assert_debug_snapshot_matches!(errors, @r###"
+       ⋮[
+       ⋮    SsrError(
+       ⋮        "expected a rule like `pattern ==>> template`",
+       ⋮    ),
+       ⋮    SsrError(
+       ⋮        "`$b` is not defined in the pattern",
+       ⋮    ),
+       ⋮    SsrError(
+       ⋮        "expected a placeholder name after `$`",
+       ⋮    ),
+       ⋮    SsrError(
+       ⋮        "the pattern can not be a single placeholder",
+       ⋮    ),
+       ⋮    SsrError(
+       ⋮        "`fn foo()` is not an expression or a type",
+       ⋮    ),
+       ⋮    SsrError(
+       ⋮        "`struct Foo;` is not an expression",
+       ⋮    ),
+       ⋮]
+        "###);
+    }
+}
@@ -27,6 +27,7 @@ use std::{
 };
 
 use fst::{self, Streamer};
+use hir::source_binder;
 use ra_db::{
     salsa::{self, ParallelDatabase},
     SourceDatabase, SourceRootId,
@@ -93,7 +94,15 @@ pub(crate) fn world_symbols(db: &RootDatabase, query: Query) -> Vec<FileSymbol>
         let snap = Snap(db.snapshot());
         files.par_iter().map_with(snap, |db, &file_id| db.0.file_symbols(file_id)).collect()
     };
-    query.search(&buf)
+    let module_path = |file_id: FileId| -> Vec<String> {
+        let module = match source_binder::module_from_file_id(db, file_id) {
+            Some(it) => it,
+            None => return Vec::new(),
+        };
+        let path = module.path_to_root(db).into_iter().rev().filter_map(|it| it.name(db));
+        path.map(|it| it.to_string().to_lowercase()).collect()
+    };
+    query.search(&buf, &module_path)
 }
 
 pub(crate) fn index_resolve(db: &RootDatabase, name_ref: &ast::NameRef) -> Vec<FileSymbol> {
@@ -193,7 +202,14 @@ impl SymbolIndex {
 }
 
 impl Query {
-    pub(crate) fn search(self, indices: &[Arc<SymbolIndex>]) -> Vec<FileSymbol> {
+    /// Searches the `indices` for the symbols matching the query.
+    /// `module_path` returns the lowercased path of the module of a file, which
+    /// is used when the qualifier of the query reaches outside of the file.
+    pub(crate) fn search(
+        self,
+        indices: &[Arc<SymbolIndex>],
+        module_path: &dyn Fn(FileId) -> Vec<String>,
+    ) -> Vec<FileSymbol> {
         let mut op = fst::map::OpBuilder::new();
         for file_symbols in indices.iter() {
             let automaton = fst::automaton::Subsequence::new(&self.lowercased);
@@ -216,12 +232,95 @@ impl Query {
                     if self.exact && symbol.name != self.query {
                         continue;
                     }
+                    if !self.kinds.is_empty() && !self.kinds.iter().any(|it| it.matches(symbol)) {
+                        continue;
+                    }
+                    if !self.matches_qualifier(symbol, module_path) {
+                        continue;
+                    }
                     res.push(symbol.clone());
                 }
             }
         }
         res
     }
+
+    /// Checks that the containers of `symbol`, from the innermost outwards,
+    /// match the qualifier of the query.
+    fn matches_qualifier(
+        &self,
+        symbol: &FileSymbol,
+        module_path: &dyn Fn(FileId) -> Vec<String>,
+    ) -> bool {
+        if self.qualifier.is_empty() {
+            return true;
+        }
+        let mut path: Vec<String> = match &symbol.container_name {
+            Some(it) => it.split("::").map(|it| it.to_lowercase()).collect(),
+            None => Vec::new(),
+        };
+        if path.len() < self.qualifier.len() {
+            let mut full_path = module_path(symbol.file_id);
+            full_path.extend(path);
+            path = full_path;
+        }
+        path.ends_with(&self.qualifier)
+    }
+}
+
+/// The kinds of symbols a `Query` can be restricted to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolKind {
+    Module,
+    Function,
+    /// A function inside an impl block or a trait.
+    Method,
+    Struct,
+    Enum,
+    Variant,
+    Trait,
+    TypeAlias,
+    Const,
+    Static,
+    Field,
+}
+
+impl SymbolKind {
+    /// Parses the keyword used in queries like `fn foo` or `method validate`.
+    pub fn from_keyword(keyword: &str) -> Option<SymbolKind> {
+        let res = match keyword {
+            "mod" => SymbolKind::Module,
+            "fn" => SymbolKind::Function,
+            "method" => SymbolKind::Method,
+            "struct" => SymbolKind::Struct,
+            "enum" => SymbolKind::Enum,
+            "variant" => SymbolKind::Variant,
+            "trait" => SymbolKind::Trait,
+            "type" => SymbolKind::TypeAlias,
+            "const" => SymbolKind::Const,
+            "static" => SymbolKind::Static,
+            "field" => SymbolKind::Field,
+            _ => return None,
+        };
+        Some(res)
+    }
+
+    fn matches(self, symbol: &FileSymbol) -> bool {
+        match (self, symbol.ptr.kind()) {
+            (SymbolKind::Module, MODULE) => true,
+            (SymbolKind::Function, FN_DEF) => !symbol.is_assoc,
+            (SymbolKind::Method, FN_DEF) => symbol.is_assoc,
+            (SymbolKind::Struct, STRUCT_DEF) => true,
+            (SymbolKind::Enum, ENUM_DEF) => true,
+            (SymbolKind::Variant, ENUM_VARIANT) => true,
+            (SymbolKind::Trait, TRAIT_DEF) => true,
+            (SymbolKind::TypeAlias, TYPE_ALIAS_DEF) => true,
+            (SymbolKind::Const, CONST_DEF) => true,
+            (SymbolKind::Static, STATIC_DEF) => true,
+            (SymbolKind::Field, NAMED_FIELD_DEF) => true,
+            _ => false,
+        }
+    }
 }
 
 fn is_type(kind: SyntaxKind) -> bool {
@@ -239,7 +338,11 @@ pub(crate) struct FileSymbol {
     pub(crate) name: SmolStr,
     pub(crate) ptr: SyntaxNodePtr,
     pub(crate) name_range: Option<TextRange>,
+    /// The names of the enclosing items, joined with `::`. Impl blocks
+    /// contribute the name of their target type.
     pub(crate) container_name: Option<SmolStr>,
+    /// Whether this is an item of an impl block or a trait.
+    pub(crate) is_assoc: bool,
 }
 
 fn source_file_to_file_symbols(source_file: &SourceFile, file_id: FileId) -> Vec<FileSymbol> {
@@ -250,15 +353,20 @@ fn source_file_to_file_symbols(source_file: &SourceFile, file_id: FileId) -> Vec
         match event {
             WalkEvent::Enter(node) => {
                 if let Some(mut symbol) = to_file_symbol(&node, file_id) {
-                    symbol.container_name = stack.last().cloned();
+                    if !stack.is_empty() {
+                        symbol.container_name = Some(SmolStr::new(stack.join("::")));
+                    }
+                    symbol.is_assoc = is_assoc_item(&node);
 
-                    stack.push(symbol.name.clone());
+                    stack.push(symbol.name.to_string());
                     symbols.push(symbol);
+                } else if let Some(name) = impl_target_name(&node) {
+                    stack.push(name);
                 }
             }
 
             WalkEvent::Leave(node) => {
-                if to_symbol(&node).is_some() {
+                if to_symbol(&node).is_some() || impl_target_name(&node).is_some() {
                     stack.pop();
                 }
             }
@@ -286,9 +394,30 @@ fn to_symbol(node: &SyntaxNode) -> Option<(SmolStr, SyntaxNodePtr, TextRange)> {
         .visit(decl::<ast::TypeAliasDef>)
         .visit(decl::<ast::ConstDef>)
         .visit(decl::<ast::StaticDef>)
+        .visit(decl::<ast::NamedFieldDef>)
+        .visit(decl::<ast::EnumVariant>)
         .accept(node)?
 }
 
+/// The name of the type an impl block is for, which is used as the container
+/// name of its items.
+fn impl_target_name(node: &SyntaxNode) -> Option<String> {
+    let target = ast::ImplBlock::cast(node.clone())?.target_type()?;
+    let name = match target.kind() {
+        ast::TypeRefKind::PathType(it) => it.path()?.segment()?.name_ref()?.text().to_string(),
+        _ => target.syntax().text().to_string(),
+    };
+    Some(name)
+}
+
+fn is_assoc_item(node: &SyntaxNode) -> bool {
+    let container = node.parent().filter(|it| it.kind() == ITEM_LIST).and_then(|it| it.parent());
+    match container.map(|it| it.kind()) {
+        Some(IMPL_BLOCK) | Some(TRAIT_DEF) => true,
+        _ => false,
+    }
+}
+
 fn to_file_symbol(node: &SyntaxNode, file_id: FileId) -> Option<FileSymbol> {
     to_symbol(node).map(move |(name, ptr, name_range)| FileSymbol {
         name,
@@ -296,12 +425,17 @@ fn to_file_symbol(node: &SyntaxNode, file_id: FileId) -> Option<FileSymbol> {
         file_id,
         name_range: Some(name_range),
         container_name: None,
+        is_assoc: false,
     })
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{display::NavigationTarget, mock_analysis::single_file, Query};
+    use crate::{
+        display::NavigationTarget,
+        mock_analysis::{single_file, MockAnalysis},
+        Query, SymbolKind,
+    };
     use ra_syntax::{
         SmolStr,
         SyntaxKind::{FN_DEF, STRUCT_DEF},
@@ -367,6 +501,100 @@ struct Foo;
         assert_eq!(struct_match, Some(STRUCT_DEF));
     }
 
+    #[test]
+    fn test_world_symbols_include_assoc_items_and_fields() {
+        let code = r#"
+struct Config { verbose: bool }
+impl Config {
+    fn validate(&self) {}
+}
+trait Validate {
+    fn validate(&self);
+}
+enum Mode { Validate }
+    "#;
+
+        let symbols = get_symbols_matching(code, "validate");
+        let actual = symbols
+            .iter()
+            .map(|s| format!("{} {:?} {:?}", s.name(), s.kind(), s.container_name()))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            actual,
+            vec![
+                "validate FN_DEF Some(\"Config\")",
+                "Validate TRAIT_DEF None",
+                "validate FN_DEF Some(\"Validate\")",
+                "Validate ENUM_VARIANT Some(\"Mode\")",
+            ]
+        );
+
+        let mut symbols = get_symbols_matching(code, "verbose");
+        let s = symbols.pop().unwrap();
+        assert_eq!(s.container_name(), Some(&SmolStr::new("Config")));
+    }
+
+    #[test]
+    fn test_world_symbols_filtered_by_kind() {
+        let code = r#"
+fn validate() {}
+struct Config;
+impl Config {
+    fn validate(&self) {}
+}
+    "#;
+
+        let (analysis, _) = single_file(code);
+        let mut query = Query::new("validate".into());
+        query.kinds(&[SymbolKind::Method]);
+        let symbols = analysis.symbol_search(query).unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].container_name(), Some(&SmolStr::new("Config")));
+
+        let mut query = Query::new("validate".into());
+        query.kinds(&[SymbolKind::Function]);
+        let symbols = analysis.symbol_search(query).unwrap();
+        assert_eq!(symbols.len(), 1);
+        assert_eq!(symbols[0].container_name(), None);
+    }
+
+    #[test]
+    fn test_world_symbols_with_qualified_query() {
+        let analysis = MockAnalysis::with_files(
+            "
+            //- /lib.rs
+            mod config;
+            struct Other;
+            impl Other {
+                fn validate(&self) {}
+            }
+            //- /config.rs
+            pub struct Config;
+            impl Config {
+                fn validate(&self) {}
+            }
+            mod inner {
+                fn validate() {}
+            }
+            ",
+        )
+        .analysis();
+
+        let search = |text: &str| {
+            analysis
+                .symbol_search(Query::new(text.into()))
+                .unwrap()
+                .into_iter()
+                .map(|it| format!("{:?} {:?}", it.file_id(), it.container_name()))
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(search("Config::validate"), vec!["FileId(2) Some(\"Config\")"]);
+        assert_eq!(search("config::Config::val"), vec!["FileId(2) Some(\"Config\")"]);
+        assert_eq!(search("config::inner::validate"), vec!["FileId(2) Some(\"inner\")"]);
+        assert!(search("other::Config::validate").is_empty());
+    }
+
     fn get_symbols_matching(text: &str, query: &str) -> Vec<NavigationTarget> {
         let (analysis, _) = single_file(text);
         analysis.symbol_search(Query::new(query.into())).unwrap()
@@ -1,3 +1,5 @@
+use std::{fmt, ops};
+
 use rustc_hash::{FxHashMap, FxHashSet};
 
 use hir::{Mutability, Ty};
@@ -5,24 +7,175 @@ use ra_db::SourceDatabase;
 use ra_prof::profile;
 use ra_syntax::{
     ast::{self, NameOwner},
-    AstNode, Direction, SmolStr, SyntaxElement, SyntaxKind,
+    AstNode, Direction, NodeOrToken, SmolStr, SyntaxElement, SyntaxKind,
     SyntaxKind::*,
     TextRange, T,
 };
 
 use crate::{
     db::RootDatabase,
-    name_ref_kind::{classify_name_ref, NameRefKind::*},
+    name_ref_kind::{classify_name_ref, NameRefKind, NameRefKind::*},
     FileId,
 };
 
 #[derive(Debug)]
 pub struct HighlightedRange {
     pub range: TextRange,
-    pub tag: &'static str,
+    pub highlight: Highlight,
     pub binding_hash: Option<u64>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Highlight {
+    pub tag: HighlightTag,
+    pub modifiers: HighlightModifiers,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightTag {
+    Attribute,
+    Comment,
+    Constant,
+    Field,
+    Function,
+    Keyword,
+    Lifetime,
+    Literal,
+    Macro,
+    Module,
+    String,
+    Type,
+    TypeParam,
+    Variable,
+    /// A name we failed to resolve.
+    Text,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HighlightModifier {
+    Mutable,
+    Unsafe,
+    /// The name is defined here, rather than referenced.
+    Declaration,
+    Static,
+    /// An item of a trait or an impl block.
+    Associated,
+    /// Defined outside of the workspace.
+    Library,
+    ControlFlow,
+    /// The token is a part of a macro invocation.
+    MacroExpanded,
+}
+
+/// A set of `HighlightModifier`s.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct HighlightModifiers(u32);
+
+impl Highlight {
+    fn new(tag: HighlightTag) -> Highlight {
+        Highlight { tag, modifiers: HighlightModifiers::default() }
+    }
+}
+
+impl ops::BitOr<HighlightModifier> for Highlight {
+    type Output = Highlight;
+
+    fn bitor(mut self, rhs: HighlightModifier) -> Highlight {
+        self.modifiers |= rhs;
+        self
+    }
+}
+
+impl fmt::Display for Highlight {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.tag)?;
+        for modifier in self.modifiers.iter() {
+            write!(f, ".{}", modifier)?;
+        }
+        Ok(())
+    }
+}
+
+impl HighlightTag {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HighlightTag::Attribute => "attribute",
+            HighlightTag::Comment => "comment",
+            HighlightTag::Constant => "constant",
+            HighlightTag::Field => "field",
+            HighlightTag::Function => "function",
+            HighlightTag::Keyword => "keyword",
+            HighlightTag::Lifetime => "lifetime",
+            HighlightTag::Literal => "literal",
+            HighlightTag::Macro => "macro",
+            HighlightTag::Module => "module",
+            HighlightTag::String => "string",
+            HighlightTag::Type => "type",
+            HighlightTag::TypeParam => "type_param",
+            HighlightTag::Variable => "variable",
+            HighlightTag::Text => "text",
+        }
+    }
+}
+
+impl fmt::Display for HighlightTag {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl HighlightModifier {
+    pub const ALL: &'static [HighlightModifier] = &[
+        HighlightModifier::Mutable,
+        HighlightModifier::Unsafe,
+        HighlightModifier::Declaration,
+        HighlightModifier::Static,
+        HighlightModifier::Associated,
+        HighlightModifier::Library,
+        HighlightModifier::ControlFlow,
+        HighlightModifier::MacroExpanded,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HighlightModifier::Mutable => "mutable",
+            HighlightModifier::Unsafe => "unsafe",
+            HighlightModifier::Declaration => "declaration",
+            HighlightModifier::Static => "static",
+            HighlightModifier::Associated => "associated",
+            HighlightModifier::Library => "library",
+            HighlightModifier::ControlFlow => "control",
+            HighlightModifier::MacroExpanded => "macro_expanded",
+        }
+    }
+
+    fn mask(self) -> u32 {
+        1 << (self as u32)
+    }
+}
+
+impl fmt::Display for HighlightModifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl HighlightModifiers {
+    pub fn contains(self, modifier: HighlightModifier) -> bool {
+        self.0 & modifier.mask() != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = HighlightModifier> {
+        HighlightModifier::ALL.iter().cloned().filter(move |it| self.contains(*it))
+    }
+}
+
+impl ops::BitOrAssign<HighlightModifier> for HighlightModifiers {
+    fn bitor_assign(&mut self, rhs: HighlightModifier) {
+        self.0 |= rhs.mask();
+    }
+}
+
 fn is_control_keyword(kind: SyntaxKind) -> bool {
     match kind {
         T![for]
@@ -58,6 +211,113 @@ fn is_variable_mutable(
     }
 }
 
+/// Checks if the element is inside the token tree of a macro call, that is, it
+/// is an input to a macro expansion.
+fn is_in_macro_call(element: &SyntaxElement) -> bool {
+    let parent = match element {
+        NodeOrToken::Node(node) => node.parent(),
+        NodeOrToken::Token(token) => Some(token.parent()),
+    };
+    parent
+        .into_iter()
+        .flat_map(|it| it.ancestors())
+        .any(|it| it.kind() == TOKEN_TREE && it.parent().map(|p| p.kind()) == Some(MACRO_CALL))
+}
+
+fn is_library_module(db: &RootDatabase, module: hir::Module) -> bool {
+    let file_id = module.definition_source(db).file_id.original_file(db);
+    db.source_root(db.file_source_root(file_id)).is_library
+}
+
+fn highlight_name_ref_kind(db: &RootDatabase, kind: &NameRefKind) -> Highlight {
+    let (highlight, module) = match kind {
+        Method(func) => (
+            Highlight::new(HighlightTag::Function) | HighlightModifier::Associated,
+            Some(func.module(db)),
+        ),
+        Macro(_) => (Highlight::new(HighlightTag::Macro), None),
+        FieldAccess(_) => (Highlight::new(HighlightTag::Field), None),
+        AssocItem(hir::ImplItem::Method(func)) => {
+            let mut highlight =
+                Highlight::new(HighlightTag::Function) | HighlightModifier::Associated;
+            if !func.data(db).has_self_param() {
+                highlight = highlight | HighlightModifier::Static;
+            }
+            (highlight, Some(func.module(db)))
+        }
+        AssocItem(hir::ImplItem::Const(it)) => (
+            Highlight::new(HighlightTag::Constant) | HighlightModifier::Associated,
+            Some(it.module(db)),
+        ),
+        AssocItem(hir::ImplItem::TypeAlias(it)) => (
+            Highlight::new(HighlightTag::Type) | HighlightModifier::Associated,
+            Some(it.module(db)),
+        ),
+        Def(hir::ModuleDef::Module(it)) => (Highlight::new(HighlightTag::Module), Some(*it)),
+        Def(hir::ModuleDef::Function(it)) => {
+            (Highlight::new(HighlightTag::Function), Some(it.module(db)))
+        }
+        Def(hir::ModuleDef::Struct(it)) => {
+            (Highlight::new(HighlightTag::Type), Some(it.module(db)))
+        }
+        Def(hir::ModuleDef::Union(it)) => (Highlight::new(HighlightTag::Type), Some(it.module(db))),
+        Def(hir::ModuleDef::Enum(it)) => (Highlight::new(HighlightTag::Type), Some(it.module(db))),
+        Def(hir::ModuleDef::EnumVariant(it)) => {
+            (Highlight::new(HighlightTag::Constant), Some(it.module(db)))
+        }
+        Def(hir::ModuleDef::Const(it)) => {
+            (Highlight::new(HighlightTag::Constant), Some(it.module(db)))
+        }
+        Def(hir::ModuleDef::Static(it)) => (
+            Highlight::new(HighlightTag::Constant) | HighlightModifier::Static,
+            Some(it.module(db)),
+        ),
+        Def(hir::ModuleDef::Trait(it)) => (Highlight::new(HighlightTag::Type), Some(it.module(db))),
+        Def(hir::ModuleDef::TypeAlias(it)) => {
+            (Highlight::new(HighlightTag::Type), Some(it.module(db)))
+        }
+        Def(hir::ModuleDef::BuiltinType(_)) => (Highlight::new(HighlightTag::Type), None),
+        SelfType(_) => (Highlight::new(HighlightTag::Type), None),
+        Pat(_) => (Highlight::new(HighlightTag::Variable), None),
+        SelfParam(_) => (Highlight::new(HighlightTag::Type), None),
+        GenericParam(_) => (Highlight::new(HighlightTag::TypeParam), None),
+    };
+    match module {
+        Some(module) if is_library_module(db, module) => highlight | HighlightModifier::Library,
+        _ => highlight,
+    }
+}
+
+fn highlight_name_declaration(name: &ast::Name) -> Highlight {
+    let tag = match name.syntax().parent().map(|it| it.kind()) {
+        Some(STRUCT_DEF) | Some(ENUM_DEF) | Some(TRAIT_DEF) | Some(TYPE_ALIAS_DEF) => {
+            HighlightTag::Type
+        }
+        Some(TYPE_PARAM) => HighlightTag::TypeParam,
+        Some(NAMED_FIELD_DEF) => HighlightTag::Field,
+        Some(CONST_DEF) | Some(STATIC_DEF) | Some(ENUM_VARIANT) => HighlightTag::Constant,
+        Some(MODULE) => HighlightTag::Module,
+        Some(MACRO_CALL) => HighlightTag::Macro,
+        _ => HighlightTag::Function,
+    };
+    let mut highlight = Highlight::new(tag) | HighlightModifier::Declaration;
+    if let Some(parent) = name.syntax().parent() {
+        if parent.kind() == STATIC_DEF {
+            highlight = highlight | HighlightModifier::Static;
+        }
+        let in_item_list = parent.parent().map(|it| it.kind()) == Some(ITEM_LIST);
+        let in_impl_or_trait = parent
+            .parent()
+            .and_then(|it| it.parent())
+            .map(|it| it.kind() == IMPL_BLOCK || it.kind() == TRAIT_DEF)
+            .unwrap_or(false);
+        if in_item_list && in_impl_or_trait {
+            highlight = highlight | HighlightModifier::Associated;
+        }
+    }
+    highlight
+}
+
 pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRange> {
     let _p = profile("highlight");
     let parse = db.parse(file_id);
@@ -86,37 +346,21 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
             continue;
         }
         let mut binding_hash = None;
-        let tag = match node.kind() {
+        let mut highlight = match node.kind() {
             FN_DEF => {
                 bindings_shadow_count.clear();
                 continue;
             }
-            COMMENT => "comment",
-            STRING | RAW_STRING | RAW_BYTE_STRING | BYTE_STRING => "string",
-            ATTR => "attribute",
+            COMMENT => Highlight::new(HighlightTag::Comment),
+            STRING | RAW_STRING | RAW_BYTE_STRING | BYTE_STRING => {
+                Highlight::new(HighlightTag::String)
+            }
+            ATTR => Highlight::new(HighlightTag::Attribute),
             NAME_REF => {
                 if let Some(name_ref) = node.as_node().cloned().and_then(ast::NameRef::cast) {
                     // FIXME: try to reuse the SourceAnalyzers
                     let analyzer = hir::SourceAnalyzer::new(db, file_id, name_ref.syntax(), None);
                     match classify_name_ref(db, &analyzer, &name_ref) {
-                        Some(Method(_)) => "function",
-                        Some(Macro(_)) => "macro",
-                        Some(FieldAccess(_)) => "field",
-                        Some(AssocItem(hir::ImplItem::Method(_))) => "function",
-                        Some(AssocItem(hir::ImplItem::Const(_))) => "constant",
-                        Some(AssocItem(hir::ImplItem::TypeAlias(_))) => "type",
-                        Some(Def(hir::ModuleDef::Module(_))) => "module",
-                        Some(Def(hir::ModuleDef::Function(_))) => "function",
-                        Some(Def(hir::ModuleDef::Struct(_))) => "type",
-                        Some(Def(hir::ModuleDef::Union(_))) => "type",
-                        Some(Def(hir::ModuleDef::Enum(_))) => "type",
-                        Some(Def(hir::ModuleDef::EnumVariant(_))) => "constant",
-                        Some(Def(hir::ModuleDef::Const(_))) => "constant",
-                        Some(Def(hir::ModuleDef::Static(_))) => "constant",
-                        Some(Def(hir::ModuleDef::Trait(_))) => "type",
-                        Some(Def(hir::ModuleDef::TypeAlias(_))) => "type",
-                        Some(Def(hir::ModuleDef::BuiltinType(_))) => "type",
-                        Some(SelfType(_)) => "type",
                         Some(Pat(ptr)) => {
                             let pat = ptr.to_node(&root);
                             if let Some(name) = pat.name() {
@@ -127,18 +371,18 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                                     Some(calc_binding_hash(file_id, &text, *shadow_count))
                             }
 
+                            let highlight = Highlight::new(HighlightTag::Variable);
                             if is_variable_mutable(db, &analyzer, ptr.to_node(&root)) {
-                                "variable.mut"
+                                highlight | HighlightModifier::Mutable
                             } else {
-                                "variable"
+                                highlight
                             }
                         }
-                        Some(SelfParam(_)) => "type",
-                        Some(GenericParam(_)) => "type",
-                        None => "text",
+                        Some(kind) => highlight_name_ref_kind(db, &kind),
+                        None => Highlight::new(HighlightTag::Text),
                     }
                 } else {
-                    "text"
+                    Highlight::new(HighlightTag::Text)
                 }
             }
             NAME => {
@@ -153,36 +397,27 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                             binding_hash = Some(calc_binding_hash(file_id, &text, *shadow_count))
                         }
 
+                        let highlight =
+                            Highlight::new(HighlightTag::Variable) | HighlightModifier::Declaration;
                         if is_variable_mutable(db, &analyzer, pat) {
-                            "variable.mut"
+                            highlight | HighlightModifier::Mutable
                         } else {
-                            "variable"
+                            highlight
                         }
-                    } else if name
-                        .syntax()
-                        .parent()
-                        .map(|x| {
-                            x.kind() == TYPE_PARAM
-                                || x.kind() == STRUCT_DEF
-                                || x.kind() == ENUM_DEF
-                                || x.kind() == TRAIT_DEF
-                                || x.kind() == TYPE_ALIAS_DEF
-                        })
-                        .unwrap_or(false)
-                    {
-                        "type"
                     } else {
-                        "function"
+                        highlight_name_declaration(&name)
                     }
                 } else {
-                    "text"
+                    Highlight::new(HighlightTag::Text)
                 }
             }
-            INT_NUMBER | FLOAT_NUMBER | CHAR | BYTE => "literal",
-            LIFETIME => "parameter",
-            T![unsafe] => "keyword.unsafe",
-            k if is_control_keyword(k) => "keyword.control",
-            k if k.is_keyword() => "keyword",
+            INT_NUMBER | FLOAT_NUMBER | CHAR | BYTE => Highlight::new(HighlightTag::Literal),
+            LIFETIME => Highlight::new(HighlightTag::Lifetime),
+            T![unsafe] => Highlight::new(HighlightTag::Keyword) | HighlightModifier::Unsafe,
+            k if is_control_keyword(k) => {
+                Highlight::new(HighlightTag::Keyword) | HighlightModifier::ControlFlow
+            }
+            k if k.is_keyword() => Highlight::new(HighlightTag::Keyword),
             _ => {
                 if let Some(macro_call) = node.as_node().cloned().and_then(ast::MacroCall::cast) {
                     if let Some(path) = macro_call.path() {
@@ -199,7 +434,7 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                                 }
                                 res.push(HighlightedRange {
                                     range: TextRange::from_to(range_start, range_end),
-                                    tag: "macro",
+                                    highlight: Highlight::new(HighlightTag::Macro),
                                     binding_hash: None,
                                 })
                             }
@@ -209,7 +444,10 @@ pub(crate) fn highlight(db: &RootDatabase, file_id: FileId) -> Vec<HighlightedRa
                 continue;
             }
         };
-        res.push(HighlightedRange { range: node.text_range(), tag, binding_hash })
+        if is_in_macro_call(&node) {
+            highlight = highlight | HighlightModifier::MacroExpanded;
+        }
+        res.push(HighlightedRange { range: node.text_range(), highlight, binding_hash })
     }
     res
 }
@@ -256,7 +494,14 @@ pub(crate) fn highlight_as_html(db: &RootDatabase, file_id: FileId, rainbow: boo
         if ranges.is_empty() {
             buf.push_str(&text);
         } else {
-            let classes = ranges.iter().map(|x| x.tag).collect::<Vec<_>>().join(" ");
+            let classes = ranges
+                .iter()
+                .flat_map(|x| {
+                    let modifiers = x.highlight.modifiers.iter().map(|it| it.as_str());
+                    std::iter::once(x.highlight.tag.as_str()).chain(modifiers)
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
             let binding_hash = ranges.first().and_then(|x| x.binding_hash);
             let color = match (rainbow, binding_hash) {
                 (true, Some(hash)) => format!(
@@ -286,18 +531,19 @@ pre                 { color: #DCDCCC; background: #3F3F3F; font-size: 22px; padd
 .comment            { color: #7F9F7F; }
 .string             { color: #CC9393; }
 .function           { color: #93E0E3; }
-.parameter          { color: #94BFF3; }
+.lifetime           { color: #94BFF3; }
+.type_param         { color: #DFAF8F; }
 .builtin            { color: #DD6718; }
 .text               { color: #DCDCCC; }
 .attribute          { color: #94BFF3; }
 .literal            { color: #BFEBBF; }
 .macro              { color: #94BFF3; }
 .variable           { color: #DCDCCC; }
-.variable\\.mut     { color: #DCDCCC; text-decoration: underline; }
+.mutable            { text-decoration: underline; }
 
 .keyword            { color: #F0DFAF; }
-.keyword\\.unsafe   { color: #DFAF8F; }
-.keyword\\.control  { color: #F0DFAF; font-weight: bold; }
+.keyword.unsafe     { color: #DFAF8F; }
+.keyword.control    { color: #F0DFAF; font-weight: bold; }
 </style>
 ";
 
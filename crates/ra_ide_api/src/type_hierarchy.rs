@@ -0,0 +1,264 @@
+//! Entry point for type-hierarchy
+
+use std::iter;
+
+use hir::{db::HirDatabase, source_binder};
+use ra_db::SourceDatabase;
+use ra_syntax::{algo::find_node_at_offset, ast, AstNode};
+use rustc_hash::FxHashSet;
+
+use crate::{
+    db::RootDatabase,
+    impls::trait_impls,
+    name_ref_kind::{classify_name_ref, NameRefKind},
+    FilePosition, NavigationTarget, RangeInfo,
+};
+
+/// A trait or a type together with the traits and types related to it.
+///
+/// The supertypes of a trait are its supertraits and the supertypes of a type
+/// are the traits it implements. The subtypes of a trait are the types
+/// implementing it. Supertypes are resolved recursively, subtypes only for the
+/// item the hierarchy was requested for.
+#[derive(Debug, Clone)]
+pub struct TypeHierarchy {
+    pub item: NavigationTarget,
+    pub supertypes: Vec<TypeHierarchy>,
+    pub subtypes: Vec<TypeHierarchy>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum TypeDef {
+    Trait(hir::Trait),
+    Adt(hir::AdtDef),
+}
+
+pub(crate) fn type_hierarchy(
+    db: &RootDatabase,
+    position: FilePosition,
+) -> Option<RangeInfo<TypeHierarchy>> {
+    let RangeInfo { range, info: def } = type_def_at(db, position)?;
+    let mut visited = FxHashSet::default();
+    let hierarchy = match def {
+        TypeDef::Trait(tr) => {
+            let mut hierarchy = trait_hierarchy(db, tr, &mut visited);
+            hierarchy.subtypes = implementing_types(db, tr)?;
+            hierarchy
+        }
+        TypeDef::Adt(adt) => {
+            let mut supertypes = Vec::new();
+            for tr in implemented_traits(db, adt)? {
+                if visited.insert(tr) {
+                    supertypes.push(trait_hierarchy(db, tr, &mut visited));
+                }
+            }
+            let item = NavigationTarget::from_adt_def(db, adt);
+            TypeHierarchy { item, supertypes, subtypes: Vec::new() }
+        }
+    };
+    Some(RangeInfo::new(range, hierarchy))
+}
+
+/// Finds the trait or type which is either defined or referenced at the
+/// position.
+fn type_def_at(db: &RootDatabase, position: FilePosition) -> Option<RangeInfo<TypeDef>> {
+    let parse = db.parse(position.file_id);
+    let syntax = parse.tree().syntax().clone();
+    if let Some(name_ref) = find_node_at_offset::<ast::NameRef>(&syntax, position.offset) {
+        let analyzer = hir::SourceAnalyzer::new(db, position.file_id, name_ref.syntax(), None);
+        let def = match classify_name_ref(db, &analyzer, &name_ref)? {
+            NameRefKind::Def(hir::ModuleDef::Trait(it)) => TypeDef::Trait(it),
+            NameRefKind::Def(hir::ModuleDef::Struct(it)) => TypeDef::Adt(it.into()),
+            NameRefKind::Def(hir::ModuleDef::Enum(it)) => TypeDef::Adt(it.into()),
+            NameRefKind::Def(hir::ModuleDef::Union(it)) => TypeDef::Adt(it.into()),
+            _ => return None,
+        };
+        return Some(RangeInfo::new(name_ref.syntax().text_range(), def));
+    }
+    let name = find_node_at_offset::<ast::Name>(&syntax, position.offset)?;
+    let parent = name.syntax().parent()?;
+    let module = source_binder::module_from_child_node(db, position.file_id, &parent)?;
+    let def = if let Some(it) = ast::TraitDef::cast(parent.clone()) {
+        TypeDef::Trait(source_binder::trait_from_module(db, module, &it))
+    } else if let Some(it) = ast::StructDef::cast(parent.clone()) {
+        TypeDef::Adt(source_binder::struct_from_module(db, module, &it).into())
+    } else if let Some(it) = ast::EnumDef::cast(parent) {
+        TypeDef::Adt(source_binder::enum_from_module(db, module, &it).into())
+    } else {
+        return None;
+    };
+    Some(RangeInfo::new(name.syntax().text_range(), def))
+}
+
+/// Builds the hierarchy of the supertraits of `tr`. Traits which are already
+/// `visited` are skipped, so that cycles and diamonds are only expanded once.
+fn trait_hierarchy(
+    db: &RootDatabase,
+    tr: hir::Trait,
+    visited: &mut FxHashSet<hir::Trait>,
+) -> TypeHierarchy {
+    visited.insert(tr);
+    let mut supertypes = Vec::new();
+    for it in tr.super_traits(db) {
+        if visited.insert(it) {
+            supertypes.push(trait_hierarchy(db, it, visited));
+        }
+    }
+    let item = NavigationTarget::from_def_source(db, tr);
+    TypeHierarchy { item, supertypes, subtypes: Vec::new() }
+}
+
+/// Finds the types with an impl of `tr` in the crate of the trait and in the
+/// crates depending on it. Impls for types which are not structs, enums or
+/// unions are returned as impl blocks.
+fn implementing_types(db: &RootDatabase, tr: hir::Trait) -> Option<Vec<TypeHierarchy>> {
+    let mut seen = FxHashSet::default();
+    let mut res = Vec::new();
+    for imp in trait_impls(db, tr) {
+        let item = match imp.target_ty(db).as_adt() {
+            Some((adt, _)) => {
+                if !seen.insert(adt) {
+                    continue;
+                }
+                NavigationTarget::from_adt_def(db, adt)
+            }
+            None => NavigationTarget::from_impl_block(db, imp),
+        };
+        res.push(TypeHierarchy { item, supertypes: Vec::new(), subtypes: Vec::new() });
+    }
+    Some(res)
+}
+
+/// Finds the traits which have an impl for `adt` in the crate of the type and
+/// in the crates depending on it, in the order of their definitions.
+fn implemented_traits(db: &RootDatabase, adt: hir::AdtDef) -> Option<Vec<hir::Trait>> {
+    let krate = adt.krate(db)?;
+
+    let mut res = Vec::new();
+    let impls = iter::once(krate)
+        .chain(krate.reverse_dependencies(db))
+        .flat_map(|krate| db.impls_in_crate(krate).all_impls().collect::<Vec<_>>());
+    for imp in impls {
+        if imp.target_ty(db).as_adt().map(|(it, _)| it) != Some(adt) {
+            continue;
+        }
+        if let Some(trait_ref) = imp.target_trait_ref(db) {
+            if !res.contains(&trait_ref.trait_) {
+                res.push(trait_ref.trait_);
+            }
+        }
+    }
+    res.sort_by_key(|&tr| {
+        let nav = NavigationTarget::from_def_source(db, tr);
+        (nav.file_id(), nav.full_range().start())
+    });
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{mock_analysis::analysis_and_position, TypeHierarchy};
+
+    fn check_hierarchy(fixture: &str, expected: &str) {
+        let (analysis, pos) = analysis_and_position(fixture);
+        let hierarchy = analysis.type_hierarchy(pos).unwrap().unwrap().info;
+        let mut actual = String::new();
+        render(&hierarchy, "", 0, &mut actual);
+        test_utils::assert_eq_text!(expected.trim(), actual.trim());
+    }
+
+    fn render(hierarchy: &TypeHierarchy, marker: &str, depth: usize, buf: &mut String) {
+        buf.push_str(&format!(
+            "{}{}{}\n",
+            "  ".repeat(depth),
+            marker,
+            hierarchy.item.debug_render().trim()
+        ));
+        for it in hierarchy.supertypes.iter() {
+            render(it, "super ", depth + 1, buf);
+        }
+        for it in hierarchy.subtypes.iter() {
+            render(it, "sub ", depth + 1, buf);
+        }
+    }
+
+    #[test]
+    fn type_hierarchy_of_trait() {
+        check_hierarchy(
+            "
+            //- /lib.rs
+            trait A {}
+            trait B: A {}
+            trait C<|>: B + A {}
+            struct S;
+            impl C for S {}
+            enum E {}
+            impl C for E {}
+            impl C for u32 {}
+            ",
+            "
+C TRAIT_DEF FileId(1) [25; 42) [31; 32)
+  super B TRAIT_DEF FileId(1) [11; 24) [17; 18)
+    super A TRAIT_DEF FileId(1) [0; 10) [6; 7)
+  sub S STRUCT_DEF FileId(1) [43; 52) [50; 51)
+  sub E ENUM_DEF FileId(1) [69; 78) [74; 75)
+  sub impl IMPL_BLOCK FileId(1) [95; 112)
+",
+        );
+    }
+
+    #[test]
+    fn type_hierarchy_of_type() {
+        check_hierarchy(
+            "
+            //- /lib.rs
+            trait A {}
+            trait B: A {}
+            struct S<|>;
+            impl S {}
+            impl B for S {}
+            impl A for S {}
+            ",
+            "
+S STRUCT_DEF FileId(1) [25; 34) [32; 33)
+  super A TRAIT_DEF FileId(1) [0; 10) [6; 7)
+  super B TRAIT_DEF FileId(1) [11; 24) [17; 18)
+",
+        );
+    }
+
+    #[test]
+    fn type_hierarchy_from_reference() {
+        check_hierarchy(
+            "
+            //- /lib.rs
+            trait A {}
+            trait B where Self: A {}
+            struct S;
+            impl B<|> for S {}
+            ",
+            "
+B TRAIT_DEF FileId(1) [11; 35) [17; 18)
+  super A TRAIT_DEF FileId(1) [0; 10) [6; 7)
+  sub S STRUCT_DEF FileId(1) [36; 45) [43; 44)
+",
+        );
+    }
+
+    #[test]
+    fn type_hierarchy_includes_dependent_crates() {
+        check_hierarchy(
+            "
+            //- /lib.rs
+            struct S;
+            impl foo::T for S {}
+            //- /foo/lib.rs
+            pub trait T<|> {}
+            ",
+            "
+T TRAIT_DEF FileId(2) [0; 14) [10; 11)
+  sub S STRUCT_DEF FileId(1) [0; 9) [7; 8)
+",
+        );
+    }
+}
@@ -6,7 +6,25 @@ use lsp_types::{
     TextDocumentSyncOptions, TypeDefinitionProviderCapability,
 };
 
-pub fn server_capabilities() -> ServerCapabilities {
+use crate::{req, semantic_tokens};
+
+/// `lsp-types` does not know about semantic tokens, declarations and call
+/// hierarchies yet, so their providers are added to the serialized
+/// capabilities.
+pub fn server_capabilities() -> serde_json::Value {
+    let mut caps = serde_json::to_value(lsp_capabilities()).unwrap();
+    caps["semanticTokensProvider"] = serde_json::to_value(req::SemanticTokensOptions {
+        legend: semantic_tokens::legend(),
+        range: false,
+        full: req::SemanticTokensFullOptions { delta: true },
+    })
+    .unwrap();
+    caps["declarationProvider"] = true.into();
+    caps["callHierarchyProvider"] = true.into();
+    caps
+}
+
+fn lsp_capabilities() -> ServerCapabilities {
     ServerCapabilities {
         text_document_sync: Some(TextDocumentSyncCapability::Options(TextDocumentSyncOptions {
             open_close: Some(true),
@@ -21,7 +39,9 @@ pub fn server_capabilities() -> ServerCapabilities {
             trigger_characters: Some(vec![":".to_string(), ".".to_string()]),
         }),
         signature_help_provider: Some(SignatureHelpOptions {
-            trigger_characters: Some(vec!["(".to_string(), ",".to_string(), ")".to_string()]),
+            trigger_characters: Some(
+                ["(", ",", ")", "<", "[", "{"].iter().map(|it| it.to_string()).collect(),
+            ),
         }),
         definition_provider: Some(true),
         type_definition_provider: Some(TypeDefinitionProviderCapability::Simple(true)),
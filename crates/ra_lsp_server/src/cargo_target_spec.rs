@@ -4,7 +4,7 @@ use crate::{
     Result,
 };
 
-use ra_ide_api::{FileId, RunnableKind};
+use ra_ide_api::{FileId, RunnableKind, TestId};
 
 pub(crate) fn runnable_args(
     world: &WorldSnapshot,
@@ -14,13 +14,16 @@ pub(crate) fn runnable_args(
     let spec = CargoTargetSpec::for_file(world, file_id)?;
     let mut res = Vec::new();
     match kind {
-        RunnableKind::Test { name } => {
+        RunnableKind::Test { test_id } => {
             res.push("test".to_string());
             if let Some(spec) = spec {
                 spec.push_to(&mut res);
             }
             res.push("--".to_string());
-            res.push(name.to_string());
+            res.push(test_id.to_string());
+            if let TestId::Path(_) = test_id {
+                res.push("--exact".to_string());
+            }
             res.push("--nocapture".to_string());
         }
         RunnableKind::TestMod { path } => {
@@ -32,15 +35,30 @@ pub(crate) fn runnable_args(
             res.push(path.to_string());
             res.push("--nocapture".to_string());
         }
-        RunnableKind::Bench { name } => {
+        RunnableKind::Bench { test_id } => {
             res.push("bench".to_string());
             if let Some(spec) = spec {
                 spec.push_to(&mut res);
             }
             res.push("--".to_string());
-            res.push(name.to_string());
+            res.push(test_id.to_string());
+            if let TestId::Path(_) = test_id {
+                res.push("--exact".to_string());
+            }
             res.push("--nocapture".to_string());
         }
+        RunnableKind::DocTest { test_id } => {
+            // Doctests are run by rustdoc, which only supports the library
+            // target, so `--lib` and `--exact` are omitted.
+            res.push("test".to_string());
+            res.push("--doc".to_string());
+            if let Some(spec) = spec {
+                res.push("--package".to_string());
+                res.push(spec.package);
+            }
+            res.push("--".to_string());
+            res.push(test_id.to_string());
+        }
         RunnableKind::Bin => {
             res.push("run".to_string());
             if let Some(spec) = spec {
@@ -21,6 +21,10 @@ pub struct ServerConfig {
     pub exclude_globs: Vec<String>,
 
     pub lru_capacity: Option<usize>,
+
+    /// Attributes which mark test functions in addition to `#[test]`, like
+    /// `tokio::test`.
+    pub test_attributes: Vec<String>,
 }
 
 impl Default for ServerConfig {
@@ -30,6 +34,7 @@ impl Default for ServerConfig {
             show_workspace_loaded: true,
             exclude_globs: Vec::new(),
             lru_capacity: None,
+            test_attributes: Vec::new(),
         }
     }
 }
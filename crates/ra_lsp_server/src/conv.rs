@@ -1,13 +1,14 @@
 use lsp_types::{
-    self, CreateFile, DiagnosticSeverity, DocumentChangeOperation, DocumentChanges, Documentation,
-    Location, LocationLink, MarkupContent, MarkupKind, Position, Range, RenameFile, ResourceOp,
-    SymbolKind, TextDocumentEdit, TextDocumentIdentifier, TextDocumentItem,
-    TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier, WorkspaceEdit,
+    self, CreateFile, DiagnosticSeverity, DocumentChangeOperation, DocumentChanges,
+    DocumentHighlightKind, Documentation, Location, LocationLink, MarkupContent, MarkupKind,
+    Position, Range, RenameFile, ResourceOp, SymbolKind, TextDocumentEdit, TextDocumentIdentifier,
+    TextDocumentItem, TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
+    WorkspaceEdit,
 };
 use ra_ide_api::{
     translate_offset_with_edit, CompletionItem, CompletionItemKind, FileId, FilePosition,
     FileRange, FileSystemEdit, InsertTextFormat, LineCol, LineIndex, NavigationTarget, RangeInfo,
-    Severity, SourceChange, SourceFileEdit,
+    ReferenceAccess, Severity, SourceChange, SourceFileEdit, TypeHierarchy,
 };
 use ra_syntax::{SyntaxKind, TextRange, TextUnit};
 use ra_text_edit::{AtomTextEdit, TextEdit};
@@ -89,6 +90,17 @@ impl Conv for Severity {
     }
 }
 
+impl Conv for ReferenceAccess {
+    type Output = DocumentHighlightKind;
+    fn conv(self) -> DocumentHighlightKind {
+        match self {
+            ReferenceAccess::Definition => DocumentHighlightKind::Text,
+            ReferenceAccess::Read => DocumentHighlightKind::Read,
+            ReferenceAccess::Write => DocumentHighlightKind::Write,
+        }
+    }
+}
+
 impl ConvWith for CompletionItem {
     type Ctx = LineIndex;
     type Output = ::lsp_types::CompletionItem;
@@ -186,24 +198,24 @@ impl Conv for ra_ide_api::Documentation {
     }
 }
 
-impl Conv for ra_ide_api::FunctionSignature {
+impl Conv for ra_ide_api::CallInfo {
     type Output = lsp_types::SignatureInformation;
     fn conv(self) -> Self::Output {
         use lsp_types::{ParameterInformation, ParameterLabel, SignatureInformation};
 
-        let label = self.to_string();
-
-        let documentation = self.doc.map(|it| it.conv());
+        let label = self.signature.to_string();
 
         let parameters: Vec<ParameterInformation> = self
-            .parameters
-            .into_iter()
+            .parameters()
+            .iter()
             .map(|param| ParameterInformation {
-                label: ParameterLabel::Simple(param),
+                label: ParameterLabel::Simple(param.clone()),
                 documentation: None,
             })
             .collect();
 
+        let documentation = self.signature.doc.map(|it| it.conv());
+
         SignatureInformation { label, documentation, parameters: Some(parameters) }
     }
 }
@@ -337,8 +349,8 @@ impl TryConvWith for SourceChange {
             }
         };
         let mut document_changes: Vec<DocumentChangeOperation> = Vec::new();
-        for resource_op in self.file_system_edits.try_conv_with(world)? {
-            document_changes.push(DocumentChangeOperation::Op(resource_op));
+        for operations in self.file_system_edits.try_conv_with(world)? {
+            document_changes.extend(operations);
         }
         for text_document_edit in self.source_file_edits.try_conv_with(world)? {
             document_changes.push(DocumentChangeOperation::Edit(text_document_edit));
@@ -367,17 +379,31 @@ impl TryConvWith for SourceFileEdit {
 
 impl TryConvWith for FileSystemEdit {
     type Ctx = WorldSnapshot;
-    type Output = ResourceOp;
-    fn try_conv_with(self, world: &WorldSnapshot) -> Result<ResourceOp> {
+    type Output = Vec<DocumentChangeOperation>;
+    fn try_conv_with(self, world: &WorldSnapshot) -> Result<Vec<DocumentChangeOperation>> {
         let res = match self {
-            FileSystemEdit::CreateFile { source_root, path } => {
+            FileSystemEdit::CreateFile { source_root, path, initial_contents } => {
                 let uri = world.path_to_uri(source_root, &path)?;
-                ResourceOp::Create(CreateFile { uri, options: None })
+                let create = ResourceOp::Create(CreateFile { uri: uri.clone(), options: None });
+                let mut res = vec![DocumentChangeOperation::Op(create)];
+                if !initial_contents.is_empty() {
+                    // The new file is empty, so the contents are inserted at its start.
+                    let start = Position::new(0, 0);
+                    res.push(DocumentChangeOperation::Edit(TextDocumentEdit {
+                        text_document: VersionedTextDocumentIdentifier { uri, version: None },
+                        edits: vec![lsp_types::TextEdit::new(
+                            Range::new(start, start),
+                            initial_contents,
+                        )],
+                    }));
+                }
+                res
             }
             FileSystemEdit::MoveFile { src, dst_source_root, dst_path } => {
                 let old_uri = world.file_id_to_uri(src)?;
                 let new_uri = world.path_to_uri(dst_source_root, &dst_path)?;
-                ResourceOp::Rename(RenameFile { old_uri, new_uri, options: None })
+                let rename = ResourceOp::Rename(RenameFile { old_uri, new_uri, options: None });
+                vec![DocumentChangeOperation::Op(rename)]
             }
         };
         Ok(res)
@@ -394,6 +420,15 @@ impl TryConvWith for &NavigationTarget {
     }
 }
 
+impl TryConvWith for FileRange {
+    type Ctx = WorldSnapshot;
+    type Output = Location;
+    fn try_conv_with(self, world: &WorldSnapshot) -> Result<Location> {
+        let line_index = world.analysis().file_line_index(self.file_id)?;
+        to_location(self.file_id, self.range, world, &line_index)
+    }
+}
+
 impl TryConvWith for (FileId, RangeInfo<NavigationTarget>) {
     type Ctx = WorldSnapshot;
     type Output = LocationLink;
@@ -454,6 +489,44 @@ pub fn to_location(
     Ok(loc)
 }
 
+pub fn to_call_hierarchy_item(
+    nav: &NavigationTarget,
+    world: &WorldSnapshot,
+) -> Result<req::CallHierarchyItem> {
+    let line_index = world.analysis().file_line_index(nav.file_id())?;
+    let range = nav.full_range().conv_with(&line_index);
+    let selection_range = nav.focus_range().map(|it| it.conv_with(&line_index)).unwrap_or(range);
+    Ok(req::CallHierarchyItem {
+        name: nav.name().to_string(),
+        kind: nav.kind().conv(),
+        detail: nav.description().map(|it| it.to_string()),
+        uri: nav.file_id().try_conv_with(world)?,
+        range,
+        selection_range,
+    })
+}
+
+pub fn to_type_hierarchy_item(
+    hierarchy: &TypeHierarchy,
+    world: &WorldSnapshot,
+) -> Result<req::TypeHierarchyItem> {
+    let req::CallHierarchyItem { name, kind, detail, uri, range, selection_range } =
+        to_call_hierarchy_item(&hierarchy.item, world)?;
+    let to_items = |items: &[TypeHierarchy]| {
+        items.iter().map(|it| to_type_hierarchy_item(it, world)).collect::<Result<Vec<_>>>()
+    };
+    Ok(req::TypeHierarchyItem {
+        name,
+        kind,
+        detail,
+        uri,
+        range,
+        selection_range,
+        supertypes: to_items(&hierarchy.supertypes)?,
+        subtypes: to_items(&hierarchy.subtypes)?,
+    })
+}
+
 pub trait MapConvWith<'a>: Sized + 'a {
     type Ctx;
     type Output;
@@ -4,6 +4,7 @@ mod conv;
 mod main_loop;
 mod markdown;
 mod project_model;
+mod semantic_tokens;
 pub mod req;
 pub mod config;
 mod world;
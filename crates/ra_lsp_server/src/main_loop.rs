@@ -96,6 +96,7 @@ pub fn main_loop(
                 .and_then(|it| it.definition)
                 .and_then(|it| it.link_support)
                 .unwrap_or(false),
+            test_attributes: config.test_attributes,
         },
     );
 
@@ -346,16 +347,26 @@ fn on_request(
         })?
         .on::<req::AnalyzerStatus>(handlers::handle_analyzer_status)?
         .on::<req::SyntaxTree>(handlers::handle_syntax_tree)?
+        .on::<req::ExpandMacro>(handlers::handle_expand_macro)?
+        .on::<req::Ssr>(handlers::handle_ssr)?
+        .on::<req::ChangeSignature>(handlers::handle_change_signature)?
         .on::<req::ExtendSelection>(handlers::handle_extend_selection)?
         .on::<req::OnTypeFormatting>(handlers::handle_on_type_formatting)?
         .on::<req::DocumentSymbolRequest>(handlers::handle_document_symbol)?
         .on::<req::WorkspaceSymbol>(handlers::handle_workspace_symbol)?
         .on::<req::GotoDefinition>(handlers::handle_goto_definition)?
+        .on::<req::GotoDeclaration>(handlers::handle_goto_declaration)?
         .on::<req::GotoImplementation>(handlers::handle_goto_implementation)?
         .on::<req::GotoTypeDefinition>(handlers::handle_goto_type_definition)?
         .on::<req::ParentModule>(handlers::handle_parent_module)?
+        .on::<req::CallHierarchyPrepare>(handlers::handle_call_hierarchy_prepare)?
+        .on::<req::CallHierarchyIncomingCalls>(handlers::handle_call_hierarchy_incoming)?
+        .on::<req::CallHierarchyOutgoingCalls>(handlers::handle_call_hierarchy_outgoing)?
+        .on::<req::TypeHierarchy>(handlers::handle_type_hierarchy)?
         .on::<req::Runnables>(handlers::handle_runnables)?
         .on::<req::DecorationsRequest>(handlers::handle_decorations)?
+        .on::<req::SemanticTokensRequest>(handlers::handle_semantic_tokens)?
+        .on::<req::SemanticTokensDeltaRequest>(handlers::handle_semantic_tokens_delta)?
         .on::<req::Completion>(handlers::handle_completion)?
         .on::<req::CodeActionRequest>(handlers::handle_code_action)?
         .on::<req::CodeLensRequest>(handlers::handle_code_lens)?
@@ -431,6 +442,7 @@ fn on_notification(
             if let Some(file_id) = state.vfs.write().remove_file_overlay(path.as_path()) {
                 subs.remove_sub(FileId(file_id.0));
             }
+            state.semantic_tokens_cache.write().remove(&uri);
             let params = req::PublishDiagnosticsParams { uri, diagnostics: Vec::new() };
             let not = RawNotification::new::<req::PublishDiagnostics>(&params);
             msg_sender.send(not.into()).unwrap();
@@ -6,13 +6,14 @@ use lsp_types::{
     DocumentFormattingParams, DocumentHighlight, DocumentSymbol, FoldingRange, FoldingRangeKind,
     FoldingRangeParams, Hover, HoverContents, Location, MarkupContent, MarkupKind, Position,
     PrepareRenameResponse, Range, RenameParams, SymbolInformation, TextDocumentIdentifier,
-    TextEdit, WorkspaceEdit,
+    TextDocumentPositionParams, TextEdit, WorkspaceEdit,
 };
 use ra_ide_api::{
-    AssistId, Cancelable, FileId, FilePosition, FileRange, FoldKind, Query, RunnableKind,
+    AssistId, Cancelable, FileId, FilePosition, FileRange, FoldKind, Highlight, HighlightModifier,
+    HighlightTag, Query, RunnableKind, SignatureParam, SymbolKind,
 };
 use ra_prof::profile;
-use ra_syntax::{AstNode, SyntaxKind, TextRange, TextUnit};
+use ra_syntax::{AstNode, SyntaxKind, TextUnit};
 use rustc_hash::FxHashMap;
 use serde::{Deserialize, Serialize};
 use serde_json::to_value;
@@ -20,8 +21,12 @@ use url_serde::Ser;
 
 use crate::{
     cargo_target_spec::{runnable_args, CargoTargetSpec},
-    conv::{to_location, Conv, ConvWith, MapConvWith, TryConvWith, TryConvWithToVec},
+    conv::{
+        to_call_hierarchy_item, to_location, to_type_hierarchy_item, Conv, ConvWith, MapConvWith,
+        TryConvWith, TryConvWithToVec,
+    },
     req::{self, Decoration, InlayHint, InlayHintsParams, InlayKind},
+    semantic_tokens::{self, SemanticTokensBuilder},
     world::WorldSnapshot,
     LspError, Result,
 };
@@ -45,6 +50,61 @@ pub fn handle_syntax_tree(world: WorldSnapshot, params: req::SyntaxTreeParams) -
     Ok(res)
 }
 
+pub fn handle_expand_macro(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<Option<req::ExpandedMacro>> {
+    let _p = profile("handle_expand_macro");
+    let position = params.try_conv_with(&world)?;
+    let res = world.analysis().expand_macro(position)?;
+    Ok(res.map(|it| req::ExpandedMacro { name: it.name, expansion: it.expansion }))
+}
+
+pub fn handle_ssr(world: WorldSnapshot, params: req::SsrParams) -> Result<req::SourceChange> {
+    let _p = profile("handle_ssr");
+    world
+        .analysis()
+        .structural_search_replace(&params.query, params.resolve_paths)?
+        .map_err(|e| LspError::new(ErrorCode::InvalidParams as i32, e.to_string()))?
+        .try_conv_with(&world)
+}
+
+pub fn handle_change_signature(
+    world: WorldSnapshot,
+    params: req::ChangeSignatureParams,
+) -> Result<Option<req::SourceChange>> {
+    let _p = profile("handle_change_signature");
+    let position = TextDocumentPositionParams {
+        text_document: params.text_document,
+        position: params.position,
+    }
+    .try_conv_with(&world)?;
+    let new_params = params
+        .parameters
+        .into_iter()
+        .map(|param| match param {
+            req::SignatureParam { index: Some(index), name, ty: None, default_value: None } => {
+                Ok(SignatureParam::Existing { index, new_name: name })
+            }
+            req::SignatureParam { index: None, name: Some(name), ty: Some(ty), default_value } => {
+                Ok(SignatureParam::Added { name, ty, default_value })
+            }
+            _ => Err(LspError::new(
+                ErrorCode::InvalidParams as i32,
+                format!("invalid parameter: {:?}", param),
+            )),
+        })
+        .collect::<std::result::Result<Vec<_>, _>>()?;
+    let change = world
+        .analysis()
+        .change_signature(position, &new_params)?
+        .map_err(|e| LspError::new(ErrorCode::InvalidParams as i32, e.to_string()))?;
+    match change {
+        None => Ok(None),
+        Some(change) => Ok(Some(change.try_conv_with(&world)?)),
+    }
+}
+
 // FIXME: drop this API
 pub fn handle_extend_selection(
     world: WorldSnapshot,
@@ -77,21 +137,8 @@ pub fn handle_selection_range(
         .positions
         .into_iter()
         .map_conv_with(&line_index)
-        .map(|position| {
-            let mut ranges = Vec::new();
-            {
-                let mut range = TextRange::from_to(position, position);
-                loop {
-                    ranges.push(range);
-                    let frange = FileRange { file_id, range };
-                    let next = world.analysis().extend_selection(frange)?;
-                    if next == range {
-                        break;
-                    } else {
-                        range = next
-                    }
-                }
-            }
+        .map(|offset| {
+            let ranges = world.analysis().selection_ranges(FilePosition { file_id, offset })?;
             let mut range = req::SelectionRange {
                 range: ranges.last().unwrap().conv_with(&line_index),
                 parent: None,
@@ -191,7 +238,7 @@ pub fn handle_document_symbol(
 
     let mut parents: Vec<(DocumentSymbol, Option<usize>)> = Vec::new();
 
-    for symbol in world.analysis().file_structure(file_id)? {
+    for symbol in world.analysis().file_structure_with_macros(file_id)? {
         let doc_symbol = DocumentSymbol {
             name: symbol.label,
             detail: symbol.detail,
@@ -203,8 +250,13 @@ pub fn handle_document_symbol(
         };
         parents.push((doc_symbol, symbol.parent));
     }
+    // Children come after their parents, so they are all collected (in reverse
+    // order) by the time the parent is popped.
     let mut res = Vec::new();
-    while let Some((node, parent)) = parents.pop() {
+    while let Some((mut node, parent)) = parents.pop() {
+        if let Some(children) = &mut node.children {
+            children.reverse();
+        }
         match parent {
             None => res.push(node),
             Some(i) => {
@@ -216,6 +268,7 @@ pub fn handle_document_symbol(
             }
         }
     }
+    res.reverse();
 
     Ok(Some(res.into()))
 }
@@ -224,23 +277,38 @@ pub fn handle_workspace_symbol(
     world: WorldSnapshot,
     params: req::WorkspaceSymbolParams,
 ) -> Result<Option<Vec<SymbolInformation>>> {
-    let all_symbols = params.query.contains('#');
-    let libs = params.query.contains('*');
+    // A leading keyword like `fn` or `method` restricts the kind of symbols.
+    let mut text = params.query.trim();
+    let mut kind = None;
+    if let Some(idx) = text.find(char::is_whitespace) {
+        if let Some(it) = SymbolKind::from_keyword(&text[..idx]) {
+            kind = Some(it);
+            text = text[idx..].trim_start();
+        }
+    }
+    let all_symbols = text.contains('#') || kind.is_some();
+    let libs = text.contains('*');
+    let text: String = text.chars().filter(|&c| c != '#' && c != '*').collect();
     let query = {
-        let query: String = params.query.chars().filter(|&c| c != '#' && c != '*').collect();
-        let mut q = Query::new(query);
+        let mut q = Query::new(text.clone());
         if !all_symbols {
             q.only_types();
         }
         if libs {
             q.libs();
         }
+        if let Some(kind) = kind {
+            q.kinds(&[kind]);
+        }
         q.limit(128);
         q
     };
     let mut res = exec_query(&world, query)?;
     if res.is_empty() && !all_symbols {
-        let mut query = Query::new(params.query);
+        let mut query = Query::new(text);
+        if libs {
+            query.libs();
+        }
         query.limit(128);
         res = exec_query(&world, query)?;
     }
@@ -276,6 +344,19 @@ pub fn handle_goto_definition(
     Ok(Some(res))
 }
 
+pub fn handle_goto_declaration(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<Option<req::GotoDefinitionResponse>> {
+    let position = params.try_conv_with(&world)?;
+    let nav_info = match world.analysis().goto_declaration(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let res = (position.file_id, nav_info).try_conv_with(&world)?;
+    Ok(Some(res))
+}
+
 pub fn handle_goto_implementation(
     world: WorldSnapshot,
     params: req::TextDocumentPositionParams,
@@ -302,6 +383,90 @@ pub fn handle_goto_type_definition(
     Ok(Some(res))
 }
 
+pub fn handle_call_hierarchy_prepare(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<Option<Vec<req::CallHierarchyItem>>> {
+    let _p = profile("handle_call_hierarchy_prepare");
+    let position = params.try_conv_with(&world)?;
+    let nav_info = match world.analysis().call_hierarchy(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let res = nav_info
+        .info
+        .iter()
+        .map(|nav| to_call_hierarchy_item(nav, &world))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Some(res))
+}
+
+pub fn handle_call_hierarchy_incoming(
+    world: WorldSnapshot,
+    params: req::CallHierarchyCallsParams,
+) -> Result<Option<Vec<req::CallHierarchyIncomingCall>>> {
+    let _p = profile("handle_call_hierarchy_incoming");
+    let position = call_hierarchy_item_position(&world, params.item)?;
+    let calls = match world.analysis().incoming_calls(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let mut res = Vec::new();
+    for call in calls {
+        let line_index = world.analysis().file_line_index(call.target.file_id())?;
+        res.push(req::CallHierarchyIncomingCall {
+            from: to_call_hierarchy_item(&call.target, &world)?,
+            from_ranges: call.ranges.into_iter().map_conv_with(&line_index).collect(),
+        });
+    }
+    Ok(Some(res))
+}
+
+pub fn handle_call_hierarchy_outgoing(
+    world: WorldSnapshot,
+    params: req::CallHierarchyCallsParams,
+) -> Result<Option<Vec<req::CallHierarchyOutgoingCall>>> {
+    let _p = profile("handle_call_hierarchy_outgoing");
+    let position = call_hierarchy_item_position(&world, params.item)?;
+    let calls = match world.analysis().outgoing_calls(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    let line_index = world.analysis().file_line_index(position.file_id)?;
+    let mut res = Vec::new();
+    for call in calls {
+        res.push(req::CallHierarchyOutgoingCall {
+            to: to_call_hierarchy_item(&call.target, &world)?,
+            from_ranges: call.ranges.into_iter().map_conv_with(&line_index).collect(),
+        });
+    }
+    Ok(Some(res))
+}
+
+pub fn handle_type_hierarchy(
+    world: WorldSnapshot,
+    params: req::TextDocumentPositionParams,
+) -> Result<Option<req::TypeHierarchyItem>> {
+    let _p = profile("handle_type_hierarchy");
+    let position = params.try_conv_with(&world)?;
+    let hierarchy = match world.analysis().type_hierarchy(position)? {
+        None => return Ok(None),
+        Some(it) => it,
+    };
+    Ok(Some(to_type_hierarchy_item(&hierarchy.info, &world)?))
+}
+
+/// Call hierarchy items are produced by us, so the start of the selection
+/// range is always the name of the function.
+fn call_hierarchy_item_position(
+    world: &WorldSnapshot,
+    item: req::CallHierarchyItem,
+) -> Result<FilePosition> {
+    let text_document = TextDocumentIdentifier::new(item.uri);
+    let frange = (&text_document, item.selection_range).try_conv_with(world)?;
+    Ok(FilePosition { file_id: frange.file_id, offset: frange.range.start() })
+}
+
 pub fn handle_parent_module(
     world: WorldSnapshot,
     params: req::TextDocumentPositionParams,
@@ -319,7 +484,7 @@ pub fn handle_runnables(
     let offset = params.position.map(|it| it.conv_with(&line_index));
     let mut res = Vec::new();
     let workspace_root = world.workspace_root_for(file_id);
-    for runnable in world.analysis().runnables(file_id)? {
+    for runnable in world.analysis().runnables(file_id, &world.options.test_attributes)? {
         if let Some(offset) = offset {
             if !runnable.range.contains_inclusive(offset) {
                 continue;
@@ -331,9 +496,10 @@ pub fn handle_runnables(
         let r = req::Runnable {
             range: runnable.range.conv_with(&line_index),
             label: match &runnable.kind {
-                RunnableKind::Test { name } => format!("test {}", name),
+                RunnableKind::Test { test_id } => format!("test {}", test_id),
                 RunnableKind::TestMod { path } => format!("test-mod {}", path),
-                RunnableKind::Bench { name } => format!("bench {}", name),
+                RunnableKind::Bench { test_id } => format!("bench {}", test_id),
+                RunnableKind::DocTest { test_id } => format!("doctest {}", test_id),
                 RunnableKind::Bin => "run binary".to_string(),
             },
             bin: "cargo".to_string(),
@@ -459,7 +625,7 @@ pub fn handle_signature_help(
     let position = params.try_conv_with(&world)?;
     if let Some(call_info) = world.analysis().call_info(position)? {
         let active_parameter = call_info.active_parameter.map(|it| it as i64);
-        let sig_info = call_info.signature.conv();
+        let sig_info = call_info.conv();
 
         Ok(Some(req::SignatureHelp {
             signatures: vec![sig_info],
@@ -482,10 +648,26 @@ pub fn handle_hover(
     };
     let line_index = world.analysis.file_line_index(position.file_id)?;
     let range = info.range.conv_with(&line_index);
+    let mut markup = info.info.to_markup();
+    for link in info.info.doc_links() {
+        let target = link.nav.file_id();
+        let line_col = world
+            .analysis()
+            .file_line_index(target)?
+            .line_col(link.nav.focus_range().unwrap_or_else(|| link.nav.full_range()).start());
+        // Clients open file links at a given position through the fragment
+        let url = format!(
+            "{}#L{},{}",
+            world.file_id_to_uri(target)?,
+            line_col.line + 1,
+            line_col.col_utf16 + 1
+        );
+        markup = markup.replace(&format!("]({})", link.target), &format!("]({})", url));
+    }
     let res = Hover {
         contents: HoverContents::Markup(MarkupContent {
             kind: MarkupKind::Markdown,
-            value: crate::markdown::format_docs(&info.info.to_markup()),
+            value: crate::markdown::format_docs(&markup),
         }),
         range: Some(range),
     };
@@ -541,7 +723,6 @@ pub fn handle_references(
     params: req::ReferenceParams,
 ) -> Result<Option<Vec<Location>>> {
     let position = params.text_document_position.try_conv_with(&world)?;
-    let line_index = world.analysis().file_line_index(position.file_id)?;
 
     let refs = match world.analysis().find_all_refs(position)? {
         None => return Ok(None),
@@ -549,15 +730,10 @@ pub fn handle_references(
     };
 
     let locations = if params.context.include_declaration {
-        refs.into_iter()
-            .filter_map(|r| to_location(r.file_id, r.range, &world, &line_index).ok())
-            .collect()
+        refs.into_iter().filter_map(|r| r.try_conv_with(&world).ok()).collect()
     } else {
         // Only iterate over the references if include_declaration was false
-        refs.references()
-            .iter()
-            .filter_map(|r| to_location(r.file_id, r.range, &world, &line_index).ok())
-            .collect()
+        refs.references().iter().filter_map(|r| r.try_conv_with(&world).ok()).collect()
     };
 
     Ok(Some(locations))
@@ -696,10 +872,11 @@ pub fn handle_code_lens(
     let workspace_root = world.workspace_root_for(file_id);
 
     // Gather runnables
-    for runnable in world.analysis().runnables(file_id)? {
+    for runnable in world.analysis().runnables(file_id, &world.options.test_attributes)? {
         let title = match &runnable.kind {
             RunnableKind::Test { .. } | RunnableKind::TestMod { .. } => Some("▶️Run Test"),
             RunnableKind::Bench { .. } => Some("Run Bench"),
+            RunnableKind::DocTest { .. } => Some("▶️Run Doctest"),
             RunnableKind::Bin => Some("️Run"),
         };
 
@@ -731,28 +908,41 @@ pub fn handle_code_lens(
         }
     }
 
-    // Handle impls
-    lenses.extend(
-        world
-            .analysis()
-            .file_structure(file_id)?
-            .into_iter()
-            .filter(|it| match it.kind {
-                SyntaxKind::TRAIT_DEF | SyntaxKind::STRUCT_DEF | SyntaxKind::ENUM_DEF => true,
-                _ => false,
-            })
-            .map(|it| {
-                let range = it.node_range.conv_with(&line_index);
-                let pos = range.start;
-                let lens_params =
-                    req::TextDocumentPositionParams::new(params.text_document.clone(), pos);
-                CodeLens {
-                    range,
-                    command: None,
-                    data: Some(to_value(CodeLensResolveData::Impls(lens_params)).unwrap()),
-                }
-            }),
-    );
+    // Handle impls and references, which are resolved lazily
+    let structure = world.analysis().file_structure(file_id)?;
+    for node in structure.iter() {
+        let lens_params = |offset: TextUnit| {
+            req::TextDocumentPositionParams::new(
+                params.text_document.clone(),
+                offset.conv_with(&line_index),
+            )
+        };
+        let in_trait =
+            node.parent.map_or(false, |idx| structure[idx].kind == SyntaxKind::TRAIT_DEF);
+        let name_start = node.navigation_range.start();
+        let resolve_data = match node.kind {
+            SyntaxKind::TRAIT_DEF | SyntaxKind::STRUCT_DEF | SyntaxKind::ENUM_DEF => {
+                vec![CodeLensResolveData::Impls(lens_params(node.node_range.start()))]
+            }
+            SyntaxKind::FN_DEF if in_trait => vec![
+                CodeLensResolveData::Impls(lens_params(name_start)),
+                CodeLensResolveData::References(lens_params(name_start)),
+            ],
+            SyntaxKind::FN_DEF
+            | SyntaxKind::CONST_DEF
+            | SyntaxKind::STATIC_DEF
+            | SyntaxKind::NAMED_FIELD_DEF => {
+                vec![CodeLensResolveData::References(lens_params(name_start))]
+            }
+            _ => continue,
+        };
+        let range = node.node_range.conv_with(&line_index);
+        lenses.extend(resolve_data.into_iter().map(|data| CodeLens {
+            range,
+            command: None,
+            data: Some(to_value(data).unwrap()),
+        }));
+    }
 
     Ok(Some(lenses))
 }
@@ -761,6 +951,7 @@ pub fn handle_code_lens(
 #[serde(rename_all = "camelCase")]
 enum CodeLensResolveData {
     Impls(req::TextDocumentPositionParams),
+    References(req::TextDocumentPositionParams),
 }
 
 pub fn handle_code_lens_resolve(world: WorldSnapshot, code_lens: CodeLens) -> Result<CodeLens> {
@@ -784,20 +975,21 @@ pub fn handle_code_lens_resolve(world: WorldSnapshot, code_lens: CodeLens) -> Re
             } else {
                 format!("{} implementations", locations.len())
             };
+            Ok(show_references_lens(code_lens.range, title, &lens_params, locations))
+        }
+        Some(CodeLensResolveData::References(lens_params)) => {
+            let position = lens_params.try_conv_with(&world)?;
+            let locations = match world.analysis().find_all_refs(position)? {
+                Some(refs) => refs.references().iter().cloned().try_conv_with_to_vec(&world)?,
+                None => vec![],
+            };
 
-            // We cannot use the 'editor.action.showReferences' command directly
-            // because that command requires vscode types which we convert in the handler
-            // on the client side.
-            let cmd = Command {
-                title,
-                command: "rust-analyzer.showReferences".into(),
-                arguments: Some(vec![
-                    to_value(&Ser::new(&lens_params.text_document.uri)).unwrap(),
-                    to_value(code_lens.range.start).unwrap(),
-                    to_value(locations).unwrap(),
-                ]),
+            let title = if locations.len() == 1 {
+                "1 reference".into()
+            } else {
+                format!("{} references", locations.len())
             };
-            Ok(CodeLens { range: code_lens.range, command: Some(cmd), data: None })
+            Ok(show_references_lens(code_lens.range, title, &lens_params, locations))
         }
         None => Ok(CodeLens {
             range: code_lens.range,
@@ -807,21 +999,47 @@ pub fn handle_code_lens_resolve(world: WorldSnapshot, code_lens: CodeLens) -> Re
     }
 }
 
+fn show_references_lens(
+    range: Range,
+    title: String,
+    lens_params: &req::TextDocumentPositionParams,
+    locations: Vec<Location>,
+) -> CodeLens {
+    // We cannot use the 'editor.action.showReferences' command directly
+    // because that command requires vscode types which we convert in the handler
+    // on the client side.
+    let cmd = Command {
+        title,
+        command: "rust-analyzer.showReferences".into(),
+        arguments: Some(vec![
+            to_value(&Ser::new(&lens_params.text_document.uri)).unwrap(),
+            to_value(range.start).unwrap(),
+            to_value(locations).unwrap(),
+        ]),
+    };
+    CodeLens { range, command: Some(cmd), data: None }
+}
+
 pub fn handle_document_highlight(
     world: WorldSnapshot,
     params: req::TextDocumentPositionParams,
 ) -> Result<Option<Vec<DocumentHighlight>>> {
+    let _p = profile("handle_document_highlight");
     let file_id = params.text_document.try_conv_with(&world)?;
     let line_index = world.analysis().file_line_index(file_id)?;
 
-    let refs = match world.analysis().find_all_refs(params.try_conv_with(&world)?)? {
+    let highlights = match world.analysis().document_highlight(params.try_conv_with(&world)?)? {
         None => return Ok(None),
-        Some(refs) => refs,
+        Some(highlights) => highlights,
     };
 
     Ok(Some(
-        refs.into_iter()
-            .map(|r| DocumentHighlight { range: r.range.conv_with(&line_index), kind: None })
+        highlights
+            .into_iter()
+            .map(|it| DocumentHighlight {
+                range: it.range.conv_with(&line_index),
+                kind: Some(it.access.conv()),
+            })
             .collect(),
     ))
 }
@@ -864,13 +1082,101 @@ fn highlight(world: &WorldSnapshot, file_id: FileId) -> Result<Vec<Decoration>>
         .into_iter()
         .map(|h| Decoration {
             range: h.range.conv_with(&line_index),
-            tag: h.tag,
+            tag: decoration_tag(h.highlight),
             binding_hash: h.binding_hash.map(|x| x.to_string()),
         })
         .collect();
     Ok(res)
 }
 
+/// Maps a highlight to the tags understood by the VS Code extension.
+fn decoration_tag(highlight: Highlight) -> &'static str {
+    match highlight.tag {
+        HighlightTag::Keyword if highlight.modifiers.contains(HighlightModifier::Unsafe) => {
+            "keyword.unsafe"
+        }
+        HighlightTag::Keyword if highlight.modifiers.contains(HighlightModifier::ControlFlow) => {
+            "keyword.control"
+        }
+        HighlightTag::Variable if highlight.modifiers.contains(HighlightModifier::Mutable) => {
+            "variable.mut"
+        }
+        HighlightTag::Lifetime => "parameter",
+        HighlightTag::TypeParam => "type",
+        tag => tag.as_str(),
+    }
+}
+
+pub fn handle_semantic_tokens(
+    world: WorldSnapshot,
+    params: req::SemanticTokensParams,
+) -> Result<Option<req::SemanticTokens>> {
+    let _p = profile("handle_semantic_tokens");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let tokens = semantic_tokens(&world, file_id)?;
+    world.semantic_tokens_cache.write().insert(params.text_document.uri, tokens.clone());
+    Ok(Some(tokens))
+}
+
+pub fn handle_semantic_tokens_delta(
+    world: WorldSnapshot,
+    params: req::SemanticTokensDeltaParams,
+) -> Result<Option<req::SemanticTokensFullDeltaResult>> {
+    let _p = profile("handle_semantic_tokens_delta");
+    let file_id = params.text_document.try_conv_with(&world)?;
+    let tokens = semantic_tokens(&world, file_id)?;
+    let mut cache = world.semantic_tokens_cache.write();
+    let res = match cache.get(&params.text_document.uri) {
+        Some(prev) if prev.result_id.as_ref() == Some(&params.previous_result_id) => {
+            let edits = semantic_tokens::diff_tokens(&prev.data, &tokens.data);
+            req::SemanticTokensFullDeltaResult::TokensDelta(req::SemanticTokensDelta {
+                result_id: tokens.result_id.clone(),
+                edits,
+            })
+        }
+        _ => req::SemanticTokensFullDeltaResult::Tokens(tokens.clone()),
+    };
+    cache.insert(params.text_document.uri, tokens);
+    Ok(Some(res))
+}
+
+fn semantic_tokens(world: &WorldSnapshot, file_id: FileId) -> Result<req::SemanticTokens> {
+    let analysis = world.analysis();
+    let text = analysis.file_text(file_id)?;
+    let line_index = analysis.file_line_index(file_id)?;
+    let mut highlights = analysis.highlight(file_id)?;
+    highlights.sort_by_key(|it| it.range.start());
+
+    let mut builder = SemanticTokensBuilder::default();
+    let mut prev_end = TextUnit::from(0);
+    for h in highlights {
+        // The protocol does not support overlapping tokens.
+        if h.range.start() < prev_end {
+            continue;
+        }
+        let token_type = match semantic_tokens::token_type(h.highlight.tag) {
+            Some(it) => it,
+            None => continue,
+        };
+        let modifiers = semantic_tokens::token_modifiers(h.highlight.modifiers);
+        prev_end = h.range.end();
+
+        // Tokens can't span several lines, so multiline comments and strings
+        // are split.
+        let start = line_index.line_col(h.range.start());
+        let (mut line, mut col) = (start.line, start.col_utf16);
+        for part in text[h.range].split('\n') {
+            let len = part.trim_end_matches('\r').encode_utf16().count() as u32;
+            if len > 0 {
+                builder.push(line, col, len, token_type, modifiers);
+            }
+            line += 1;
+            col = 0;
+        }
+    }
+    Ok(builder.build())
+}
+
 pub fn handle_inlay_hints(
     world: WorldSnapshot,
     params: InlayHintsParams,
@@ -1,26 +1,47 @@
 pub(crate) fn format_docs(src: &str) -> String {
     let mut processed_lines = Vec::new();
     let mut in_code_block = false;
+    let mut is_rust = false;
     for line in src.lines() {
-        if in_code_block && code_line_ignored_by_rustdoc(line) {
+        if in_code_block && is_rust && code_line_ignored_by_rustdoc(line) {
             continue;
         }
 
         if line.starts_with("```") {
-            in_code_block ^= true
+            in_code_block ^= true;
+            if in_code_block {
+                is_rust = is_rust_fence(&line["```".len()..]);
+            }
         }
 
-        let line = if in_code_block && line.starts_with("```") && !line.contains("rust") {
-            "```rust"
-        } else {
-            line
-        };
+        let line =
+            if in_code_block && is_rust && line.starts_with("```") { "```rust" } else { line };
 
         processed_lines.push(line);
     }
     processed_lines.join("\n")
 }
 
+/// Like rustdoc, treats code blocks as Rust unless the fence names another
+/// language. Rustdoc attributes, like `ignore`, don't count as one.
+fn is_rust_fence(header: &str) -> bool {
+    const RUSTDOC_FENCE_TOKENS: &[&str] = &[
+        "rust",
+        "ignore",
+        "should_panic",
+        "no_run",
+        "compile_fail",
+        "edition2015",
+        "edition2018",
+        "allow_fail",
+        "test_harness",
+    ];
+    header
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|it| !it.is_empty())
+        .all(|it| RUSTDOC_FENCE_TOKENS.contains(&it))
+}
+
 fn code_line_ignored_by_rustdoc(line: &str) -> bool {
     let trimmed = line.trim();
     trimmed == "#" || trimmed.starts_with("# ") || trimmed.starts_with("#\t")
@@ -71,4 +92,15 @@ let a = 1;
         );
     }
 
+    #[test]
+    fn test_format_docs_handles_rustdoc_attributes() {
+        let comment = "```ignore\n# skip\nfoo();\n```\n```rust, should_panic\npanic!();\n```";
+        assert_eq!(format_docs(comment), "```rust\nfoo();\n```\n```rust\npanic!();\n```");
+    }
+
+    #[test]
+    fn test_format_docs_keeps_other_languages() {
+        let comment = "```text\n# stay\n```\n```sh\n$ cargo test\n```";
+        assert_eq!(format_docs(comment), comment);
+    }
 }
@@ -0,0 +1,158 @@
+//! Encoding of highlighted ranges into the semantic tokens of the protocol.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use ra_ide_api::{HighlightModifier, HighlightModifiers, HighlightTag};
+
+use crate::req::{SemanticTokens, SemanticTokensEdit, SemanticTokensLegend};
+
+/// Token types, in the order of the legend we send to the client. The
+/// standard types come first, followed by our own.
+const SUPPORTED_TYPES: &[&str] = &[
+    "comment",
+    "function",
+    "keyword",
+    "macro",
+    "namespace",
+    "number",
+    "property",
+    "string",
+    "type",
+    "typeParameter",
+    "variable",
+    "attribute",
+    "constant",
+    "lifetime",
+];
+
+pub fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: SUPPORTED_TYPES.to_vec(),
+        token_modifiers: HighlightModifier::ALL.iter().map(|it| modifier_name(*it)).collect(),
+    }
+}
+
+/// Returns the index of the tag in the legend. Unresolved names have no token
+/// type and are left to the client's syntax highlighting.
+pub fn token_type(tag: HighlightTag) -> Option<u32> {
+    let name = match tag {
+        HighlightTag::Comment => "comment",
+        HighlightTag::Function => "function",
+        HighlightTag::Keyword => "keyword",
+        HighlightTag::Macro => "macro",
+        HighlightTag::Module => "namespace",
+        HighlightTag::Literal => "number",
+        HighlightTag::Field => "property",
+        HighlightTag::String => "string",
+        HighlightTag::Type => "type",
+        HighlightTag::TypeParam => "typeParameter",
+        HighlightTag::Variable => "variable",
+        HighlightTag::Attribute => "attribute",
+        HighlightTag::Constant => "constant",
+        HighlightTag::Lifetime => "lifetime",
+        HighlightTag::Text => return None,
+    };
+    SUPPORTED_TYPES.iter().position(|it| *it == name).map(|idx| idx as u32)
+}
+
+pub fn token_modifiers(modifiers: HighlightModifiers) -> u32 {
+    modifiers
+        .iter()
+        .filter_map(|modifier| HighlightModifier::ALL.iter().position(|it| *it == modifier))
+        .fold(0, |acc, idx| acc | 1 << idx)
+}
+
+fn modifier_name(modifier: HighlightModifier) -> &'static str {
+    match modifier {
+        HighlightModifier::Mutable => "mutable",
+        HighlightModifier::Unsafe => "unsafe",
+        HighlightModifier::Declaration => "declaration",
+        HighlightModifier::Static => "static",
+        HighlightModifier::Associated => "associated",
+        HighlightModifier::Library => "library",
+        HighlightModifier::ControlFlow => "controlFlow",
+        HighlightModifier::MacroExpanded => "macroExpanded",
+    }
+}
+
+/// Accumulates tokens in the relative encoding of the protocol.
+#[derive(Default)]
+pub struct SemanticTokensBuilder {
+    prev_line: u32,
+    prev_char: u32,
+    data: Vec<u32>,
+}
+
+impl SemanticTokensBuilder {
+    /// Pushes a single-line token. Tokens must be pushed in order.
+    pub fn push(&mut self, line: u32, start: u32, len: u32, token_type: u32, modifiers: u32) {
+        let delta_line = line - self.prev_line;
+        let delta_start = if delta_line == 0 { start - self.prev_char } else { start };
+        self.data.extend_from_slice(&[delta_line, delta_start, len, token_type, modifiers]);
+        self.prev_line = line;
+        self.prev_char = start;
+    }
+
+    pub fn build(self) -> SemanticTokens {
+        static NEXT_RESULT_ID: AtomicUsize = AtomicUsize::new(1);
+        let result_id = NEXT_RESULT_ID.fetch_add(1, Ordering::SeqCst);
+        SemanticTokens { result_id: Some(result_id.to_string()), data: self.data }
+    }
+}
+
+/// Computes a single edit which turns `old` tokens into `new` ones, by
+/// trimming the common prefix and suffix.
+pub fn diff_tokens(old: &[u32], new: &[u32]) -> Vec<SemanticTokensEdit> {
+    let prefix = old.iter().zip(new.iter()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(a, b)| a == b).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+    if old.is_empty() && new.is_empty() {
+        return Vec::new();
+    }
+    vec![SemanticTokensEdit {
+        start: prefix as u32,
+        delete_count: old.len() as u32,
+        data: if new.is_empty() { None } else { Some(new.to_vec()) },
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builder_uses_relative_positions() {
+        let mut builder = SemanticTokensBuilder::default();
+        builder.push(0, 4, 3, 1, 0);
+        builder.push(0, 10, 2, 2, 1);
+        builder.push(2, 1, 5, 3, 0);
+        let tokens = builder.build();
+        assert_eq!(tokens.data, vec![0, 4, 3, 1, 0, 0, 6, 2, 2, 1, 2, 1, 5, 3, 0]);
+    }
+
+    #[test]
+    fn test_diff_insert() {
+        let old = [0, 4, 3, 1, 0, 1, 0, 2, 2, 0];
+        let new = [0, 4, 3, 1, 0, 0, 5, 1, 1, 0, 1, 0, 2, 2, 0];
+        let edits = diff_tokens(&old, &new);
+        assert_eq!(
+            edits,
+            vec![SemanticTokensEdit { start: 5, delete_count: 0, data: Some(vec![0, 5, 1, 1, 0]) }]
+        );
+    }
+
+    #[test]
+    fn test_diff_remove() {
+        let old = [0, 4, 3, 1, 0, 0, 5, 1, 1, 0, 1, 0, 2, 2, 0];
+        let new = [0, 4, 3, 1, 0, 1, 0, 2, 2, 0];
+        let edits = diff_tokens(&old, &new);
+        assert_eq!(edits, vec![SemanticTokensEdit { start: 5, delete_count: 5, data: None }]);
+    }
+
+    #[test]
+    fn test_diff_unchanged() {
+        let tokens = [0, 4, 3, 1, 0];
+        assert!(diff_tokens(&tokens, &tokens).is_empty());
+    }
+}
@@ -12,10 +12,12 @@ use ra_ide_api::{
 use ra_vfs::{RootEntry, Vfs, VfsChange, VfsFile, VfsRoot};
 use ra_vfs_glob::{Glob, RustPackageFilterBuilder};
 use relative_path::RelativePathBuf;
+use rustc_hash::FxHashMap;
 
 use crate::{
     main_loop::pending_requests::{CompletedRequest, LatestRequests},
     project_model::ProjectWorkspace,
+    req::SemanticTokens,
     LspError, Result,
 };
 
@@ -24,6 +26,7 @@ pub struct Options {
     pub publish_decorations: bool,
     pub show_workspace_loaded: bool,
     pub supports_location_link: bool,
+    pub test_attributes: Vec<String>,
 }
 
 /// `WorldState` is the primary mutable state of the language server
@@ -40,6 +43,8 @@ pub struct WorldState {
     pub analysis_host: AnalysisHost,
     pub vfs: Arc<RwLock<Vfs>>,
     pub latest_requests: Arc<RwLock<LatestRequests>>,
+    /// Last semantic tokens sent for each document, used to compute deltas.
+    pub semantic_tokens_cache: Arc<RwLock<FxHashMap<Url, SemanticTokens>>>,
 }
 
 /// An immutable snapshot of the world's state at a point in time.
@@ -49,6 +54,7 @@ pub struct WorldSnapshot {
     pub analysis: Analysis,
     pub vfs: Arc<RwLock<Vfs>>,
     pub latest_requests: Arc<RwLock<LatestRequests>>,
+    pub semantic_tokens_cache: Arc<RwLock<FxHashMap<Url, SemanticTokens>>>,
 }
 
 impl WorldState {
@@ -109,6 +115,7 @@ impl WorldState {
             analysis_host,
             vfs: Arc::new(RwLock::new(vfs)),
             latest_requests: Default::default(),
+            semantic_tokens_cache: Default::default(),
         }
     }
 
@@ -170,6 +177,7 @@ impl WorldState {
             analysis: self.analysis_host.analysis(),
             vfs: Arc::clone(&self.vfs),
             latest_requests: Arc::clone(&self.latest_requests),
+            semantic_tokens_cache: Arc::clone(&self.semantic_tokens_cache),
         }
     }
 
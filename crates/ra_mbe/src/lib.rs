@@ -43,6 +43,7 @@ pub enum ExpandError {
 pub use crate::syntax_bridge::{
     ast_to_token_tree, syntax_node_to_token_tree, token_tree_to_ast_item_list, token_tree_to_expr,
     token_tree_to_macro_items, token_tree_to_macro_stmts, token_tree_to_pat, token_tree_to_ty,
+    RevTokenMap, TokenMap,
 };
 
 /// This struct contains AST for a single `macro_rules` definition. What might
@@ -61,6 +62,11 @@ impl MacroRules {
     pub fn expand(&self, tt: &tt::Subtree) -> Result<tt::Subtree, ExpandError> {
         mbe_expander::expand(self, tt)
     }
+
+    /// Returns the index of the rule `tt` would be expanded with.
+    pub fn matching_rule(&self, tt: &tt::Subtree) -> Option<usize> {
+        mbe_expander::matching_rule(self, tt)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Eq)]
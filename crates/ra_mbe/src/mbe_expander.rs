@@ -15,6 +15,13 @@ pub(crate) fn expand(
     rules.rules.iter().find_map(|it| expand_rule(it, input).ok()).ok_or(ExpandError::NoMatchingRule)
 }
 
+pub(crate) fn matching_rule(rules: &crate::MacroRules, input: &tt::Subtree) -> Option<usize> {
+    rules.rules.iter().position(|rule| {
+        let mut input = TtCursor::new(input);
+        match_lhs(&rule.lhs, &mut input).is_ok() && input.is_eof()
+    })
+}
+
 fn expand_rule(rule: &crate::Rule, input: &tt::Subtree) -> Result<tt::Subtree, ExpandError> {
     let mut input = TtCursor::new(input);
     let bindings = match_lhs(&rule.lhs, &mut input)?;
@@ -15,6 +15,13 @@ pub struct TokenMap {
     tokens: Vec<TextRange>,
 }
 
+/// Maps ranges of identifiers in a macro expansion to the
+/// `tt::TokenId`s of the input tokens they were substituted from.
+#[derive(Debug, Default)]
+pub struct RevTokenMap {
+    ranges: Vec<(TextRange, tt::TokenId)>,
+}
+
 /// Convert the syntax tree (what user has written) to a `TokenTree` (what macro
 /// will consume).
 pub fn ast_to_token_tree(ast: &ast::TokenTree) -> Option<(tt::Subtree, TokenMap)> {
@@ -46,7 +53,10 @@ pub fn syntax_node_to_token_tree(node: &SyntaxNode) -> Option<(tt::Subtree, Toke
 //
 //
 
-fn token_tree_to_syntax_node<F>(tt: &tt::Subtree, f: F) -> Result<Parse<SyntaxNode>, ExpandError>
+fn token_tree_to_syntax_node<F>(
+    tt: &tt::Subtree,
+    f: F,
+) -> Result<(Parse<SyntaxNode>, RevTokenMap), ExpandError>
 where
     F: Fn(&mut dyn ra_parser::TokenSource, &mut dyn ra_parser::TreeSink),
 {
@@ -59,44 +69,57 @@ where
         return Err(ExpandError::ConversionError);
     }
     //FIXME: would be cool to report errors
-    let parse = tree_sink.inner.finish();
-    Ok(parse)
+    let (parse, range_map) = tree_sink.finish();
+    Ok((parse, range_map))
 }
 
 /// Parses the token tree (result of macro expansion) to an expression
-pub fn token_tree_to_expr(tt: &tt::Subtree) -> Result<Parse<ast::Expr>, ExpandError> {
-    let parse = token_tree_to_syntax_node(tt, ra_parser::parse_expr)?;
-    parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)
+pub fn token_tree_to_expr(
+    tt: &tt::Subtree,
+) -> Result<(Parse<ast::Expr>, RevTokenMap), ExpandError> {
+    let (parse, map) = token_tree_to_syntax_node(tt, ra_parser::parse_expr)?;
+    let parse = parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)?;
+    Ok((parse, map))
 }
 
 /// Parses the token tree (result of macro expansion) to a Pattern
-pub fn token_tree_to_pat(tt: &tt::Subtree) -> Result<Parse<ast::Pat>, ExpandError> {
-    let parse = token_tree_to_syntax_node(tt, ra_parser::parse_pat)?;
-    parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)
+pub fn token_tree_to_pat(tt: &tt::Subtree) -> Result<(Parse<ast::Pat>, RevTokenMap), ExpandError> {
+    let (parse, map) = token_tree_to_syntax_node(tt, ra_parser::parse_pat)?;
+    let parse = parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)?;
+    Ok((parse, map))
 }
 
 /// Parses the token tree (result of macro expansion) to a Type
-pub fn token_tree_to_ty(tt: &tt::Subtree) -> Result<Parse<ast::TypeRef>, ExpandError> {
-    let parse = token_tree_to_syntax_node(tt, ra_parser::parse_ty)?;
-    parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)
+pub fn token_tree_to_ty(
+    tt: &tt::Subtree,
+) -> Result<(Parse<ast::TypeRef>, RevTokenMap), ExpandError> {
+    let (parse, map) = token_tree_to_syntax_node(tt, ra_parser::parse_ty)?;
+    let parse = parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)?;
+    Ok((parse, map))
 }
 
 /// Parses the token tree (result of macro expansion) as a sequence of stmts
-pub fn token_tree_to_macro_stmts(tt: &tt::Subtree) -> Result<Parse<ast::MacroStmts>, ExpandError> {
-    let parse = token_tree_to_syntax_node(tt, ra_parser::parse_macro_stmts)?;
-    parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)
+pub fn token_tree_to_macro_stmts(
+    tt: &tt::Subtree,
+) -> Result<(Parse<ast::MacroStmts>, RevTokenMap), ExpandError> {
+    let (parse, map) = token_tree_to_syntax_node(tt, ra_parser::parse_macro_stmts)?;
+    let parse = parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)?;
+    Ok((parse, map))
 }
 
 /// Parses the token tree (result of macro expansion) as a sequence of items
-pub fn token_tree_to_macro_items(tt: &tt::Subtree) -> Result<Parse<ast::MacroItems>, ExpandError> {
-    let parse = token_tree_to_syntax_node(tt, ra_parser::parse_macro_items)?;
-    parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)
+pub fn token_tree_to_macro_items(
+    tt: &tt::Subtree,
+) -> Result<(Parse<ast::MacroItems>, RevTokenMap), ExpandError> {
+    let (parse, map) = token_tree_to_syntax_node(tt, ra_parser::parse_macro_items)?;
+    let parse = parse.cast().ok_or_else(|| crate::ExpandError::ConversionError)?;
+    Ok((parse, map))
 }
 
 /// Parses the token tree (result of macro expansion) as a sequence of items
-pub fn token_tree_to_ast_item_list(tt: &tt::Subtree) -> Parse<ast::SourceFile> {
-    let parse = token_tree_to_syntax_node(tt, ra_parser::parse).unwrap();
-    parse.cast().unwrap()
+pub fn token_tree_to_ast_item_list(tt: &tt::Subtree) -> (Parse<ast::SourceFile>, RevTokenMap) {
+    let (parse, map) = token_tree_to_syntax_node(tt, ra_parser::parse).unwrap();
+    (parse.cast().unwrap(), map)
 }
 
 impl TokenMap {
@@ -105,6 +128,12 @@ impl TokenMap {
         self.tokens.get(idx).copied()
     }
 
+    /// Returns the id of the token at `relative_range`, if it was converted.
+    pub fn token_by_range(&self, relative_range: TextRange) -> Option<tt::TokenId> {
+        let idx = self.tokens.iter().position(|&it| it == relative_range)?;
+        Some(tt::TokenId(idx as u32))
+    }
+
     fn alloc(&mut self, relative_range: TextRange) -> tt::TokenId {
         let id = self.tokens.len();
         self.tokens.push(relative_range);
@@ -112,6 +141,22 @@ impl TokenMap {
     }
 }
 
+impl RevTokenMap {
+    /// Returns the range of the first identifier which was expanded from `token_id`.
+    pub fn range_by_token(&self, token_id: tt::TokenId) -> Option<TextRange> {
+        self.ranges.iter().find(|&&(_, id)| id == token_id).map(|&(range, _)| range)
+    }
+
+    /// Returns the id of the input token the identifier at `range` was expanded from.
+    pub fn token_by_range(&self, range: TextRange) -> Option<tt::TokenId> {
+        self.ranges.iter().find(|&&(it, _)| it == range).map(|&(_, id)| id)
+    }
+
+    fn add(&mut self, range: TextRange, token_id: tt::TokenId) {
+        self.ranges.push((range, token_id));
+    }
+}
+
 /// Returns the textual content of a doc comment block as a quoted string
 /// That is, strips leading `///` (or `/**`, etc)
 /// and strips the ending `*/`
@@ -258,6 +303,7 @@ struct TtTreeSink<'a> {
     cursor: Cursor<'a>,
     text_pos: TextUnit,
     inner: SyntaxTreeBuilder,
+    range_map: RevTokenMap,
 
     // Number of roots
     // Use for detect ill-form tree which is not single root
@@ -271,9 +317,14 @@ impl<'a> TtTreeSink<'a> {
             cursor,
             text_pos: 0.into(),
             inner: SyntaxTreeBuilder::default(),
+            range_map: RevTokenMap::default(),
             roots: smallvec::SmallVec::new(),
         }
     }
+
+    fn finish(self) -> (Parse<SyntaxNode>, RevTokenMap) {
+        (self.inner.finish(), self.range_map)
+    }
 }
 
 fn delim_to_str(d: tt::Delimiter, closing: bool) -> SmolStr {
@@ -304,6 +355,15 @@ impl<'a> TreeSink for TtTreeSink<'a> {
             match self.cursor.token_tree() {
                 Some(tt::TokenTree::Leaf(leaf)) => {
                     self.cursor = self.cursor.bump();
+                    if let tt::Leaf::Ident(ident) = leaf {
+                        // Identifiers from the macro definition have no id,
+                        // so only the ones from the invocation are recorded.
+                        if ident.id != tt::TokenId::unspecified() {
+                            let start = self.text_pos + TextUnit::of_str(&self.buf);
+                            let range = TextRange::offset_len(start, TextUnit::of_str(&ident.text));
+                            self.range_map.add(range, ident.id);
+                        }
+                    }
                     self.buf += &format!("{}", leaf);
                 }
                 Some(tt::TokenTree::Subtree(subtree)) => {
@@ -333,6 +393,7 @@ impl<'a> TreeSink for TtTreeSink<'a> {
         {
             if curr.spacing == tt::Spacing::Alone {
                 self.inner.token(WHITESPACE, " ".into());
+                self.text_pos += TextUnit::of_char(' ');
             }
         }
     }
@@ -404,6 +465,28 @@ mod tests {
         assert_eq!(tokens[2 + 15 + 3].0, STRING);
     }
 
+    #[test]
+    fn maps_expanded_identifiers_to_invocation_tokens() {
+        let rules = create_rules(
+            r#"
+            macro_rules! foobar {
+                ($e:ident) => { fn $e() {} }
+            }
+            "#,
+        );
+        let source_file = ast::SourceFile::parse("foobar!(baz);").ok().unwrap();
+        let token_tree = source_file.syntax().descendants().find_map(ast::TokenTree::cast).unwrap();
+        let (invocation, token_map) = ast_to_token_tree(&token_tree).unwrap();
+        let expansion = rules.expand(&invocation).unwrap();
+        let (parse, range_map) = token_tree_to_macro_items(&expansion).unwrap();
+
+        let token_id = token_map.token_by_range(TextRange::from_to(1.into(), 4.into())).unwrap();
+        let range = range_map.range_by_token(token_id).unwrap();
+        let text = parse.tree().syntax().to_string();
+        assert_eq!(&text[range.start().to_usize()..range.end().to_usize()], "baz");
+        assert_eq!(range_map.token_by_range(range), Some(token_id));
+    }
+
     #[test]
     fn stmts_token_trees_to_expr_is_err() {
         let rules = create_rules(
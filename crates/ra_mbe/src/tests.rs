@@ -69,18 +69,18 @@ pub(crate) fn expand(rules: &MacroRules, invocation: &str) -> tt::Subtree {
 
 pub(crate) fn expand_to_items(rules: &MacroRules, invocation: &str) -> ast::MacroItems {
     let expanded = expand(rules, invocation);
-    token_tree_to_macro_items(&expanded).unwrap().tree()
+    token_tree_to_macro_items(&expanded).unwrap().0.tree()
 }
 
 #[allow(unused)]
 pub(crate) fn expand_to_stmts(rules: &MacroRules, invocation: &str) -> ast::MacroStmts {
     let expanded = expand(rules, invocation);
-    token_tree_to_macro_stmts(&expanded).unwrap().tree()
+    token_tree_to_macro_stmts(&expanded).unwrap().0.tree()
 }
 
 pub(crate) fn expand_to_expr(rules: &MacroRules, invocation: &str) -> ast::Expr {
     let expanded = expand(rules, invocation);
-    token_tree_to_expr(&expanded).unwrap().tree()
+    token_tree_to_expr(&expanded).unwrap().0.tree()
 }
 
 pub(crate) fn text_to_tokentree(text: &str) -> tt::Subtree {
@@ -155,8 +155,8 @@ pub(crate) fn assert_expansion(
 
     let (expanded_tree, expected_tree) = match kind {
         MacroKind::Items => {
-            let expanded_tree = token_tree_to_macro_items(&expanded).unwrap().tree();
-            let expected_tree = token_tree_to_macro_items(&expected).unwrap().tree();
+            let expanded_tree = token_tree_to_macro_items(&expanded).unwrap().0.tree();
+            let expected_tree = token_tree_to_macro_items(&expected).unwrap().0.tree();
 
             (
                 debug_dump_ignore_spaces(expanded_tree.syntax()).trim().to_string(),
@@ -165,8 +165,8 @@ pub(crate) fn assert_expansion(
         }
 
         MacroKind::Stmts => {
-            let expanded_tree = token_tree_to_macro_stmts(&expanded).unwrap().tree();
-            let expected_tree = token_tree_to_macro_stmts(&expected).unwrap().tree();
+            let expanded_tree = token_tree_to_macro_stmts(&expanded).unwrap().0.tree();
+            let expected_tree = token_tree_to_macro_stmts(&expected).unwrap().0.tree();
 
             (
                 debug_dump_ignore_spaces(expanded_tree.syntax()).trim().to_string(),
@@ -231,6 +231,34 @@ fn test_fail_match_pattern_by_last_token() {
     assert_expansion(MacroKind::Items, &rules, "foo! { Baz + }", "struct Baz ;");
 }
 
+#[test]
+fn test_matching_rule() {
+    let rules = create_rules(
+        r#"
+        macro_rules! foo {
+            ($ i:ident) => (
+                mod $ i {}
+            );
+            ($ i:ident =) => (
+                fn $ i() {}
+            )
+        }
+"#,
+    );
+
+    let matching_rule = |invocation: &str| {
+        let source_file = ast::SourceFile::parse(invocation).ok().unwrap();
+        let macro_invocation =
+            source_file.syntax().descendants().find_map(ast::MacroCall::cast).unwrap();
+        let (invocation_tt, _) =
+            ast_to_token_tree(&macro_invocation.token_tree().unwrap()).unwrap();
+        rules.matching_rule(&invocation_tt)
+    };
+    assert_eq!(matching_rule("foo! { foo }"), Some(0));
+    assert_eq!(matching_rule("foo! { bar = }"), Some(1));
+    assert_eq!(matching_rule("foo! { bar + }"), None);
+}
+
 #[test]
 fn test_fail_match_pattern_by_word_token() {
     let rules = create_rules(
@@ -410,7 +438,7 @@ fn test_expand_to_item_list() {
             ",
     );
     let expansion = expand(&rules, "structs!(Foo, Bar);");
-    let tree = token_tree_to_macro_items(&expansion).unwrap().tree();
+    let tree = token_tree_to_macro_items(&expansion).unwrap().0.tree();
     assert_eq!(
         format!("{:#?}", tree.syntax()).trim(),
         r#"
@@ -528,7 +556,7 @@ fn test_tt_to_stmts() {
     );
 
     let expanded = expand(&rules, "foo!{}");
-    let stmts = token_tree_to_macro_stmts(&expanded).unwrap().tree();
+    let stmts = token_tree_to_macro_stmts(&expanded).unwrap().0.tree();
 
     assert_eq!(
         format!("{:#?}", stmts.syntax()).trim(),
@@ -0,0 +1,112 @@
+import * as vscode from 'vscode';
+import { Position, TextDocumentIdentifier } from 'vscode-languageclient';
+
+import { Server } from '../server';
+import {
+    handle as applySourceChange,
+    SourceChange
+} from './apply_source_change';
+
+interface SignatureParam {
+    index?: number;
+    name?: string;
+    ty?: string;
+    defaultValue?: string;
+}
+
+interface ChangeSignatureParams {
+    textDocument: TextDocumentIdentifier;
+    position: Position;
+    parameters: SignatureParam[];
+}
+
+export async function handle() {
+    const editor = vscode.window.activeTextEditor;
+    if (editor == null || editor.document.languageId !== 'rust') {
+        return;
+    }
+    const spec = await vscode.window.showInputBox({
+        prompt:
+            'Enter the new parameters: indexes of the current ones, `1 as name` to rename, `name: Type = default` to add',
+        placeHolder: '1, 0 as count, flag: bool = false',
+        validateInput: (value: string) =>
+            parseParams(value) ? null : 'Invalid parameter list'
+    });
+    if (spec === undefined) {
+        return;
+    }
+    const parameters = parseParams(spec);
+    if (!parameters) {
+        return;
+    }
+    const request: ChangeSignatureParams = {
+        textDocument: { uri: editor.document.uri.toString() },
+        position: Server.client.code2ProtocolConverter.asPosition(
+            editor.selection.active
+        ),
+        parameters
+    };
+    const change = await Server.client.sendRequest<SourceChange | null>(
+        'rust-analyzer/changeSignature',
+        request
+    );
+    if (change == null) {
+        vscode.window.showWarningMessage(
+            'The signature of this function cannot be changed'
+        );
+        return;
+    }
+    await applySourceChange(change);
+}
+
+function parseParams(spec: string): SignatureParam[] | null {
+    const res: SignatureParam[] = [];
+    for (const part of splitTopLevel(spec)) {
+        const param = parseParam(part.trim());
+        if (!param) {
+            return null;
+        }
+        res.push(param);
+    }
+    return res;
+}
+
+function parseParam(text: string): SignatureParam | null {
+    const existing = /^(\d+)(?:\s+as\s+([A-Za-z_][A-Za-z0-9_]*))?$/.exec(text);
+    if (existing) {
+        return { index: Number(existing[1]), name: existing[2] };
+    }
+    const added = /^([A-Za-z_][A-Za-z0-9_]*)\s*:([^=]+)(?:=(.+))?$/.exec(text);
+    if (added) {
+        return {
+            name: added[1],
+            ty: added[2].trim(),
+            defaultValue: added[3] && added[3].trim()
+        };
+    }
+    return null;
+}
+
+// Splits on the commas which are not nested in brackets, so that types like
+// `HashMap<K, V>` stay in one piece.
+function splitTopLevel(spec: string): string[] {
+    if (spec.trim() === '') {
+        return [];
+    }
+    const res: string[] = [];
+    let depth = 0;
+    let start = 0;
+    for (let i = 0; i < spec.length; i++) {
+        const c = spec[i];
+        if ('<([{'.includes(c)) {
+            depth++;
+        } else if ('>)]}'.includes(c)) {
+            depth--;
+        } else if (c === ',' && depth === 0) {
+            res.push(spec.slice(start, i));
+            start = i + 1;
+        }
+    }
+    res.push(spec.slice(start));
+    return res;
+}
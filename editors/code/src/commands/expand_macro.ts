@@ -0,0 +1,81 @@
+import * as vscode from 'vscode';
+import { Position, TextDocumentIdentifier } from 'vscode-languageclient';
+import { Server } from '../server';
+
+export const expandMacroUri = vscode.Uri.parse(
+    'rust-analyzer-macro://expandMacro/[EXPANSION].rs'
+);
+
+export class ExpandMacroContentProvider
+    implements vscode.TextDocumentContentProvider {
+    public eventEmitter = new vscode.EventEmitter<vscode.Uri>();
+
+    public provideTextDocumentContent(
+        uri: vscode.Uri
+    ): vscode.ProviderResult<string> {
+        async function handle() {
+            const editor = vscode.window.activeTextEditor;
+            if (editor == null) {
+                return '';
+            }
+
+            const request: MacroExpandParams = {
+                textDocument: { uri: editor.document.uri.toString() },
+                position: Server.client.code2ProtocolConverter.asPosition(
+                    editor.selection.active
+                )
+            };
+            const expanded = await Server.client.sendRequest<ExpandMacroResult>(
+                'rust-analyzer/expandMacro',
+                request
+            );
+            if (expanded == null) {
+                return 'Not available';
+            }
+            return codeFormat(expanded);
+        }
+
+        return handle();
+    }
+
+    get onDidChange(): vscode.Event<vscode.Uri> {
+        return this.eventEmitter.event;
+    }
+}
+
+// Opens the virtual file that will show the expansion of the macro call
+// under the cursor
+//
+// The contents of the file come from the `TextDocumentContentProvider`
+export function createHandle(provider: ExpandMacroContentProvider) {
+    return async () => {
+        const document = await vscode.workspace.openTextDocument(
+            expandMacroUri
+        );
+
+        provider.eventEmitter.fire(expandMacroUri);
+
+        return vscode.window.showTextDocument(
+            document,
+            vscode.ViewColumn.Two,
+            true
+        );
+    };
+}
+
+interface MacroExpandParams {
+    textDocument: TextDocumentIdentifier;
+    position: Position;
+}
+
+interface ExpandedMacro {
+    name: string;
+    expansion: string;
+}
+
+type ExpandMacroResult = ExpandedMacro | null;
+
+function codeFormat(expanded: ExpandedMacro): string {
+    const header = `// Recursive expansion of ${expanded.name}! macro`;
+    return `${header}\n\n${expanded.expansion}`;
+}
@@ -0,0 +1,30 @@
+import * as vscode from 'vscode';
+
+import { Server } from '../server';
+import {
+    handle as applySourceChange,
+    SourceChange
+} from './apply_source_change';
+
+interface SsrParams {
+    query: string;
+    resolvePaths: boolean;
+}
+
+export async function handle() {
+    const query = await vscode.window.showInputBox({
+        prompt: 'Enter a rule like `foo($a, $b) ==>> $a.foo($b)`',
+        placeHolder: 'foo($a, $b) ==>> $a.foo($b)',
+        validateInput: (value: string) =>
+            value.includes('==>>') ? null : 'The rule must contain `==>>`'
+    });
+    if (!query) {
+        return;
+    }
+    const request: SsrParams = { query, resolvePaths: true };
+    const change = await Server.client.sendRequest<SourceChange>(
+        'rust-analyzer/ssr',
+        request
+    );
+    await applySourceChange(change);
+}
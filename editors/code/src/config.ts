@@ -23,6 +23,7 @@ export class Config {
     public lruCapacity: null | number = null;
     public displayInlayHints = true;
     public excludeGlobs = [];
+    public testAttributes = [];
     public cargoWatchOptions: CargoWatchOptions = {
         enableOnStartup: 'ask',
         trace: 'off',
@@ -132,5 +133,8 @@ export class Config {
         if (config.has('excludeGlobs')) {
             this.excludeGlobs = config.get('excludeGlobs') || [];
         }
+        if (config.has('testAttributes')) {
+            this.testAttributes = config.get('testAttributes') || [];
+        }
     }
 }
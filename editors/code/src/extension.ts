@@ -8,6 +8,7 @@ import {
     interactivelyStartCargoWatch,
     startCargoWatch
 } from './commands/runnables';
+import { ExpandMacroContentProvider } from './commands/expand_macro';
 import { SyntaxTreeContentProvider } from './commands/syntaxTree';
 import * as events from './events';
 import * as notifications from './notifications';
@@ -63,6 +64,11 @@ export function activate(context: vscode.ExtensionContext) {
         commands.matchingBrace.handle
     );
     registerCommand('rust-analyzer.joinLines', commands.joinLines.handle);
+    registerCommand('rust-analyzer.ssr', commands.ssr.handle);
+    registerCommand(
+        'rust-analyzer.changeSignature',
+        commands.changeSignature.handle
+    );
     registerCommand('rust-analyzer.parentModule', commands.parentModule.handle);
     registerCommand('rust-analyzer.run', commands.runnables.handle);
     // Unlike the above this does not send requests to the language server
@@ -115,6 +121,20 @@ export function activate(context: vscode.ExtensionContext) {
         commands.syntaxTree.createHandle(syntaxTreeContentProvider)
     );
 
+    const expandMacroContentProvider = new ExpandMacroContentProvider();
+
+    disposeOnDeactivation(
+        vscode.workspace.registerTextDocumentContentProvider(
+            'rust-analyzer-macro',
+            expandMacroContentProvider
+        )
+    );
+
+    registerCommand(
+        'rust-analyzer.expandMacro',
+        commands.expandMacro.createHandle(expandMacroContentProvider)
+    );
+
     vscode.workspace.onDidChangeTextDocument(
         events.changeTextDocument.createHandler(syntaxTreeContentProvider),
         null,
@@ -37,7 +37,8 @@ export class Server {
                 showWorkspaceLoaded:
                     Server.config.showWorkspaceLoadedNotification,
                 lruCapacity: Server.config.lruCapacity,
-                excludeGlobs: Server.config.excludeGlobs
+                excludeGlobs: Server.config.excludeGlobs,
+                testAttributes: Server.config.testAttributes
             },
             traceOutputChannel
         };